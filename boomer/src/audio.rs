@@ -0,0 +1,104 @@
+use core::{f64::consts::PI, time::Duration};
+use std::{
+    io,
+    path::{Path, PathBuf},
+    thread::{self, JoinHandle},
+};
+
+use linux_alsa::{Pcm, PcmConfig, PcmDirection};
+use tracing::{debug, info, warn};
+
+const TONE_CHANNELS: u32 = 2;
+const TONE_RATE: u32 = 48_000;
+const TONE_PERIODS: u32 = 4;
+
+// Half of the full scale, to stay clear of any clipping.
+const TONE_AMPLITUDE: f64 = 16384.0;
+
+// The PCM might go away while the display is reconfigured, so we wait a bit before opening it
+// again.
+const TONE_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+fn tone_samples(frequency: u32, start: u32, frames: u32) -> Vec<i16> {
+    (start..start + frames)
+        .flat_map(|idx| {
+            let phase =
+                2.0 * PI * f64::from(frequency) * f64::from(idx % TONE_RATE) / f64::from(TONE_RATE);
+
+            #[expect(
+                clippy::cast_possible_truncation,
+                reason = "The amplitude fits in an i16"
+            )]
+            let sample = (phase.sin() * TONE_AMPLITUDE) as i16;
+
+            [sample; TONE_CHANNELS as usize]
+        })
+        .collect()
+}
+
+fn play_tone(path: &Path, frequency: u32) -> io::Result<()> {
+    let mut pcm = Pcm::open(
+        path,
+        PcmDirection::Playback,
+        &PcmConfig {
+            channels: TONE_CHANNELS,
+            rate: TONE_RATE,
+            period_frames: TONE_RATE / 100,
+            periods: TONE_PERIODS,
+        },
+    )?;
+
+    let config = pcm.config();
+    info!(
+        "Playing a {}Hz tone on {}: {}",
+        frequency,
+        path.display(),
+        config
+    );
+
+    let mut idx = 0;
+    loop {
+        let samples = tone_samples(frequency, idx, config.period_frames);
+        let frames = pcm.write(&samples)?;
+
+        #[expect(
+            clippy::cast_possible_truncation,
+            reason = "We never write more than a period"
+        )]
+        let frames = frames as u32;
+
+        idx = (idx + frames) % TONE_RATE;
+    }
+}
+
+/// Plays a sine tone on an ALSA PCM device until Boomer stops.
+///
+/// Dradis looks for this tone in the audio it captures.
+pub(crate) fn spawn_tone(path: PathBuf, frequency: u32) -> io::Result<JoinHandle<()>> {
+    thread::Builder::new().name("tone".into()).spawn(move || {
+        loop {
+            if let Err(e) = play_tone(&path, frequency) {
+                warn!("Couldn't play our tone on {}: {}", path.display(), e);
+            }
+
+            debug!("Restarting the tone playback.");
+            thread::sleep(TONE_RETRY_DELAY);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests_audio {
+    use super::{TONE_CHANNELS, TONE_RATE, tone_samples};
+
+    #[test]
+    fn test_tone_samples() {
+        let samples = tone_samples(1000, 0, 48);
+
+        assert_eq!(samples.len(), 48 * TONE_CHANNELS as usize);
+        assert_eq!(samples[0], 0);
+        assert_eq!(samples[0], samples[1]);
+        assert_eq!(samples[24], 16384);
+        assert_eq!(tone_samples(1000, TONE_RATE, 24), tone_samples(1000, 0, 24));
+    }
+}
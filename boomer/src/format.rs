@@ -0,0 +1,54 @@
+use clap::ValueEnum;
+use frame_check::PixelFormat;
+use nucleid::Format;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub(crate) enum BufferFormat {
+    Xrgb8888,
+    Rgb565,
+    Xrgb2101010,
+    Nv12,
+    Yuyv,
+}
+
+impl BufferFormat {
+    pub(crate) fn drm_format(self) -> Format {
+        match self {
+            Self::Xrgb8888 => Format::XRGB8888,
+            Self::Rgb565 => Format::RGB565,
+            Self::Xrgb2101010 => Format::XRGB2101010,
+            Self::Nv12 => Format::NV12,
+            Self::Yuyv => Format::YUYV,
+        }
+    }
+
+    pub(crate) fn pixel_format(self) -> PixelFormat {
+        match self {
+            Self::Xrgb8888 => PixelFormat::Xrgb8888,
+            Self::Rgb565 => PixelFormat::Rgb565,
+            Self::Xrgb2101010 => PixelFormat::Xrgb2101010,
+            Self::Nv12 => PixelFormat::Nv12,
+            Self::Yuyv => PixelFormat::Yuyv,
+        }
+    }
+
+    // Dumb buffers only have a notion of bits per pixel and lines, so multi-planar formats are
+    // allocated as a single buffer large enough to hold all the planes. Returns the bpp and
+    // number of lines to allocate.
+    pub(crate) fn dumb_buffer_layout(self, height: u32) -> (u32, u32) {
+        match self {
+            Self::Xrgb8888 | Self::Xrgb2101010 => (32, height),
+            Self::Rgb565 | Self::Yuyv => (16, height),
+            Self::Nv12 => (8, height + height / 2),
+        }
+    }
+
+    // Offsets, in bytes, of each plane within a dumb buffer allocated with the layout above. All
+    // the planes share the pitch of the dumb buffer.
+    pub(crate) fn plane_offsets(self, pitch: u32, height: u32) -> Vec<u32> {
+        match self {
+            Self::Xrgb8888 | Self::Xrgb2101010 | Self::Rgb565 | Self::Yuyv => vec![0],
+            Self::Nv12 => vec![0, pitch * height],
+        }
+    }
+}
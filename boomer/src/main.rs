@@ -2,32 +2,73 @@
 
 extern crate alloc;
 
+mod audio;
+mod format;
+mod mode;
+mod planes;
+mod properties;
+mod scenario;
+mod timing;
+
 use alloc::rc::Rc;
-use core::time::Duration;
-use std::{io, path::PathBuf, thread::sleep, time::Instant};
+use core::{mem, time::Duration};
+use std::{
+    io,
+    os::fd::AsFd as _,
+    path::PathBuf,
+    process,
+    sync::OnceLock,
+    thread::sleep,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use anyhow::{Context as _, Result, anyhow};
-use clap::Parser;
-use frame_check::{Frame, Metadata, QRCODE_HEIGHT, QRCODE_WIDTH};
-use image::{Rgba, imageops::FilterType};
+use clap::{Parser, ValueEnum};
+use frame_check::{
+    ClearedFrame, Control, Frame, IndexedChecksum, Metadata, OutputProperties, Pattern,
+    PlaneDescription, QRCODE_HEIGHT, QRCODE_WIDTH, SourceMode,
+};
+use image::Rgba;
 use linux_uevent::{Action, UeventSocket};
 use nucleid::{
     BufferType, Connector, ConnectorStatus, ConnectorType, ConnectorUpdate, Device, Format,
     Framebuffer, Mode, Object as _, ObjectUpdate as _, Output, Plane, PlaneType, PlaneUpdate,
 };
-use pix::{Raster, bgr::Bgra8, rgb::Rgba8};
-use qrcode::QrCode;
+use pix::{
+    Raster,
+    rgb::{Rgb8, Rgba8},
+};
+use qrcode::{EcLevel, QrCode};
 use tracing::{Level, debug, debug_span, info, trace, warn};
 use tracing_subscriber::fmt::format::FmtSpan;
 
+use crate::{
+    audio::spawn_tone,
+    format::BufferFormat,
+    mode::{ModeLine, ModeSelection, ModeSpec, list_modes},
+    planes::{OverlayPlane, PlaneConfig, setup_overlays},
+    properties::{OUTPUT_PROPERTIES, set_output_properties},
+    scenario::{Scenario, Step},
+    timing::{FlipTimer, PageFlipEvent, monotonic_now},
+};
+
 const HEADER_VERSION_MAJOR: u8 = 2;
-const HEADER_VERSION_MINOR: u8 = 0;
+const HEADER_VERSION_MINOR: u8 = 9;
 
 const NUM_BUFFERS: usize = 3;
 
-const MODE_POLL_TIMEOUT: Duration = Duration::from_secs(10);
-
-const PATTERN: &[u8] = include_bytes!("../resources/smpte-color-bars.png");
+// Number of frames between two page flip statistics reports.
+const FLIP_STATS_PERIOD: usize = 600;
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum PatternKind {
+    SmpteColorBars,
+    HorizontalGradient,
+    VerticalGradient,
+    Checkerboard,
+    MovingBars,
+    Noise,
+}
 
 #[derive(Parser)]
 #[command(about = "KMS Crash Test Pattern", version)]
@@ -43,10 +84,128 @@ struct CliArgs {
     #[arg(short = 'C', long, help = "Connector name, for example: HDMI-A-1")]
     connector_name: Option<String>,
 
+    #[arg(
+        short = 'M',
+        long,
+        help = "Mode to use, as WIDTHxHEIGHT[@REFRESH]. Defaults to the preferred mode."
+    )]
+    mode: Option<ModeSpec>,
+
+    #[arg(
+        long,
+        help = "Index of the mode to use in the connector modes list",
+        conflicts_with = "mode"
+    )]
+    mode_index: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Custom mode to use, using the X11 modeline syntax",
+        conflicts_with_all = ["mode", "mode_index"]
+    )]
+    modeline: Option<ModeLine>,
+
+    #[arg(long, help = "List the connector modes and exit")]
+    list_modes: bool,
+
+    #[arg(
+        short = 'F',
+        long,
+        help = "Framebuffer Pixel Format",
+        value_enum,
+        default_value_t = BufferFormat::Xrgb8888
+    )]
+    format: BufferFormat,
+
+    #[arg(
+        short = 'P',
+        long,
+        help = "Test Pattern to display",
+        value_enum,
+        default_value_t = PatternKind::SmpteColorBars
+    )]
+    pattern: PatternKind,
+
+    #[arg(
+        long,
+        help = "Checkerboard square size, in pixels",
+        default_value_t = 1
+    )]
+    checker_size: u32,
+
+    #[arg(long, help = "Moving bars width, in pixels", default_value_t = 64)]
+    bar_width: u32,
+
+    #[arg(
+        long,
+        help = "Moving bars displacement at each frame, in pixels",
+        default_value_t = 8
+    )]
+    bar_step: u32,
+
+    #[arg(long, help = "Noise pattern seed", default_value_t = 0)]
+    noise_seed: u64,
+
+    #[arg(short = 'S', long, help = "Test Scenario File")]
+    scenario: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Ignore the instructions Dradis stores in the display EDID"
+    )]
+    ignore_control: bool,
+
+    #[arg(
+        long,
+        help = "Keep the current output when the display is disconnected or connected again"
+    )]
+    ignore_hotplug: bool,
+
+    #[arg(long, help = "Test Identifier to put in every frame metadata")]
+    test_id: Option<u32>,
+
+    #[arg(long, help = "ALSA PCM Playback Device File to play a tone on")]
+    audio_device: Option<PathBuf>,
+
+    #[arg(long, help = "Frequency of the tone, in Hz", default_value_t = 1000)]
+    tone_frequency: u32,
+
     #[arg(short, long, action = clap::ArgAction::Count)]
     verbose: u8,
 }
 
+impl CliArgs {
+    fn mode_selection(&self) -> ModeSelection {
+        if let Some(spec) = self.mode {
+            ModeSelection::Spec(spec)
+        } else if let Some(idx) = self.mode_index {
+            ModeSelection::Index(idx)
+        } else if let Some(modeline) = self.modeline {
+            ModeSelection::Custom(modeline)
+        } else {
+            ModeSelection::Preferred
+        }
+    }
+
+    fn pattern(&self) -> Pattern {
+        match self.pattern {
+            PatternKind::SmpteColorBars => Pattern::SmpteColorBars,
+            PatternKind::HorizontalGradient => Pattern::HorizontalGradient,
+            PatternKind::VerticalGradient => Pattern::VerticalGradient,
+            PatternKind::Checkerboard => Pattern::Checkerboard {
+                size: self.checker_size,
+            },
+            PatternKind::MovingBars => Pattern::MovingBars {
+                width: self.bar_width,
+                step: self.bar_step,
+            },
+            PatternKind::Noise => Pattern::Noise {
+                seed: self.noise_seed,
+            },
+        }
+    }
+}
+
 fn find_connector(device: &Device, connector_name: Option<&str>) -> Result<Rc<Connector>> {
     if let Some(name) = connector_name {
         device
@@ -73,36 +232,54 @@ fn find_connector(device: &Device, connector_name: Option<&str>) -> Result<Rc<Co
     }
 }
 
-fn find_mode_for_connector(connector: &Rc<Connector>) -> io::Result<Mode> {
-    connector.preferred_mode()
-}
-
-fn find_plane_for_output(output: &Output) -> Option<Rc<Plane>> {
+fn find_plane_for_output(output: &Output, format: Format) -> Option<Rc<Plane>> {
     output.planes().into_iter().find(|plane| {
-        plane.formats().any(|fmt| fmt == Format::XRGB8888)
+        plane.formats().any(|fmt| fmt == format)
             && plane.plane_type().expect("Can't get plane type") == PlaneType::Primary
     })
 }
 
+// Allocates our framebuffers, and returns them together with their pitch, in bytes.
 fn get_framebuffers(
     device: &Device,
     num: usize,
     width: u32,
     height: u32,
-    fmt: Format,
-    bpp: u32,
-) -> io::Result<Vec<Framebuffer>> {
+    format: BufferFormat,
+) -> io::Result<(Vec<Framebuffer>, u32)> {
+    let (bpp, lines) = format.dumb_buffer_layout(height);
     let mut buffers = Vec::with_capacity(num);
+    let mut pitch = 0;
 
     for _idx in 0..num {
-        let buffer = device
-            .allocate_buffer(BufferType::Dumb, width, height, bpp)?
-            .into_framebuffer(fmt)?;
+        let buffer = device.allocate_buffer(BufferType::Dumb, width, lines, bpp)?;
+
+        // Multi-planar formats need a handle, pitch and offset for each plane. They all live in
+        // the same dumb buffer, so they only differ by their offset.
+        pitch = buffer.pitch();
+        let offsets = format.plane_offsets(pitch, height);
+        let buffer = buffer.into_framebuffer_with_offsets(format.drm_format(), &offsets)?;
 
         buffers.push(buffer);
     }
 
-    Ok(buffers)
+    Ok((buffers, pitch))
+}
+
+// The primary plane carries the QR Code and the frame index band, so it always covers the whole
+// CRTC.
+fn primary_plane_update<'a>(
+    plane: &'a Rc<Plane>,
+    fb: &'a Framebuffer,
+    width: u16,
+    height: u16,
+) -> PlaneUpdate<'a> {
+    PlaneUpdate::new(plane)
+        .set_framebuffer(fb)
+        .set_source_size(width.into(), height.into())
+        .set_source_coordinates(0.0, 0.0)
+        .set_display_size(width.into(), height.into())
+        .set_display_coordinates(0, 0)
 }
 
 fn initial_commit(
@@ -111,13 +288,10 @@ fn initial_commit(
     mode: Mode,
     plane: &Rc<Plane>,
     fb: &Framebuffer,
-    src: (f32, f32),
-    display: (usize, usize),
-) -> io::Result<Output> {
-    let (src_w, src_h) = src;
-    let (display_w, display_h) = display;
-
-    output
+    overlays: &[OverlayPlane],
+) -> Result<Output> {
+    let (width, height) = (mode.width(), mode.height());
+    let mut update = output
         .start_update()
         .set_mode(mode)
         .add_connector(if connector.property("top margin")?.is_some() {
@@ -133,237 +307,686 @@ fn initial_commit(
 
             ConnectorUpdate::new(connector)
         })
-        .add_plane(
-            PlaneUpdate::new(plane)
-                .set_framebuffer(fb)
-                .set_source_size(src_w, src_h)
-                .set_source_coordinates(0.0, 0.0)
-                .set_display_size(display_w, display_h)
-                .set_display_coordinates(0, 0),
-        )
-        .commit()
+        .add_plane(primary_plane_update(plane, fb, width, height));
+
+    for overlay in overlays {
+        update = update.add_plane(overlay.update()?);
+    }
+
+    Ok(update.set_page_flip_event(true).commit()?)
+}
+
+fn get_rgb_pattern(pattern: Pattern, width: u32, height: u32, index: usize) -> Result<Frame> {
+    pattern
+        .render(width, height, index)
+        .ok_or(anyhow!("Couldn't render pattern {pattern}"))
 }
 
-fn create_metadata_json(
+// Renders the pattern, and computes the checksum of what the source will actually display once
+// the pattern has been converted to the framebuffer format.
+//
+// The additional planes are composed on top of the primary plane by the display controller, so
+// they are part of the checksum, but not of the primary plane content.
+fn prepare_pattern(
+    pattern: Pattern,
+    format: BufferFormat,
     width: u32,
     height: u32,
-    hash: u64,
     index: usize,
-) -> Result<String, serde_json::Error> {
-    let metadata = Metadata {
-        version: (HEADER_VERSION_MAJOR, HEADER_VERSION_MINOR),
-        qrcode_width: QRCODE_WIDTH,
-        qrcode_height: QRCODE_HEIGHT,
-        width,
-        height,
-        hash,
-        index,
-    };
-
-    debug!("{}", metadata);
-
-    serde_json::to_string(&metadata)
+    planes: &[PlaneDescription],
+) -> Result<(ClearedFrame<Rgb8>, IndexedChecksum)> {
+    let cleared = get_rgb_pattern(pattern, width, height, index)?.clear();
+    let composed = cleared
+        .round_trip(format.pixel_format())
+        .compose(planes)
+        .context("Couldn't compose our planes")?;
+    let checksum =
+        IndexedChecksum::new(&composed).context("The frame is too small for the index band")?;
+
+    Ok((cleared, checksum))
 }
 
-fn get_rgb_pattern(width: u32, height: u32) -> Result<Frame, image::ImageError> {
-    Ok(Raster::with_u8_buffer(
-        width,
-        height,
-        image::load_from_memory(PATTERN)?
-            .resize_exact(width, height, FilterType::Nearest)
-            .to_rgb8()
-            .to_vec(),
-    )
-    .into())
+// Our QR Code area has a fixed size, so we use the largest module size that fits in it.
+fn qr_code_module_size(code: &QrCode) -> u32 {
+    // The renderer adds a quiet zone of 4 modules on each side.
+    let modules = u32::try_from(code.width())
+        .unwrap_or(u32::MAX)
+        .saturating_add(8);
+
+    QRCODE_WIDTH.min(QRCODE_HEIGHT) / modules
 }
 
-fn create_qr_code(bytes: &[u8]) -> Result<Raster<Bgra8>, qrcode::types::QrError> {
-    let qrcode = QrCode::new(bytes)?
+fn create_qr_code(bytes: &[u8]) -> Result<Raster<Rgb8>> {
+    // The frames go through a digital link, so if the metadata don't fit with the default error
+    // correction level, we can afford the lowest one.
+    let code = [EcLevel::M, EcLevel::L]
+        .into_iter()
+        .filter_map(|level| QrCode::with_error_correction_level(bytes, level).ok())
+        .find(|code| qr_code_module_size(code) > 0)
+        .ok_or(anyhow!(
+            "{} bytes of metadata don't fit in a {}x{} QR Code",
+            bytes.len(),
+            QRCODE_WIDTH,
+            QRCODE_HEIGHT
+        ))?;
+
+    let module_size = qr_code_module_size(&code);
+    let qrcode = code
         .render::<Rgba<u8>>()
-        .min_dimensions(QRCODE_WIDTH, QRCODE_HEIGHT)
-        .max_dimensions(QRCODE_WIDTH, QRCODE_HEIGHT)
+        .module_dimensions(module_size, module_size)
         .build();
 
     let rgba_raster: Raster<Rgba8> =
         Raster::with_u8_buffer(qrcode.width(), qrcode.height(), qrcode.to_vec());
 
-    Ok(Raster::with_raster(&rgba_raster))
+    // The code doesn't necessarily cover the whole area, the rest is part of the quiet zone.
+    let mut raster = Raster::with_color(QRCODE_WIDTH, QRCODE_HEIGHT, Rgb8::new(255, 255, 255));
+    raster.copy_raster(
+        (0, 0, qrcode.width(), qrcode.height()),
+        &Raster::<Rgb8>::with_raster(&rgba_raster),
+        (),
+    );
+
+    Ok(raster)
 }
 
-enum TestResult {
+enum TestError {
     Restart,
     Error(anyhow::Error),
 }
 
-macro_rules! try_anyhow {
-    ($e:expr, $c:literal) => {
-        match $e.context($c) {
-            Ok(v) => v,
-            Err(e) => return TestResult::Error(e),
-        }
-    };
+impl From<anyhow::Error> for TestError {
+    fn from(value: anyhow::Error) -> Self {
+        Self::Error(value)
+    }
 }
 
-#[expect(clippy::too_many_lines, reason = "🎶 Too long... 🎶")]
-fn start_output(
+fn received_hotplug(
     args: &CliArgs,
     socket: &mut UeventSocket,
-    device: &Device,
     connector: &Rc<Connector>,
-) -> TestResult {
-    info!("Running from Connector {}", connector);
+) -> Result<bool> {
+    let event = debug_span!("Uevent Processing").in_scope(|| {
+        socket.event_filter(|e| {
+            if e.subsystem() != "drm" {
+                return false;
+            }
+
+            if e.action() != Action::Change {
+                return false;
+            }
+
+            if let Some(devpath) = e.attribute("DEVNAME") {
+                let dev_path = PathBuf::from("/dev").join(devpath);
+
+                if dev_path != args.device {
+                    return false;
+                }
+            }
+
+            if e.attribute("HOTPLUG").is_none() {
+                return false;
+            }
+
+            if let Some(conn) = e.attribute("CONNECTOR") {
+                let conn_id = conn.parse::<u32>().expect("Malformed Connector ID");
+
+                if conn_id != connector.object_id() {
+                    return false;
+                }
+            }
+
+            true
+        })
+    });
 
-    // The modes aren't always updated right away after receiving a hotplug event, so we might need
-    // to wait for a bit.
-    let loop_start = Instant::now();
-    let mode = loop {
-        let preferred = find_mode_for_connector(connector);
+    Ok(event.context("Couldn't receive uevent")?.is_some())
+}
 
-        if let Ok(preferred) = preferred {
-            break preferred;
-        }
+// Identifies our instance in the frames metadata, so that Dradis can reject the frames a previous
+// instance emitted.
+fn session_id() -> u64 {
+    static SESSION_ID: OnceLock<u64> = OnceLock::new();
 
-        if loop_start.elapsed() > MODE_POLL_TIMEOUT {
-            warn!("Timed out waiting for a preferred mode.");
-            return TestResult::Error(anyhow!("Couldn't find a mode for the connector"));
-        }
+    *SESSION_ID.get_or_init(|| {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
 
-        debug!("Couldn't find a preferred mode. Waiting.");
+        u64::try_from(now.as_nanos()).unwrap_or(u64::MAX) ^ (u64::from(process::id()) << 32)
+    })
+}
 
-        sleep(Duration::from_millis(100));
+// Dradis might pass us some instructions through the EDID of the display.
+fn read_control(args: &CliArgs, connector: &Rc<Connector>) -> Result<Option<Control>> {
+    if args.ignore_control {
+        return Ok(None);
+    }
+
+    let Some(edid) = connector.edid()? else {
+        debug!("Connector doesn't have an EDID.");
+        return Ok(None);
     };
 
-    let width = mode.width();
-    let height = mode.height();
+    Ok(match Control::from_edid(&edid) {
+        Ok(Some(control)) => {
+            info!("Found instructions in the EDID: {}", control);
+            Some(control)
+        }
+        Ok(None) => None,
+        Err(e) => {
+            warn!("Couldn't parse the EDID instructions: {}. Ignoring.", e);
+            None
+        }
+    })
+}
 
-    info!("Using mode {}", mode);
+struct OutputState<'a> {
+    args: &'a CliArgs,
+    control: Option<Control>,
+    connector: &'a Rc<Connector>,
+    plane: Rc<Plane>,
+    overlays: Vec<OverlayPlane>,
+    plane_configs: &'a [PlaneConfig],
+    mode: Mode,
+    pattern: Pattern,
+    format: BufferFormat,
+    buffers: Vec<Framebuffer>,
+    pitch: u32,
+    cleared_pattern: ClearedFrame<Rgb8>,
+    checksum: IndexedChecksum,
+    planes_changed: bool,
+    active: bool,
+    async_flips: bool,
+    output_properties: OutputProperties,
+    timer: FlipTimer,
+    index: usize,
+}
 
-    let output = try_anyhow!(
-        device.output_from_connector(connector),
-        "Couldn't find a valid output for that connector"
-    );
+impl<'a> OutputState<'a> {
+    #[expect(clippy::too_many_arguments, reason = "It's still fairly readable.")]
+    fn new(
+        args: &'a CliArgs,
+        control: Option<Control>,
+        device: &Device,
+        connector: &'a Rc<Connector>,
+        plane_configs: &'a [PlaneConfig],
+        output: &Output,
+        mode: Mode,
+        index: usize,
+    ) -> Result<Self> {
+        let width = mode.width();
+        let height = mode.height();
+
+        info!("Using mode {}", mode);
+
+        let format = args.format;
+        info!("Using format {}", format.pixel_format());
+
+        let plane = find_plane_for_output(output, format.drm_format())
+            .context("Couldn't find a plane with the proper format")?;
+
+        let overlays = setup_overlays(device, output, plane_configs, width.into(), height.into())
+            .context("Couldn't setup our additional planes")?;
+        let planes = overlays
+            .iter()
+            .map(OverlayPlane::description)
+            .collect::<Vec<_>>();
+
+        let pattern = control
+            .and_then(|control| control.pattern)
+            .unwrap_or_else(|| args.pattern());
+        info!("Using pattern {}", pattern);
+
+        // Each frame has its index encoded at the bottom of the frame, so the hash changes for
+        // every frame. The rest of the frame doesn't change, so we only hash it once.
+        let (cleared_pattern, checksum) =
+            prepare_pattern(pattern, format, width.into(), height.into(), index, &planes)
+                .context("Couldn't load our pattern.")?;
+
+        let (buffers, pitch) =
+            get_framebuffers(device, NUM_BUFFERS, width.into(), height.into(), format)
+                .context("Couldn't create our framebuffers")?;
+
+        Ok(Self {
+            args,
+            control,
+            connector,
+            plane,
+            overlays,
+            plane_configs,
+            mode,
+            pattern,
+            format,
+            buffers,
+            pitch,
+            cleared_pattern,
+            checksum,
+            planes_changed: false,
+            active: false,
+            async_flips: false,
+            output_properties: OutputProperties::default(),
+            timer: FlipTimer::new(mode.refresh()),
+            index,
+        })
+    }
 
-    info!("Using output: {}", output);
+    fn width(&self) -> u32 {
+        self.mode.width().into()
+    }
 
-    let plane = try_anyhow!(
-        find_plane_for_output(&output),
-        "Couldn't find a plane with the proper format"
-    );
+    fn height(&self) -> u32 {
+        self.mode.height().into()
+    }
 
-    let pattern_bgr = try_anyhow!(
-        get_rgb_pattern(width.into(), height.into()),
-        "Couldn't load our pattern."
-    );
-    let cleared_pattern_bgr = pattern_bgr.clear();
-
-    let hash = cleared_pattern_bgr.compute_checksum();
-    info!("Hash {:#x}", hash);
-
-    let cleared_pattern_xrgb = cleared_pattern_bgr.convert::<Bgra8>();
-
-    let mut buffers = try_anyhow!(
-        get_framebuffers(
-            device,
-            NUM_BUFFERS,
-            width.into(),
-            height.into(),
-            Format::XRGB8888,
-            32,
-        ),
-        "Couldn't create our framebuffers"
-    );
+    fn enable(&mut self, device: &Device, output: Output) -> Result<Output> {
+        info!("Setting up the pipeline");
 
-    info!("Setting up the pipeline");
+        self.timer.reset();
 
-    let mut output = try_anyhow!(
-        initial_commit(
+        let start = monotonic_now();
+        let output = initial_commit(
             output,
-            connector,
-            mode,
-            &plane,
-            &buffers[0],
-            (width.into(), height.into()),
-            (width.into(), height.into()),
-        ),
-        "Couldn't perform initial commit"
-    );
+            self.connector,
+            self.mode.clone(),
+            &self.plane,
+            &self.buffers[self.index % NUM_BUFFERS],
+            &self.overlays,
+        )
+        .context("Couldn't perform initial commit")?;
 
-    info!("Starting to output");
+        let event = PageFlipEvent::wait(device.as_fd()).context("Couldn't get the flip event")?;
+        self.timer.record(start, event);
+        self.active = true;
 
-    let mut index: usize = 0;
-    loop {
-        if try_anyhow!(
-            debug_span!("Uevent Processing").in_scope(|| {
-                socket.event_filter(|e| {
-                    if e.subsystem() != "drm" {
-                        return false;
-                    }
+        Ok(output)
+    }
 
-                    if e.action() != Action::Change {
-                        return false;
-                    }
+    fn planes(&self) -> Vec<PlaneDescription> {
+        self.overlays
+            .iter()
+            .map(OverlayPlane::description)
+            .collect()
+    }
 
-                    if let Some(devpath) = e.attribute("DEVNAME") {
-                        let dev_path = PathBuf::from("/dev").join(devpath);
+    // The instructions passed through the EDID take precedence over our arguments.
+    fn ignore_hotplug(&self) -> bool {
+        self.args.ignore_hotplug || self.control.is_some_and(|control| control.ignore_hotplug)
+    }
 
-                        if dev_path != args.device {
-                            return false;
-                        }
-                    }
+    fn test_id(&self) -> Option<u32> {
+        self.control
+            .map(|control| control.test_id)
+            .or(self.args.test_id)
+    }
 
-                    if e.attribute("HOTPLUG").is_none() {
-                        return false;
-                    }
+    fn metadata_json(&self, hash: u64) -> Result<String, serde_json::Error> {
+        let metadata = Metadata {
+            version: (HEADER_VERSION_MAJOR, HEADER_VERSION_MINOR),
+            qrcode_width: QRCODE_WIDTH,
+            qrcode_height: QRCODE_HEIGHT,
+            width: self.width(),
+            height: self.height(),
+            hash,
+            index: self.index,
+            pattern: Some(self.pattern),
+            index_band: true,
+            format: Some(self.format.pixel_format()),
+            planes: self.planes(),
+            output: self.output_properties,
+            timing: if self.async_flips {
+                None
+            } else {
+                self.timer.expected()
+            },
+            test_id: self.test_id(),
+            session: Some(session_id()),
+            mode: Some(SourceMode {
+                width: self.width(),
+                height: self.height(),
+                refresh: self.mode.refresh(),
+            }),
+        };
 
-                    if let Some(conn) = e.attribute("CONNECTOR") {
-                        let conn_id = conn.parse::<u32>().expect("Malformed Connector ID");
+        debug!("{}", metadata);
 
-                        if conn_id != connector.object_id() {
-                            return false;
-                        }
-                    }
-
-                    true
-                })
-            }),
-            "Couldn't receive uevent"
-        )
-        .is_some()
-        {
-            info!("Received Uevent, restarting the test.");
-            return TestResult::Restart;
-        }
+        serde_json::to_string(&metadata)
+    }
 
+    fn output_frame(&mut self, device: &Device, output: Output) -> Result<Output> {
         let span = debug_span!("Frame Generation");
         let _enter = span.enter();
 
-        let buffer = &mut buffers[index % NUM_BUFFERS];
-        let data = buffer.data();
+        let index = self.index;
+        let width = self.width();
+        let height = self.height();
 
         debug!("Switching to frame {}", index);
 
-        let json = try_anyhow!(
-            create_metadata_json(width.into(), height.into(), hash, index),
-            "Metadata JSON serialization failed."
-        );
+        if self.pattern.is_animated() && index > 0 {
+            (self.cleared_pattern, self.checksum) = prepare_pattern(
+                self.pattern,
+                self.format,
+                width,
+                height,
+                index,
+                &self.planes(),
+            )
+            .context("Couldn't render our pattern.")?;
+        }
 
-        trace!("Metadata JSON {}", json);
+        self.cleared_pattern.draw_index_band(index);
+
+        let hash = self.checksum.checksum(index);
+        trace!("Hash {:#x}", hash);
 
-        let qrcode = try_anyhow!(create_qr_code(json.as_bytes()), "QR Code creation failed");
+        let json = self
+            .metadata_json(hash)
+            .context("Metadata JSON serialization failed.")?;
 
-        let merged_buffer = cleared_pattern_xrgb.with_qr_code(&qrcode);
-        data.copy_from_slice(merged_buffer.as_bytes());
+        trace!("Metadata JSON {}", json);
+
+        let qrcode = create_qr_code(json.as_bytes()).context("QR Code creation failed")?;
 
-        output = try_anyhow!(
-            output
-                .start_update()
-                .add_plane(PlaneUpdate::new(&plane).set_framebuffer(buffer))
-                .commit(),
-            "Commit Failed"
+        let buffer = &mut self.buffers[index % NUM_BUFFERS];
+        let merged_buffer = self.cleared_pattern.with_qr_code(&qrcode);
+        self.format.pixel_format().encode_with_pitch(
+            width,
+            height,
+            self.pitch as usize,
+            merged_buffer.as_bytes(),
+            buffer.data(),
         );
 
-        index += 1;
+        // Asynchronous flips can only change the primary plane framebuffer, so the planes changes
+        // go through a regular commit.
+        let async_flip = self.async_flips && !self.planes_changed;
+
+        let mut update = output
+            .start_update()
+            .set_async(async_flip)
+            .set_page_flip_event(!async_flip)
+            .add_plane(primary_plane_update(
+                &self.plane,
+                buffer,
+                self.mode.width(),
+                self.mode.height(),
+            ));
+
+        // The planes have to change along with the metadata describing them.
+        if self.planes_changed {
+            for overlay in &self.overlays {
+                update = update.add_plane(overlay.update()?);
+            }
+
+            self.planes_changed = false;
+        }
+
+        let start = monotonic_now();
+        let output = update.commit().context("Commit Failed")?;
+
+        // Asynchronous flips complete right away, there's nothing to measure.
+        if !async_flip {
+            let event =
+                PageFlipEvent::wait(device.as_fd()).context("Couldn't get the flip event")?;
+            self.timer.record(start, event);
+        }
+
+        self.index += 1;
+
+        if self.index % FLIP_STATS_PERIOD == 0 {
+            info!("Page Flips: {}", self.timer.take_stats());
+        }
+
+        Ok(output)
     }
+
+    fn run_step(
+        &mut self,
+        device: &Device,
+        socket: &mut UeventSocket,
+        output: Output,
+        step: &Step,
+    ) -> Result<Output, TestError> {
+        Ok(match step {
+            Step::Mode {
+                width,
+                height,
+                refresh,
+            } => {
+                let mode = ModeSelection::Spec(ModeSpec {
+                    width: *width,
+                    height: *height,
+                    refresh: *refresh,
+                })
+                .wait_for_mode(self.connector)?;
+
+                // Our planes need to be released before we can allocate them again, but their
+                // framebuffers are still scanned out until the next commit completes.
+                let mut previous_buffers = self
+                    .overlays
+                    .drain(..)
+                    .map(OverlayPlane::into_buffer)
+                    .collect::<Vec<_>>();
+
+                // The connector properties are kept across modesets.
+                let output_properties = self.output_properties;
+
+                let state = Self::new(
+                    self.args,
+                    self.control,
+                    device,
+                    self.connector,
+                    self.plane_configs,
+                    &output,
+                    mode,
+                    self.index,
+                )?;
+                let previous = mem::replace(self, state);
+                previous_buffers.extend(previous.buffers);
+
+                self.output_properties = output_properties;
+                let output = self.enable(device, output)?;
+
+                // Our commits are blocking, so the old framebuffers aren't used anymore.
+                drop(previous_buffers);
+
+                output
+            }
+            Step::Frames { count } => {
+                let mut remaining = *count;
+
+                if !self.active {
+                    info!("Output is disabled, skipping the frames.");
+                }
+
+                let mut output = output;
+                while remaining != Some(0) {
+                    if received_hotplug(self.args, socket, self.connector)? {
+                        if self.ignore_hotplug() {
+                            info!("Received Uevent, ignoring it.");
+                        } else {
+                            info!("Received Uevent, restarting the test.");
+                            return Err(TestError::Restart);
+                        }
+                    }
+
+                    // There's no point in committing frames to a disabled CRTC, but we still
+                    // want the step to last as long as if it was enabled.
+                    if self.active {
+                        output = self.output_frame(device, output)?;
+                    } else {
+                        sleep(Duration::from_secs(1) / self.mode.refresh().max(1));
+                    }
+
+                    remaining = remaining.map(|r| r - 1);
+                }
+
+                info!("Page Flips: {}", self.timer.take_stats());
+
+                output
+            }
+            Step::Sleep { milliseconds } => {
+                sleep(Duration::from_millis(*milliseconds));
+                output
+            }
+            Step::Disable => {
+                info!("Disabling the output");
+
+                self.timer.reset();
+                self.active = false;
+
+                output
+                    .start_update()
+                    .set_active(false)
+                    .commit()
+                    .context("Couldn't disable the output")?
+            }
+            Step::Enable => self.enable(device, output)?,
+            Step::Plane {
+                index,
+                x,
+                y,
+                width,
+                height,
+            } => {
+                let (frame_width, frame_height) = (self.width(), self.height());
+                let Some(overlay) = self.overlays.get_mut(*index) else {
+                    return Err(anyhow!(
+                        "There's no plane {} in the scenario planes, and the primary plane can't \
+                         be moved",
+                        index
+                    )
+                    .into());
+                };
+
+                let current = overlay.description();
+                let geometry = (
+                    *x,
+                    *y,
+                    width.unwrap_or(current.width),
+                    height.unwrap_or(current.height),
+                );
+
+                overlay
+                    .set_geometry(geometry, frame_width, frame_height)
+                    .context("Invalid plane geometry")?;
+
+                info!(
+                    "Moving plane {} to {}x{}, size {}x{}",
+                    index, geometry.0, geometry.1, geometry.2, geometry.3
+                );
+
+                // The planes are part of our checksum.
+                (self.cleared_pattern, self.checksum) = prepare_pattern(
+                    self.pattern,
+                    self.format,
+                    frame_width,
+                    frame_height,
+                    self.index,
+                    &self.planes(),
+                )
+                .context("Couldn't render our pattern.")?;
+                self.planes_changed = true;
+
+                output
+            }
+            Step::AsyncFlips { enabled } => {
+                self.async_flips = *enabled;
+                self.timer.reset();
+                output
+            }
+            Step::Property { name, value } => {
+                // Dradis needs to know about these to check the frames.
+                if OUTPUT_PROPERTIES
+                    .iter()
+                    .any(|property| property.eq_ignore_ascii_case(name))
+                {
+                    return Err(anyhow!(
+                        "The \"{}\" property must be set through its dedicated step",
+                        name
+                    )
+                    .into());
+                }
+
+                info!("Setting connector property {} to {}", name, value);
+
+                output
+                    .start_update()
+                    .add_connector(
+                        ConnectorUpdate::new(self.connector).set_property(name.as_str(), *value),
+                    )
+                    .commit()
+                    .context("Couldn't set connector property")?
+            }
+            Step::BroadcastRgb { value } => {
+                self.output_properties.broadcast_rgb = Some(*value);
+                set_output_properties(device, self.connector, output, &self.output_properties)?
+            }
+            Step::MaxBpc { value } => {
+                self.output_properties.max_bpc = Some(*value);
+                set_output_properties(device, self.connector, output, &self.output_properties)?
+            }
+            Step::Colorspace { value } => {
+                self.output_properties.colorspace = Some(*value);
+                set_output_properties(device, self.connector, output, &self.output_properties)?
+            }
+            Step::ContentType { value } => {
+                self.output_properties.content_type = Some(*value);
+                set_output_properties(device, self.connector, output, &self.output_properties)?
+            }
+            Step::HdrOutputMetadata { metadata } => {
+                self.output_properties.hdr_output_metadata = *metadata;
+                set_output_properties(device, self.connector, output, &self.output_properties)?
+            }
+        })
+    }
+}
+
+fn start_output(
+    args: &CliArgs,
+    scenario: &Scenario,
+    socket: &mut UeventSocket,
+    device: &Device,
+    connector: &Rc<Connector>,
+) -> Result<(), TestError> {
+    info!("Running from Connector {}", connector);
+
+    let control = read_control(args, connector)?;
+
+    let mode = match control.and_then(|control| control.mode) {
+        Some(mode) => ModeSelection::Spec(mode.into()),
+        None => args.mode_selection(),
+    }
+    .wait_for_mode(connector)?;
+
+    let output = device
+        .output_from_connector(connector)
+        .context("Couldn't find a valid output for that connector")?;
+
+    info!("Using output: {}", output);
+
+    let mut state = OutputState::new(
+        args,
+        control,
+        device,
+        connector,
+        &scenario.planes,
+        &output,
+        mode,
+        0,
+    )?;
+    let mut output = state.enable(device, output)?;
+
+    info!("Starting to output");
+
+    for (idx, step) in scenario.steps.iter().enumerate() {
+        debug!("Running step {}", idx);
+
+        output = state.run_step(device, socket, output, step)?;
+    }
+
+    Ok(())
 }
 
 fn main() -> Result<()> {
@@ -379,6 +1002,26 @@ fn main() -> Result<()> {
         })
         .init();
 
+    info!("Starting session {:#x}", session_id());
+
+    let scenario = if let Some(path) = &args.scenario {
+        Scenario::from_file(path).context(format!(
+            "Couldn't load the scenario file \"{}\"",
+            path.display()
+        ))?
+    } else {
+        Scenario::forever()
+    };
+
+    let _tone = if let Some(path) = &args.audio_device {
+        Some(
+            spawn_tone(path.clone(), args.tone_frequency)
+                .context("Couldn't start the tone playback")?,
+        )
+    } else {
+        None
+    };
+
     let mut socket = UeventSocket::new().context("Couldn't create a netlink socket")?;
 
     let device = Device::new(&args.device).context(format!(
@@ -388,9 +1031,110 @@ fn main() -> Result<()> {
 
     let connector = find_connector(&device, args.connector_name.as_deref())?;
 
+    if args.list_modes {
+        return list_modes(&connector);
+    }
+
     loop {
-        if let TestResult::Error(e) = start_output(&args, &mut socket, &device, &connector) {
-            return Err(e);
+        match start_output(&args, &scenario, &mut socket, &device, &connector) {
+            Ok(()) => {
+                info!("Scenario complete.");
+                return Ok(());
+            }
+            Err(TestError::Restart) => {}
+            Err(TestError::Error(e)) => return Err(e),
         }
     }
 }
+
+#[cfg(test)]
+mod tests_qr_code {
+    use std::{fs, path::Path};
+
+    use frame_check::{
+        BroadcastRgb, Colorspace, ContentType, Eotf, FrameTiming, HdrMetadata, Metadata,
+        OutputProperties, Pattern, PixelFormat, PlaneDescription, QRCODE_HEIGHT, QRCODE_WIDTH,
+        SourceMode,
+    };
+
+    use super::{HEADER_VERSION_MAJOR, HEADER_VERSION_MINOR, create_qr_code};
+    use crate::scenario::Scenario;
+
+    // Every field is set, to its longest representation.
+    fn largest_metadata(planes: Vec<PlaneDescription>) -> Metadata {
+        Metadata {
+            version: (HEADER_VERSION_MAJOR, HEADER_VERSION_MINOR),
+            qrcode_width: QRCODE_WIDTH,
+            qrcode_height: QRCODE_HEIGHT,
+            width: 7680,
+            height: 4320,
+            hash: u64::MAX,
+            index: usize::MAX,
+            pattern: Some(Pattern::MovingBars {
+                width: u32::MAX,
+                step: u32::MAX,
+            }),
+            index_band: true,
+            format: Some(PixelFormat::Xrgb2101010),
+            planes,
+            output: OutputProperties {
+                broadcast_rgb: Some(BroadcastRgb::Automatic),
+                max_bpc: Some(16),
+                colorspace: Some(Colorspace::DciP3RgbTheater),
+                content_type: Some(ContentType::Graphics),
+                hdr_output_metadata: Some(HdrMetadata {
+                    eotf: Eotf::TraditionalHdr,
+                    display_primaries: [(u16::MAX, u16::MAX); 3],
+                    white_point: (u16::MAX, u16::MAX),
+                    max_display_mastering_luminance: u16::MAX,
+                    min_display_mastering_luminance: u16::MAX,
+                    max_cll: u16::MAX,
+                    max_fall: u16::MAX,
+                }),
+            },
+            timing: Some(FrameTiming {
+                presentation_ns: u64::MAX,
+                vblank: u64::MAX,
+            }),
+            test_id: Some(u32::MAX),
+            session: Some(u64::MAX),
+            mode: Some(SourceMode {
+                width: 7680,
+                height: 4320,
+                refresh: 240,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_largest_metadata_round_trip() {
+        let samples = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples");
+
+        for entry in fs::read_dir(samples).unwrap() {
+            let scenario = Scenario::from_file(&entry.unwrap().path()).unwrap();
+            let planes = scenario
+                .planes
+                .iter()
+                .enumerate()
+                .map(|(idx, plane)| plane.description(idx).unwrap())
+                .collect();
+
+            let metadata = largest_metadata(planes);
+            let json = serde_json::to_string(&metadata).unwrap();
+            let qrcode = create_qr_code(json.as_bytes()).unwrap();
+
+            let frame = Pattern::SmpteColorBars
+                .render(1280, 720, 0)
+                .unwrap()
+                .clear()
+                .with_qr_code(&qrcode);
+
+            assert_eq!(frame.metadata().unwrap(), metadata);
+        }
+    }
+
+    #[test]
+    fn test_metadata_too_large() {
+        assert!(create_qr_code(&[b'a'; 4096]).is_err());
+    }
+}
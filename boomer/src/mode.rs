@@ -0,0 +1,364 @@
+use alloc::rc::Rc;
+use core::{str::FromStr, time::Duration};
+use std::{thread::sleep, time::Instant};
+
+use anyhow::{Result, anyhow};
+use frame_check::ControlMode;
+use nucleid::{Connector, Mode};
+use tracing::{debug, warn};
+
+const MODE_POLL_TIMEOUT: Duration = Duration::from_secs(10);
+
+const DRM_MODE_FLAG_PHSYNC: u32 = 1 << 0;
+const DRM_MODE_FLAG_NHSYNC: u32 = 1 << 1;
+const DRM_MODE_FLAG_PVSYNC: u32 = 1 << 2;
+const DRM_MODE_FLAG_NVSYNC: u32 = 1 << 3;
+const DRM_MODE_FLAG_INTERLACE: u32 = 1 << 4;
+const DRM_MODE_FLAG_DBLSCAN: u32 = 1 << 5;
+const DRM_MODE_FLAG_DBLCLK: u32 = 1 << 12;
+
+/// A mode, described by its active size and, optionally, its refresh rate.
+///
+/// Parsed from the `WIDTHxHEIGHT[@REFRESH]` syntax.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct ModeSpec {
+    pub(crate) width: u16,
+    pub(crate) height: u16,
+    pub(crate) refresh: Option<u32>,
+}
+
+impl FromStr for ModeSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (size, refresh) = match s.split_once('@') {
+            Some((size, refresh)) => (
+                size,
+                Some(
+                    refresh
+                        .parse()
+                        .map_err(|e| format!("Invalid refresh rate \"{refresh}\": {e}"))?,
+                ),
+            ),
+            None => (s, None),
+        };
+
+        let (width, height) = size.split_once('x').ok_or(format!(
+            "Invalid mode size \"{size}\", expected WIDTHxHEIGHT"
+        ))?;
+
+        Ok(Self {
+            width: width
+                .parse()
+                .map_err(|e| format!("Invalid width \"{width}\": {e}"))?,
+            height: height
+                .parse()
+                .map_err(|e| format!("Invalid height \"{height}\": {e}"))?,
+            refresh,
+        })
+    }
+}
+
+impl From<ControlMode> for ModeSpec {
+    fn from(value: ControlMode) -> Self {
+        Self {
+            width: value.width,
+            height: value.height,
+            refresh: value.refresh.map(u32::from),
+        }
+    }
+}
+
+/// A custom mode, following the X11 modeline syntax:
+///
+/// `CLOCK_MHZ HDISPLAY HSYNC_START HSYNC_END HTOTAL VDISPLAY VSYNC_START VSYNC_END VTOTAL [FLAGS]`
+///
+/// Where the flags are any of `+hsync`, `-hsync`, `+vsync`, `-vsync`, `interlace`, `doublescan`
+/// and `dblclk`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct ModeLine {
+    clock_khz: u32,
+    hdisplay: u16,
+    hsync_start: u16,
+    hsync_end: u16,
+    htotal: u16,
+    vdisplay: u16,
+    vsync_start: u16,
+    vsync_end: u16,
+    vtotal: u16,
+    flags: u32,
+}
+
+// Parses a clock expressed in MHz, with up to three decimals, into kHz.
+fn parse_clock_khz(s: &str) -> Result<u32, String> {
+    let (int, frac) = s.split_once('.').unwrap_or((s, ""));
+    if frac.len() > 3 {
+        return Err(format!("Clock \"{s}\" is too precise"));
+    }
+
+    let int: u32 = int
+        .parse()
+        .map_err(|e| format!("Invalid clock \"{s}\": {e}"))?;
+    let frac: u32 = if frac.is_empty() {
+        0
+    } else {
+        format!("{frac:0<3}")
+            .parse()
+            .map_err(|e| format!("Invalid clock \"{s}\": {e}"))?
+    };
+
+    int.checked_mul(1000)
+        .and_then(|khz| khz.checked_add(frac))
+        .ok_or(format!("Clock \"{s}\" is too high"))
+}
+
+impl FromStr for ModeLine {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut tokens = s.split_whitespace();
+
+        let clock_khz = parse_clock_khz(tokens.next().ok_or("Missing clock")?)?;
+
+        let mut timings = [0_u16; 8];
+        for timing in &mut timings {
+            let token = tokens.next().ok_or("Missing timings")?;
+
+            *timing = token
+                .parse()
+                .map_err(|e| format!("Invalid timing \"{token}\": {e}"))?;
+        }
+
+        let mut flags = 0;
+        for token in tokens {
+            flags |= match token.to_lowercase().as_str() {
+                "+hsync" => DRM_MODE_FLAG_PHSYNC,
+                "-hsync" => DRM_MODE_FLAG_NHSYNC,
+                "+vsync" => DRM_MODE_FLAG_PVSYNC,
+                "-vsync" => DRM_MODE_FLAG_NVSYNC,
+                "interlace" => DRM_MODE_FLAG_INTERLACE,
+                "doublescan" => DRM_MODE_FLAG_DBLSCAN,
+                "dblclk" => DRM_MODE_FLAG_DBLCLK,
+                _ => return Err(format!("Unknown modeline flag \"{token}\"")),
+            };
+        }
+
+        let [
+            hdisplay,
+            hsync_start,
+            hsync_end,
+            htotal,
+            vdisplay,
+            vsync_start,
+            vsync_end,
+            vtotal,
+        ] = timings;
+
+        if !(hdisplay <= hsync_start && hsync_start <= hsync_end && hsync_end <= htotal) {
+            return Err(String::from(
+                "Horizontal timings aren't in increasing order",
+            ));
+        }
+
+        if !(vdisplay <= vsync_start && vsync_start <= vsync_end && vsync_end <= vtotal) {
+            return Err(String::from("Vertical timings aren't in increasing order"));
+        }
+
+        Ok(Self {
+            clock_khz,
+            hdisplay,
+            hsync_start,
+            hsync_end,
+            htotal,
+            vdisplay,
+            vsync_start,
+            vsync_end,
+            vtotal,
+            flags,
+        })
+    }
+}
+
+impl ModeLine {
+    fn to_mode(self) -> Mode {
+        Mode::new(
+            self.clock_khz,
+            self.hdisplay,
+            self.hsync_start,
+            self.hsync_end,
+            self.htotal,
+            self.vdisplay,
+            self.vsync_start,
+            self.vsync_end,
+            self.vtotal,
+            self.flags,
+        )
+    }
+}
+
+/// How the mode to use is picked.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum ModeSelection {
+    /// The connector preferred mode.
+    Preferred,
+
+    /// The first connector mode matching the [`ModeSpec`].
+    Spec(ModeSpec),
+
+    /// The connector mode at the given index in its modes list.
+    Index(usize),
+
+    /// A custom mode, that doesn't need to be in the connector modes list.
+    Custom(ModeLine),
+}
+
+impl ModeSelection {
+    fn find(&self, connector: &Rc<Connector>) -> Result<Mode> {
+        Ok(match self {
+            Self::Preferred => connector.preferred_mode()?,
+            Self::Spec(spec) => connector
+                .modes()?
+                .into_iter()
+                .find(|mode| {
+                    mode.width() == spec.width
+                        && mode.height() == spec.height
+                        && spec.refresh.is_none_or(|refresh| mode.refresh() == refresh)
+                })
+                .ok_or(anyhow!(
+                    "Couldn't find a {}x{} mode for the connector",
+                    spec.width,
+                    spec.height
+                ))?,
+            Self::Index(idx) => connector
+                .modes()?
+                .into_iter()
+                .nth(*idx)
+                .ok_or(anyhow!("The connector doesn't have a mode {}", idx))?,
+            Self::Custom(modeline) => modeline.to_mode(),
+        })
+    }
+
+    pub(crate) fn wait_for_mode(&self, connector: &Rc<Connector>) -> Result<Mode> {
+        // The modes aren't always updated right away after receiving a hotplug event, so we might
+        // need to wait for a bit.
+        let loop_start = Instant::now();
+        loop {
+            match self.find(connector) {
+                Ok(mode) => break Ok(mode),
+                Err(e) => {
+                    if loop_start.elapsed() > MODE_POLL_TIMEOUT {
+                        warn!("Timed out waiting for a mode.");
+                        break Err(e.context("Couldn't find a mode for the connector"));
+                    }
+
+                    debug!("Couldn't find our mode: {}. Waiting.", e);
+                }
+            }
+
+            sleep(Duration::from_millis(100));
+        }
+    }
+}
+
+#[expect(
+    clippy::print_stdout,
+    reason = "Listing the modes is the whole point of that function."
+)]
+pub(crate) fn list_modes(connector: &Rc<Connector>) -> Result<()> {
+    println!("Modes for connector {connector}:");
+
+    for (idx, mode) in connector.modes()?.into_iter().enumerate() {
+        println!("  {idx}: {mode}");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests_mode_spec {
+    use super::ModeSpec;
+
+    #[test]
+    fn test_size() {
+        assert_eq!(
+            "1280x720".parse::<ModeSpec>(),
+            Ok(ModeSpec {
+                width: 1280,
+                height: 720,
+                refresh: None
+            })
+        );
+    }
+
+    #[test]
+    fn test_refresh() {
+        assert_eq!(
+            "1920x1080@50".parse::<ModeSpec>(),
+            Ok(ModeSpec {
+                width: 1920,
+                height: 1080,
+                refresh: Some(50)
+            })
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!("1920".parse::<ModeSpec>().is_err());
+        assert!("1920x1080@".parse::<ModeSpec>().is_err());
+    }
+}
+
+#[cfg(test)]
+mod tests_modeline {
+    use super::{
+        DRM_MODE_FLAG_DBLCLK, DRM_MODE_FLAG_NVSYNC, DRM_MODE_FLAG_PHSYNC, ModeLine, parse_clock_khz,
+    };
+
+    #[test]
+    fn test_clock() {
+        assert_eq!(parse_clock_khz("148.5"), Ok(148_500));
+        assert_eq!(parse_clock_khz("74.25"), Ok(74_250));
+        assert_eq!(parse_clock_khz("25"), Ok(25_000));
+        assert!(parse_clock_khz("25.1751").is_err());
+    }
+
+    #[test]
+    fn test_modeline() {
+        let modeline: ModeLine =
+            "148.50 1920 2008 2052 2200 1080 1084 1089 1125 +hsync -vsync dblclk"
+                .parse()
+                .unwrap();
+
+        assert_eq!(
+            modeline,
+            ModeLine {
+                clock_khz: 148_500,
+                hdisplay: 1920,
+                hsync_start: 2008,
+                hsync_end: 2052,
+                htotal: 2200,
+                vdisplay: 1080,
+                vsync_start: 1084,
+                vsync_end: 1089,
+                vtotal: 1125,
+                flags: DRM_MODE_FLAG_PHSYNC | DRM_MODE_FLAG_NVSYNC | DRM_MODE_FLAG_DBLCLK,
+            }
+        );
+    }
+
+    #[test]
+    fn test_modeline_invalid() {
+        assert!("148.50 1920 2008 2052".parse::<ModeLine>().is_err());
+        assert!(
+            "148.50 1920 1900 2052 2200 1080 1084 1089 1125"
+                .parse::<ModeLine>()
+                .is_err()
+        );
+        assert!(
+            "148.50 1920 2008 2052 2200 1080 1084 1089 1125 +csync"
+                .parse::<ModeLine>()
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,327 @@
+use alloc::rc::Rc;
+use core::fmt;
+
+use anyhow::{Context as _, Result, anyhow};
+use frame_check::{
+    INDEX_BAND_HEIGHT, PLANE_ALPHA_OPAQUE, Pattern, PixelFormat, PlaneDescription, PlaneSource,
+    QRCODE_HEIGHT, QRCODE_WIDTH, ScalingFilter,
+};
+use nucleid::{
+    BufferType, Device, Format, Framebuffer, Object as _, ObjectUpdate as _, Output, Plane,
+    PlaneType, PlaneUpdate,
+};
+use serde::Deserialize;
+use tracing::info;
+
+const DRM_SCALING_FILTER_NEAREST_NEIGHBOR: u64 = 1;
+
+fn default_alpha() -> u16 {
+    PLANE_ALPHA_OPAQUE
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum PlaneKind {
+    Overlay,
+    Cursor,
+}
+
+impl fmt::Display for PlaneKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Overlay => "Overlay",
+            Self::Cursor => "Cursor",
+        })
+    }
+}
+
+impl PlaneKind {
+    fn plane_type(self) -> PlaneType {
+        match self {
+            Self::Overlay => PlaneType::Overlay,
+            Self::Cursor => PlaneType::Cursor,
+        }
+    }
+}
+
+/// An additional plane to display on top of the primary plane, as described in the scenario
+/// file.
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct PlaneConfig {
+    #[serde(rename = "type")]
+    kind: PlaneKind,
+    pattern: Pattern,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+
+    #[serde(default = "default_alpha")]
+    alpha: u16,
+
+    /// Defaults to the position of the plane in the list, the primary plane being at 0.
+    #[serde(default)]
+    zpos: Option<u32>,
+
+    /// Buffer size and crop. Defaults to a buffer of the plane size, displayed entirely.
+    #[serde(default)]
+    source: Option<PlaneSource>,
+
+    #[serde(default)]
+    filter: ScalingFilter,
+}
+
+impl PlaneConfig {
+    pub(crate) fn description(&self, idx: usize) -> Result<PlaneDescription> {
+        let zpos = match self.zpos {
+            Some(zpos) => zpos,
+            None => u32::try_from(idx + 1).context("Too many planes")?,
+        };
+
+        Ok(PlaneDescription {
+            pattern: self.pattern,
+            x: self.x,
+            y: self.y,
+            width: self.width,
+            height: self.height,
+            alpha: self.alpha,
+            zpos,
+            source: self.source,
+            filter: self.filter,
+        })
+    }
+}
+
+pub(crate) struct OverlayPlane {
+    plane: Rc<Plane>,
+    buffer: Framebuffer,
+    description: PlaneDescription,
+}
+
+impl OverlayPlane {
+    pub(crate) fn description(&self) -> PlaneDescription {
+        self.description
+    }
+
+    // Releases the plane, but keeps its framebuffer around.
+    pub(crate) fn into_buffer(self) -> Framebuffer {
+        self.buffer
+    }
+
+    /// Moves and resizes the plane. The whole area of the buffer that was displayed stays
+    /// displayed, and gets scaled to the new size.
+    pub(crate) fn set_geometry(
+        &mut self,
+        (x, y, width, height): (u32, u32, u32, u32),
+        frame_width: u32,
+        frame_height: u32,
+    ) -> Result<()> {
+        let mut desc = self.description;
+
+        if desc.source.is_none() && (width != desc.width || height != desc.height) {
+            desc.source = Some(PlaneSource {
+                buffer_width: desc.width,
+                buffer_height: desc.height,
+                x: 0,
+                y: 0,
+                width: desc.width,
+                height: desc.height,
+            });
+        }
+
+        desc.x = x;
+        desc.y = y;
+        desc.width = width;
+        desc.height = height;
+
+        check_plane_geometry(&desc, frame_width, frame_height)?;
+
+        if desc.is_scaled() && self.plane.plane_type()? == PlaneType::Cursor {
+            return Err(anyhow!("Cursor planes can't be scaled"));
+        }
+
+        self.description = desc;
+
+        Ok(())
+    }
+
+    pub(crate) fn update(&self) -> Result<PlaneUpdate<'_>> {
+        let desc = &self.description;
+        let width = u16::try_from(desc.width).context("Plane is too large")?;
+        let height = u16::try_from(desc.height).context("Plane is too large")?;
+
+        let (src_x, src_y, src_w, src_h) = match desc.source {
+            Some(src) => (
+                u16::try_from(src.x).context("Invalid Source Position")?,
+                u16::try_from(src.y).context("Invalid Source Position")?,
+                u16::try_from(src.width).context("Source is too large")?,
+                u16::try_from(src.height).context("Source is too large")?,
+            ),
+            None => (0, 0, width, height),
+        };
+
+        let mut update = PlaneUpdate::new(&self.plane)
+            .set_framebuffer(&self.buffer)
+            .set_source_size(src_w.into(), src_h.into())
+            .set_source_coordinates(src_x.into(), src_y.into())
+            .set_display_size(width.into(), height.into())
+            .set_display_coordinates(
+                usize::try_from(desc.x).context("Invalid Plane Position")?,
+                usize::try_from(desc.y).context("Invalid Plane Position")?,
+            );
+
+        // Cursor planes often have an immutable zpos, or none at all.
+        if self.plane.property("zpos")?.is_some() {
+            update = update.set_property("zpos", u64::from(desc.zpos));
+        }
+
+        if desc.is_translucent() {
+            update = update.set_property("alpha", u64::from(desc.alpha));
+        }
+
+        if desc.filter == ScalingFilter::NearestNeighbor {
+            update = update.set_property("SCALING_FILTER", DRM_SCALING_FILTER_NEAREST_NEIGHBOR);
+        }
+
+        Ok(update)
+    }
+}
+
+// The planes must not cover the areas dradis uses to identify the frame.
+fn check_plane_geometry(desc: &PlaneDescription, width: u32, height: u32) -> Result<()> {
+    if desc.width == 0
+        || desc.height == 0
+        || desc
+            .source
+            .is_some_and(|src| src.width == 0 || src.height == 0)
+    {
+        return Err(anyhow!("Plane can't be empty"));
+    }
+
+    if desc.x.saturating_add(desc.width) > width || desc.y.saturating_add(desc.height) > height {
+        return Err(anyhow!("Plane doesn't fit in a {}x{} frame", width, height));
+    }
+
+    if desc.intersects(0, 0, QRCODE_WIDTH, QRCODE_HEIGHT) {
+        return Err(anyhow!("Plane overlaps with the QR Code"));
+    }
+
+    if desc.intersects(
+        0,
+        height.saturating_sub(INDEX_BAND_HEIGHT),
+        width,
+        INDEX_BAND_HEIGHT,
+    ) {
+        return Err(anyhow!("Plane overlaps with the Frame Index Band"));
+    }
+
+    Ok(())
+}
+
+fn find_plane(
+    output: &Output,
+    kind: PlaneKind,
+    used: &[OverlayPlane],
+) -> Option<(Rc<Plane>, Format)> {
+    output.planes().into_iter().find_map(|plane| {
+        if plane.plane_type().expect("Can't get plane type") != kind.plane_type() {
+            return None;
+        }
+
+        if used
+            .iter()
+            .any(|overlay| overlay.plane.object_id() == plane.object_id())
+        {
+            return None;
+        }
+
+        let format = [Format::XRGB8888, Format::ARGB8888]
+            .into_iter()
+            .find(|format| plane.formats().any(|fmt| fmt == *format))?;
+
+        Some((plane, format))
+    })
+}
+
+pub(crate) fn setup_overlays(
+    device: &Device,
+    output: &Output,
+    configs: &[PlaneConfig],
+    width: u32,
+    height: u32,
+) -> Result<Vec<OverlayPlane>> {
+    let mut overlays: Vec<OverlayPlane> = Vec::with_capacity(configs.len());
+
+    for (idx, config) in configs.iter().enumerate() {
+        let description = config.description(idx)?;
+
+        check_plane_geometry(&description, width, height)?;
+
+        let (plane, format) = find_plane(output, config.kind, &overlays)
+            .ok_or(anyhow!("Couldn't find a free {} plane", config.kind))?;
+
+        if description.is_scaled() && config.kind == PlaneKind::Cursor {
+            return Err(anyhow!("Cursor planes can't be scaled"));
+        }
+
+        if config.filter != ScalingFilter::Default && plane.property("SCALING_FILTER")?.is_none() {
+            return Err(anyhow!(
+                "Plane {} doesn't support scaling filters",
+                plane.object_id()
+            ));
+        }
+
+        if config.alpha != PLANE_ALPHA_OPAQUE && plane.property("alpha")?.is_none() {
+            return Err(anyhow!(
+                "Plane {} doesn't support plane alpha",
+                plane.object_id()
+            ));
+        }
+
+        let (buffer_width, buffer_height) =
+            config.source.map_or((config.width, config.height), |src| {
+                (src.buffer_width, src.buffer_height)
+            });
+
+        let content = config
+            .pattern
+            .render(buffer_width, buffer_height, 0)
+            .ok_or(anyhow!("Couldn't render plane pattern {}", config.pattern))?;
+
+        let buffer = device.allocate_buffer(BufferType::Dumb, buffer_width, buffer_height, 32)?;
+        let pitch = buffer.pitch();
+        let mut buffer = buffer.into_framebuffer(format)?;
+
+        let data = buffer.data();
+        PixelFormat::Xrgb8888.encode_with_pitch(
+            buffer_width,
+            buffer_height,
+            pitch as usize,
+            content.as_bytes(),
+            data,
+        );
+
+        // We don't want any per-pixel alpha, so make sure ARGB buffers are opaque.
+        for pixel in data.chunks_exact_mut(4) {
+            pixel[3] = 0xff;
+        }
+
+        info!(
+            "Using plane {} at {}x{}, size {}x{}, zpos {}",
+            plane.object_id(),
+            description.x,
+            description.y,
+            description.width,
+            description.height,
+            description.zpos
+        );
+
+        overlays.push(OverlayPlane {
+            plane,
+            buffer,
+            description,
+        });
+    }
+
+    Ok(overlays)
+}
@@ -0,0 +1,169 @@
+use alloc::rc::Rc;
+
+use anyhow::{Context as _, Result, anyhow};
+use frame_check::{BroadcastRgb, Colorspace, ContentType, Eotf, HdrMetadata, OutputProperties};
+use nucleid::{Connector, ConnectorUpdate, Device, Object as _, ObjectUpdate as _, Output};
+use tracing::info;
+
+// HDMI_STATIC_METADATA_TYPE1, the only metadata type the kernel supports.
+const HDMI_STATIC_METADATA_TYPE1: u8 = 0;
+
+// Size of struct hdr_output_metadata, including its trailing padding.
+const HDR_OUTPUT_METADATA_SIZE: usize = 32;
+
+/// The connector properties we describe in the frames metadata.
+pub(crate) const OUTPUT_PROPERTIES: [&str; 5] = [
+    "Broadcast RGB",
+    "max bpc",
+    "Colorspace",
+    "content type",
+    "HDR_OUTPUT_METADATA",
+];
+
+fn broadcast_rgb_value(value: BroadcastRgb) -> u64 {
+    match value {
+        BroadcastRgb::Automatic => 0,
+        BroadcastRgb::Full => 1,
+        BroadcastRgb::Limited => 2,
+    }
+}
+
+// Follows the DRM_MODE_COLORIMETRY_* values.
+fn colorspace_value(value: Colorspace) -> u64 {
+    match value {
+        Colorspace::Default => 0,
+        Colorspace::Smpte170mYcc => 1,
+        Colorspace::Bt709Ycc => 2,
+        Colorspace::Xvycc601 => 3,
+        Colorspace::Xvycc709 => 4,
+        Colorspace::Sycc601 => 5,
+        Colorspace::Opycc601 => 6,
+        Colorspace::Oprgb => 7,
+        Colorspace::Bt2020Cycc => 8,
+        Colorspace::Bt2020Rgb => 9,
+        Colorspace::Bt2020Ycc => 10,
+        Colorspace::DciP3RgbD65 => 11,
+        Colorspace::DciP3RgbTheater => 12,
+    }
+}
+
+// Follows the DRM_MODE_CONTENT_TYPE_* values.
+fn content_type_value(value: ContentType) -> u64 {
+    match value {
+        ContentType::NoData => 0,
+        ContentType::Graphics => 1,
+        ContentType::Photo => 2,
+        ContentType::Cinema => 3,
+        ContentType::Game => 4,
+    }
+}
+
+fn eotf_value(value: Eotf) -> u8 {
+    match value {
+        Eotf::TraditionalSdr => 0,
+        Eotf::TraditionalHdr => 1,
+        Eotf::SmpteSt2084 => 2,
+        Eotf::Hlg => 3,
+    }
+}
+
+// Serializes the metadata following the layout of struct hdr_output_metadata.
+fn hdr_output_metadata_blob(metadata: &HdrMetadata) -> [u8; HDR_OUTPUT_METADATA_SIZE] {
+    let mut blob = [0; HDR_OUTPUT_METADATA_SIZE];
+
+    blob[0..4].copy_from_slice(&u32::from(HDMI_STATIC_METADATA_TYPE1).to_ne_bytes());
+    blob[4] = eotf_value(metadata.eotf);
+    blob[5] = HDMI_STATIC_METADATA_TYPE1;
+
+    let values = metadata
+        .display_primaries
+        .iter()
+        .chain(core::iter::once(&metadata.white_point))
+        .flat_map(|(x, y)| [*x, *y])
+        .chain([
+            metadata.max_display_mastering_luminance,
+            metadata.min_display_mastering_luminance,
+            metadata.max_cll,
+            metadata.max_fall,
+        ]);
+
+    for (chunk, value) in blob[6..].chunks_exact_mut(2).zip(values) {
+        chunk.copy_from_slice(&value.to_ne_bytes());
+    }
+
+    blob
+}
+
+fn check_property(connector: &Rc<Connector>, name: &str) -> Result<()> {
+    if connector.property(name)?.is_none() {
+        return Err(anyhow!(
+            "Connector {} doesn't support the \"{}\" property",
+            connector,
+            name
+        ));
+    }
+
+    Ok(())
+}
+
+/// Sets the connector properties that have been set in `properties`.
+///
+/// The HDR metadata are always set if the connector supports them, so that removing them from
+/// `properties` clears them.
+pub(crate) fn set_output_properties(
+    device: &Device,
+    connector: &Rc<Connector>,
+    output: Output,
+    properties: &OutputProperties,
+) -> Result<Output> {
+    info!("Setting connector properties: {}", properties);
+
+    let mut update = ConnectorUpdate::new(connector);
+
+    if let Some(broadcast_rgb) = properties.broadcast_rgb {
+        check_property(connector, "Broadcast RGB")?;
+        update = update.set_property("Broadcast RGB", broadcast_rgb_value(broadcast_rgb));
+    }
+
+    if let Some(max_bpc) = properties.max_bpc {
+        check_property(connector, "max bpc")?;
+        update = update.set_property("max bpc", u64::from(max_bpc));
+    }
+
+    if let Some(colorspace) = properties.colorspace {
+        check_property(connector, "Colorspace")?;
+        update = update.set_property("Colorspace", colorspace_value(colorspace));
+    }
+
+    if let Some(content_type) = properties.content_type {
+        check_property(connector, "content type")?;
+        update = update.set_property("content type", content_type_value(content_type));
+    }
+
+    // The blob needs to outlive the commit.
+    let blob = match &properties.hdr_output_metadata {
+        Some(metadata) => {
+            check_property(connector, "HDR_OUTPUT_METADATA")?;
+
+            Some(
+                device
+                    .create_blob(&hdr_output_metadata_blob(metadata))
+                    .context("Couldn't create the HDR metadata blob")?,
+            )
+        }
+        None => None,
+    };
+
+    if connector.property("HDR_OUTPUT_METADATA")?.is_some() {
+        update = update.set_property(
+            "HDR_OUTPUT_METADATA",
+            blob.as_ref().map_or(0, |blob| u64::from(blob.object_id())),
+        );
+    }
+
+    output
+        .start_update()
+        .add_connector(update)
+        .commit()
+        .context("Couldn't set the connector properties")
+}
@@ -0,0 +1,103 @@
+use std::{fs::File, io, path::Path};
+
+use frame_check::{BroadcastRgb, Colorspace, ContentType, HdrMetadata};
+use serde::Deserialize;
+
+use crate::planes::PlaneConfig;
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub(crate) enum Step {
+    /// Switches to the mode matching the given parameters, through a full modeset.
+    Mode {
+        width: u16,
+        height: u16,
+
+        #[serde(default)]
+        refresh: Option<u32>,
+    },
+
+    /// Displays frames. Runs forever if no count is given.
+    Frames {
+        #[serde(default)]
+        count: Option<usize>,
+    },
+
+    /// Keeps the current frame on screen for a while.
+    Sleep { milliseconds: u64 },
+
+    /// Disables the CRTC.
+    Disable,
+
+    /// Enables the CRTC again, using the current mode.
+    Enable,
+
+    /// Changes the position and size of one of the additional planes, starting from the next
+    /// frame. The plane is identified by its position in the `planes` list, and its size defaults
+    /// to the current one. A new size scales the plane buffer.
+    ///
+    /// The primary plane carries the QR Code and the frame index band, so it can't be moved.
+    Plane {
+        index: usize,
+        x: u32,
+        y: u32,
+
+        #[serde(default)]
+        width: Option<u32>,
+
+        #[serde(default)]
+        height: Option<u32>,
+    },
+
+    /// Enables or disables asynchronous page flips for the next frames.
+    AsyncFlips { enabled: bool },
+
+    /// Sets a connector property. The properties that have a dedicated step, and end up in the
+    /// frames metadata, are rejected.
+    Property { name: String, value: u64 },
+
+    /// Sets the connector quantization range.
+    BroadcastRgb { value: BroadcastRgb },
+
+    /// Sets the maximum bits per color component of the connector.
+    MaxBpc { value: u8 },
+
+    /// Sets the connector colorimetry.
+    Colorspace { value: Colorspace },
+
+    /// Sets the type of content displayed.
+    ContentType { value: ContentType },
+
+    /// Sets the static HDR metadata sent to the sink. Clears them if no metadata are given.
+    HdrOutputMetadata {
+        #[serde(default)]
+        metadata: Option<HdrMetadata>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Scenario {
+    /// Additional planes to display on top of the primary plane, for the whole scenario.
+    #[serde(default)]
+    pub(crate) planes: Vec<PlaneConfig>,
+
+    pub(crate) steps: Vec<Step>,
+}
+
+impl Scenario {
+    pub(crate) fn from_file(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+
+        serde_yaml::from_reader(file)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+    }
+
+    /// The scenario used when none is provided: displaying frames with the preferred mode,
+    /// forever.
+    pub(crate) fn forever() -> Self {
+        Self {
+            planes: Vec::new(),
+            steps: vec![Step::Frames { count: None }],
+        }
+    }
+}
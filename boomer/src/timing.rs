@@ -0,0 +1,276 @@
+use core::{fmt, time::Duration};
+use std::{io, os::fd::BorrowedFd};
+
+use frame_check::FrameTiming;
+use rustix::time::{ClockId, clock_gettime};
+
+// See struct drm_event and struct drm_event_vblank in include/uapi/drm/drm.h
+const DRM_EVENT_HEADER_SIZE: usize = 8;
+const DRM_EVENT_FLIP_COMPLETE: u32 = 0x02;
+const DRM_EVENT_VBLANK_TV_SEC_OFFSET: usize = 16;
+const DRM_EVENT_VBLANK_TV_USEC_OFFSET: usize = 20;
+const DRM_EVENT_VBLANK_SEQUENCE_OFFSET: usize = 24;
+
+pub(crate) fn monotonic_now() -> Duration {
+    let ts = clock_gettime(ClockId::Monotonic);
+
+    Duration::new(
+        u64::try_from(ts.tv_sec).expect("The monotonic clock is always positive"),
+        u32::try_from(ts.tv_nsec).expect("Nanoseconds always fit in a u32"),
+    )
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    bytes
+        .get(offset..offset + 4)?
+        .try_into()
+        .ok()
+        .map(u32::from_ne_bytes)
+}
+
+/// Completion of a page flip, as reported by the DRM page flip event.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) struct PageFlipEvent {
+    /// Value of the CRTC vblank counter at the vblank the flip completed at.
+    pub(crate) sequence: u32,
+
+    /// `CLOCK_MONOTONIC` timestamp of that vblank.
+    pub(crate) timestamp: Duration,
+}
+
+impl PageFlipEvent {
+    // Parses the events read from a DRM device, and returns the last page flip completion, if
+    // any.
+    fn parse(mut bytes: &[u8]) -> Option<Self> {
+        let mut flip = None;
+
+        while let (Some(kind), Some(length)) = (read_u32(bytes, 0), read_u32(bytes, 4)) {
+            let length = length as usize;
+            if length < DRM_EVENT_HEADER_SIZE || length > bytes.len() {
+                break;
+            }
+
+            let (event, rest) = bytes.split_at(length);
+
+            if kind == DRM_EVENT_FLIP_COMPLETE {
+                if let (Some(secs), Some(usecs), Some(sequence)) = (
+                    read_u32(event, DRM_EVENT_VBLANK_TV_SEC_OFFSET),
+                    read_u32(event, DRM_EVENT_VBLANK_TV_USEC_OFFSET),
+                    read_u32(event, DRM_EVENT_VBLANK_SEQUENCE_OFFSET),
+                ) {
+                    flip = Some(Self {
+                        sequence,
+                        timestamp: Duration::from_secs(secs.into())
+                            + Duration::from_micros(usecs.into()),
+                    });
+                }
+            }
+
+            bytes = rest;
+        }
+
+        flip
+    }
+
+    /// Waits for the completion event of the page flip we requested one for.
+    pub(crate) fn wait(fd: BorrowedFd<'_>) -> io::Result<Self> {
+        let mut buffer = [0; 1024];
+
+        loop {
+            let len = rustix::io::read(fd, &mut buffer[..])?;
+
+            if let Some(event) = buffer.get(..len).and_then(Self::parse) {
+                return Ok(event);
+            }
+        }
+    }
+}
+
+/// Page Flip statistics, accumulated over a series of frames.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct FlipStats {
+    frames: u64,
+    latency_min: Option<Duration>,
+    latency_max: Duration,
+    latency_total: Duration,
+    missed_vblanks: u64,
+}
+
+impl FlipStats {
+    fn record(&mut self, latency: Duration, missed: u64) {
+        self.frames += 1;
+        self.latency_min = Some(self.latency_min.map_or(latency, |min| min.min(latency)));
+        self.latency_max = self.latency_max.max(latency);
+        self.latency_total += latency;
+        self.missed_vblanks += missed;
+    }
+}
+
+impl fmt::Display for FlipStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(latency_min) = self.latency_min else {
+            return f.write_str("No page flip recorded");
+        };
+
+        let average = self
+            .latency_total
+            .checked_div(u32::try_from(self.frames).unwrap_or(u32::MAX))
+            .unwrap_or_default();
+
+        f.write_fmt(format_args!(
+            "{} flips, latency min {}us, avg {}us, max {}us, {} missed vblanks",
+            self.frames,
+            latency_min.as_micros(),
+            average.as_micros(),
+            self.latency_max.as_micros(),
+            self.missed_vblanks
+        ))
+    }
+}
+
+/// Tracks when our page flips complete, to estimate when the next frame will be displayed.
+///
+/// The page flip events give us the timestamp and counter value of the vblank each new
+/// framebuffer got latched at, so we can deduce the vblanks we missed.
+#[derive(Debug)]
+pub(crate) struct FlipTimer {
+    period: Duration,
+    last_flip: Option<PageFlipEvent>,
+    vblank: u64,
+    stats: FlipStats,
+}
+
+impl FlipTimer {
+    pub(crate) fn new(refresh: u32) -> Self {
+        Self {
+            period: Duration::from_secs(1) / refresh.max(1),
+            last_flip: None,
+            vblank: 0,
+            stats: FlipStats::default(),
+        }
+    }
+
+    /// Returns when the frame we're about to commit is expected to be displayed, ie. at the vblank
+    /// following the last page flip.
+    pub(crate) fn expected(&self) -> Option<FrameTiming> {
+        let last_flip = self.last_flip?;
+
+        Some(FrameTiming {
+            presentation_ns: u64::try_from((last_flip.timestamp + self.period).as_nanos()).ok()?,
+            vblank: self.vblank + 1,
+        })
+    }
+
+    /// Records a page flip, from the time the commit started to its completion event.
+    pub(crate) fn record(&mut self, start: Duration, event: PageFlipEvent) {
+        // Our vblank counter only needs to be consistent across our frames, so we don't follow
+        // the CRTC counter when it starts over, for example after a modeset.
+        let vblanks = self.last_flip.map_or(1, |last_flip| {
+            event.sequence.wrapping_sub(last_flip.sequence).max(1)
+        });
+
+        self.vblank += u64::from(vblanks);
+        self.last_flip = Some(event);
+        self.stats.record(
+            event.timestamp.saturating_sub(start),
+            u64::from(vblanks - 1),
+        );
+    }
+
+    /// Forgets about the past flips, for example because the output was disabled or because the
+    /// flips aren't synchronized to the vblank anymore.
+    pub(crate) fn reset(&mut self) {
+        self.last_flip = None;
+    }
+
+    /// Returns the statistics accumulated since the last call, and starts over.
+    pub(crate) fn take_stats(&mut self) -> FlipStats {
+        core::mem::take(&mut self.stats)
+    }
+}
+
+#[cfg(test)]
+mod tests_flip_timer {
+    use core::time::Duration;
+
+    use super::{DRM_EVENT_FLIP_COMPLETE, FlipTimer, PageFlipEvent};
+
+    const DRM_EVENT_VBLANK: u32 = 0x01;
+
+    fn event(kind: u32, secs: u32, usecs: u32, sequence: u32) -> Vec<u8> {
+        [kind, 32, 0, 0, secs, usecs, sequence, 42]
+            .into_iter()
+            .flat_map(u32::to_ne_bytes)
+            .collect()
+    }
+
+    fn flip(sequence: u32, timestamp: Duration) -> PageFlipEvent {
+        PageFlipEvent {
+            sequence,
+            timestamp,
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            PageFlipEvent::parse(&event(DRM_EVENT_FLIP_COMPLETE, 12, 345_678, 1000)),
+            Some(flip(1000, Duration::new(12, 345_678_000)))
+        );
+
+        let mut events = event(DRM_EVENT_FLIP_COMPLETE, 12, 0, 1000);
+        events.extend(event(DRM_EVENT_VBLANK, 12, 16_666, 1001));
+        assert_eq!(
+            PageFlipEvent::parse(&events),
+            Some(flip(1000, Duration::from_secs(12)))
+        );
+
+        assert_eq!(
+            PageFlipEvent::parse(&event(DRM_EVENT_VBLANK, 12, 0, 1000)),
+            None
+        );
+        assert_eq!(
+            PageFlipEvent::parse(&event(DRM_EVENT_FLIP_COMPLETE, 12, 0, 1000)[..16]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_missed_vblanks() {
+        let period = Duration::from_micros(16_666);
+        let mut timer = FlipTimer::new(60);
+
+        assert_eq!(timer.expected(), None);
+
+        timer.record(Duration::ZERO, flip(u32::MAX - 1, period));
+        timer.record(period, flip(u32::MAX, period * 2));
+        // The CRTC counter wraps around, and we miss a vblank.
+        timer.record(period * 2, flip(1, period * 4));
+
+        let stats = timer.take_stats();
+        assert_eq!(stats.frames, 3);
+        assert_eq!(stats.missed_vblanks, 1);
+        assert_eq!(stats.latency_max, period * 2);
+
+        let expected = timer.expected().unwrap();
+        assert_eq!(expected.vblank, 5);
+        assert_eq!(
+            u128::from(expected.presentation_ns),
+            (period * 4 + timer.period).as_nanos()
+        );
+    }
+
+    #[test]
+    fn test_reset() {
+        let period = Duration::from_micros(16_666);
+        let mut timer = FlipTimer::new(60);
+
+        timer.record(Duration::ZERO, flip(100, period));
+        timer.reset();
+
+        // The CRTC counter might have started over, but ours doesn't.
+        timer.record(period * 10, flip(3, period * 11));
+        assert_eq!(timer.expected().unwrap().vblank, 3);
+        assert_eq!(timer.take_stats().missed_vblanks, 0);
+    }
+}
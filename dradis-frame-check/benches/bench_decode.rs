@@ -3,7 +3,7 @@
 
 use criterion::{criterion_group, criterion_main};
 use dradis_frame_check::{
-    DecodeCheckArgs, DecodeCheckArgsDump, Metadata, QRCODE_HEIGHT, QRCODE_WIDTH,
+    DecodeCheckArgs, DecodeCheckArgsDump, Metadata, OutputProperties, QRCODE_HEIGHT, QRCODE_WIDTH,
     decode_and_check_frame,
 };
 
@@ -26,6 +26,9 @@ fn bench_frame_detect(c: &mut criterion::Criterion) {
                     height: FRAME_HEIGHT,
                     swap_channels: false,
                     dump: DecodeCheckArgsDump::Never,
+                    expected_test_id: None,
+                    expected_mode: None,
+                    expected_session: None,
                 },
             )
             .unwrap();
@@ -38,7 +41,16 @@ fn bench_frame_detect(c: &mut criterion::Criterion) {
                     width: FRAME_WIDTH,
                     height: FRAME_HEIGHT,
                     hash: 0xcddbc559fb8264e6,
-                    index: 6
+                    index: 6,
+                    pattern: None,
+                    index_band: false,
+                    format: None,
+                    planes: Vec::new(),
+                    output: OutputProperties::default(),
+                    timing: None,
+                    test_id: None,
+                    session: None,
+                    mode: None,
                 }
             )
         });
@@ -54,6 +66,9 @@ fn bench_frame_detect(c: &mut criterion::Criterion) {
                     height: FRAME_HEIGHT,
                     swap_channels: true,
                     dump: DecodeCheckArgsDump::Never,
+                    expected_test_id: None,
+                    expected_mode: None,
+                    expected_session: None,
                 },
             )
             .unwrap();
@@ -66,7 +81,16 @@ fn bench_frame_detect(c: &mut criterion::Criterion) {
                     width: FRAME_WIDTH,
                     height: FRAME_HEIGHT,
                     hash: 0xcddbc559fb8264e6,
-                    index: 39
+                    index: 39,
+                    pattern: None,
+                    index_band: false,
+                    format: None,
+                    planes: Vec::new(),
+                    output: OutputProperties::default(),
+                    timing: None,
+                    test_id: None,
+                    session: None,
+                    mode: None,
                 }
             )
         });
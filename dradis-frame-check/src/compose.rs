@@ -0,0 +1,280 @@
+use pix::{Raster, rgb::Rgb8};
+use serde::{Deserialize, Serialize};
+
+use crate::Pattern;
+
+/// Plane Alpha value for a fully opaque plane, following the KMS `alpha` property convention.
+pub const PLANE_ALPHA_OPAQUE: u16 = u16::MAX;
+
+fn default_alpha() -> u16 {
+    PLANE_ALPHA_OPAQUE
+}
+
+/// Scaling filter used by a plane, following the KMS `SCALING_FILTER` property values.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScalingFilter {
+    /// Driver default filter. We expect it to be close to a bilinear filter.
+    #[default]
+    Default,
+
+    /// Nearest Neighbor filter.
+    NearestNeighbor,
+}
+
+impl ScalingFilter {
+    /// Returns whether the filter is the driver default one.
+    #[must_use]
+    #[expect(
+        clippy::trivially_copy_pass_by_ref,
+        reason = "serde's skip_serializing_if passes a reference."
+    )]
+    pub fn is_default(&self) -> bool {
+        *self == Self::Default
+    }
+}
+
+/// Part of the plane buffer displayed by a plane.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct PlaneSource {
+    /// Width of the plane buffer, in pixels.
+    pub buffer_width: u32,
+
+    /// Height of the plane buffer, in pixels.
+    pub buffer_height: u32,
+
+    /// Horizontal position of the displayed area in the buffer, in pixels.
+    pub x: u32,
+
+    /// Vertical position of the displayed area in the buffer, in pixels.
+    pub y: u32,
+
+    /// Width of the displayed area in the buffer, in pixels.
+    pub width: u32,
+
+    /// Height of the displayed area in the buffer, in pixels.
+    pub height: u32,
+}
+
+/// Description of a plane displayed on top of the primary plane.
+///
+/// The plane content is always generated from the first frame of its [`Pattern`], and the plane
+/// is blended using the plane alpha only, without any per-pixel alpha.
+///
+/// The pattern is generated at the size of the plane buffer, cropped, and then scaled to the plane
+/// size.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct PlaneDescription {
+    /// Pattern displayed by the plane.
+    pub pattern: Pattern,
+
+    /// Horizontal position of the plane on the frame, in pixels.
+    pub x: u32,
+
+    /// Vertical position of the plane on the frame, in pixels.
+    pub y: u32,
+
+    /// Width of the plane, in pixels.
+    pub width: u32,
+
+    /// Height of the plane, in pixels.
+    pub height: u32,
+
+    /// Plane Alpha, from 0 (transparent) to [`PLANE_ALPHA_OPAQUE`].
+    #[serde(default = "default_alpha")]
+    pub alpha: u16,
+
+    /// Plane Z position. The primary plane is always at the bottom.
+    pub zpos: u32,
+
+    /// Part of the plane buffer displayed. If not set, the buffer is the size of the plane, and is
+    /// displayed entirely.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<PlaneSource>,
+
+    /// Scaling filter used if the plane is scaled.
+    #[serde(default, skip_serializing_if = "ScalingFilter::is_default")]
+    pub filter: ScalingFilter,
+}
+
+impl PlaneDescription {
+    /// Returns whether the plane covers the given pixel.
+    #[must_use]
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        (self.x..self.x.saturating_add(self.width)).contains(&x)
+            && (self.y..self.y.saturating_add(self.height)).contains(&y)
+    }
+
+    /// Returns whether the plane overlaps with the given rectangle.
+    #[must_use]
+    pub fn intersects(&self, x: u32, y: u32, width: u32, height: u32) -> bool {
+        self.x < x.saturating_add(width)
+            && x < self.x.saturating_add(self.width)
+            && self.y < y.saturating_add(height)
+            && y < self.y.saturating_add(self.height)
+    }
+
+    /// Returns whether the plane buffer is scaled to be displayed.
+    #[must_use]
+    pub fn is_scaled(&self) -> bool {
+        self.source
+            .is_some_and(|src| src.width != self.width || src.height != self.height)
+    }
+
+    // Generates the plane content, as RGB24, at the plane size.
+    fn content(&self) -> Option<Vec<u8>> {
+        let Some(src) = self.source else {
+            return Some(
+                self.pattern
+                    .render(self.width, self.height, 0)?
+                    .as_bytes()
+                    .to_vec(),
+            );
+        };
+
+        if src.x.checked_add(src.width)? > src.buffer_width
+            || src.y.checked_add(src.height)? > src.buffer_height
+            || src.width == 0
+            || src.height == 0
+        {
+            return None;
+        }
+
+        let buffer = self
+            .pattern
+            .render(src.buffer_width, src.buffer_height, 0)?;
+        let buffer = buffer.as_bytes();
+        let pixel = |x: u64, y: u64| {
+            let offset = usize::try_from((y * u64::from(src.buffer_width) + x) * 3)
+                .expect("The offset is within the buffer");
+
+            &buffer[offset..offset + 3]
+        };
+
+        let mut content = Vec::with_capacity(self.width as usize * self.height as usize * 3);
+        for dy in 0..u64::from(self.height) {
+            let ys = sample_position(dy, src.y, src.height, self.height);
+
+            for dx in 0..u64::from(self.width) {
+                let xs = sample_position(dx, src.x, src.width, self.width);
+
+                match self.filter {
+                    ScalingFilter::NearestNeighbor => {
+                        content.extend_from_slice(pixel(xs.nearest(), ys.nearest()));
+                    }
+                    ScalingFilter::Default => {
+                        for ch in 0..3 {
+                            let top = lerp(
+                                pixel(xs.first, ys.first)[ch],
+                                pixel(xs.second, ys.first)[ch],
+                                xs.frac,
+                            );
+                            let bottom = lerp(
+                                pixel(xs.first, ys.second)[ch],
+                                pixel(xs.second, ys.second)[ch],
+                                xs.frac,
+                            );
+
+                            content.push(lerp(top, bottom, ys.frac));
+                        }
+                    }
+                }
+            }
+        }
+
+        Some(content)
+    }
+
+    /// Returns whether the plane is blended with the planes below it.
+    #[must_use]
+    pub fn is_translucent(&self) -> bool {
+        self.alpha != PLANE_ALPHA_OPAQUE
+    }
+}
+
+// Position, in the source, of a sample used to compute a destination pixel. The sample lies
+// between the first and second source pixels, frac being the position between them, in 1/256th.
+struct SamplePosition {
+    first: u64,
+    second: u64,
+    frac: u64,
+}
+
+impl SamplePosition {
+    fn nearest(&self) -> u64 {
+        if self.frac >= 128 {
+            self.second
+        } else {
+            self.first
+        }
+    }
+}
+
+// Computes the source position of the center of the destination pixel, the way most scalers do.
+fn sample_position(dst: u64, src_offset: u32, src_len: u32, dst_len: u32) -> SamplePosition {
+    let src_len = u64::from(src_len);
+    let dst_len = u64::from(dst_len.max(1));
+
+    // (dst + 0.5) * src_len / dst_len - 0.5, in 1/256th of pixels
+    let pos = ((2 * dst + 1) * src_len * 256 / (2 * dst_len)).saturating_sub(128);
+    let first = (pos >> 8).min(src_len - 1);
+
+    SamplePosition {
+        first: u64::from(src_offset) + first,
+        second: u64::from(src_offset) + (first + 1).min(src_len - 1),
+        frac: pos & 0xff,
+    }
+}
+
+fn lerp(a: u8, b: u8, frac: u64) -> u8 {
+    let val = (u64::from(a) * (256 - frac) + u64::from(b) * frac + 128) >> 8;
+
+    u8::try_from(val).expect("Interpolating two u8 results in a u8")
+}
+
+fn blend(src: u8, dst: u8, alpha: u16) -> u8 {
+    let alpha = u32::from(alpha);
+    let max = u32::from(PLANE_ALPHA_OPAQUE);
+    let val = (u32::from(src) * alpha + u32::from(dst) * (max - alpha) + max / 2) / max;
+
+    u8::try_from(val).expect("Blending two u8 results in a u8")
+}
+
+// Composes the planes on top of the given frame, in increasing zpos order. Returns None if one of
+// the planes is empty or doesn't fit in the frame, or if its pattern can't be generated.
+pub(crate) fn compose_planes(raster: &mut Raster<Rgb8>, planes: &[PlaneDescription]) -> Option<()> {
+    let frame_width = raster.width() as usize;
+
+    let mut sorted = planes.to_vec();
+    sorted.sort_by_key(|plane| plane.zpos);
+
+    for plane in sorted {
+        // The planes come from the frame metadata, so we can't trust them.
+        if plane.width == 0
+            || plane.height == 0
+            || plane.x.checked_add(plane.width)? > raster.width()
+            || plane.y.checked_add(plane.height)? > raster.height()
+        {
+            return None;
+        }
+
+        let width = plane.width as usize;
+        let content = plane.content()?;
+
+        for (row, src) in content.chunks_exact(width * 3).enumerate() {
+            let y = plane.y as usize + row;
+            let start = (y * frame_width + plane.x as usize) * 3;
+            let dst = raster.as_u8_slice_mut().get_mut(start..start + width * 3)?;
+
+            if plane.is_translucent() {
+                for (dst, src) in dst.iter_mut().zip(src) {
+                    *dst = blend(*src, *dst, plane.alpha);
+                }
+            } else {
+                dst.copy_from_slice(src);
+            }
+        }
+    }
+
+    Some(())
+}
@@ -0,0 +1,331 @@
+use core::fmt;
+
+use thiserror::Error;
+
+use crate::Pattern;
+
+/// IEEE Company ID the control payload is stored under, in the EDID vendor-specific data blocks.
+///
+/// It's a locally administered CID, so it can't clash with any registered OUI.
+pub const CONTROL_OUI: u32 = 0x00DA_D1A5;
+
+/// Version of the control payload.
+///
+/// The payload can only be extended by appending new fields, older versions of the parser ignoring
+/// them. Any other change needs a new version, that older parsers will reject.
+pub const CONTROL_VERSION: u8 = 1;
+
+const EDID_BLOCK_SIZE: usize = 128;
+const EDID_EXTENSION_COUNT_OFFSET: usize = 126;
+const EDID_CHECKSUM_OFFSET: usize = 127;
+
+const CTA_EXTENSION_TAG: u8 = 0x02;
+const CTA_DATA_BLOCKS_OFFSET: usize = 4;
+const CTA_DTD_SIZE: usize = 18;
+const CTA_VENDOR_SPECIFIC_DATA_BLOCK_TAG: u8 = 3;
+const CTA_DATA_BLOCK_MAX_LEN: u8 = 31;
+
+const FLAG_IGNORE_HOTPLUG: u8 = 1 << 0;
+const FLAG_PATTERN: u8 = 1 << 1;
+const FLAG_MODE: u8 = 1 << 2;
+
+// Version, flags, test ID, pattern kind and parameters, mode width, height and refresh rate.
+const PAYLOAD_SIZE: u8 = 1 + 1 + 4 + 1 + 8 + 2 + 2 + 1;
+
+// The data block payload is made of the OUI, followed by our payload.
+const DATA_BLOCK_PAYLOAD_SIZE: u8 = 3 + PAYLOAD_SIZE;
+
+const _: () = assert!(
+    DATA_BLOCK_PAYLOAD_SIZE <= CTA_DATA_BLOCK_MAX_LEN,
+    "Our payload must fit in a data block"
+);
+
+/// Our Control Payload Error Type.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ControlError {
+    /// The payload version isn't supported.
+    #[error("Unsupported Control Payload Version {0}.")]
+    UnsupportedVersion(u8),
+
+    /// The payload is shorter than what its version requires.
+    #[error("Control Payload is Truncated.")]
+    Truncated,
+
+    /// The payload pattern is unknown.
+    #[error("Unknown Control Payload Pattern {0}.")]
+    UnknownPattern(u8),
+
+    /// The EDID doesn't have a CTA-861 extension to store the payload into.
+    #[error("EDID doesn't have a CTA-861 Extension.")]
+    MissingExtension,
+
+    /// The CTA-861 extension doesn't have enough room left for the payload.
+    #[error("EDID CTA-861 Extension is Full.")]
+    ExtensionFull,
+}
+
+/// Mode the source is asked to use.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ControlMode {
+    /// Active Width, in pixels.
+    pub width: u16,
+
+    /// Active Height, in pixels.
+    pub height: u16,
+
+    /// Refresh Rate, in Hz. Any refresh rate will do if not set.
+    pub refresh: Option<u8>,
+}
+
+impl fmt::Display for ControlMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!("{}x{}", self.width, self.height))?;
+
+        if let Some(refresh) = self.refresh {
+            f.write_fmt(format_args!("@{refresh}"))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Instructions passed by the receiving side to the source, through the EDID of the display.
+///
+/// The payload is stored in a CTA-861 vendor-specific data block, under [`CONTROL_OUI`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Control {
+    /// Identifier of the test, to be echoed back by the source in the frames metadata.
+    pub test_id: u32,
+
+    /// Should the source keep its current output when it receives a hotplug event?
+    pub ignore_hotplug: bool,
+
+    /// Pattern to display. The source picks its own if not set.
+    pub pattern: Option<Pattern>,
+
+    /// Mode to use. The source uses the preferred mode if not set.
+    pub mode: Option<ControlMode>,
+}
+
+impl fmt::Display for Control {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!(
+            "Control Version {}, Test ID {:#x}",
+            CONTROL_VERSION, self.test_id
+        ))?;
+
+        if self.ignore_hotplug {
+            f.write_str(", ignoring hotplug")?;
+        }
+
+        if let Some(pattern) = &self.pattern {
+            f.write_fmt(format_args!(", pattern {pattern}"))?;
+        }
+
+        if let Some(mode) = &self.mode {
+            f.write_fmt(format_args!(", mode {mode}"))?;
+        }
+
+        Ok(())
+    }
+}
+
+fn encode_pattern(pattern: Pattern) -> (u8, u64) {
+    match pattern {
+        Pattern::SmpteColorBars => (0, 0),
+        Pattern::HorizontalGradient => (1, 0),
+        Pattern::VerticalGradient => (2, 0),
+        Pattern::Checkerboard { size } => (3, size.into()),
+        Pattern::MovingBars { width, step } => (4, u64::from(width) | (u64::from(step) << 32)),
+        Pattern::Noise { seed } => (5, seed),
+    }
+}
+
+#[expect(
+    clippy::cast_possible_truncation,
+    reason = "The parameters are stored in the lower or upper 32 bits of the 64 bits parameter."
+)]
+fn decode_pattern(kind: u8, param: u64) -> Result<Pattern, ControlError> {
+    Ok(match kind {
+        0 => Pattern::SmpteColorBars,
+        1 => Pattern::HorizontalGradient,
+        2 => Pattern::VerticalGradient,
+        3 => Pattern::Checkerboard { size: param as u32 },
+        4 => Pattern::MovingBars {
+            width: param as u32,
+            step: (param >> 32) as u32,
+        },
+        5 => Pattern::Noise { seed: param },
+        _ => return Err(ControlError::UnknownPattern(kind)),
+    })
+}
+
+fn read_array<const N: usize>(bytes: &[u8], offset: usize) -> Result<[u8; N], ControlError> {
+    bytes
+        .get(offset..offset + N)
+        .and_then(|slice| slice.try_into().ok())
+        .ok_or(ControlError::Truncated)
+}
+
+impl Control {
+    /// Serializes the payload, without the OUI.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut flags = 0;
+        if self.ignore_hotplug {
+            flags |= FLAG_IGNORE_HOTPLUG;
+        }
+
+        if self.pattern.is_some() {
+            flags |= FLAG_PATTERN;
+        }
+
+        if self.mode.is_some() {
+            flags |= FLAG_MODE;
+        }
+
+        let (kind, param) = self.pattern.map_or((0, 0), encode_pattern);
+        let mode = self.mode.unwrap_or(ControlMode {
+            width: 0,
+            height: 0,
+            refresh: None,
+        });
+
+        let mut bytes = Vec::with_capacity(PAYLOAD_SIZE.into());
+        bytes.push(CONTROL_VERSION);
+        bytes.push(flags);
+        bytes.extend_from_slice(&self.test_id.to_le_bytes());
+        bytes.push(kind);
+        bytes.extend_from_slice(&param.to_le_bytes());
+        bytes.extend_from_slice(&mode.width.to_le_bytes());
+        bytes.extend_from_slice(&mode.height.to_le_bytes());
+        bytes.push(mode.refresh.unwrap_or(0));
+
+        bytes
+    }
+
+    /// Parses a payload, without the OUI.
+    ///
+    /// # Errors
+    ///
+    /// If the payload version isn't supported, or if the payload is malformed.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ControlError> {
+        let [version, flags] = read_array(bytes, 0)?;
+        if version != CONTROL_VERSION {
+            return Err(ControlError::UnsupportedVersion(version));
+        }
+
+        let test_id = u32::from_le_bytes(read_array(bytes, 2)?);
+        let [kind] = read_array(bytes, 6)?;
+        let param = u64::from_le_bytes(read_array(bytes, 7)?);
+        let width = u16::from_le_bytes(read_array(bytes, 15)?);
+        let height = u16::from_le_bytes(read_array(bytes, 17)?);
+        let [refresh] = read_array(bytes, 19)?;
+
+        Ok(Self {
+            test_id,
+            ignore_hotplug: flags & FLAG_IGNORE_HOTPLUG != 0,
+            pattern: if flags & FLAG_PATTERN == 0 {
+                None
+            } else {
+                Some(decode_pattern(kind, param)?)
+            },
+            mode: (flags & FLAG_MODE != 0).then_some(ControlMode {
+                width,
+                height,
+                refresh: (refresh != 0).then_some(refresh),
+            }),
+        })
+    }
+
+    /// Stores the payload in the first CTA-861 extension of the EDID, and updates its checksum.
+    ///
+    /// # Errors
+    ///
+    /// If the EDID doesn't have any CTA-861 extension, or if it's full.
+    pub fn add_to_edid(&self, edid: &mut [u8]) -> Result<(), ControlError> {
+        let block = edid
+            .chunks_exact_mut(EDID_BLOCK_SIZE)
+            .skip(1)
+            .filter_map(|block| <&mut [u8; EDID_BLOCK_SIZE]>::try_from(block).ok())
+            .find(|block| block[0] == CTA_EXTENSION_TAG)
+            .ok_or(ControlError::MissingExtension)?;
+
+        let mut data_block = Vec::with_capacity(usize::from(DATA_BLOCK_PAYLOAD_SIZE) + 1);
+        data_block.push((CTA_VENDOR_SPECIFIC_DATA_BLOCK_TAG << 5) | DATA_BLOCK_PAYLOAD_SIZE);
+        data_block.extend_from_slice(&CONTROL_OUI.to_le_bytes()[..3]);
+        data_block.extend_from_slice(&self.to_bytes());
+
+        // A DTD offset of 0 means that there's neither data blocks nor DTDs.
+        let dtd_offset = match usize::from(block[2]) {
+            0 => CTA_DATA_BLOCKS_OFFSET,
+            offset => offset,
+        };
+
+        let mut used_end = dtd_offset;
+        while used_end + CTA_DTD_SIZE <= EDID_CHECKSUM_OFFSET
+            && block[used_end..used_end + 2] != [0, 0]
+        {
+            used_end += CTA_DTD_SIZE;
+        }
+
+        if used_end + data_block.len() > EDID_CHECKSUM_OFFSET {
+            return Err(ControlError::ExtensionFull);
+        }
+
+        block.copy_within(dtd_offset..used_end, dtd_offset + data_block.len());
+        block[dtd_offset..dtd_offset + data_block.len()].copy_from_slice(&data_block);
+        block[2] = u8::try_from(dtd_offset + data_block.len())
+            .map_err(|_e| ControlError::ExtensionFull)?;
+
+        let sum = block[..EDID_CHECKSUM_OFFSET]
+            .iter()
+            .fold(0_u8, |sum, byte| sum.wrapping_add(*byte));
+        block[EDID_CHECKSUM_OFFSET] = 0_u8.wrapping_sub(sum);
+
+        Ok(())
+    }
+
+    /// Looks for a payload in the CTA-861 extensions of the EDID.
+    ///
+    /// Returns `Ok(None)` if the EDID doesn't have any.
+    ///
+    /// # Errors
+    ///
+    /// If the payload found can't be parsed.
+    pub fn from_edid(edid: &[u8]) -> Result<Option<Self>, ControlError> {
+        let extensions = edid
+            .get(EDID_EXTENSION_COUNT_OFFSET)
+            .map_or(0, |count| usize::from(*count));
+
+        for block in edid
+            .chunks_exact(EDID_BLOCK_SIZE)
+            .skip(1)
+            .take(extensions)
+            .filter_map(|block| <&[u8; EDID_BLOCK_SIZE]>::try_from(block).ok())
+            .filter(|block| block[0] == CTA_EXTENSION_TAG)
+        {
+            let data_end = usize::from(block[2]).min(EDID_CHECKSUM_OFFSET);
+            let mut offset = CTA_DATA_BLOCKS_OFFSET;
+
+            while offset < data_end {
+                let header = block.get(offset).copied().ok_or(ControlError::Truncated)?;
+                let len = usize::from(header & 0x1f);
+                let payload = block
+                    .get(offset + 1..offset + 1 + len)
+                    .ok_or(ControlError::Truncated)?;
+
+                if header >> 5 == CTA_VENDOR_SPECIFIC_DATA_BLOCK_TAG
+                    && payload.len() >= 3
+                    && payload[..3] == CONTROL_OUI.to_le_bytes()[..3]
+                {
+                    return Self::from_bytes(&payload[3..]).map(Some);
+                }
+
+                offset += len + 1;
+            }
+        }
+
+        Ok(None)
+    }
+}
@@ -0,0 +1,352 @@
+use core::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Pixel Format of the buffer the source displayed.
+///
+/// The frames are always generated and checked as RGB24, but the source might have converted
+/// them to another format before displaying them. Some of these formats can't represent RGB24
+/// exactly, so the receiving side needs to take that into account when comparing frames.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PixelFormat {
+    /// 32 bits per pixel, 8 bits per component, RGB format. Lossless.
+    Xrgb8888,
+
+    /// 16 bits per pixel, 5 bits for red and blue, 6 bits for green, RGB format.
+    Rgb565,
+
+    /// 32 bits per pixel, 10 bits per component, RGB format. Lossless.
+    Xrgb2101010,
+
+    /// 12 bits per pixel, YUV 4:2:0, with a luma plane and an interleaved chroma plane.
+    Nv12,
+
+    /// 16 bits per pixel, YUV 4:2:2, packed.
+    Yuyv,
+}
+
+/// How a frame should be compared to the frame that was expected.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Comparison {
+    /// The frames must be bit-identical.
+    Exact,
+
+    /// Each color component can differ by up to the given value.
+    Tolerance(u8),
+}
+
+impl PixelFormat {
+    /// Returns whether the format can represent an RGB24 frame without any loss.
+    #[must_use]
+    pub fn is_lossless(self) -> bool {
+        matches!(self, Self::Xrgb8888 | Self::Xrgb2101010)
+    }
+
+    /// Returns how a frame displayed with that format should be compared to the expected frame.
+    ///
+    /// For formats that can't represent RGB24 exactly, the expected frame must first go through
+    /// [`PixelFormat::round_trip`]. The YUV to RGB conversion the source performs isn't
+    /// standardized though, so we allow for small rounding differences.
+    #[must_use]
+    pub fn comparison(self) -> Comparison {
+        match self {
+            Self::Xrgb8888 | Self::Xrgb2101010 => Comparison::Exact,
+            Self::Rgb565 => Comparison::Tolerance(2),
+            Self::Nv12 | Self::Yuyv => Comparison::Tolerance(4),
+        }
+    }
+
+    /// Size, in bytes, of a frame buffer of that format.
+    #[must_use]
+    pub fn frame_size(self, width: u32, height: u32) -> usize {
+        let pixels = width as usize * height as usize;
+
+        match self {
+            Self::Xrgb8888 | Self::Xrgb2101010 => pixels * 4,
+            Self::Rgb565 | Self::Yuyv => pixels * 2,
+            Self::Nv12 => pixels * 3 / 2,
+        }
+    }
+
+    /// Size, in bytes, of a line of a frame buffer of that format, without any padding.
+    ///
+    /// For [`PixelFormat::Nv12`], both the luma and chroma planes have that line size.
+    #[must_use]
+    pub fn line_size(self, width: u32) -> usize {
+        let width = width as usize;
+
+        match self {
+            Self::Xrgb8888 | Self::Xrgb2101010 => width * 4,
+            Self::Rgb565 | Self::Yuyv => width * 2,
+            Self::Nv12 => width,
+        }
+    }
+
+    /// Converts a RGB24 frame buffer into that format. The width and height must be even.
+    ///
+    /// # Panics
+    ///
+    /// If `rgb` or `out` aren't large enough for the frame dimensions.
+    pub fn encode(self, width: u32, height: u32, rgb: &[u8], out: &mut [u8]) {
+        self.encode_with_pitch(width, height, self.line_size(width), rgb, out);
+    }
+
+    /// Converts a RGB24 frame buffer into that format, with lines `pitch` bytes apart. The width
+    /// and height must be even.
+    ///
+    /// For [`PixelFormat::Nv12`], the chroma plane starts right after the `height` lines of the
+    /// luma plane, and uses the same pitch.
+    ///
+    /// # Panics
+    ///
+    /// If `pitch` is smaller than the line size, or if `rgb` or `out` aren't large enough for the
+    /// frame dimensions.
+    pub fn encode_with_pitch(
+        self,
+        width: u32,
+        height: u32,
+        pitch: usize,
+        rgb: &[u8],
+        out: &mut [u8],
+    ) {
+        let line_size = self.line_size(width);
+        let lines = match self {
+            Self::Nv12 => height as usize * 3 / 2,
+            Self::Xrgb8888 | Self::Xrgb2101010 | Self::Rgb565 | Self::Yuyv => height as usize,
+        };
+
+        assert!(pitch >= line_size, "Pitch is smaller than a line");
+        assert!(
+            rgb.len() >= width as usize * height as usize * 3,
+            "RGB Buffer is too small"
+        );
+        assert!(
+            out.len() >= pitch * lines.saturating_sub(1) + line_size,
+            "Output Buffer is too small"
+        );
+
+        let rgb_lines = rgb.chunks_exact(width as usize * 3);
+
+        match self {
+            Self::Xrgb8888 => {
+                for (dst, src) in out.chunks_mut(pitch).zip(rgb_lines) {
+                    for (dst, src) in dst.chunks_exact_mut(4).zip(src.chunks_exact(3)) {
+                        dst.copy_from_slice(&[src[2], src[1], src[0], 0]);
+                    }
+                }
+            }
+            Self::Rgb565 => {
+                for (dst, src) in out.chunks_mut(pitch).zip(rgb_lines) {
+                    for (dst, src) in dst.chunks_exact_mut(2).zip(src.chunks_exact(3)) {
+                        let val = (u16::from(src[0] >> 3) << 11)
+                            | (u16::from(src[1] >> 2) << 5)
+                            | u16::from(src[2] >> 3);
+
+                        dst.copy_from_slice(&val.to_le_bytes());
+                    }
+                }
+            }
+            Self::Xrgb2101010 => {
+                for (dst, src) in out.chunks_mut(pitch).zip(rgb_lines) {
+                    for (dst, src) in dst.chunks_exact_mut(4).zip(src.chunks_exact(3)) {
+                        let val = (expand_to_10(src[0]) << 20)
+                            | (expand_to_10(src[1]) << 10)
+                            | expand_to_10(src[2]);
+
+                        dst.copy_from_slice(&val.to_le_bytes());
+                    }
+                }
+            }
+            Self::Yuyv => {
+                for (dst, src) in out.chunks_mut(pitch).zip(rgb_lines) {
+                    for (dst, src) in dst.chunks_exact_mut(4).zip(src.chunks_exact(6)) {
+                        let (y0, u0, v0) = rgb_to_yuv(&src[0..3]);
+                        let (y1, u1, v1) = rgb_to_yuv(&src[3..6]);
+
+                        dst.copy_from_slice(&[y0, average(&[u0, u1]), y1, average(&[v0, v1])]);
+                    }
+                }
+            }
+            Self::Nv12 => {
+                let width = width as usize;
+                let (luma, chroma) = out.split_at_mut(pitch * height as usize);
+
+                for (dst, src) in luma.chunks_mut(pitch).zip(rgb_lines) {
+                    for (dst, src) in dst.iter_mut().zip(src.chunks_exact(3)) {
+                        *dst = rgb_to_yuv(src).0;
+                    }
+                }
+
+                for (dst, rows) in chroma
+                    .chunks_mut(pitch)
+                    .zip(rgb.chunks_exact(width * 3 * 2))
+                {
+                    let (top, bottom) = rows.split_at(width * 3);
+
+                    for ((dst, top), bottom) in dst
+                        .chunks_exact_mut(2)
+                        .zip(top.chunks_exact(6))
+                        .zip(bottom.chunks_exact(6))
+                    {
+                        let (_, u0, v0) = rgb_to_yuv(&top[0..3]);
+                        let (_, u1, v1) = rgb_to_yuv(&top[3..6]);
+                        let (_, u2, v2) = rgb_to_yuv(&bottom[0..3]);
+                        let (_, u3, v3) = rgb_to_yuv(&bottom[3..6]);
+
+                        dst.copy_from_slice(&[
+                            average(&[u0, u1, u2, u3]),
+                            average(&[v0, v1, v2, v3]),
+                        ]);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Converts a frame buffer in that format into RGB24. The width and height must be even.
+    ///
+    /// # Panics
+    ///
+    /// If `bytes` isn't large enough for the frame dimensions.
+    #[must_use]
+    pub fn decode(self, width: u32, height: u32, bytes: &[u8]) -> Vec<u8> {
+        let pixels = width as usize * height as usize;
+        assert!(
+            bytes.len() >= self.frame_size(width, height),
+            "Input Buffer is too small"
+        );
+
+        let mut rgb = vec![0; pixels * 3];
+
+        match self {
+            Self::Xrgb8888 => {
+                for (dst, src) in rgb.chunks_exact_mut(3).zip(bytes.chunks_exact(4)) {
+                    dst.copy_from_slice(&[src[2], src[1], src[0]]);
+                }
+            }
+            Self::Rgb565 => {
+                for (dst, src) in rgb.chunks_exact_mut(3).zip(bytes.chunks_exact(2)) {
+                    let val = u16::from_le_bytes([src[0], src[1]]);
+                    let [r, g, b] = [(val >> 11) & 0x1f, (val >> 5) & 0x3f, val & 0x1f]
+                        .map(|c| u8::try_from(c).expect("Components are at most 6 bits wide"));
+
+                    dst.copy_from_slice(&[
+                        (r << 3) | (r >> 2),
+                        (g << 2) | (g >> 4),
+                        (b << 3) | (b >> 2),
+                    ]);
+                }
+            }
+            Self::Xrgb2101010 => {
+                for (dst, src) in rgb.chunks_exact_mut(3).zip(bytes.chunks_exact(4)) {
+                    let val = u32::from_le_bytes([src[0], src[1], src[2], src[3]]);
+
+                    dst.copy_from_slice(&[(val >> 20), (val >> 10), val].map(reduce_from_10));
+                }
+            }
+            Self::Yuyv => {
+                for (dst, src) in rgb.chunks_exact_mut(6).zip(bytes.chunks_exact(4)) {
+                    let (first, second) = dst.split_at_mut(3);
+
+                    first.copy_from_slice(&yuv_to_rgb(src[0], src[1], src[3]));
+                    second.copy_from_slice(&yuv_to_rgb(src[2], src[1], src[3]));
+                }
+            }
+            Self::Nv12 => {
+                let width = width as usize;
+                let (luma, chroma) = bytes.split_at(pixels);
+
+                for (row, (dst, luma)) in rgb
+                    .chunks_exact_mut(width * 3)
+                    .zip(luma.chunks_exact(width))
+                    .enumerate()
+                {
+                    let chroma = &chroma[(row / 2) * width..];
+
+                    for ((dst, y), uv) in dst
+                        .chunks_exact_mut(6)
+                        .zip(luma.chunks_exact(2))
+                        .zip(chroma.chunks_exact(2))
+                    {
+                        let (first, second) = dst.split_at_mut(3);
+
+                        first.copy_from_slice(&yuv_to_rgb(y[0], uv[0], uv[1]));
+                        second.copy_from_slice(&yuv_to_rgb(y[1], uv[0], uv[1]));
+                    }
+                }
+            }
+        }
+
+        rgb
+    }
+
+    /// Converts a RGB24 frame buffer into that format, and back into RGB24.
+    ///
+    /// This is what an ideal source would output when displaying a frame converted to that
+    /// format.
+    #[must_use]
+    pub fn round_trip(self, width: u32, height: u32, rgb: &[u8]) -> Vec<u8> {
+        if self == Self::Xrgb8888 {
+            return rgb.to_vec();
+        }
+
+        let mut converted = vec![0; self.frame_size(width, height)];
+        self.encode(width, height, rgb, &mut converted);
+        self.decode(width, height, &converted)
+    }
+}
+
+impl fmt::Display for PixelFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Xrgb8888 => "XRGB8888",
+            Self::Rgb565 => "RGB565",
+            Self::Xrgb2101010 => "XRGB2101010",
+            Self::Nv12 => "NV12",
+            Self::Yuyv => "YUYV",
+        })
+    }
+}
+
+fn expand_to_10(val: u8) -> u32 {
+    (u32::from(val) << 2) | (u32::from(val) >> 6)
+}
+
+fn reduce_from_10(val: u32) -> u8 {
+    u8::try_from((val & 0x3ff) >> 2).expect("Value is 8 bits wide")
+}
+
+fn clamp_to_u8(val: i32) -> u8 {
+    u8::try_from(val.clamp(0, 255)).expect("Value was clamped")
+}
+
+fn average(vals: &[u8]) -> u8 {
+    let count = u32::try_from(vals.len()).expect("We only average a handful of values");
+    let sum: u32 = vals.iter().copied().map(u32::from).sum();
+
+    u8::try_from((sum + count / 2) / count).expect("The average of u8 fits in a u8")
+}
+
+// BT.601, limited range, conversion.
+fn rgb_to_yuv(rgb: &[u8]) -> (u8, u8, u8) {
+    let [r, g, b] = [rgb[0], rgb[1], rgb[2]].map(i32::from);
+
+    (
+        clamp_to_u8(((66 * r + 129 * g + 25 * b + 128) >> 8) + 16),
+        clamp_to_u8(((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128),
+        clamp_to_u8(((112 * r - 94 * g - 18 * b + 128) >> 8) + 128),
+    )
+}
+
+fn yuv_to_rgb(y: u8, u: u8, v: u8) -> [u8; 3] {
+    let c = i32::from(y) - 16;
+    let d = i32::from(u) - 128;
+    let e = i32::from(v) - 128;
+
+    [
+        clamp_to_u8((298 * c + 409 * e + 128) >> 8),
+        clamp_to_u8((298 * c - 100 * d - 208 * e + 128) >> 8),
+        clamp_to_u8((298 * c + 516 * d + 128) >> 8),
+    ]
+}
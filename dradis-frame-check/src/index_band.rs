@@ -0,0 +1,145 @@
+use core::hash::Hasher as _;
+
+use pix::{Raster, el::Pixel as _, rgb::Rgb8};
+use twox_hash::XxHash64;
+
+use crate::{ClearedFrame, FramePixel};
+
+/// Height of the Frame Index Band, in pixels.
+///
+/// The band spans the whole width of the frame, at its bottom, and encodes the lower bits of the
+/// frame index as a row of black (0) and white (1) cells, least significant bit first.
+pub const INDEX_BAND_HEIGHT: u32 = 16;
+
+const INDEX_BAND_BITS: u32 = 32;
+
+#[expect(
+    clippy::cast_possible_truncation,
+    reason = "We only encode the lower bits of the index."
+)]
+pub(crate) fn index_bits(index: usize) -> u32 {
+    index as u32
+}
+
+fn cell_width(width: u32) -> u32 {
+    (width / INDEX_BAND_BITS).max(1)
+}
+
+fn band_top(height: u32) -> u32 {
+    height.saturating_sub(INDEX_BAND_HEIGHT)
+}
+
+// Returns whether the pixel at the given column of the band is lit.
+fn is_lit(x: u32, width: u32, bits: u32) -> bool {
+    let bit = x / cell_width(width);
+
+    bit < INDEX_BAND_BITS && (bits >> bit) & 1 == 1
+}
+
+pub(crate) fn draw_index_band<P>(raster: &mut Raster<P>, index: usize)
+where
+    P: FramePixel,
+{
+    let width = raster.width();
+    let top = band_top(raster.height());
+    let bits = index_bits(index);
+
+    let white: P = Rgb8::new(255, 255, 255).convert();
+    let black: P = Rgb8::new(0, 0, 0).convert();
+    let row: Vec<P> = (0..width)
+        .map(|x| if is_lit(x, width, bits) { white } else { black })
+        .collect();
+
+    for line in raster
+        .pixels_mut()
+        .chunks_exact_mut(width as usize)
+        .skip(top as usize)
+    {
+        line.copy_from_slice(&row);
+    }
+}
+
+// Decodes the index band of a RGB24 frame buffer, sampling the center of each cell.
+pub(crate) fn decode_index_band(bytes: &[u8], width: u32, height: u32) -> Option<u32> {
+    let cell = cell_width(width);
+    let y = band_top(height) + INDEX_BAND_HEIGHT / 2;
+    if y >= height || cell * INDEX_BAND_BITS > width {
+        return None;
+    }
+
+    let mut bits = 0;
+    for bit in 0..INDEX_BAND_BITS {
+        let x = bit * cell + cell / 2;
+        let offset = (y as usize * width as usize + x as usize) * 3;
+        let pixel = bytes.get(offset..offset + 3)?;
+
+        let sum: u32 = pixel.iter().copied().map(u32::from).sum();
+        if sum > 3 * 127 {
+            bits |= 1 << bit;
+        }
+    }
+
+    Some(bits)
+}
+
+/// Computes the checksum of a [`ClearedFrame`] with a Frame Index Band drawn into it.
+///
+/// Only the band changes from one frame to the next, so the rest of the frame is only hashed
+/// once, and each checksum only needs to hash the band itself.
+#[derive(Clone)]
+pub struct IndexedChecksum {
+    prefix: XxHash64,
+    width: u32,
+}
+
+impl IndexedChecksum {
+    /// Creates a new [`IndexedChecksum`] from a [`ClearedFrame`]. The content of the frame
+    /// in the band area is ignored.
+    ///
+    /// Returns `None` if the frame isn't large enough to hold the band.
+    #[must_use]
+    pub fn new(frame: &ClearedFrame<Rgb8>) -> Option<Self> {
+        let width = frame.0.0.width();
+        let height = frame.0.0.height();
+        if width == 0 || height < INDEX_BAND_HEIGHT {
+            return None;
+        }
+
+        let prefix_len = band_top(height) as usize * width as usize * 3;
+
+        let mut prefix = XxHash64::with_seed(0);
+        prefix.write(frame.as_bytes().get(..prefix_len)?);
+
+        Some(Self { prefix, width })
+    }
+
+    /// Computes the checksum of the frame, with the band encoding the given index.
+    #[must_use]
+    pub fn checksum(&self, index: usize) -> u64 {
+        let bits = index_bits(index);
+        let row: Vec<u8> = (0..self.width)
+            .flat_map(|x| {
+                if is_lit(x, self.width, bits) {
+                    [255; 3]
+                } else {
+                    [0; 3]
+                }
+            })
+            .collect();
+
+        let mut hasher = self.prefix.clone();
+        for _ in 0..INDEX_BAND_HEIGHT {
+            hasher.write(&row);
+        }
+
+        hasher.finish()
+    }
+}
+
+impl core::fmt::Debug for IndexedChecksum {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("IndexedChecksum")
+            .field("width", &self.width)
+            .finish_non_exhaustive()
+    }
+}
@@ -28,8 +28,40 @@ use threads_pool::ThreadPool;
 use tracing::{debug, error, trace_span, warn};
 use twox_hash::XxHash64;
 
+mod compose;
+pub use crate::compose::{PLANE_ALPHA_OPAQUE, PlaneDescription, PlaneSource, ScalingFilter};
+
+mod control;
+pub use crate::control::{CONTROL_OUI, CONTROL_VERSION, Control, ControlError, ControlMode};
+
+mod format;
+pub use crate::format::{Comparison, PixelFormat};
+
+mod index_band;
+pub use crate::index_band::{INDEX_BAND_HEIGHT, IndexedChecksum};
+
+mod output;
+pub use crate::output::{
+    BroadcastRgb, Colorspace, ContentType, Eotf, HdrMetadata, OutputProperties,
+};
+
+mod pattern;
+pub use crate::pattern::Pattern;
+
+mod timing;
+pub use crate::timing::{FrameTiming, SourceMode};
+
 const HEADER_VERSION_MAJOR: u8 = 2;
 
+// Scalers implementations vary a lot, so we can only expect the scaled planes to look alike.
+const SCALED_PLANE_TOLERANCE: u8 = 24;
+
+// Display controllers don't all round the same way when blending planes.
+const BLENDED_PLANE_TOLERANCE: u8 = 2;
+
+// Going through the limited quantization range and back loses up to one code value.
+const LIMITED_RANGE_TOLERANCE: u8 = 1;
+
 /// Width of the QR Code Area, in pixels.
 pub const QRCODE_WIDTH: u32 = 128;
 
@@ -47,6 +79,20 @@ pub enum FrameError {
     /// The frame metadata couldn't be decoded.
     #[error("Frame Header is Invalid.")]
     InvalidFrame,
+
+    /// The frame content doesn't match the frame index found in the metadata, ie. part of the
+    /// frame comes from an older frame.
+    #[error("Frame Content is Stale.")]
+    StaleContent,
+
+    /// The frame is valid, but was emitted for another test, or with another mode.
+    #[error("Frame Belongs to Another Test.")]
+    TestMismatch,
+
+    /// The frame is valid, but was emitted by another instance of the source than the one we
+    /// expected. Contains the instance that emitted it, if known.
+    #[error("Frame Belongs to Another Session.")]
+    SessionMismatch(Option<u64>),
 }
 
 /// Frame Metadata
@@ -74,6 +120,44 @@ pub struct Metadata {
 
     /// Frame index. Ever increasing.
     pub index: usize,
+
+    /// Test Pattern displayed in the frame, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<Pattern>,
+
+    /// Is the frame index encoded at the bottom of the frame?
+    ///
+    /// See [`INDEX_BAND_HEIGHT`].
+    #[serde(default, skip_serializing_if = "core::ops::Not::not")]
+    pub index_band: bool,
+
+    /// Pixel Format the frame was displayed with by the source, if known. RGB24 otherwise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<PixelFormat>,
+
+    /// Planes displayed on top of the primary plane, if any.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub planes: Vec<PlaneDescription>,
+
+    /// Connector properties the source set up, if any.
+    #[serde(default, skip_serializing_if = "OutputProperties::is_default")]
+    pub output: OutputProperties,
+
+    /// When the source expects the frame to be displayed, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timing: Option<FrameTiming>,
+
+    /// Identifier of the test the source was running when it emitted the frame, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub test_id: Option<u32>,
+
+    /// Identifier of the source instance that emitted the frame, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<u64>,
+
+    /// Display mode the source used, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<SourceMode>,
 }
 
 impl fmt::Display for Metadata {
@@ -88,7 +172,45 @@ impl fmt::Display for Metadata {
             self.qrcode_height,
             self.index,
             self.hash,
-        ))
+        ))?;
+
+        if let Some(pattern) = &self.pattern {
+            f.write_fmt(format_args!(", pattern {pattern}"))?;
+        }
+
+        if self.index_band {
+            f.write_str(", with index band")?;
+        }
+
+        if let Some(format) = &self.format {
+            f.write_fmt(format_args!(", format {format}"))?;
+        }
+
+        if !self.planes.is_empty() {
+            f.write_fmt(format_args!(", {} additional planes", self.planes.len()))?;
+        }
+
+        if !self.output.is_default() {
+            f.write_fmt(format_args!(", output {}", self.output))?;
+        }
+
+        if let Some(timing) = &self.timing {
+            f.write_fmt(format_args!(", expected at {timing}"))?;
+        }
+
+        if let Some(test_id) = self.test_id {
+            f.write_fmt(format_args!(", test ID {test_id:#x}"))?;
+        }
+
+        if let Some(session) = self.session {
+            f.write_fmt(format_args!(", session {session:#x}"))?;
+        }
+
+        if let Some(mode) = &self.mode {
+            f.write_fmt(format_args!(", mode {mode}"))?;
+        }
+
+        Ok(())
     }
 }
 
@@ -352,6 +474,13 @@ where
 
         QRCodeFrame(FrameInner(merged))
     }
+
+    /// Draws the Frame Index Band for the given index at the bottom of the [`ClearedFrame`]
+    ///
+    /// See [`INDEX_BAND_HEIGHT`].
+    pub fn draw_index_band(&mut self, index: usize) {
+        index_band::draw_index_band(&mut self.0.0, index);
+    }
 }
 
 impl ClearedFrame<Rgb8> {
@@ -362,6 +491,108 @@ impl ClearedFrame<Rgb8> {
         hasher.write(self.0.0.as_u8_slice());
         hasher.finish()
     }
+
+    /// Converts the [`ClearedFrame`] to the given [`PixelFormat`] and back.
+    ///
+    /// See [`PixelFormat::round_trip`].
+    #[must_use]
+    pub fn round_trip(&self, format: PixelFormat) -> Self {
+        let width = self.0.0.width();
+        let height = self.0.0.height();
+
+        Self(FrameInner::from_raw_bytes(
+            width,
+            height,
+            &format.round_trip(width, height, self.as_bytes()),
+        ))
+    }
+
+    /// Compresses the [`ClearedFrame`] into the limited quantization range, and expands it back,
+    /// the way a receiver would do with a limited range output.
+    #[must_use]
+    pub fn limited_range_round_trip(&self) -> Self {
+        let width = self.0.0.width();
+        let height = self.0.0.height();
+
+        let mut bytes = self.as_bytes().to_vec();
+        output::limited_range_round_trip_buffer(&mut bytes);
+
+        Self(FrameInner::from_raw_bytes(width, height, &bytes))
+    }
+
+    /// Composes the given planes on top of the [`ClearedFrame`], the way a display controller
+    /// would.
+    ///
+    /// Returns `None` if the content of one of the planes can't be generated, or if a plane
+    /// doesn't fit in the frame.
+    #[must_use]
+    pub fn compose(mut self, planes: &[PlaneDescription]) -> Option<Self> {
+        compose::compose_planes(&mut self.0.0, planes)?;
+
+        Some(self)
+    }
+
+    /// Compares two [`ClearedFrame`] pixel by pixel.
+    ///
+    /// A pixel is considered different if any of its color components differs by more than
+    /// `tolerance`.
+    #[must_use]
+    pub fn difference(&self, other: &Self, tolerance: u8) -> FrameDifference {
+        self.difference_with(other, |_x, _y| tolerance)
+    }
+
+    /// Compares two [`ClearedFrame`] pixel by pixel, with a tolerance depending on the pixel.
+    ///
+    /// A pixel is considered different if any of its color components differs by more than the
+    /// tolerance `tolerance` returns for its coordinates.
+    #[must_use]
+    pub fn difference_with<F>(&self, other: &Self, tolerance: F) -> FrameDifference
+    where
+        F: Fn(u32, u32) -> u8,
+    {
+        let width = self.0.0.width();
+        let mut diff = FrameDifference::default();
+
+        for (idx, (a, b)) in self
+            .0
+            .as_bytes()
+            .chunks_exact(3)
+            .zip(other.0.as_bytes().chunks_exact(3))
+            .enumerate()
+        {
+            let delta = a
+                .iter()
+                .zip(b.iter())
+                .map(|(ch_a, ch_b)| ch_a.abs_diff(*ch_b))
+                .max()
+                .unwrap_or_default();
+
+            diff.max_delta = diff.max_delta.max(delta);
+
+            let idx = u32::try_from(idx).expect("Raster dimensions are stored as u32");
+            let (x, y) = (idx % width, idx / width);
+
+            if delta > tolerance(x, y) {
+                diff.pixels += 1;
+                diff.first.get_or_insert((x, y));
+            }
+        }
+
+        diff
+    }
+}
+
+/// The result of a pixel by pixel comparison of two frames.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameDifference {
+    /// Number of pixels that differ by more than the tolerance.
+    pub pixels: usize,
+
+    /// Largest difference found on a color component, across all pixels.
+    pub max_delta: u8,
+
+    /// Coordinates of the first pixel that differs by more than the tolerance, if any.
+    pub first: Option<(u32, u32)>,
 }
 
 impl<P> Deref for ClearedFrame<P>
@@ -435,6 +666,121 @@ pub struct DecodeCheckArgs {
 
     /// Frame Dump options.
     pub dump: DecodeCheckArgsDump,
+
+    /// Test Identifier the frame must have been emitted for, if any.
+    pub expected_test_id: Option<u32>,
+
+    /// Display mode the frame must have been emitted with, if any. The frames that don't report
+    /// their mode are accepted.
+    pub expected_mode: Option<SourceMode>,
+
+    /// Source instance the frame must have been emitted by, if any. Usually the one of the
+    /// previous valid frames.
+    pub expected_session: Option<u64>,
+}
+
+// Combines the tolerances of several sources of differences.
+fn combine_comparisons<I>(comparisons: I) -> Comparison
+where
+    I: IntoIterator<Item = Comparison>,
+{
+    comparisons
+        .into_iter()
+        .fold(Comparison::Exact, |acc, comparison| {
+            match (acc, comparison) {
+                (Comparison::Exact, Comparison::Exact) => Comparison::Exact,
+                (Comparison::Tolerance(tolerance), Comparison::Exact)
+                | (Comparison::Exact, Comparison::Tolerance(tolerance)) => {
+                    Comparison::Tolerance(tolerance)
+                }
+                (Comparison::Tolerance(a), Comparison::Tolerance(b)) => {
+                    Comparison::Tolerance(a.saturating_add(b))
+                }
+            }
+        })
+}
+
+// How the frame should be compared to the frame we expect, depending on the format, planes and
+// output properties the source used.
+//
+// The format and output properties affect the whole frame, but the planes only affect the area
+// they cover.
+struct FrameTolerance<'a> {
+    frame: Comparison,
+    planes: Vec<(&'a PlaneDescription, Comparison)>,
+}
+
+impl<'a> FrameTolerance<'a> {
+    fn new(metadata: &'a Metadata) -> Self {
+        let format = metadata
+            .format
+            .map_or(Comparison::Exact, PixelFormat::comparison);
+
+        let output = if metadata.output.is_limited_range() {
+            Comparison::Tolerance(LIMITED_RANGE_TOLERANCE)
+        } else {
+            Comparison::Exact
+        };
+
+        let planes = metadata
+            .planes
+            .iter()
+            .filter_map(|plane| {
+                let comparison = if plane.is_scaled() {
+                    Comparison::Tolerance(SCALED_PLANE_TOLERANCE)
+                } else if plane.is_translucent() {
+                    Comparison::Tolerance(BLENDED_PLANE_TOLERANCE)
+                } else {
+                    return None;
+                };
+
+                Some((plane, comparison))
+            })
+            .collect();
+
+        Self {
+            frame: combine_comparisons([format, output]),
+            planes,
+        }
+    }
+
+    fn is_exact(&self) -> bool {
+        self.frame == Comparison::Exact && self.planes.is_empty()
+    }
+
+    // Returns by how much each color component of the given pixel can differ.
+    fn at(&self, x: u32, y: u32) -> u8 {
+        let planes = self
+            .planes
+            .iter()
+            .filter(|(plane, _)| plane.contains(x, y))
+            .map(|(_, comparison)| *comparison);
+
+        match combine_comparisons(core::iter::once(self.frame).chain(planes)) {
+            Comparison::Exact => 0,
+            Comparison::Tolerance(tolerance) => tolerance,
+        }
+    }
+}
+
+// Renders the frame the source should have emitted, from its metadata, if the pattern can be
+// rendered.
+fn expected_frame(metadata: &Metadata) -> Option<ClearedFrame<Rgb8>> {
+    let frame = metadata
+        .pattern?
+        .render(metadata.width, metadata.height, metadata.index)?;
+    let mut cleared = frame.clear();
+
+    if metadata.index_band {
+        cleared.draw_index_band(metadata.index);
+    }
+
+    let converted = match metadata.format {
+        Some(format) if !format.is_lossless() => cleared.round_trip(format),
+        _ => cleared,
+    };
+
+    converted.compose(&metadata.planes)
 }
 
 /// Decodes a raw frame buffer and checks whether the frame is valid or not.
@@ -444,6 +790,7 @@ pub struct DecodeCheckArgs {
 /// - Its version must match our current version expectations
 /// - Its index must be in sequence compared to the previous frame, if any.
 /// - Its hash must be identical.
+/// - If it has a Frame Index Band, the band must match its index.
 ///
 /// # Errors
 ///
@@ -493,6 +840,37 @@ pub fn decode_and_check_frame(data: &[u8], args: DecodeCheckArgs) -> Result<Meta
 
     debug!("Frame {}: Found Metadata {metadata}", metadata.index);
 
+    if let Some(expected) = args.expected_test_id {
+        if metadata.test_id != Some(expected) {
+            warn!(
+                "Frame {}: Frame wasn't emitted for test {expected:#x}",
+                metadata.index
+            );
+            return Err(FrameError::TestMismatch);
+        }
+    }
+
+    // The sources older than 2.9 don't report their mode, so we can only check the others.
+    if let (Some(expected), Some(mode)) = (args.expected_mode, metadata.mode) {
+        if !mode.matches(&expected) {
+            warn!(
+                "Frame {}: Frame wasn't emitted with mode {expected}",
+                metadata.index
+            );
+            return Err(FrameError::TestMismatch);
+        }
+    }
+
+    if let Some(expected) = args.expected_session {
+        if metadata.session != Some(expected) {
+            warn!(
+                "Frame {}: Frame wasn't emitted by session {expected:#x}",
+                metadata.index
+            );
+            return Err(FrameError::SessionMismatch(metadata.session));
+        }
+    }
+
     if let Some(last_index) = last_frame_index {
         let index = metadata.index;
 
@@ -509,33 +887,109 @@ pub fn decode_and_check_frame(data: &[u8], args: DecodeCheckArgs) -> Result<Meta
     let cleared = image.cleared_frame_with_metadata(&metadata);
     let hash = trace_span!("Checksum Computation").in_scope(|| cleared.compute_checksum());
 
-    if hash != metadata.hash {
-        warn!(
-            "Frame {}: Hash mismatch: {:#x} vs expected {:#x}",
-            metadata.index, hash, metadata.hash
-        );
+    if hash == metadata.hash {
+        return Ok(metadata);
+    }
 
-        if let DecodeCheckArgsDump::Corrupted(pool) = &args.dump {
-            let thread_image = image.clone();
+    // Rendering the expected frame is expensive, so we only do it once we know the frame doesn't
+    // match the hash the source computed, to check whether it's within tolerance and report
+    // where it differs.
+    let expected = trace_span!("Pattern Generation").in_scope(|| expected_frame(&metadata));
 
-            pool.borrow_mut().spawn_and_queue(move || {
-                if let Err(e) = thread_image
-                    .write_to_png(format!("dumped-buffer-broken-{}.png", metadata.index))
-                {
-                    error!("Error writing file: {e}");
-                }
+    if let Some(expected) = &expected {
+        let expected_hash = expected.compute_checksum();
 
-                if let Err(e) = thread_image.write_to_raw(format!(
-                    "dumped-buffer-broken-{}.rgb888.raw",
-                    metadata.index
-                )) {
-                    error!("Error writing file: {e}");
-                }
-            });
+        if expected_hash != metadata.hash {
+            warn!(
+                "Frame {}: Metadata hash {:#x} doesn't match the pattern hash {:#x}",
+                metadata.index, metadata.hash, expected_hash
+            );
+            return Err(FrameError::IntegrityFailure);
+        }
+    }
+
+    // The metadata hash is computed by the source, before its output goes through the limited
+    // range. The receiver expands it back, so we need to expect the rounding it introduces.
+    let expected = if metadata.output.is_limited_range() {
+        expected.map(|frame| frame.limited_range_round_trip())
+    } else {
+        expected
+    };
+
+    let tolerance = FrameTolerance::new(&metadata);
+
+    if let Some(expected) = expected.as_ref().filter(|_| !tolerance.is_exact()) {
+        let diff = trace_span!("Frame Comparison")
+            .in_scope(|| cleared.difference_with(expected, |x, y| tolerance.at(x, y)));
+
+        if diff.pixels == 0 {
+            debug!(
+                "Frame {}: Hash mismatch, but within tolerance (max delta {}).",
+                metadata.index, diff.max_delta
+            );
+
+            return Ok(metadata);
         }
+    }
 
-        return Err(FrameError::IntegrityFailure);
+    warn!(
+        "Frame {}: Hash mismatch: {:#x} vs expected {:#x}",
+        metadata.index, hash, metadata.hash
+    );
+
+    if let Some(expected) = &expected {
+        let diff = cleared.difference(expected, 0);
+
+        if let Some((x, y)) = diff.first {
+            warn!(
+                "Frame {}: {} pixels differ from the pattern, first at {}x{}, max delta {}",
+                metadata.index, diff.pixels, x, y, diff.max_delta
+            );
+
+            if let Some(plane) = metadata
+                .planes
+                .iter()
+                .filter(|plane| plane.contains(x, y))
+                .max_by_key(|plane| plane.zpos)
+            {
+                warn!(
+                    "Frame {}: First difference is on the plane at {}x{}, zpos {}",
+                    metadata.index, plane.x, plane.y, plane.zpos
+                );
+            }
+        }
+    }
+
+    let stale = metadata.index_band
+        && index_band::decode_index_band(cleared.as_bytes(), args.width, args.height)
+            .is_some_and(|bits| bits != index_band::index_bits(metadata.index));
+
+    if let DecodeCheckArgsDump::Corrupted(pool) = &args.dump {
+        let thread_image = image.clone();
+
+        pool.borrow_mut().spawn_and_queue(move || {
+            if let Err(e) =
+                thread_image.write_to_png(format!("dumped-buffer-broken-{}.png", metadata.index))
+            {
+                error!("Error writing file: {e}");
+            }
+
+            if let Err(e) = thread_image.write_to_raw(format!(
+                "dumped-buffer-broken-{}.rgb888.raw",
+                metadata.index
+            )) {
+                error!("Error writing file: {e}");
+            }
+        });
+    }
+
+    if stale {
+        warn!(
+            "Frame {}: Frame content doesn't match its index.",
+            metadata.index
+        );
+        return Err(FrameError::StaleContent);
     }
 
-    Ok(metadata)
+    Err(FrameError::IntegrityFailure)
 }
@@ -0,0 +1,268 @@
+use core::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Quantization range of the RGB output, following the KMS `Broadcast RGB` property.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BroadcastRgb {
+    /// The driver picks the range depending on the mode.
+    Automatic,
+
+    /// Full Range, 0 to 255.
+    Full,
+
+    /// Limited Range, 16 to 235.
+    Limited,
+}
+
+impl fmt::Display for BroadcastRgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Automatic => "Automatic",
+            Self::Full => "Full",
+            Self::Limited => "Limited 16:235",
+        })
+    }
+}
+
+/// Colorimetry of the output, following the KMS `Colorspace` property.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Colorspace {
+    /// Driver default colorimetry.
+    Default,
+
+    /// ITU-R BT.601 / SMPTE 170M, YCbCr.
+    Smpte170mYcc,
+
+    /// ITU-R BT.709, YCbCr.
+    Bt709Ycc,
+
+    /// xvYCC 601.
+    Xvycc601,
+
+    /// xvYCC 709.
+    Xvycc709,
+
+    /// sYCC 601.
+    Sycc601,
+
+    /// opYCC 601.
+    Opycc601,
+
+    /// opRGB.
+    Oprgb,
+
+    /// ITU-R BT.2020, Constant Luminance YCbCr.
+    Bt2020Cycc,
+
+    /// ITU-R BT.2020, RGB.
+    Bt2020Rgb,
+
+    /// ITU-R BT.2020, YCbCr.
+    Bt2020Ycc,
+
+    /// DCI-P3, D65 White Point.
+    DciP3RgbD65,
+
+    /// DCI-P3, Theater White Point.
+    DciP3RgbTheater,
+}
+
+impl fmt::Display for Colorspace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Default => "Default",
+            Self::Smpte170mYcc => "SMPTE_170M_YCC",
+            Self::Bt709Ycc => "BT709_YCC",
+            Self::Xvycc601 => "XVYCC_601",
+            Self::Xvycc709 => "XVYCC_709",
+            Self::Sycc601 => "SYCC_601",
+            Self::Opycc601 => "opYCC_601",
+            Self::Oprgb => "opRGB",
+            Self::Bt2020Cycc => "BT2020_CYCC",
+            Self::Bt2020Rgb => "BT2020_RGB",
+            Self::Bt2020Ycc => "BT2020_YCC",
+            Self::DciP3RgbD65 => "DCI-P3_RGB_D65",
+            Self::DciP3RgbTheater => "DCI-P3_RGB_Theater",
+        })
+    }
+}
+
+/// Type of content displayed, following the KMS `content type` property.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ContentType {
+    /// No content type information.
+    NoData,
+
+    /// Graphics.
+    Graphics,
+
+    /// Photo.
+    Photo,
+
+    /// Cinema.
+    Cinema,
+
+    /// Game.
+    Game,
+}
+
+impl fmt::Display for ContentType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::NoData => "No Data",
+            Self::Graphics => "Graphics",
+            Self::Photo => "Photo",
+            Self::Cinema => "Cinema",
+            Self::Game => "Game",
+        })
+    }
+}
+
+/// Electro-Optical Transfer Function, as defined by CTA-861-G.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Eotf {
+    /// Traditional Gamma, SDR Luminance Range.
+    TraditionalSdr,
+
+    /// Traditional Gamma, HDR Luminance Range.
+    TraditionalHdr,
+
+    /// SMPTE ST 2084, aka. PQ.
+    SmpteSt2084,
+
+    /// Hybrid Log-Gamma.
+    Hlg,
+}
+
+impl fmt::Display for Eotf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::TraditionalSdr => "Traditional Gamma SDR",
+            Self::TraditionalHdr => "Traditional Gamma HDR",
+            Self::SmpteSt2084 => "SMPTE ST 2084",
+            Self::Hlg => "HLG",
+        })
+    }
+}
+
+/// Static HDR Metadata (Type 1), as sent in the DRM Infoframe.
+///
+/// The chromaticity coordinates are in units of 0.00002, the mastering display maximum luminance
+/// in cd/m², and its minimum luminance in units of 0.0001 cd/m².
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct HdrMetadata {
+    /// Transfer Function.
+    pub eotf: Eotf,
+
+    /// Chromaticity coordinates of the mastering display red, green and blue primaries.
+    pub display_primaries: [(u16, u16); 3],
+
+    /// Chromaticity coordinates of the mastering display white point.
+    pub white_point: (u16, u16),
+
+    /// Maximum luminance of the mastering display.
+    pub max_display_mastering_luminance: u16,
+
+    /// Minimum luminance of the mastering display.
+    pub min_display_mastering_luminance: u16,
+
+    /// Maximum Content Light Level, in cd/m².
+    pub max_cll: u16,
+
+    /// Maximum Frame-Average Light Level, in cd/m².
+    pub max_fall: u16,
+}
+
+/// Connector properties the source set up for its output.
+///
+/// Properties that are `None` haven't been changed by the source, and are using the driver
+/// defaults.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct OutputProperties {
+    /// Quantization Range.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub broadcast_rgb: Option<BroadcastRgb>,
+
+    /// Maximum bits per color component.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_bpc: Option<u8>,
+
+    /// Colorimetry.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub colorspace: Option<Colorspace>,
+
+    /// Content Type.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<ContentType>,
+
+    /// Static HDR Metadata.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hdr_output_metadata: Option<HdrMetadata>,
+}
+
+impl OutputProperties {
+    /// Returns whether the source didn't change any property.
+    #[must_use]
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Returns whether the output explicitly uses the limited quantization range.
+    #[must_use]
+    pub fn is_limited_range(&self) -> bool {
+        self.broadcast_rgb == Some(BroadcastRgb::Limited)
+    }
+}
+
+impl fmt::Display for OutputProperties {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut sep = "";
+
+        if let Some(broadcast_rgb) = self.broadcast_rgb {
+            f.write_fmt(format_args!("Broadcast RGB {broadcast_rgb}"))?;
+            sep = ", ";
+        }
+
+        if let Some(max_bpc) = self.max_bpc {
+            f.write_fmt(format_args!("{sep}max bpc {max_bpc}"))?;
+            sep = ", ";
+        }
+
+        if let Some(colorspace) = self.colorspace {
+            f.write_fmt(format_args!("{sep}Colorspace {colorspace}"))?;
+            sep = ", ";
+        }
+
+        if let Some(content_type) = self.content_type {
+            f.write_fmt(format_args!("{sep}content type {content_type}"))?;
+            sep = ", ";
+        }
+
+        if self.hdr_output_metadata.is_some() {
+            f.write_fmt(format_args!("{sep}with HDR metadata"))?;
+        }
+
+        Ok(())
+    }
+}
+
+// Compresses a full range component into the limited range, and expands it back, the way a
+// receiver that honors the quantization range would.
+fn limited_range_round_trip(val: u8) -> u8 {
+    let limited = 16 + (u32::from(val) * 219 + 127) / 255;
+    let full = ((limited - 16) * 255 + 109) / 219;
+
+    u8::try_from(full.min(255)).expect("The value was clamped to a u8")
+}
+
+// Applies the limited range round trip to a RGB24 buffer.
+pub(crate) fn limited_range_round_trip_buffer(bytes: &mut [u8]) {
+    for val in bytes {
+        *val = limited_range_round_trip(*val);
+    }
+}
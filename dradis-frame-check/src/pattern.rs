@@ -0,0 +1,239 @@
+use core::fmt;
+use std::{io, sync::OnceLock};
+
+use pix::{Raster, rgb::Rgb8};
+use png::{Decoder, Transformations};
+use serde::{Deserialize, Serialize};
+
+use crate::Frame;
+
+// Rendered from resources/smpte-color-bars.svg.
+const SMPTE_COLOR_BARS: &[u8] = include_bytes!("../resources/smpte-color-bars.png");
+
+/// Test Pattern Description
+///
+/// The pattern, together with its parameters, is embedded in the frame [`Metadata`](crate::Metadata)
+/// so that the receiving side can generate the exact same pattern and check the frame against it.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Pattern {
+    /// SMPTE Color Bars. The pattern comes from an embedded image, scaled to the frame size with
+    /// a nearest-neighbour filter.
+    SmpteColorBars,
+
+    /// Four horizontal bands (red, green, blue, and gray) going from black on the left to full
+    /// intensity on the right. Useful to detect bit-depth truncation.
+    HorizontalGradient,
+
+    /// Four vertical bands (red, green, blue, and gray) going from black on the top to full
+    /// intensity on the bottom. Useful to detect bit-depth truncation.
+    VerticalGradient,
+
+    /// Black and white checkerboard. Useful to detect pixel shifts.
+    Checkerboard {
+        /// Size of a square, in pixels.
+        size: u32,
+    },
+
+    /// White vertical bars moving horizontally over a black background. Useful to detect tearing.
+    MovingBars {
+        /// Width of a bar, in pixels. Bars are separated by a gap of the same width.
+        width: u32,
+
+        /// Number of pixels the bars move by at each frame.
+        step: u32,
+    },
+
+    /// Pseudo-random noise. Useful to detect compression artifacts.
+    Noise {
+        /// Seed of the pseudo-random number generator.
+        seed: u64,
+    },
+}
+
+impl Pattern {
+    /// Returns whether the pattern content changes from one frame to the next.
+    #[must_use]
+    pub fn is_animated(&self) -> bool {
+        matches!(self, Self::MovingBars { .. })
+    }
+
+    /// Generates the pattern for a frame of the given size and index.
+    #[must_use]
+    pub fn render(&self, width: u32, height: u32, index: usize) -> Option<Frame> {
+        let mut raster = Raster::<Rgb8>::with_clear(width, height);
+
+        match *self {
+            Self::SmpteColorBars => {
+                let bars = smpte_color_bars();
+
+                fill(&mut raster, |x, y| {
+                    bars.pixel(
+                        nearest(x, width, bars.width()),
+                        nearest(y, height, bars.height()),
+                    )
+                });
+            }
+            Self::HorizontalGradient => {
+                fill(&mut raster, |x, y| {
+                    gradient_pixel(band(y, height), ramp(x, width))
+                });
+            }
+            Self::VerticalGradient => {
+                fill(&mut raster, |x, y| {
+                    gradient_pixel(band(x, width), ramp(y, height))
+                });
+            }
+            Self::Checkerboard { size } => {
+                let size = size.max(1);
+
+                fill(&mut raster, |x, y| {
+                    if ((x / size) + (y / size)).is_multiple_of(2) {
+                        Rgb8::new(255, 255, 255)
+                    } else {
+                        Rgb8::new(0, 0, 0)
+                    }
+                });
+            }
+            Self::MovingBars {
+                width: bar_width,
+                step,
+            } => {
+                let bar_width = u64::from(bar_width.max(1));
+                let period = bar_width * 2;
+                let offset = (index as u64).wrapping_mul(u64::from(step)) % period;
+
+                fill(&mut raster, |x, _y| {
+                    if (u64::from(x) + period - offset) % period < bar_width {
+                        Rgb8::new(255, 255, 255)
+                    } else {
+                        Rgb8::new(0, 0, 0)
+                    }
+                });
+            }
+            Self::Noise { seed } => {
+                let mut rng = XorShift64::new(seed);
+
+                fill(&mut raster, |_x, _y| {
+                    let [r, g, b, ..] = rng.next_u64().to_le_bytes();
+
+                    Rgb8::new(r, g, b)
+                });
+            }
+        }
+
+        Some(raster.into())
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::SmpteColorBars => f.write_str("SMPTE Color Bars"),
+            Self::HorizontalGradient => f.write_str("Horizontal Gradient"),
+            Self::VerticalGradient => f.write_str("Vertical Gradient"),
+            Self::Checkerboard { size } => {
+                f.write_fmt(format_args!("Checkerboard ({size} pixels)"))
+            }
+            Self::MovingBars { width, step } => f.write_fmt(format_args!(
+                "Moving Bars ({width} pixels, {step} pixels per frame)"
+            )),
+            Self::Noise { seed } => f.write_fmt(format_args!("Noise (seed {seed:#x})")),
+        }
+    }
+}
+
+fn fill<F>(raster: &mut Raster<Rgb8>, mut f: F)
+where
+    F: FnMut(u32, u32) -> Rgb8,
+{
+    let width = raster.width();
+
+    for (idx, pixel) in raster.pixels_mut().iter_mut().enumerate() {
+        let idx = u32::try_from(idx).expect("Raster dimensions are stored as u32");
+
+        *pixel = f(idx % width, idx / width);
+    }
+}
+
+// Returns the decoded SMPTE Color Bars image. Decoding it is fairly expensive, so we only do it
+// once.
+fn smpte_color_bars() -> &'static Raster<Rgb8> {
+    static BARS: OnceLock<Raster<Rgb8>> = OnceLock::new();
+
+    BARS.get_or_init(|| decode_png(SMPTE_COLOR_BARS).expect("The embedded image is a valid PNG"))
+}
+
+fn decode_png(bytes: &[u8]) -> Result<Raster<Rgb8>, png::DecodingError> {
+    let mut decoder = Decoder::new(io::Cursor::new(bytes));
+    decoder.set_transformations(Transformations::normalize_to_color8());
+
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![
+        0;
+        reader
+            .output_buffer_size()
+            .expect("The image dimensions are small enough")
+    ];
+
+    let info = reader.next_frame(&mut buffer)?;
+    let rgb = buffer[..info.buffer_size()]
+        .chunks_exact(info.color_type.samples())
+        .flat_map(|pixel| pixel.iter().copied().take(3))
+        .collect::<Vec<_>>();
+
+    Ok(Raster::with_u8_buffer(info.width, info.height, rgb))
+}
+
+// Returns the position, in a dimension of `src_len` pixels, of the pixel closest to the center of
+// the pixel at the given position in a dimension of `len` pixels.
+fn nearest(pos: u32, len: u32, src_len: u32) -> i32 {
+    i32::try_from((u64::from(pos) * 2 + 1) * u64::from(src_len) / (u64::from(len.max(1)) * 2))
+        .expect("The position is always smaller than the source length")
+}
+
+// Splits the given dimension into four equal bands, and returns the band the position is in.
+fn band(pos: u32, len: u32) -> u32 {
+    u32::try_from(u64::from(pos) * 4 / u64::from(len.max(1)))
+        .expect("The position is always smaller than the length")
+}
+
+// Returns the intensity of a 0 to 255 ramp spanning the given dimension at the given position.
+fn ramp(pos: u32, len: u32) -> u8 {
+    u8::try_from(u64::from(pos) * 256 / u64::from(len.max(1)))
+        .expect("The position is always smaller than the length")
+}
+
+fn gradient_pixel(band: u32, val: u8) -> Rgb8 {
+    match band {
+        0 => Rgb8::new(val, 0, 0),
+        1 => Rgb8::new(0, val, 0),
+        2 => Rgb8::new(0, 0, val),
+        _ => Rgb8::new(val, val, val),
+    }
+}
+
+// A xorshift64* Pseudo-Random Number Generator. It's not meant to be cryptographically secure, but
+// to be fast, and to provide the same sequence on every platform.
+struct XorShift64(u64);
+
+impl XorShift64 {
+    fn new(seed: u64) -> Self {
+        // The state must never be zero, or the generator will only ever return zeros.
+        Self(if seed == 0 {
+            0x9e37_79b9_7f4a_7c15
+        } else {
+            seed
+        })
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.0 = x;
+
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+}
@@ -0,0 +1,63 @@
+use core::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// When the source expects the frame to be displayed.
+///
+/// The source can only predict it from the previous page flips, so the values are an estimation
+/// based on the refresh rate it programmed.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct FrameTiming {
+    /// Expected presentation timestamp, in nanoseconds, from the source `CLOCK_MONOTONIC`.
+    pub presentation_ns: u64,
+
+    /// Expected vblank counter value at presentation, as counted by the source. Only the
+    /// difference between two frames is meaningful.
+    pub vblank: u64,
+}
+
+impl fmt::Display for FrameTiming {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!(
+            "vblank {} at {}.{:09}s",
+            self.vblank,
+            self.presentation_ns / 1_000_000_000,
+            self.presentation_ns % 1_000_000_000
+        ))
+    }
+}
+
+/// Display mode the source used to emit the frame.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SourceMode {
+    /// Active Width, in pixels.
+    pub width: u32,
+
+    /// Active Height, in pixels.
+    pub height: u32,
+
+    /// Refresh Rate, in Hz.
+    pub refresh: u32,
+}
+
+impl SourceMode {
+    /// Returns whether both modes are the same.
+    ///
+    /// The refresh rates are rounded from the exact timings, possibly differently on both ends, so
+    /// they are allowed to be off by one.
+    #[must_use]
+    pub fn matches(&self, other: &Self) -> bool {
+        self.width == other.width
+            && self.height == other.height
+            && self.refresh.abs_diff(other.refresh) <= 1
+    }
+}
+
+impl fmt::Display for SourceMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!(
+            "{}x{}@{}",
+            self.width, self.height, self.refresh
+        ))
+    }
+}
@@ -0,0 +1,111 @@
+#![allow(missing_docs)]
+#![allow(unused_crate_dependencies)]
+
+use dradis_frame_check::{CONTROL_VERSION, Control, ControlError, ControlMode, Pattern};
+
+const DTD: [u8; 18] = [
+    0x01, 0x1d, 0x00, 0x72, 0x51, 0xd0, 0x1e, 0x20, 0x6e, 0x28, 0x55, 0x00, 0x40, 0x84, 0x63, 0x00,
+    0x00, 0x1e,
+];
+
+fn checksum(block: &[u8]) -> u8 {
+    0_u8.wrapping_sub(
+        block[..127]
+            .iter()
+            .fold(0, |sum: u8, b| sum.wrapping_add(*b)),
+    )
+}
+
+// A base block, followed by a CTA-861 extension with a colorimetry data block and a DTD.
+fn test_edid() -> Vec<u8> {
+    let mut edid = vec![0; 256];
+    edid[126] = 1;
+    edid[127] = checksum(&edid[..128]);
+
+    let cta = &mut edid[128..];
+    cta[..4].copy_from_slice(&[0x02, 0x03, 8, 0x00]);
+    cta[4..8].copy_from_slice(&[0xe3, 0x05, 0x03, 0x00]);
+    cta[8..26].copy_from_slice(&DTD);
+    cta[127] = checksum(cta);
+
+    edid
+}
+
+fn test_control() -> Control {
+    Control {
+        test_id: 0x4242_4242,
+        ignore_hotplug: true,
+        pattern: Some(Pattern::MovingBars { width: 16, step: 4 }),
+        mode: Some(ControlMode {
+            width: 1280,
+            height: 720,
+            refresh: Some(60),
+        }),
+    }
+}
+
+#[test_log::test]
+fn test_control_round_trip() {
+    let control = test_control();
+
+    assert_eq!(Control::from_bytes(&control.to_bytes()).unwrap(), control);
+
+    let minimal = Control {
+        test_id: 1,
+        ignore_hotplug: false,
+        pattern: None,
+        mode: None,
+    };
+
+    assert_eq!(Control::from_bytes(&minimal.to_bytes()).unwrap(), minimal);
+}
+
+#[test_log::test]
+fn test_control_version() {
+    let mut bytes = test_control().to_bytes();
+    bytes[0] = CONTROL_VERSION + 1;
+
+    assert_eq!(
+        Control::from_bytes(&bytes),
+        Err(ControlError::UnsupportedVersion(CONTROL_VERSION + 1))
+    );
+
+    // Fields appended by later minor revisions are ignored.
+    let mut bytes = test_control().to_bytes();
+    bytes.extend_from_slice(&[0xff, 0xff]);
+    assert_eq!(Control::from_bytes(&bytes).unwrap(), test_control());
+
+    let bytes = test_control().to_bytes();
+    assert_eq!(
+        Control::from_bytes(&bytes[..10]),
+        Err(ControlError::Truncated)
+    );
+}
+
+#[test_log::test]
+fn test_control_edid() {
+    let mut edid = test_edid();
+    assert_eq!(Control::from_edid(&edid).unwrap(), None);
+
+    test_control().add_to_edid(&mut edid).unwrap();
+    assert_eq!(Control::from_edid(&edid).unwrap(), Some(test_control()));
+
+    let cta = &edid[128..];
+    assert_eq!(cta[127], checksum(cta));
+
+    // The existing data block is left untouched, and the DTD moved after ours.
+    assert_eq!(cta[4..8], [0xe3, 0x05, 0x03, 0x00]);
+    let dtd_offset = usize::from(cta[2]);
+    assert_eq!(cta[dtd_offset..dtd_offset + 18], DTD);
+}
+
+#[test_log::test]
+fn test_control_edid_no_extension() {
+    let mut edid = test_edid();
+    edid.truncate(128);
+
+    assert_eq!(
+        test_control().add_to_edid(&mut edid),
+        Err(ControlError::MissingExtension)
+    );
+}
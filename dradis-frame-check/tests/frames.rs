@@ -4,7 +4,9 @@
 use std::fs;
 
 use dradis_frame_check::{
-    DecodeCheckArgs, DecodeCheckArgsDump, FrameError, Metadata, QRCODE_HEIGHT, QRCODE_WIDTH,
+    BroadcastRgb, DecodeCheckArgs, DecodeCheckArgsDump, FrameError, INDEX_BAND_HEIGHT,
+    IndexedChecksum, Metadata, OutputProperties, PLANE_ALPHA_OPAQUE, Pattern, PixelFormat,
+    PlaneDescription, PlaneSource, QRCODE_HEIGHT, QRCODE_WIDTH, ScalingFilter, SourceMode,
     decode_and_check_frame,
 };
 
@@ -25,6 +27,9 @@ fn test_bgr() {
                 height: TEST_HEIGHT,
                 swap_channels: false,
                 dump: DecodeCheckArgsDump::Never,
+                expected_test_id: None,
+                expected_mode: None,
+                expected_session: None,
             },
         ),
         Err(FrameError::IntegrityFailure)
@@ -45,6 +50,9 @@ fn test_bgr_swap_channels() {
                 height: TEST_HEIGHT,
                 swap_channels: true,
                 dump: DecodeCheckArgsDump::Never,
+                expected_test_id: None,
+                expected_mode: None,
+                expected_session: None,
             },
         )
         .unwrap(),
@@ -55,7 +63,16 @@ fn test_bgr_swap_channels() {
             width: TEST_WIDTH,
             height: TEST_HEIGHT,
             hash: 0xcddbc559fb8264e6,
-            index: 39
+            index: 39,
+            pattern: None,
+            index_band: false,
+            format: None,
+            planes: Vec::new(),
+            output: OutputProperties::default(),
+            timing: None,
+            test_id: None,
+            session: None,
+            mode: None,
         }
     )
 }
@@ -74,6 +91,9 @@ fn test_rgb() {
                 height: TEST_HEIGHT,
                 swap_channels: false,
                 dump: DecodeCheckArgsDump::Never,
+                expected_test_id: None,
+                expected_mode: None,
+                expected_session: None,
             },
         )
         .unwrap(),
@@ -84,7 +104,16 @@ fn test_rgb() {
             width: TEST_WIDTH,
             height: TEST_HEIGHT,
             hash: 0xcddbc559fb8264e6,
-            index: 6
+            index: 6,
+            pattern: None,
+            index_band: false,
+            format: None,
+            planes: Vec::new(),
+            output: OutputProperties::default(),
+            timing: None,
+            test_id: None,
+            session: None,
+            mode: None,
         }
     )
 }
@@ -103,8 +132,429 @@ fn test_rgb_swap_channels() {
                 height: TEST_HEIGHT,
                 swap_channels: true,
                 dump: DecodeCheckArgsDump::Never,
+                expected_test_id: None,
+                expected_mode: None,
+                expected_session: None,
             },
         ),
         Err(FrameError::IntegrityFailure)
     )
 }
+
+// The frame was emitted without any test ID or session, so it can't belong to the ones we expect.
+// It doesn't report its mode either, but older sources don't, so that one is accepted.
+#[test_log::test]
+fn test_test_mismatch() {
+    let data = fs::read("tests/data/valid-frame-ver-2-0.rgb888.raw").unwrap();
+    let args = || DecodeCheckArgs {
+        sequence: 0,
+        previous_frame_idx: None,
+        width: TEST_WIDTH,
+        height: TEST_HEIGHT,
+        swap_channels: false,
+        dump: DecodeCheckArgsDump::Never,
+        expected_test_id: None,
+        expected_mode: None,
+        expected_session: None,
+    };
+
+    assert_eq!(
+        decode_and_check_frame(
+            &data,
+            DecodeCheckArgs {
+                expected_test_id: Some(42),
+                ..args()
+            },
+        ),
+        Err(FrameError::TestMismatch)
+    );
+
+    assert_eq!(
+        decode_and_check_frame(
+            &data,
+            DecodeCheckArgs {
+                expected_mode: Some(SourceMode {
+                    width: TEST_WIDTH,
+                    height: TEST_HEIGHT,
+                    refresh: 60,
+                }),
+                ..args()
+            },
+        )
+        .unwrap()
+        .mode,
+        None
+    );
+
+    assert_eq!(
+        decode_and_check_frame(
+            &data,
+            DecodeCheckArgs {
+                expected_session: Some(0x1234),
+                ..args()
+            },
+        ),
+        Err(FrameError::SessionMismatch(None))
+    );
+}
+
+#[test_log::test]
+fn test_source_mode_matches() {
+    let mode = SourceMode {
+        width: 1920,
+        height: 1080,
+        refresh: 60,
+    };
+
+    assert!(mode.matches(&SourceMode {
+        refresh: 59,
+        ..mode
+    }));
+    assert!(!mode.matches(&SourceMode {
+        refresh: 50,
+        ..mode
+    }));
+    assert!(!mode.matches(&SourceMode {
+        width: 1280,
+        height: 720,
+        ..mode
+    }));
+}
+
+#[test_log::test]
+fn test_pattern_render() {
+    for pattern in [
+        Pattern::SmpteColorBars,
+        Pattern::HorizontalGradient,
+        Pattern::VerticalGradient,
+        Pattern::Checkerboard { size: 1 },
+        Pattern::MovingBars { width: 64, step: 8 },
+        Pattern::Noise { seed: 42 },
+    ] {
+        let first = pattern.render(TEST_WIDTH, TEST_HEIGHT, 3).unwrap().clear();
+        let second = pattern.render(TEST_WIDTH, TEST_HEIGHT, 3).unwrap().clear();
+
+        assert_eq!(first.compute_checksum(), second.compute_checksum());
+        assert_eq!(first.difference(&second, 0).pixels, 0);
+    }
+}
+
+#[test_log::test]
+fn test_pattern_moving_bars() {
+    let pattern = Pattern::MovingBars { width: 64, step: 8 };
+
+    let first = pattern.render(TEST_WIDTH, TEST_HEIGHT, 0).unwrap().clear();
+    let second = pattern.render(TEST_WIDTH, TEST_HEIGHT, 1).unwrap().clear();
+
+    let diff = first.difference(&second, 0);
+    assert_ne!(diff.pixels, 0);
+    assert_eq!(diff.max_delta, 255);
+}
+
+#[test_log::test]
+fn test_index_band_checksum() {
+    let pattern = Pattern::Noise { seed: 42 };
+    let checksum =
+        IndexedChecksum::new(&pattern.render(TEST_WIDTH, TEST_HEIGHT, 0).unwrap().clear()).unwrap();
+
+    for index in [0, 1, 2, 0xdead_beef] {
+        let mut frame = pattern.render(TEST_WIDTH, TEST_HEIGHT, 0).unwrap().clear();
+        frame.draw_index_band(index);
+
+        assert_eq!(checksum.checksum(index), frame.compute_checksum());
+    }
+
+    assert_ne!(checksum.checksum(1), checksum.checksum(2));
+}
+
+#[test_log::test]
+fn test_index_band_checksum_too_small() {
+    let frame = Pattern::Noise { seed: 42 }
+        .render(TEST_WIDTH, INDEX_BAND_HEIGHT - 1, 0)
+        .unwrap()
+        .clear();
+
+    assert!(IndexedChecksum::new(&frame).is_none());
+}
+
+#[test_log::test]
+fn test_format_round_trip() {
+    let frame = Pattern::Checkerboard { size: 2 }
+        .render(TEST_WIDTH, TEST_HEIGHT, 0)
+        .unwrap()
+        .clear();
+
+    for format in [PixelFormat::Xrgb8888, PixelFormat::Xrgb2101010] {
+        let converted = frame.round_trip(format);
+
+        assert_eq!(frame.compute_checksum(), converted.compute_checksum());
+    }
+
+    // Our checkerboard is only made of black and white 2x2 squares, so even the lossy formats
+    // should be able to represent it exactly.
+    for format in [PixelFormat::Rgb565, PixelFormat::Nv12, PixelFormat::Yuyv] {
+        let converted = frame.round_trip(format);
+
+        assert_eq!(frame.difference(&converted, 0).pixels, 0);
+    }
+}
+
+#[test_log::test]
+fn test_format_encode_with_pitch() {
+    let frame = Pattern::Noise { seed: 42 }
+        .render(TEST_WIDTH, TEST_HEIGHT, 0)
+        .unwrap();
+
+    for format in [
+        PixelFormat::Xrgb8888,
+        PixelFormat::Rgb565,
+        PixelFormat::Xrgb2101010,
+        PixelFormat::Nv12,
+        PixelFormat::Yuyv,
+    ] {
+        let line_size = format.line_size(TEST_WIDTH);
+        let pitch = line_size + 64;
+        let lines = format.frame_size(TEST_WIDTH, TEST_HEIGHT) / line_size;
+
+        let mut packed = vec![0; format.frame_size(TEST_WIDTH, TEST_HEIGHT)];
+        format.encode(TEST_WIDTH, TEST_HEIGHT, frame.as_bytes(), &mut packed);
+
+        let mut padded = vec![0; pitch * lines];
+        format.encode_with_pitch(
+            TEST_WIDTH,
+            TEST_HEIGHT,
+            pitch,
+            frame.as_bytes(),
+            &mut padded,
+        );
+
+        for (packed, padded) in packed
+            .chunks_exact(line_size)
+            .zip(padded.chunks_exact(pitch))
+        {
+            assert_eq!(packed, &padded[..line_size]);
+        }
+    }
+}
+
+#[test_log::test]
+fn test_format_lossy_round_trip() {
+    let frame = Pattern::HorizontalGradient
+        .render(TEST_WIDTH, TEST_HEIGHT, 0)
+        .unwrap()
+        .clear();
+
+    let converted = frame.round_trip(PixelFormat::Rgb565);
+    let diff = frame.difference(&converted, 0);
+
+    assert_ne!(diff.pixels, 0);
+    assert!(diff.max_delta < 8);
+}
+
+#[test_log::test]
+fn test_difference_with_per_pixel_tolerance() {
+    let frame = Pattern::HorizontalGradient
+        .render(TEST_WIDTH, TEST_HEIGHT, 0)
+        .unwrap()
+        .clear();
+    let converted = frame.round_trip(PixelFormat::Rgb565);
+
+    let plane = PlaneDescription {
+        pattern: Pattern::HorizontalGradient,
+        x: 256,
+        y: 256,
+        width: 256,
+        height: 128,
+        alpha: PLANE_ALPHA_OPAQUE,
+        zpos: 1,
+        source: None,
+        filter: ScalingFilter::Default,
+    };
+
+    assert_eq!(frame.difference(&converted, 8).pixels, 0);
+
+    let inside = frame.difference_with(&converted, |x, y| if plane.contains(x, y) { 8 } else { 0 });
+    let (x, y) = inside.first.unwrap();
+    assert!(!plane.contains(x, y));
+
+    let outside =
+        frame.difference_with(&converted, |x, y| if plane.contains(x, y) { 0 } else { 8 });
+    let (x, y) = outside.first.unwrap();
+    assert!(plane.contains(x, y));
+    assert!(outside.pixels <= 256 * 128);
+}
+
+#[test_log::test]
+fn test_limited_range_round_trip() {
+    // The gradient goes through every code value, so some of them can't survive the round trip.
+    let frame = Pattern::HorizontalGradient
+        .render(TEST_WIDTH, TEST_HEIGHT, 0)
+        .unwrap()
+        .clear();
+
+    let converted = frame.limited_range_round_trip();
+    let diff = frame.difference(&converted, 0);
+
+    assert_ne!(diff.pixels, 0);
+    assert!(diff.max_delta <= 1);
+}
+
+#[test_log::test]
+fn test_output_properties_serialization() {
+    let output = OutputProperties {
+        broadcast_rgb: Some(BroadcastRgb::Limited),
+        max_bpc: Some(10),
+        ..Default::default()
+    };
+
+    assert!(OutputProperties::default().is_default());
+    assert!(!output.is_default());
+    assert!(output.is_limited_range());
+
+    let json = serde_json::to_string(&output).unwrap();
+    assert_eq!(json, r#"{"broadcast_rgb":"limited","max_bpc":10}"#);
+    assert_eq!(
+        serde_json::from_str::<OutputProperties>(&json).unwrap(),
+        output
+    );
+}
+
+#[test_log::test]
+fn test_compose_planes() {
+    let base = || {
+        Pattern::Checkerboard { size: 1 }
+            .render(TEST_WIDTH, TEST_HEIGHT, 0)
+            .unwrap()
+            .clear()
+    };
+
+    let plane = PlaneDescription {
+        pattern: Pattern::Noise { seed: 1 },
+        x: 256,
+        y: 256,
+        width: 64,
+        height: 32,
+        alpha: PLANE_ALPHA_OPAQUE,
+        zpos: 1,
+        source: None,
+        filter: ScalingFilter::Default,
+    };
+
+    let composed = base().compose(&[plane]).unwrap();
+    let diff = base().difference(&composed, 0);
+
+    assert_ne!(diff.pixels, 0);
+    assert!(diff.pixels <= 64 * 32);
+    assert!(plane.contains(diff.first.unwrap().0, diff.first.unwrap().1));
+
+    let transparent = base()
+        .compose(&[PlaneDescription { alpha: 0, ..plane }])
+        .unwrap();
+    assert_eq!(base().difference(&transparent, 0).pixels, 0);
+
+    let outside = PlaneDescription {
+        x: TEST_WIDTH - 32,
+        ..plane
+    };
+    assert!(base().compose(&[outside]).is_none());
+
+    let below = PlaneDescription {
+        y: TEST_HEIGHT - 16,
+        ..plane
+    };
+    assert!(base().compose(&[below]).is_none());
+
+    let empty = PlaneDescription { width: 0, ..plane };
+    assert!(base().compose(&[empty]).is_none());
+
+    let overflow = PlaneDescription {
+        x: u32::MAX,
+        ..plane
+    };
+    assert!(base().compose(&[overflow]).is_none());
+}
+
+#[test_log::test]
+fn test_compose_scaled_planes() {
+    let base = || {
+        Pattern::Checkerboard { size: 1 }
+            .render(TEST_WIDTH, TEST_HEIGHT, 0)
+            .unwrap()
+            .clear()
+    };
+
+    let unscaled = PlaneDescription {
+        pattern: Pattern::HorizontalGradient,
+        x: 256,
+        y: 256,
+        width: 256,
+        height: 128,
+        alpha: PLANE_ALPHA_OPAQUE,
+        zpos: 1,
+        source: None,
+        filter: ScalingFilter::Default,
+    };
+
+    // A source rectangle covering the whole buffer, with the same size, is the same thing than no
+    // source rectangle at all.
+    let identity = PlaneDescription {
+        source: Some(PlaneSource {
+            buffer_width: 256,
+            buffer_height: 128,
+            x: 0,
+            y: 0,
+            width: 256,
+            height: 128,
+        }),
+        ..unscaled
+    };
+    assert!(!identity.is_scaled());
+
+    for filter in [ScalingFilter::Default, ScalingFilter::NearestNeighbor] {
+        let expected = base().compose(&[unscaled]).unwrap();
+        let composed = base()
+            .compose(&[PlaneDescription { filter, ..identity }])
+            .unwrap();
+
+        assert_eq!(expected.difference(&composed, 0).pixels, 0);
+    }
+
+    // Upscaling a checkerboard twice with a nearest neighbor filter should give the same result
+    // than a checkerboard with squares twice as large.
+    let large = PlaneDescription {
+        pattern: Pattern::Checkerboard { size: 4 },
+        ..unscaled
+    };
+    let upscaled = PlaneDescription {
+        pattern: Pattern::Checkerboard { size: 2 },
+        source: Some(PlaneSource {
+            buffer_width: 128,
+            buffer_height: 64,
+            x: 0,
+            y: 0,
+            width: 128,
+            height: 64,
+        }),
+        filter: ScalingFilter::NearestNeighbor,
+        ..unscaled
+    };
+    assert!(upscaled.is_scaled());
+
+    let expected = base().compose(&[large]).unwrap();
+    let composed = base().compose(&[upscaled]).unwrap();
+    assert_eq!(expected.difference(&composed, 0).pixels, 0);
+
+    // Crops outside of the buffer are invalid.
+    let invalid = PlaneDescription {
+        source: Some(PlaneSource {
+            buffer_width: 128,
+            buffer_height: 64,
+            x: 64,
+            y: 0,
+            width: 128,
+            height: 64,
+        }),
+        ..unscaled
+    };
+    assert!(base().compose(&[invalid]).is_none());
+}
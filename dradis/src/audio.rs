@@ -0,0 +1,627 @@
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::{f64::consts::PI, fmt, time::Duration};
+use std::{
+    collections::{HashSet, VecDeque},
+    io,
+    path::{Path, PathBuf},
+    thread::{self, JoinHandle},
+    time::Instant,
+};
+
+use linux_alsa::{Pcm, PcmConfig, PcmDirection};
+use linux_mc::{
+    MediaControllerEntity, MediaControllerInterfaceAlsaKind, MediaControllerInterfaceKind,
+};
+use serde::{Deserialize, Deserializer, de::Error as _};
+use serde_with::{DurationSeconds, serde_as};
+use thiserror::Error;
+use tracing::{debug, trace, warn};
+
+use crate::Cli;
+
+// Tone detection granularity. It's also our tone onset resolution.
+const WINDOWS_PER_SECOND: u32 = 100;
+const PCM_PERIODS: u32 = 4;
+
+// How long we wait for the audio on top of the capture duration before giving up.
+const AUDIO_TIMEOUT: Duration = Duration::from_secs(5);
+
+// How often the capture checks whether it's been stopped while waiting for the audio.
+const AUDIO_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// A window is considered silent below -40dBFS.
+const TONE_MIN_AMPLITUDE: f64 = 327.0;
+
+// Ratio between the amplitude of the tone and the amplitude of the whole signal for the tone to
+// be considered present.
+const TONE_MIN_RATIO: f64 = 0.5;
+
+const fn default_audio_frequency() -> u32 {
+    1000
+}
+
+const fn default_audio_channels() -> u32 {
+    2
+}
+
+const fn default_audio_rate() -> u32 {
+    48_000
+}
+
+const fn default_audio_duration() -> Duration {
+    Duration::from_secs(2)
+}
+
+#[derive(Debug, Error)]
+pub(crate) enum AudioError {
+    #[error("I/O Error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("No ALSA PCM Capture Device Found")]
+    NoDevice,
+
+    #[error("Audio Capture Thread Panicked")]
+    Panicked,
+
+    #[error("No Audio Received: captured {0} out of {1} frames")]
+    NoAudio(u64, u64),
+
+    #[error("Audio Capture Stopped")]
+    Stopped,
+}
+
+/// Finds the ALSA PCM capture device associated to the HDMI bridge.
+///
+/// The entities are walked through their data links, starting from the bridge, until one
+/// exposes an ALSA PCM capture interface.
+pub(crate) fn bridge_find_pcm(
+    bridge: &MediaControllerEntity,
+) -> Result<Option<PathBuf>, io::Error> {
+    let mut visited = HashSet::new();
+    let mut queue = VecDeque::from([bridge.clone()]);
+
+    while let Some(entity) = queue.pop_front() {
+        if !visited.insert(entity.id().valid()) {
+            continue;
+        }
+
+        trace!(
+            "Looking for an ALSA PCM interface on entity {}",
+            entity.name()
+        );
+
+        for itf in entity.interfaces().valid()? {
+            if itf.kind().valid()
+                != MediaControllerInterfaceKind::Alsa(MediaControllerInterfaceAlsaKind::PcmCapture)
+            {
+                continue;
+            }
+
+            if let Some(node) = itf.device_node().valid() {
+                debug!(
+                    "Found ALSA PCM {} on entity {}",
+                    node.path().display(),
+                    entity.name()
+                );
+
+                return Ok(Some(node.path().to_path_buf()));
+            }
+        }
+
+        for pad in entity.pads().valid()? {
+            let is_source = pad.is_source().valid();
+
+            for link in pad.links().valid()? {
+                let remote = if is_source {
+                    link.sink_pad().valid()
+                } else {
+                    link.source_pad().valid()
+                };
+
+                queue.push_back(remote.entity().valid());
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Returns the ALSA PCM device to capture from, either found in the media graph or given on the
+/// command line.
+pub(crate) fn audio_device(
+    args: &Cli,
+    bridge: &MediaControllerEntity,
+) -> Result<PathBuf, AudioError> {
+    if let Some(path) = bridge_find_pcm(bridge)? {
+        return Ok(path);
+    }
+
+    args.audio_device.clone().ok_or(AudioError::NoDevice)
+}
+
+fn windows_duration(windows: u32) -> Duration {
+    Duration::from_secs(1) * windows / WINDOWS_PER_SECOND
+}
+
+/// Looks for a sine tone in interleaved samples, one window at a time.
+#[derive(Debug)]
+struct ToneDetector {
+    coeff: f64,
+    channels: usize,
+    window_frames: u32,
+    pending: Vec<i16>,
+    windows: u32,
+    first_tone: Option<u32>,
+    tone_windows: u32,
+    last_present: bool,
+    dropouts: u32,
+    channels_detected: Vec<bool>,
+    crossings: u64,
+}
+
+impl ToneDetector {
+    fn new(frequency: u32, rate: u32, channels: u32) -> Self {
+        let channels = channels as usize;
+
+        Self {
+            coeff: 2.0 * (2.0 * PI * f64::from(frequency) / f64::from(rate)).cos(),
+            channels,
+            window_frames: (rate / WINDOWS_PER_SECOND).max(1),
+            pending: Vec::new(),
+            windows: 0,
+            first_tone: None,
+            tone_windows: 0,
+            last_present: false,
+            dropouts: 0,
+            channels_detected: vec![false; channels],
+            crossings: 0,
+        }
+    }
+
+    fn window_samples(&self) -> usize {
+        self.window_frames as usize * self.channels
+    }
+
+    // Returns whether the tone is present in one channel of the window, and the number of zero
+    // crossings of that channel.
+    fn analyze_channel(&self, window: &[i16], channel: usize) -> (bool, u64) {
+        let mut s1 = 0.0_f64;
+        let mut s2 = 0.0_f64;
+        let mut energy = 0.0_f64;
+        let mut crossings = 0;
+        let mut previous: Option<i16> = None;
+
+        for sample in window.iter().skip(channel).step_by(self.channels) {
+            let val = f64::from(*sample);
+
+            let s0 = val + self.coeff * s1 - s2;
+            s2 = s1;
+            s1 = s0;
+            energy += val * val;
+
+            if let Some(previous) = previous {
+                if (previous < 0) != (*sample < 0) {
+                    crossings += 1;
+                }
+            }
+            previous = Some(*sample);
+        }
+
+        let len = f64::from(self.window_frames);
+        let power = (s1 * s1 + s2 * s2 - self.coeff * s1 * s2).max(0.0);
+        let tone_amplitude = 2.0 * power.sqrt() / len;
+        let amplitude = (2.0 * energy / len).sqrt();
+
+        (
+            amplitude > TONE_MIN_AMPLITUDE && tone_amplitude > amplitude * TONE_MIN_RATIO,
+            crossings,
+        )
+    }
+
+    fn analyze_window(&mut self, window: &[i16]) {
+        let mut present = true;
+        let mut crossings = 0;
+
+        for channel in 0..self.channels {
+            let (channel_present, channel_crossings) = self.analyze_channel(window, channel);
+
+            if channel_present {
+                if let Some(detected) = self.channels_detected.get_mut(channel) {
+                    *detected = true;
+                }
+            }
+
+            present &= channel_present;
+            crossings += channel_crossings;
+        }
+
+        if present {
+            if self.first_tone.is_none() {
+                debug!("Tone detected after {} windows", self.windows);
+                self.first_tone = Some(self.windows);
+            }
+
+            self.tone_windows += 1;
+            self.crossings += crossings;
+        } else if self.last_present {
+            debug!("Tone dropout at window {}", self.windows);
+            self.dropouts += 1;
+        }
+
+        self.last_present = present;
+        self.windows += 1;
+    }
+
+    fn feed(&mut self, samples: &[i16]) {
+        self.pending.extend_from_slice(samples);
+
+        let window_samples = self.window_samples();
+        while window_samples > 0 && self.pending.len() >= window_samples {
+            let window = self.pending.drain(..window_samples).collect::<Vec<_>>();
+            self.analyze_window(&window);
+        }
+    }
+
+    fn report(&self, rate: u32, channels: u32, xruns: u64) -> AudioReport {
+        let frequency = if self.tone_windows > 0 && self.channels > 0 {
+            // Two zero crossings per period.
+            #[expect(
+                clippy::cast_precision_loss,
+                reason = "We won't capture enough samples for it to matter"
+            )]
+            let crossings = self.crossings as f64 / self.channels as f64;
+
+            Some(crossings / 2.0 / windows_duration(self.tone_windows).as_secs_f64())
+        } else {
+            None
+        };
+
+        AudioReport {
+            rate,
+            channels,
+            frequency,
+            channels_detected: self.channels_detected.clone(),
+            tone_onset: self.first_tone.map(windows_duration),
+            xruns,
+            dropouts: self.dropouts,
+        }
+    }
+}
+
+/// What we measured on the captured audio.
+#[derive(Debug)]
+pub(crate) struct AudioReport {
+    rate: u32,
+    channels: u32,
+    frequency: Option<f64>,
+    channels_detected: Vec<bool>,
+
+    // Time between the start of the capture, ie. the first valid frame, and the first tone. The
+    // source doesn't timestamp its tone, so it's not the audio latency.
+    tone_onset: Option<Duration>,
+    xruns: u64,
+    dropouts: u32,
+}
+
+impl AudioReport {
+    fn glitches(&self) -> u64 {
+        self.xruns + u64::from(self.dropouts)
+    }
+}
+
+impl fmt::Display for AudioReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!("{}Hz, {} channels", self.rate, self.channels))?;
+
+        if let Some(frequency) = self.frequency {
+            f.write_fmt(format_args!(", tone at {frequency:.0}Hz"))?;
+        } else {
+            f.write_str(", no tone")?;
+        }
+
+        if let Some(onset) = self.tone_onset {
+            f.write_fmt(format_args!(", tone onset {}ms", onset.as_millis()))?;
+        }
+
+        f.write_fmt(format_args!(
+            ", {} glitches ({} xruns, {} dropouts)",
+            self.glitches(),
+            self.xruns,
+            self.dropouts
+        ))
+    }
+}
+
+/// Captures audio from the PCM device, and looks for the test tone.
+///
+/// The source might not send any audio, so we give up if we don't get enough of it in time, or if
+/// `stop` gets set.
+pub(crate) fn audio_capture(
+    path: &Path,
+    test: &TestAudio,
+    stop: &AtomicBool,
+) -> Result<AudioReport, AudioError> {
+    let mut pcm = Pcm::open_nonblocking(
+        path,
+        PcmDirection::Capture,
+        &PcmConfig {
+            channels: test.channels,
+            rate: test.rate,
+            period_frames: (test.rate / WINDOWS_PER_SECOND).max(1),
+            periods: PCM_PERIODS,
+        },
+    )?;
+
+    let config = pcm.config();
+    debug!("Capturing from {}: {}", path.display(), config);
+
+    let mut detector = ToneDetector::new(test.frequency, config.rate, config.channels);
+    let mut samples = vec![0; config.period_frames as usize * config.channels as usize];
+    let total_frames = u64::from(config.rate) * test.duration.as_secs();
+    let mut captured_frames = 0_u64;
+    let deadline = Instant::now() + test.duration + AUDIO_TIMEOUT;
+
+    while captured_frames < total_frames {
+        if stop.load(Ordering::Relaxed) {
+            return Err(AudioError::Stopped);
+        }
+
+        let Some(remaining) = deadline.checked_duration_since(Instant::now()) else {
+            return Err(AudioError::NoAudio(captured_frames, total_frames));
+        };
+
+        if !pcm.wait(remaining.min(AUDIO_POLL_INTERVAL))? {
+            continue;
+        }
+
+        let frames = pcm.read(&mut samples)?;
+        if frames == 0 {
+            continue;
+        }
+
+        let len = (frames * config.channels as usize).min(samples.len());
+        detector.feed(samples.get(..len).unwrap_or_default());
+        captured_frames += frames as u64;
+    }
+
+    Ok(detector.report(config.rate, config.channels, pcm.xruns()))
+}
+
+/// An audio capture running in the background while the video frames are checked.
+///
+/// The PCM device can only be opened once, so dropping the capture stops it and waits for it to
+/// be over.
+#[derive(Debug)]
+pub(crate) struct AudioCapture {
+    handle: Option<JoinHandle<Result<AudioReport, AudioError>>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl AudioCapture {
+    /// Starts capturing audio from the PCM device.
+    pub(crate) fn start(path: PathBuf, test: TestAudio) -> Self {
+        debug!("Starting the audio capture");
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+
+        Self {
+            handle: Some(thread::spawn(move || {
+                audio_capture(&path, &test, &thread_stop)
+            })),
+            stop,
+        }
+    }
+
+    /// Waits for the capture to be over, and returns what we measured.
+    pub(crate) fn finish(mut self) -> Result<AudioReport, AudioError> {
+        self.handle
+            .take()
+            .ok_or(AudioError::Panicked)?
+            .join()
+            .map_err(|_e| AudioError::Panicked)?
+    }
+}
+
+impl Drop for AudioCapture {
+    fn drop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            debug!("Stopping the audio capture");
+
+            self.stop.store(true, Ordering::Relaxed);
+            if handle.join().is_err() {
+                warn!("Audio Capture Thread Panicked");
+            }
+        }
+    }
+}
+
+// We need at least one channel to look for the tone in.
+fn deserialize_channels<'de, D>(deserializer: D) -> Result<u32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match u32::deserialize(deserializer)? {
+        0 => Err(D::Error::custom("The audio channels count can't be 0")),
+        channels => Ok(channels),
+    }
+}
+
+/// Audio to capture, starting with the first valid frame, and check once the test is over.
+#[serde_as]
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub(crate) struct TestAudio {
+    /// Frequency of the tone played by the source, in Hz.
+    #[serde(default = "default_audio_frequency")]
+    frequency: u32,
+
+    #[serde(
+        default = "default_audio_channels",
+        deserialize_with = "deserialize_channels"
+    )]
+    channels: u32,
+
+    #[serde(default = "default_audio_rate")]
+    rate: u32,
+
+    #[serde_as(as = "DurationSeconds<u64>")]
+    #[serde(default = "default_audio_duration")]
+    duration: Duration,
+
+    #[serde(rename = "max-glitches", default)]
+    max_glitches: u64,
+
+    /// Maximum time, in milliseconds, between the first valid frame and the first tone.
+    ///
+    /// This isn't the audio latency: the source doesn't timestamp its tone, which it starts
+    /// playing independently of its frames, so it only tells how long the audio takes to show
+    /// up once the video is there.
+    #[serde(rename = "max-tone-onset-ms", default)]
+    max_tone_onset_ms: Option<u64>,
+}
+
+impl TestAudio {
+    /// Checks the captured audio against our expectations.
+    pub(crate) fn check(&self, report: &AudioReport) -> Result<(), String> {
+        let mut errors = Vec::new();
+
+        if report.rate != self.rate {
+            errors.push(format!(
+                "Sample Rate is {}Hz, expected {}Hz",
+                report.rate, self.rate
+            ));
+        }
+
+        if report.channels != self.channels {
+            errors.push(format!(
+                "Channels count is {}, expected {}",
+                report.channels, self.channels
+            ));
+        }
+
+        let missing = report
+            .channels_detected
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, detected)| (!detected).then(|| idx.to_string()))
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            errors.push(format!(
+                "{}Hz tone missing on channels {}",
+                self.frequency,
+                missing.join(", ")
+            ));
+        }
+
+        if report.glitches() > self.max_glitches {
+            errors.push(format!(
+                "{} glitches, expected at most {}",
+                report.glitches(),
+                self.max_glitches
+            ));
+        }
+
+        if let (Some(max), Some(onset)) = (self.max_tone_onset_ms, report.tone_onset) {
+            if onset > Duration::from_millis(max) {
+                errors.push(format!(
+                    "Tone Onset is {}ms, expected at most {max}ms",
+                    onset.as_millis()
+                ));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_audio {
+    use core::{f64::consts::PI, time::Duration};
+
+    use super::{TestAudio, ToneDetector};
+
+    const RATE: u32 = 48_000;
+
+    fn sine(frequency: u32, channels: usize, frames: u32, start: u32) -> Vec<i16> {
+        (start..start + frames)
+            .flat_map(|idx| {
+                let val = (2.0 * PI * f64::from(frequency) * f64::from(idx) / f64::from(RATE))
+                    .sin()
+                    * 16384.0;
+
+                #[expect(
+                    clippy::cast_possible_truncation,
+                    reason = "The amplitude fits in an i16"
+                )]
+                let val = val as i16;
+
+                vec![val; channels]
+            })
+            .collect()
+    }
+
+    fn test_audio() -> TestAudio {
+        TestAudio {
+            frequency: 1000,
+            channels: 2,
+            rate: RATE,
+            duration: Duration::from_secs(1),
+            max_glitches: 0,
+            max_tone_onset_ms: None,
+        }
+    }
+
+    #[test]
+    fn test_tone_detected() {
+        let mut detector = ToneDetector::new(1000, RATE, 2);
+
+        detector.feed(&vec![0; 4800 * 2]);
+        detector.feed(&sine(1000, 2, RATE, 0));
+
+        let report = detector.report(RATE, 2, 0);
+        assert_eq!(report.tone_onset, Some(Duration::from_millis(100)));
+        assert_eq!(report.dropouts, 0);
+        assert!((report.frequency.unwrap() - 1000.0).abs() < 50.0);
+        assert!(test_audio().check(&report).is_ok());
+    }
+
+    #[test]
+    fn test_tone_wrong_frequency() {
+        let mut detector = ToneDetector::new(1000, RATE, 2);
+        detector.feed(&sine(440, 2, RATE, 0));
+
+        let report = detector.report(RATE, 2, 0);
+        assert_eq!(report.tone_onset, None);
+        assert!(test_audio().check(&report).is_err());
+    }
+
+    #[test]
+    fn test_tone_dropout() {
+        let mut detector = ToneDetector::new(1000, RATE, 2);
+
+        detector.feed(&sine(1000, 2, 4800, 0));
+        detector.feed(&vec![0; 4800 * 2]);
+        detector.feed(&sine(1000, 2, 4800, 9600));
+
+        let report = detector.report(RATE, 2, 1);
+        assert_eq!(report.dropouts, 1);
+        assert_eq!(report.glitches(), 2);
+        assert!(test_audio().check(&report).is_err());
+    }
+
+    #[test]
+    fn test_channels() {
+        let audio: TestAudio = serde_yaml::from_str("channels: 8").unwrap();
+        assert_eq!(audio.channels, 8);
+
+        let err = serde_yaml::from_str::<TestAudio>("channels: 0")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("can't be 0"), "{err}");
+    }
+}
@@ -0,0 +1,290 @@
+use core::time::Duration;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    thread::sleep,
+};
+
+use linux_cec::{
+    CEC_LOG_ADDR_PLAYBACK_1, CEC_LOG_ADDR_TV, CecCapabilities, CecDevice, CecDeviceKind,
+    CecMessage, CecOpcode, PhysicalAddress,
+};
+use linux_mc::MediaControllerEntity;
+use serde::Deserialize;
+use serde_with::{DisplayFromStr, serde_as};
+use thiserror::Error;
+use tracing::{debug, info};
+
+use crate::Cli;
+
+/// The Physical Address we put in the HDMI Vendor-Specific Data Block of our EDID.
+pub(crate) const EDID_PHYSICAL_ADDRESS: [u8; 4] = [1, 0, 0, 0];
+
+const SYSFS_CEC_PATH: &str = "/sys/class/cec";
+
+const CEC_OSD_NAME: &str = "dradis";
+const CEC_REPLY_TIMEOUT: Duration = Duration::from_secs(1);
+
+// The DUT might still be claiming its logical address if the display was just set up.
+const CEC_RETRIES: usize = 5;
+const CEC_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+const fn default_cec_logical_address() -> u8 {
+    CEC_LOG_ADDR_PLAYBACK_1
+}
+
+#[derive(Debug, Error)]
+pub(crate) enum CecError {
+    #[error("I/O Error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("No CEC Device Found")]
+    NoDevice,
+
+    #[error("{0} wasn't acknowledged")]
+    NotAcked(String),
+
+    #[error("No reply to {0}")]
+    NoReply(String),
+
+    #[error("Physical Address is {found}, expected {expected}")]
+    PhysicalAddress {
+        expected: PhysicalAddress,
+        found: PhysicalAddress,
+    },
+}
+
+/// Finds the CEC adapter of the HDMI bridge.
+///
+/// The HDMI receivers register their CEC adapter as a child of their own device, so we look for
+/// the adapter whose parent is the device behind the bridge sub-device node.
+pub(crate) fn bridge_find_cec(
+    bridge: &MediaControllerEntity,
+) -> Result<Option<PathBuf>, io::Error> {
+    let adapters = match fs::read_dir(SYSFS_CEC_PATH) {
+        Ok(adapters) => adapters.collect::<Result<Vec<_>, _>>()?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    for itf in bridge.interfaces().valid()? {
+        let Some(node) = itf.device_node().valid() else {
+            continue;
+        };
+
+        let Ok(device) = PathBuf::from(format!(
+            "/sys/dev/char/{}:{}/device",
+            node.major(),
+            node.minor()
+        ))
+        .canonicalize() else {
+            continue;
+        };
+
+        for adapter in &adapters {
+            if adapter
+                .path()
+                .join("device")
+                .canonicalize()
+                .is_ok_and(|parent| parent == device)
+            {
+                let path = PathBuf::from("/dev").join(adapter.file_name());
+                debug!(
+                    "Found CEC adapter {} for entity {}",
+                    path.display(),
+                    bridge.name()
+                );
+
+                return Ok(Some(path));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Returns the CEC device to use, either found from the HDMI bridge or given on the command line.
+pub(crate) fn cec_device(args: &Cli, bridge: &MediaControllerEntity) -> Result<PathBuf, CecError> {
+    if let Some(path) = bridge_find_cec(bridge)? {
+        return Ok(path);
+    }
+
+    args.cec_device.clone().ok_or(CecError::NoDevice)
+}
+
+/// A message to exchange with the DUT.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum TestCecMessage {
+    Poll,
+    GivePhysicalAddress,
+    Standby,
+}
+
+/// CEC checks to run once the test is over.
+#[serde_as]
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct TestCec {
+    /// Physical Address the DUT should announce. Defaults to the one in our EDID.
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(rename = "physical-address", default)]
+    physical_address: Option<PhysicalAddress>,
+
+    /// Logical Address the DUT claimed.
+    #[serde(rename = "logical-address", default = "default_cec_logical_address")]
+    logical_address: u8,
+
+    /// Messages to exchange once the physical address has been checked.
+    #[serde(default)]
+    messages: Vec<TestCecMessage>,
+}
+
+fn transmit_with_retries(dev: &CecDevice, msg: CecMessage) -> Result<CecMessage, CecError> {
+    let mut ret = dev.transmit(msg)?;
+
+    for _ in 0..CEC_RETRIES {
+        if ret.is_acked() {
+            break;
+        }
+
+        debug!("{} wasn't acknowledged, trying again.", msg);
+        sleep(CEC_RETRY_DELAY);
+        ret = dev.transmit(msg)?;
+    }
+
+    if !ret.is_acked() {
+        return Err(CecError::NotAcked(msg.to_string()));
+    }
+
+    Ok(ret)
+}
+
+impl TestCec {
+    fn physical_address(&self, dev: &CecDevice) -> Result<PhysicalAddress, CecError> {
+        let msg = CecMessage::new(
+            CEC_LOG_ADDR_TV,
+            self.logical_address,
+            CecOpcode::GivePhysicalAddr,
+            &[],
+        )
+        .with_reply(CecOpcode::ReportPhysicalAddr, CEC_REPLY_TIMEOUT);
+
+        let reply = transmit_with_retries(dev, msg)?;
+        if !reply.is_replied() {
+            return Err(CecError::NoReply(msg.to_string()));
+        }
+
+        reply
+            .reported_physical_address()
+            .ok_or(CecError::NoReply(msg.to_string()))
+    }
+
+    fn exchange(&self, dev: &CecDevice, message: TestCecMessage) -> Result<(), CecError> {
+        match message {
+            TestCecMessage::Poll => {
+                transmit_with_retries(dev, CecMessage::poll(CEC_LOG_ADDR_TV, self.logical_address))
+                    .map(|_msg| ())
+            }
+            TestCecMessage::GivePhysicalAddress => self.physical_address(dev).map(|_addr| ()),
+            TestCecMessage::Standby => transmit_with_retries(
+                dev,
+                CecMessage::new(
+                    CEC_LOG_ADDR_TV,
+                    self.logical_address,
+                    CecOpcode::Standby,
+                    &[],
+                ),
+            )
+            .map(|_msg| ()),
+        }
+    }
+
+    fn expected_physical_address(&self) -> PhysicalAddress {
+        self.physical_address
+            .unwrap_or(PhysicalAddress::from_components(EDID_PHYSICAL_ADDRESS))
+    }
+
+    /// Checks the physical address the DUT announces, and exchanges our messages with it.
+    pub(crate) fn check(&self, path: &Path) -> Result<(), CecError> {
+        let dev = CecDevice::new(path)?;
+
+        let caps = dev.caps()?;
+        debug!("CEC Adapter {} ({})", caps.name, caps.driver);
+
+        if caps.capabilities.contains(CecCapabilities::PHYS_ADDR) {
+            dev.set_physical_address(PhysicalAddress::ROOT)?;
+        }
+
+        dev.claim_logical_address(CecDeviceKind::Tv, CEC_OSD_NAME)?;
+
+        let expected = self.expected_physical_address();
+        let found = self.physical_address(&dev)?;
+        info!("DUT CEC Physical Address: {}", found);
+
+        if found != expected {
+            return Err(CecError::PhysicalAddress { expected, found });
+        }
+
+        for message in &self.messages {
+            self.exchange(&dev, *message)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests_cec {
+    use linux_cec::{CEC_LOG_ADDR_PLAYBACK_1, PhysicalAddress};
+
+    use super::{TestCec, TestCecMessage};
+
+    #[test]
+    fn test_deserialize() {
+        let cec: TestCec = serde_yaml::from_str("{}").unwrap();
+        assert_eq!(cec.physical_address, None);
+        assert_eq!(cec.logical_address, CEC_LOG_ADDR_PLAYBACK_1);
+        assert!(cec.messages.is_empty());
+
+        let cec: TestCec = serde_yaml::from_str(
+            "
+            physical-address: 2.1.0.0
+            logical-address: 8
+            messages: [poll, give-physical-address, standby]
+            ",
+        )
+        .unwrap();
+        assert_eq!(cec.physical_address, Some(PhysicalAddress::from(0x2100)));
+        assert_eq!(cec.logical_address, 8);
+        assert_eq!(
+            cec.messages,
+            [
+                TestCecMessage::Poll,
+                TestCecMessage::GivePhysicalAddress,
+                TestCecMessage::Standby
+            ]
+        );
+
+        assert!(serde_yaml::from_str::<TestCec>("physical-address: 1.0.0").is_err());
+        assert!(serde_yaml::from_str::<TestCec>("messages: [power-on]").is_err());
+    }
+
+    #[test]
+    fn test_expected_physical_address() {
+        let cec: TestCec = serde_yaml::from_str("{}").unwrap();
+        assert_eq!(
+            cec.expected_physical_address(),
+            PhysicalAddress::from(0x1000)
+        );
+
+        let cec: TestCec = serde_yaml::from_str("physical-address: 3.0.0.0").unwrap();
+        assert_eq!(
+            cec.expected_physical_address(),
+            PhysicalAddress::from(0x3000)
+        );
+        assert_ne!(
+            cec.expected_physical_address(),
+            PhysicalAddress::from(0x1000)
+        );
+    }
+}
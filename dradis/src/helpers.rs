@@ -10,6 +10,7 @@ use std::{
     time::Instant,
 };
 
+use frame_check::Control;
 use num_traits::{One, ToPrimitive as _, Zero};
 use redid::{
     EdidChromaticityPoint, EdidChromaticityPoints, EdidDescriptorDetailedTiming,
@@ -43,6 +44,7 @@ use v4lise::Device;
 
 use crate::{
     BUFFER_TYPE, Cli, Dradis, MEMORY_TYPE, PipelineItem, SetupError, TestEdid, V4l2EntityWrapper,
+    cec::EDID_PHYSICAL_ADDRESS,
 };
 
 const HFREQ_TOLERANCE_KHZ: u32 = 5;
@@ -204,6 +206,7 @@ pub(crate) fn bridge_set_edid(
     args: &Cli,
     dev: &V4l2EntityWrapper,
     edid: &TestEdid,
+    control: Option<&Control>,
 ) -> Result<(), SetupError> {
     let TestEdid::DetailedTiming(dtd) = edid;
 
@@ -343,7 +346,7 @@ pub(crate) fn bridge_set_edid(
                 ))
                 .add_data_block(EdidExtensionCTA861Revision3DataBlock::HDMI(
                     EdidExtensionCTA861HdmiDataBlock::builder()
-                        .source_physical_address([1, 0, 0, 0].try_into()?)
+                        .source_physical_address(EDID_PHYSICAL_ADDRESS.try_into()?)
                         .build(),
                 ))
                 .build(),
@@ -352,6 +355,14 @@ pub(crate) fn bridge_set_edid(
 
     let mut bytes = test_edid.into_bytes();
 
+    if let Some(control) = control {
+        debug!("Embedding {control} in the EDID");
+
+        control
+            .add_to_edid(&mut bytes)
+            .map_err(|e| SetupError::Value(e.to_string()))?;
+    }
+
     if let Some(folder) = &args.dump_edid {
         if !folder.exists() {
             fs::create_dir(folder)?;
@@ -365,6 +376,13 @@ pub(crate) fn bridge_set_edid(
     Ok(())
 }
 
+// Bridges deassert the Hotplug Detect signal when they don't have an EDID anymore.
+pub(crate) fn bridge_clear_edid(dev: &V4l2EntityWrapper) -> Result<(), SetupError> {
+    mc_wrapper_v4l2_s_edid(dev, &mut [])?;
+
+    Ok(())
+}
+
 pub(crate) fn wait_and_set_dv_timings(
     suite: &Dradis<'_>,
     width: u32,
@@ -0,0 +1,213 @@
+use core::time::Duration;
+use std::{io, thread::sleep};
+
+use frame_check::Control;
+use rustix::io::Errno;
+use serde::Deserialize;
+use serde_with::{DurationSeconds, serde_as};
+use tracing::info;
+
+use crate::{
+    Cli, Dradis, PipelineItem, SetupError, TestEdid, TestError, TestItem,
+    helpers::{bridge_clear_edid, bridge_set_edid},
+    test_capture,
+};
+
+/// What the source is expected to emit once the display is connected again.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum TestHotplugExpectation {
+    /// The source keeps emitting the timings it was using before the hotplug.
+    SameTimings,
+
+    /// The source switches to the preferred timings of the new EDID.
+    NewTimings,
+}
+
+#[serde_as]
+#[derive(Debug, Deserialize)]
+pub(crate) struct TestHotplug {
+    /// How long the display stays disconnected.
+    #[serde_as(as = "DurationSeconds<u64>")]
+    #[serde(rename = "disconnect-duration")]
+    disconnect_duration: Duration,
+
+    /// EDID of the display connected afterwards. Defaults to the one of the previous test.
+    #[serde(default)]
+    edid: Option<TestEdid>,
+
+    expect: TestHotplugExpectation,
+
+    /// Instructions passed to Boomer through the new EDID. Defaults to the ones of the previous
+    /// test.
+    #[serde(default, deserialize_with = "crate::deserialize_control")]
+    control: Option<Control>,
+}
+
+/// A test disconnecting the display, and connecting the same or another one.
+#[serde_as]
+#[derive(Debug, Deserialize)]
+pub(crate) struct TestHotplugItem {
+    hotplug: TestHotplug,
+
+    #[serde_as(as = "Option<DurationSeconds<u64>>")]
+    #[serde(default)]
+    duration: Option<Duration>,
+}
+
+impl TestHotplugItem {
+    // Returns the test we expect to pass once the display is connected again.
+    fn expected_test(&self, previous: &TestItem) -> TestItem {
+        let edid = self.hotplug.edid.as_ref().unwrap_or(&previous.edid);
+        let control = self.hotplug.control.or(previous.control);
+
+        match self.hotplug.expect {
+            // The new display is still connected, even though we expect the source to ignore it.
+            TestHotplugExpectation::SameTimings => TestItem {
+                duration: self.duration,
+                edid: edid.clone(),
+                timings_edid: Some(
+                    previous
+                        .timings_edid
+                        .as_ref()
+                        .unwrap_or(&previous.edid)
+                        .clone(),
+                ),
+                control,
+                ..previous.clone()
+            },
+            TestHotplugExpectation::NewTimings => {
+                let TestEdid::DetailedTiming(dtd) = edid;
+
+                TestItem {
+                    duration: self.duration,
+                    expected_height: dtd.vdisplay.into(),
+                    expected_width: dtd.hdisplay.into(),
+                    refresh_tolerance_mhz: previous.refresh_tolerance_mhz,
+                    edid: edid.clone(),
+                    timings_edid: None,
+                    control,
+                    infoframes: previous.infoframes,
+                    audio: previous.audio,
+                    cec: previous.cec.clone(),
+                }
+            }
+        }
+    }
+}
+
+/// Disconnects the display, connects it again with the new EDID, and checks that the source
+/// emits the expected timings.
+///
+/// Returns the test that passed after the hotplug, ie. the current state of the display.
+pub(crate) fn test_hotplug(
+    args: &Cli,
+    suite: &Dradis<'_>,
+    test: &TestHotplugItem,
+    previous: &TestItem,
+) -> Result<TestItem, TestError> {
+    let PipelineItem { entity: bridge, .. } =
+        suite
+            .pipeline
+            .last()
+            .ok_or(SetupError::from(io::Error::new(
+                Errno::NODEV.kind(),
+                "Missing HDMI Bridge Entity",
+            )))?;
+
+    let expected = test.expected_test(previous);
+
+    info!(
+        "Disconnecting the display for {} seconds",
+        test.hotplug.disconnect_duration.as_secs()
+    );
+
+    bridge_clear_edid(bridge)?;
+    sleep(test.hotplug.disconnect_duration);
+
+    info!(
+        "Connecting the display again, expecting {}x{}",
+        expected.expected_width, expected.expected_height
+    );
+
+    bridge_set_edid(args, bridge, &expected.edid, expected.control.as_ref())?;
+
+    test_capture(args, suite, &expected)?;
+
+    Ok(expected)
+}
+
+#[cfg(test)]
+mod tests_hotplug {
+    use crate::{TestEdid, TestItem, hotplug::TestHotplugItem};
+
+    const PREVIOUS: &str = "
+        expected-height: 720
+        expected-width: 1280
+        edid:
+            type: detailed
+            timings:
+                clock_khz: 74250
+                hfp: 220
+                hdisplay: 1280
+                hbp: 110
+                hsync: 40
+                vfp: 20
+                vdisplay: 720
+                vbp: 5
+                vsync: 5
+        control:
+            test-id: 1
+            ignore-hotplug: true
+        ";
+
+    fn hotplug(expect: &str) -> TestHotplugItem {
+        serde_yaml::from_str(&format!(
+            "
+            duration: 10
+            hotplug:
+                disconnect-duration: 2
+                expect: {expect}
+                edid:
+                    type: detailed
+                    timings:
+                        clock_khz: 148500
+                        hdisplay: 1920
+                        hbp: 88
+                        hsync: 44
+                        hfp: 148
+                        vdisplay: 1080
+                        vbp: 4
+                        vsync: 5
+                        vfp: 36
+            "
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_same_timings() {
+        let previous: TestItem = serde_yaml::from_str(PREVIOUS).unwrap();
+        let expected = hotplug("same-timings").expected_test(&previous);
+
+        assert_eq!(expected.expected_width, 1280);
+        assert_eq!(expected.expected_height, 720);
+        assert_eq!(expected.control.unwrap().test_id, 1);
+
+        assert_eq!(expected.expected_refresh_mhz(), 60_000);
+
+        let TestEdid::DetailedTiming(dtd) = expected.edid;
+        assert_eq!(dtd.hdisplay, 1920);
+    }
+
+    #[test]
+    fn test_new_timings() {
+        let previous: TestItem = serde_yaml::from_str(PREVIOUS).unwrap();
+        let expected = hotplug("new-timings").expected_test(&previous);
+
+        assert_eq!(expected.expected_width, 1920);
+        assert_eq!(expected.expected_height, 1080);
+        assert_eq!(expected.expected_refresh_mhz(), 60_000);
+        assert_eq!(expected.control.unwrap().test_id, 1);
+    }
+}
@@ -0,0 +1,1155 @@
+use core::{fmt, str::FromStr};
+use std::{fs, io, os::fd::AsFd as _, path::PathBuf};
+
+use frame_check::{BroadcastRgb, Colorspace, ContentType, Eotf, HdrMetadata, OutputProperties};
+use rustix::{
+    fs::{Mode, OFlags, SeekFrom, open, seek},
+    io::{Errno, read},
+};
+use serde::Deserialize;
+use thiserror::Error;
+use tracing::{debug, warn};
+use v4l2_raw::wrapper::v4l2_ioctl_log_status;
+
+use crate::V4l2EntityWrapper;
+
+// The HDMI receivers drivers expose the last InfoFrames they received in
+// /sys/kernel/debug/v4l2/$NAME/infoframes, $NAME being the sub-device name. The AVI, Audio, SPD
+// and HDMI Vendor-Specific InfoFrames are exposed there, and the DRM InfoFrame by some receivers
+// only.
+const DEBUGFS_V4L2_PATH: &str = "/sys/kernel/debug/v4l2";
+const KMSG_PATH: &str = "/dev/kmsg";
+const KMSG_RECORD_MAX_LEN: usize = 4096;
+
+// Type, Version, Length and Checksum
+const INFOFRAME_HEADER_SIZE: usize = 4;
+
+const INFOFRAME_TYPE_VENDOR: u8 = 0x81;
+const INFOFRAME_TYPE_AVI: u8 = 0x82;
+const INFOFRAME_TYPE_AUDIO: u8 = 0x84;
+const INFOFRAME_TYPE_DRM: u8 = 0x87;
+
+// HDMI_STATIC_METADATA_TYPE1, the only metadata type defined by CTA-861-G.
+const STATIC_METADATA_TYPE1: u8 = 0;
+
+const HDMI_OUI: u32 = 0x00_0C03;
+
+#[derive(Debug, Error)]
+pub(crate) enum InfoFrameError {
+    #[error("I/O Error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("Malformed {0} InfoFrame")]
+    Malformed(&'static str),
+
+    #[error("Unsupported {0} InfoFrame: {1}")]
+    Unsupported(&'static str, &'static str),
+}
+
+impl From<Errno> for InfoFrameError {
+    fn from(value: Errno) -> Self {
+        Self::Io(value.into())
+    }
+}
+
+/// Pixel Encoding, as found in the AVI InfoFrame.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum AviColorspace {
+    Rgb,
+    Yuv422,
+    Yuv444,
+    Yuv420,
+}
+
+impl fmt::Display for AviColorspace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Rgb => "RGB",
+            Self::Yuv422 => "YUV 4:2:2",
+            Self::Yuv444 => "YUV 4:4:4",
+            Self::Yuv420 => "YUV 4:2:0",
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct AviInfoFrame {
+    colorspace: AviColorspace,
+    colorimetry: Colorspace,
+    quantization_range: BroadcastRgb,
+    content_type: ContentType,
+    vic: u8,
+}
+
+// Raw AVI InfoFrame fields, named after CTA-861.
+#[derive(Clone, Copy, Debug, Default)]
+struct AviFields {
+    y: u8,
+    c: u8,
+    ec: u8,
+    ace: u8,
+    q: u8,
+    itc: bool,
+    cn: u8,
+    vic: u8,
+}
+
+// The Linux HDMI helpers log one field per line, as "name: value", after the InfoFrame header
+// line. The lines are prefixed by the device name, followed by a colon.
+fn log_fields<'a>(
+    lines: impl Iterator<Item = &'a str>,
+) -> impl Iterator<Item = (&'a str, &'a str)> {
+    lines
+        .filter_map(|line| line.rsplit_once(": "))
+        .map(|(name, value)| {
+            (
+                name.rsplit(':').next().unwrap_or_default().trim(),
+                value.trim(),
+            )
+        })
+}
+
+fn log_number<T>(value: &str, frame: &'static str) -> Result<T, InfoFrameError>
+where
+    T: FromStr,
+{
+    value.parse().map_err(|_e| InfoFrameError::Malformed(frame))
+}
+
+impl AviInfoFrame {
+    fn from_fields(fields: AviFields) -> Result<Self, InfoFrameError> {
+        let colorspace = match fields.y {
+            0 => AviColorspace::Rgb,
+            1 => AviColorspace::Yuv422,
+            2 => AviColorspace::Yuv444,
+            3 => AviColorspace::Yuv420,
+            _ => return Err(InfoFrameError::Malformed("AVI")),
+        };
+
+        let colorimetry = match (fields.c, fields.ec, fields.ace) {
+            (0, _, _) => Colorspace::Default,
+            (1, _, _) => Colorspace::Smpte170mYcc,
+            (2, _, _) => Colorspace::Bt709Ycc,
+            (3, 0, _) => Colorspace::Xvycc601,
+            (3, 1, _) => Colorspace::Xvycc709,
+            (3, 2, _) => Colorspace::Sycc601,
+            (3, 3, _) => Colorspace::Opycc601,
+            (3, 4, _) => Colorspace::Oprgb,
+            (3, 5, _) => Colorspace::Bt2020Cycc,
+            (3, 6, _) if colorspace == AviColorspace::Rgb => Colorspace::Bt2020Rgb,
+            (3, 6, _) => Colorspace::Bt2020Ycc,
+            (3, 7, 0) => Colorspace::DciP3RgbD65,
+            (3, 7, 1) => Colorspace::DciP3RgbTheater,
+            _ => return Err(InfoFrameError::Malformed("AVI")),
+        };
+
+        let quantization_range = match fields.q {
+            0 => BroadcastRgb::Automatic,
+            1 => BroadcastRgb::Limited,
+            2 => BroadcastRgb::Full,
+            _ => return Err(InfoFrameError::Malformed("AVI")),
+        };
+
+        let content_type = match (fields.itc, fields.cn) {
+            (false, _) => ContentType::NoData,
+            (true, 0) => ContentType::Graphics,
+            (true, 1) => ContentType::Photo,
+            (true, 2) => ContentType::Cinema,
+            (true, 3) => ContentType::Game,
+            _ => return Err(InfoFrameError::Malformed("AVI")),
+        };
+
+        Ok(Self {
+            colorspace,
+            colorimetry,
+            quantization_range,
+            content_type,
+            vic: fields.vic,
+        })
+    }
+
+    fn from_payload(payload: &[u8]) -> Result<Self, InfoFrameError> {
+        let pb = |idx: usize| payload.get(idx).copied().unwrap_or_default();
+
+        Self::from_fields(AviFields {
+            y: (pb(0) >> 5) & 0x3,
+            c: (pb(1) >> 6) & 0x3,
+            ec: (pb(2) >> 4) & 0x7,
+            ace: (pb(13) >> 4) & 0xf,
+            q: (pb(2) >> 2) & 0x3,
+            itc: pb(2) & 0x80 != 0,
+            cn: (pb(4) >> 4) & 0x3,
+            vic: pb(3),
+        })
+    }
+
+    fn from_log<'a>(lines: impl Iterator<Item = &'a str>) -> Result<Self, InfoFrameError> {
+        let mut fields = AviFields::default();
+
+        for (name, value) in log_fields(lines) {
+            match name {
+                "colorspace" => {
+                    fields.y = match value {
+                        "RGB" => 0,
+                        "YCbCr 4:2:2" => 1,
+                        "YCbCr 4:4:4" => 2,
+                        "YCbCr 4:2:0" => 3,
+                        _ => return Err(InfoFrameError::Malformed("AVI")),
+                    };
+                }
+                "colorimetry" => {
+                    fields.c = match value {
+                        "No Data" => 0,
+                        "ITU601" => 1,
+                        "ITU709" => 2,
+                        "Extended" => 3,
+                        _ => return Err(InfoFrameError::Malformed("AVI")),
+                    };
+                }
+                "extended colorimetry" => {
+                    fields.ec = match value {
+                        "xvYCC 601" => 0,
+                        "xvYCC 709" => 1,
+                        "sYCC 601" => 2,
+                        "opYCC 601" => 3,
+                        "opRGB" => 4,
+                        "BT.2020 Constant Luminance" => 5,
+                        "BT.2020" => 6,
+                        // The additional colorimetry extension that tells the reserved value
+                        // apart isn't logged.
+                        "Reserved" => {
+                            return Err(InfoFrameError::Unsupported(
+                                "AVI",
+                                "the additional colorimetry extension isn't logged",
+                            ));
+                        }
+                        _ => return Err(InfoFrameError::Malformed("AVI")),
+                    };
+                }
+                "quantization range" => {
+                    fields.q = match value {
+                        "Default" => 0,
+                        "Limited" => 1,
+                        "Full" => 2,
+                        _ => return Err(InfoFrameError::Malformed("AVI")),
+                    };
+                }
+                "itc" => fields.itc = value == "IT Content",
+                "hdmi content type" | "content type" => {
+                    fields.cn = match value {
+                        "Graphics" => 0,
+                        "Photo" => 1,
+                        "Cinema" => 2,
+                        "Game" => 3,
+                        _ => return Err(InfoFrameError::Malformed("AVI")),
+                    };
+                }
+                "video code" => fields.vic = log_number(value, "AVI")?,
+                _ => {}
+            }
+        }
+
+        Self::from_fields(fields)
+    }
+}
+
+impl fmt::Display for AviInfoFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!(
+            "AVI: VIC {}, {}, colorimetry {}, quantization range {}, content type {}",
+            self.vic, self.colorspace, self.colorimetry, self.quantization_range, self.content_type
+        ))
+    }
+}
+
+/// Audio InfoFrame. The fields that aren't set refer to the audio stream header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct AudioInfoFrame {
+    channels: Option<u8>,
+    sample_rate: Option<u32>,
+    sample_size: Option<u8>,
+}
+
+impl AudioInfoFrame {
+    fn from_payload(payload: &[u8]) -> Self {
+        let pb = |idx: usize| payload.get(idx).copied().unwrap_or_default();
+
+        let channels = match pb(0) & 0x7 {
+            0 => None,
+            cc => Some(cc + 1),
+        };
+
+        let sample_rate = match (pb(1) >> 2) & 0x7 {
+            1 => Some(32_000),
+            2 => Some(44_100),
+            3 => Some(48_000),
+            4 => Some(88_200),
+            5 => Some(96_000),
+            6 => Some(176_400),
+            7 => Some(192_000),
+            _ => None,
+        };
+
+        let sample_size = match pb(1) & 0x3 {
+            1 => Some(16),
+            2 => Some(20),
+            3 => Some(24),
+            _ => None,
+        };
+
+        Self {
+            channels,
+            sample_rate,
+            sample_size,
+        }
+    }
+
+    fn from_log<'a>(lines: impl Iterator<Item = &'a str>) -> Result<Self, InfoFrameError> {
+        let mut frame = Self {
+            channels: None,
+            sample_rate: None,
+            sample_size: None,
+        };
+
+        for (name, value) in log_fields(lines) {
+            match name {
+                // The kernel logs the Channel Count field minus one, which wraps around when the
+                // count refers to the stream header.
+                "channels" => {
+                    frame.channels = match log_number::<u32>(value, "Audio")?.wrapping_add(1) {
+                        0 => None,
+                        cc => Some(
+                            u8::try_from(cc + 1)
+                                .map_err(|_e| InfoFrameError::Malformed("Audio"))?,
+                        ),
+                    };
+                }
+                "sample frequency" => {
+                    frame.sample_rate = match value {
+                        "Stream Header" => None,
+                        "32 kHz" => Some(32_000),
+                        "44.1 kHz" => Some(44_100),
+                        "48 kHz" => Some(48_000),
+                        "88.2 kHz" => Some(88_200),
+                        "96 kHz" => Some(96_000),
+                        "176.4 kHz" => Some(176_400),
+                        "192 kHz" => Some(192_000),
+                        _ => return Err(InfoFrameError::Malformed("Audio")),
+                    };
+                }
+                "sample size" => {
+                    frame.sample_size = match value {
+                        "Stream Header" => None,
+                        "16 bit" => Some(16),
+                        "20 bit" => Some(20),
+                        "24 bit" => Some(24),
+                        _ => return Err(InfoFrameError::Malformed("Audio")),
+                    };
+                }
+                _ => {}
+            }
+        }
+
+        Ok(frame)
+    }
+}
+
+impl fmt::Display for AudioInfoFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Audio:")?;
+
+        if let Some(channels) = self.channels {
+            f.write_fmt(format_args!(" {channels} channels"))?;
+        }
+
+        if let Some(rate) = self.sample_rate {
+            f.write_fmt(format_args!(" {rate}Hz"))?;
+        }
+
+        if let Some(size) = self.sample_size {
+            f.write_fmt(format_args!(" {size} bits"))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct VendorInfoFrame {
+    oui: u32,
+    hdmi_vic: Option<u8>,
+}
+
+impl VendorInfoFrame {
+    fn from_payload(payload: &[u8]) -> Result<Self, InfoFrameError> {
+        let Some(&[oui0, oui1, oui2]) = payload.get(..3) else {
+            return Err(InfoFrameError::Malformed("Vendor-Specific"));
+        };
+
+        let oui = u32::from_le_bytes([oui0, oui1, oui2, 0]);
+        let pb = |idx: usize| payload.get(idx).copied().unwrap_or_default();
+
+        // The HDMI 1.4 InfoFrame carries the VIC of the 4k modes when the video format is 1.
+        let hdmi_vic = (oui == HDMI_OUI && (pb(3) >> 5) == 1).then(|| pb(4));
+
+        Ok(Self { oui, hdmi_vic })
+    }
+
+    // Only the HDMI Vendor-Specific InfoFrames are logged.
+    fn from_log<'a>(lines: impl Iterator<Item = &'a str>) -> Result<Self, InfoFrameError> {
+        let mut hdmi_vic = None;
+
+        for (name, value) in log_fields(lines) {
+            if name == "HDMI VIC" {
+                hdmi_vic = Some(log_number(value, "Vendor-Specific")?);
+            }
+        }
+
+        Ok(Self {
+            oui: HDMI_OUI,
+            hdmi_vic,
+        })
+    }
+}
+
+impl fmt::Display for VendorInfoFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!("Vendor-Specific: OUI {:#08x}", self.oui))?;
+
+        if let Some(vic) = self.hdmi_vic {
+            f.write_fmt(format_args!(", HDMI VIC {vic}"))?;
+        }
+
+        Ok(())
+    }
+}
+
+fn eotf(value: u8) -> Result<Eotf, InfoFrameError> {
+    Ok(match value {
+        0 => Eotf::TraditionalSdr,
+        1 => Eotf::TraditionalHdr,
+        2 => Eotf::SmpteSt2084,
+        3 => Eotf::Hlg,
+        _ => return Err(InfoFrameError::Malformed("DRM")),
+    })
+}
+
+/// Dynamic Range and Mastering InfoFrame, carrying the static HDR metadata.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct DrmInfoFrame {
+    metadata: HdrMetadata,
+}
+
+impl DrmInfoFrame {
+    fn from_payload(payload: &[u8]) -> Result<Self, InfoFrameError> {
+        let pb = |idx: usize| payload.get(idx).copied().unwrap_or_default();
+        let word = |idx: usize| u16::from_le_bytes([pb(idx), pb(idx + 1)]);
+
+        if pb(1) & 0x7 != STATIC_METADATA_TYPE1 {
+            return Err(InfoFrameError::Unsupported(
+                "DRM",
+                "only the Static Metadata Type 1 is supported",
+            ));
+        }
+
+        Ok(Self {
+            metadata: HdrMetadata {
+                eotf: eotf(pb(0) & 0x7)?,
+                display_primaries: [(word(2), word(4)), (word(6), word(8)), (word(10), word(12))],
+                white_point: (word(14), word(16)),
+                max_display_mastering_luminance: word(18),
+                min_display_mastering_luminance: word(20),
+                max_cll: word(22),
+                max_fall: word(24),
+            },
+        })
+    }
+
+    fn from_log<'a>(lines: impl Iterator<Item = &'a str>) -> Result<Self, InfoFrameError> {
+        let mut metadata_type = STATIC_METADATA_TYPE1;
+        let mut metadata = HdrMetadata {
+            eotf: Eotf::TraditionalSdr,
+            display_primaries: [(0, 0); 3],
+            white_point: (0, 0),
+            max_display_mastering_luminance: 0,
+            min_display_mastering_luminance: 0,
+            max_cll: 0,
+            max_fall: 0,
+        };
+
+        for (name, value) in log_fields(lines) {
+            // The primaries are logged as x[0], y[0], x[1], etc.
+            if let Some((axis, idx)) = name.strip_suffix(']').and_then(|name| name.split_once('['))
+            {
+                let primary = idx
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|idx| metadata.display_primaries.get_mut(idx))
+                    .ok_or(InfoFrameError::Malformed("DRM"))?;
+
+                match axis {
+                    "x" => primary.0 = log_number(value, "DRM")?,
+                    "y" => primary.1 = log_number(value, "DRM")?,
+                    _ => return Err(InfoFrameError::Malformed("DRM")),
+                }
+
+                continue;
+            }
+
+            match name {
+                "metadata type" => metadata_type = log_number(value, "DRM")?,
+                "eotf" => metadata.eotf = eotf(log_number(value, "DRM")?)?,
+                "white point x" => metadata.white_point.0 = log_number(value, "DRM")?,
+                "white point y" => metadata.white_point.1 = log_number(value, "DRM")?,
+                "max_display_mastering_luminance" => {
+                    metadata.max_display_mastering_luminance = log_number(value, "DRM")?;
+                }
+                "min_display_mastering_luminance" => {
+                    metadata.min_display_mastering_luminance = log_number(value, "DRM")?;
+                }
+                "max_cll" => metadata.max_cll = log_number(value, "DRM")?,
+                "max_fall" => metadata.max_fall = log_number(value, "DRM")?,
+                _ => {}
+            }
+        }
+
+        if metadata_type != STATIC_METADATA_TYPE1 {
+            return Err(InfoFrameError::Unsupported(
+                "DRM",
+                "only the Static Metadata Type 1 is supported",
+            ));
+        }
+
+        Ok(Self { metadata })
+    }
+}
+
+impl fmt::Display for DrmInfoFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!(
+            "DRM: EOTF {}, max CLL {}, max FALL {}",
+            self.metadata.eotf, self.metadata.max_cll, self.metadata.max_fall
+        ))
+    }
+}
+
+/// The InfoFrames the receiver got from the source.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct InfoFrames {
+    avi: Option<AviInfoFrame>,
+    audio: Option<AudioInfoFrame>,
+    vendor: Option<VendorInfoFrame>,
+    drm: Option<DrmInfoFrame>,
+
+    /// Whether the receiver reports the DRM InfoFrame at all. Most of them don't.
+    drm_reported: bool,
+}
+
+impl fmt::Display for InfoFrames {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let frames = [
+            self.avi.as_ref().map(ToString::to_string),
+            self.audio.as_ref().map(ToString::to_string),
+            self.vendor.as_ref().map(ToString::to_string),
+            self.drm.as_ref().map(ToString::to_string),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+
+        if frames.is_empty() {
+            return f.write_str("None");
+        }
+
+        f.write_str(&frames.join("; "))
+    }
+}
+
+// Checks the InfoFrame header and checksum, and returns its payload.
+fn infoframe_payload<'a>(
+    bytes: &'a [u8],
+    kind: u8,
+    name: &'static str,
+) -> Result<&'a [u8], InfoFrameError> {
+    let Some(&[frame_kind, _version, len, _checksum]) = bytes.get(..INFOFRAME_HEADER_SIZE) else {
+        return Err(InfoFrameError::Malformed(name));
+    };
+
+    if frame_kind != kind {
+        return Err(InfoFrameError::Malformed(name));
+    }
+
+    let frame = bytes
+        .get(..INFOFRAME_HEADER_SIZE + usize::from(len))
+        .ok_or(InfoFrameError::Malformed(name))?;
+
+    if frame.iter().fold(0_u8, |sum, b| sum.wrapping_add(*b)) != 0 {
+        return Err(InfoFrameError::Malformed(name));
+    }
+
+    frame
+        .get(INFOFRAME_HEADER_SIZE..)
+        .ok_or(InfoFrameError::Malformed(name))
+}
+
+impl InfoFrames {
+    fn from_debugfs(name: &str) -> Result<Option<Self>, InfoFrameError> {
+        let dir = PathBuf::from(DEBUGFS_V4L2_PATH)
+            .join(name)
+            .join("infoframes");
+        if !dir.exists() {
+            return Ok(None);
+        }
+
+        // The files are empty if the receiver didn't get any InfoFrame of that type.
+        let read_file = |file: &str| -> io::Result<Option<Vec<u8>>> {
+            let path = dir.join(file);
+            if !path.exists() {
+                return Ok(None);
+            }
+
+            let bytes = fs::read(path)?;
+            Ok((!bytes.is_empty()).then_some(bytes))
+        };
+
+        Ok(Some(Self {
+            avi: read_file("avi")?
+                .map(|bytes| {
+                    AviInfoFrame::from_payload(infoframe_payload(
+                        &bytes,
+                        INFOFRAME_TYPE_AVI,
+                        "AVI",
+                    )?)
+                })
+                .transpose()?,
+            audio: read_file("audio")?
+                .map(|bytes| {
+                    infoframe_payload(&bytes, INFOFRAME_TYPE_AUDIO, "Audio")
+                        .map(AudioInfoFrame::from_payload)
+                })
+                .transpose()?,
+            vendor: read_file("hdmi")?
+                .map(|bytes| {
+                    VendorInfoFrame::from_payload(infoframe_payload(
+                        &bytes,
+                        INFOFRAME_TYPE_VENDOR,
+                        "Vendor-Specific",
+                    )?)
+                })
+                .transpose()?,
+            drm: read_file("drm")?
+                .map(|bytes| {
+                    DrmInfoFrame::from_payload(infoframe_payload(
+                        &bytes,
+                        INFOFRAME_TYPE_DRM,
+                        "DRM",
+                    )?)
+                })
+                .transpose()?,
+            drm_reported: dir.join("drm").exists(),
+        }))
+    }
+
+    // The Linux HDMI helpers log a "<type> infoframe, version <v> (length <l>)" header, followed by
+    // the InfoFrame fields. The drivers log the other parts of their status in sections starting
+    // with a "-----<name>-----" line.
+    fn from_log(messages: &[String]) -> Result<Self, InfoFrameError> {
+        let header = |msg: &str| {
+            msg.split_once(" infoframe, version").map(|(kind, _)| {
+                kind.rsplit(": ")
+                    .next()
+                    .unwrap_or_default()
+                    .trim()
+                    .to_owned()
+            })
+        };
+
+        let mut frames = Self::default();
+        for (idx, msg) in messages.iter().enumerate() {
+            let Some(kind) = header(msg) else {
+                continue;
+            };
+
+            let lines = messages
+                .iter()
+                .skip(idx + 1)
+                .take_while(|msg| header(msg).is_none() && !msg.contains("-----"))
+                .map(String::as_str);
+
+            match kind.as_str() {
+                "Auxiliary Video Information (AVI)" | "AVI" => {
+                    frames.avi = Some(AviInfoFrame::from_log(lines)?);
+                }
+                "Audio" => frames.audio = Some(AudioInfoFrame::from_log(lines)?),
+                "Vendor" => frames.vendor = Some(VendorInfoFrame::from_log(lines)?),
+                // We can't tell a receiver that doesn't log the DRM InfoFrame from a source that
+                // doesn't send one.
+                "Dynamic Range and Mastering" => {
+                    frames.drm = Some(DrmInfoFrame::from_log(lines)?);
+                    frames.drm_reported = true;
+                }
+                _ => debug!("Ignoring the {} InfoFrame", kind),
+            }
+        }
+
+        Ok(frames)
+    }
+
+    // Asks the driver to log its status, and collects what it logged.
+    fn from_log_status(dev: &V4l2EntityWrapper) -> Result<Self, InfoFrameError> {
+        let Some(device) = &dev.device else {
+            return Ok(Self::default());
+        };
+
+        let kmsg = open(
+            KMSG_PATH,
+            OFlags::RDONLY | OFlags::NONBLOCK | OFlags::CLOEXEC,
+            Mode::empty(),
+        )?;
+        seek(&kmsg, SeekFrom::End(0))?;
+
+        debug!("Running VIDIOC_LOG_STATUS on entity {}", dev.entity.name());
+        v4l2_ioctl_log_status(device.as_fd())?;
+
+        // Each read returns a single record, formatted as "prio,seq,timestamp,flags;message".
+        let mut messages = Vec::new();
+        let mut buf = vec![0; KMSG_RECORD_MAX_LEN];
+        loop {
+            let len = match read(&kmsg, &mut buf) {
+                Ok(len) => len,
+                Err(Errno::AGAIN) => break,
+                Err(Errno::PIPE) => continue,
+                Err(e) => return Err(e.into()),
+            };
+
+            let record = String::from_utf8_lossy(buf.get(..len).unwrap_or_default());
+            if let Some((_, message)) = record.split_once(';') {
+                messages.push(message.lines().next().unwrap_or_default().to_owned());
+            }
+        }
+
+        Self::from_log(&messages)
+    }
+}
+
+/// Retrieves the InfoFrames the HDMI receiver got.
+///
+/// The receiver debugfs entries are used if they exist, otherwise we fall back to parsing what
+/// the driver logs in the kernel log on `VIDIOC_LOG_STATUS`.
+pub(crate) fn bridge_read_infoframes(
+    dev: &V4l2EntityWrapper,
+) -> Result<InfoFrames, InfoFrameError> {
+    let name = dev.entity.name().valid();
+
+    if let Some(frames) = InfoFrames::from_debugfs(&name)? {
+        debug!("Retrieved the InfoFrames from debugfs");
+        return Ok(frames);
+    }
+
+    InfoFrames::from_log_status(dev)
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+pub(crate) struct TestAviInfoFrame {
+    #[serde(default)]
+    vic: Option<u8>,
+
+    #[serde(default)]
+    colorspace: Option<AviColorspace>,
+
+    #[serde(default)]
+    colorimetry: Option<Colorspace>,
+
+    #[serde(rename = "quantization-range", default)]
+    quantization_range: Option<BroadcastRgb>,
+
+    #[serde(rename = "content-type", default)]
+    content_type: Option<ContentType>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+pub(crate) struct TestAudioInfoFrame {
+    #[serde(default)]
+    channels: Option<u8>,
+
+    #[serde(rename = "sample-rate", default)]
+    sample_rate: Option<u32>,
+
+    #[serde(rename = "sample-size", default)]
+    sample_size: Option<u8>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+pub(crate) struct TestDrmInfoFrame {
+    #[serde(default)]
+    eotf: Option<Eotf>,
+}
+
+/// The InfoFrames fields a test expects.
+///
+/// The fields that aren't set are expected to match the connector properties the source reported
+/// in its metadata, if any.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+pub(crate) struct TestInfoFrames {
+    #[serde(default)]
+    avi: TestAviInfoFrame,
+
+    #[serde(default)]
+    audio: TestAudioInfoFrame,
+
+    /// Only checked if the receiver reports the DRM InfoFrame.
+    #[serde(default)]
+    drm: TestDrmInfoFrame,
+}
+
+fn check_field<T>(errors: &mut Vec<String>, name: &str, expected: Option<T>, found: Option<T>)
+where
+    T: fmt::Display + PartialEq,
+{
+    let Some(expected) = expected else {
+        return;
+    };
+
+    match found {
+        Some(found) if found == expected => {}
+        Some(found) => errors.push(format!("{name} is {found}, expected {expected}")),
+        None => errors.push(format!("{name} is missing, expected {expected}")),
+    }
+}
+
+impl TestInfoFrames {
+    /// Checks the InfoFrames against our expectations, and the connector properties of the
+    /// source.
+    pub(crate) fn check(
+        &self,
+        frames: &InfoFrames,
+        output: Option<&OutputProperties>,
+    ) -> Result<(), String> {
+        let output = output.copied().unwrap_or_default();
+        let mut errors = Vec::new();
+
+        let avi = frames.avi;
+        check_field(
+            &mut errors,
+            "AVI VIC",
+            self.avi.vic,
+            avi.map(|frame| frame.vic),
+        );
+        check_field(
+            &mut errors,
+            "AVI Colorspace",
+            self.avi.colorspace,
+            avi.map(|frame| frame.colorspace),
+        );
+        check_field(
+            &mut errors,
+            "AVI Colorimetry",
+            self.avi.colorimetry.or(output.colorspace),
+            avi.map(|frame| frame.colorimetry),
+        );
+        check_field(
+            &mut errors,
+            "AVI Quantization Range",
+            self.avi.quantization_range.or(output
+                .broadcast_rgb
+                .filter(|range| *range != BroadcastRgb::Automatic)),
+            avi.map(|frame| frame.quantization_range),
+        );
+        check_field(
+            &mut errors,
+            "AVI Content Type",
+            self.avi.content_type.or(output.content_type),
+            avi.map(|frame| frame.content_type),
+        );
+
+        // Setting HDR metadata should make the source send a DRM InfoFrame with them.
+        let hdr_metadata = output.hdr_output_metadata;
+        let expected_eotf = self.drm.eotf.or(hdr_metadata.map(|metadata| metadata.eotf));
+        if frames.drm_reported {
+            let drm = frames.drm;
+            check_field(
+                &mut errors,
+                "DRM EOTF",
+                expected_eotf,
+                drm.map(|frame| frame.metadata.eotf),
+            );
+
+            if let (Some(expected), Some(frame)) = (hdr_metadata, drm) {
+                if frame.metadata.eotf == expected.eotf && frame.metadata != expected {
+                    errors.push(String::from(
+                        "DRM static metadata don't match the ones the source set",
+                    ));
+                }
+            }
+        } else if expected_eotf.is_some() {
+            warn!("The receiver doesn't report the DRM InfoFrame, skipping its checks.");
+        }
+
+        let audio = frames.audio;
+        check_field(
+            &mut errors,
+            "Audio Channels",
+            self.audio.channels,
+            audio.and_then(|frame| frame.channels),
+        );
+        check_field(
+            &mut errors,
+            "Audio Sample Rate",
+            self.audio.sample_rate,
+            audio.and_then(|frame| frame.sample_rate),
+        );
+        check_field(
+            &mut errors,
+            "Audio Sample Size",
+            self.audio.sample_size,
+            audio.and_then(|frame| frame.sample_size),
+        );
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_infoframes {
+    use frame_check::{BroadcastRgb, Colorspace, ContentType, Eotf, HdrMetadata, OutputProperties};
+
+    use super::{
+        AviColorspace, AviInfoFrame, DrmInfoFrame, InfoFrames, TestInfoFrames, VendorInfoFrame,
+        infoframe_payload,
+    };
+
+    fn hdr_metadata() -> HdrMetadata {
+        HdrMetadata {
+            eotf: Eotf::SmpteSt2084,
+            display_primaries: [(35_400, 14_600), (8_500, 39_850), (6_550, 2_300)],
+            white_point: (15_635, 16_450),
+            max_display_mastering_luminance: 1000,
+            min_display_mastering_luminance: 50,
+            max_cll: 1000,
+            max_fall: 400,
+        }
+    }
+
+    // 720p60, RGB, BT.2020, full range, game content type.
+    const AVI_INFOFRAME: [u8; 17] = [
+        0x82, 0x02, 0x0d, 0x00, 0x00, 0xc0, 0xe8, 0x04, 0x30, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00,
+    ];
+
+    fn avi_infoframe() -> Vec<u8> {
+        let mut bytes = AVI_INFOFRAME.to_vec();
+        bytes[3] = 0_u8.wrapping_sub(bytes.iter().fold(0_u8, |sum, b| sum.wrapping_add(*b)));
+        bytes
+    }
+
+    #[test]
+    fn test_avi_payload() {
+        let bytes = avi_infoframe();
+        let frame =
+            AviInfoFrame::from_payload(infoframe_payload(&bytes, 0x82, "AVI").unwrap()).unwrap();
+
+        assert_eq!(frame.vic, 4);
+        assert_eq!(frame.colorspace, AviColorspace::Rgb);
+        assert_eq!(frame.colorimetry, Colorspace::Bt2020Rgb);
+        assert_eq!(frame.quantization_range, BroadcastRgb::Full);
+        assert_eq!(frame.content_type, ContentType::Game);
+    }
+
+    #[test]
+    fn test_avi_checksum() {
+        let mut bytes = avi_infoframe();
+        bytes[3] = bytes[3].wrapping_add(1);
+
+        assert!(infoframe_payload(&bytes, 0x82, "AVI").is_err());
+        assert!(infoframe_payload(&avi_infoframe(), 0x84, "Audio").is_err());
+    }
+
+    #[test]
+    fn test_avi_log() {
+        let messages = [
+            "tc358743 10-000f: -----HDMI status-----",
+            "tc358743 10-000f: AVI infoframe, version 2 (length 13)",
+            "tc358743 10-000f:     colorspace: YCbCr 4:4:4",
+            "tc358743 10-000f:     scan mode: No Data",
+            "tc358743 10-000f:     colorimetry: ITU709",
+            "tc358743 10-000f:     picture aspect: 16:9",
+            "tc358743 10-000f:     itc: No Data",
+            "tc358743 10-000f:     extended colorimetry: xvYCC 601",
+            "tc358743 10-000f:     quantization range: Limited",
+            "tc358743 10-000f:     video code: 16",
+            "tc358743 10-000f:     hdmi content type: Graphics",
+            "tc358743 10-000f: -----Test pattern-----",
+            "tc358743 10-000f:     colorspace: RGB",
+        ]
+        .map(String::from);
+
+        let frames = InfoFrames::from_log(&messages).unwrap();
+        let avi = frames.avi.unwrap();
+
+        assert_eq!(avi.vic, 16);
+        assert_eq!(avi.colorspace, AviColorspace::Yuv444);
+        assert_eq!(avi.colorimetry, Colorspace::Bt709Ycc);
+        assert_eq!(avi.quantization_range, BroadcastRgb::Limited);
+        assert_eq!(avi.content_type, ContentType::NoData);
+        assert_eq!(frames.audio, None);
+    }
+
+    #[test]
+    fn test_avi_log_reserved_colorimetry() {
+        let messages = [
+            "tc358743 10-000f: AVI infoframe, version 2 (length 13)",
+            "tc358743 10-000f:     colorspace: RGB",
+            "tc358743 10-000f:     colorimetry: Extended",
+            "tc358743 10-000f:     extended colorimetry: Reserved",
+        ]
+        .map(String::from);
+
+        assert!(InfoFrames::from_log(&messages).is_err());
+    }
+
+    #[test]
+    fn test_vendor_payload() {
+        let frame = VendorInfoFrame::from_payload(&[0x03, 0x0c, 0x00, 0x20, 0x01]).unwrap();
+
+        assert_eq!(frame.oui, 0x00_0c03);
+        assert_eq!(frame.hdmi_vic, Some(1));
+    }
+
+    #[test]
+    fn test_check() {
+        let bytes = avi_infoframe();
+        let frames = InfoFrames {
+            avi: Some(
+                AviInfoFrame::from_payload(infoframe_payload(&bytes, 0x82, "AVI").unwrap())
+                    .unwrap(),
+            ),
+            ..InfoFrames::default()
+        };
+
+        let expected: TestInfoFrames = serde_yaml::from_str("avi: { vic: 4 }").unwrap();
+        assert_eq!(expected.check(&frames, None), Ok(()));
+
+        let output = OutputProperties {
+            broadcast_rgb: Some(BroadcastRgb::Limited),
+            ..OutputProperties::default()
+        };
+        assert!(expected.check(&frames, Some(&output)).is_err());
+
+        // The source sends a DRM InfoFrame as soon as it sets HDR metadata, but this receiver
+        // doesn't report it.
+        let output = OutputProperties {
+            hdr_output_metadata: Some(hdr_metadata()),
+            ..OutputProperties::default()
+        };
+        assert_eq!(expected.check(&frames, Some(&output)), Ok(()));
+
+        let reported = InfoFrames {
+            drm: Some(DrmInfoFrame {
+                metadata: hdr_metadata(),
+            }),
+            drm_reported: true,
+            ..frames
+        };
+        assert_eq!(expected.check(&reported, Some(&output)), Ok(()));
+
+        let output = OutputProperties {
+            hdr_output_metadata: Some(HdrMetadata {
+                max_cll: 500,
+                ..hdr_metadata()
+            }),
+            ..OutputProperties::default()
+        };
+        assert!(expected.check(&reported, Some(&output)).is_err());
+
+        let expected: TestInfoFrames = serde_yaml::from_str("drm: { eotf: hlg }").unwrap();
+        assert!(expected.check(&reported, None).is_err());
+        assert!(
+            expected
+                .check(
+                    &InfoFrames {
+                        drm_reported: true,
+                        ..frames
+                    },
+                    None
+                )
+                .is_err()
+        );
+        assert_eq!(expected.check(&frames, None), Ok(()));
+    }
+
+    #[test]
+    fn test_drm_payload() {
+        let mut payload = vec![0x02, 0x00];
+        for word in [
+            35_400_u16, 14_600, 8_500, 39_850, 6_550, 2_300, 15_635, 16_450,
+        ] {
+            payload.extend_from_slice(&word.to_le_bytes());
+        }
+        for word in [1000_u16, 50, 1000, 400] {
+            payload.extend_from_slice(&word.to_le_bytes());
+        }
+
+        let frame = DrmInfoFrame::from_payload(&payload).unwrap();
+        assert_eq!(frame.metadata, hdr_metadata());
+
+        payload[1] = 1;
+        assert!(DrmInfoFrame::from_payload(&payload).is_err());
+    }
+
+    #[test]
+    fn test_log() {
+        let messages = [
+            "adv7604 1-004c: -----Chip status-----",
+            "adv7604 1-004c: Auxiliary Video Information (AVI) infoframe, version 2 (length 13)",
+            "adv7604 1-004c:     colorspace: RGB",
+            "adv7604 1-004c:     quantization range: Full",
+            "adv7604 1-004c:     video code: 4",
+            "adv7604 1-004c: Audio infoframe, version 1 (length 10)",
+            "adv7604 1-004c:     channels: 1",
+            "adv7604 1-004c:     coding type: Refer to Stream Header",
+            "adv7604 1-004c:     sample size: 16 bit",
+            "adv7604 1-004c:     sample frequency: 48 kHz",
+            "adv7604 1-004c: Vendor infoframe, version 1 (length 5)",
+            "adv7604 1-004c:     HDMI VIC: 1",
+            "adv7604 1-004c: Dynamic Range and Mastering infoframe, version 1 (length 26)",
+            "adv7604 1-004c:     length: 26",
+            "adv7604 1-004c:     metadata type: 0",
+            "adv7604 1-004c:     eotf: 2",
+            "adv7604 1-004c:     x[0]: 35400",
+            "adv7604 1-004c:     y[0]: 14600",
+            "adv7604 1-004c:     x[1]: 8500",
+            "adv7604 1-004c:     y[1]: 39850",
+            "adv7604 1-004c:     x[2]: 6550",
+            "adv7604 1-004c:     y[2]: 2300",
+            "adv7604 1-004c:     white point x: 15635",
+            "adv7604 1-004c:     white point y: 16450",
+            "adv7604 1-004c:     max_display_mastering_luminance: 1000",
+            "adv7604 1-004c:     min_display_mastering_luminance: 50",
+            "adv7604 1-004c:     max_cll: 1000",
+            "adv7604 1-004c:     max_fall: 400",
+            "adv7604 1-004c: -----Test pattern-----",
+        ]
+        .map(String::from);
+
+        let frames = InfoFrames::from_log(&messages).unwrap();
+
+        let avi = frames.avi.unwrap();
+        assert_eq!(avi.vic, 4);
+        assert_eq!(avi.quantization_range, BroadcastRgb::Full);
+
+        let audio = frames.audio.unwrap();
+        assert_eq!(audio.channels, Some(3));
+        assert_eq!(audio.sample_rate, Some(48_000));
+        assert_eq!(audio.sample_size, Some(16));
+
+        assert_eq!(frames.vendor.unwrap().hdmi_vic, Some(1));
+
+        assert!(frames.drm_reported);
+        assert_eq!(frames.drm.unwrap().metadata, hdr_metadata());
+    }
+}
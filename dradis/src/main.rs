@@ -16,11 +16,18 @@ use anyhow::Context as _;
 use clap::{Parser, ValueEnum};
 use dma_buf::{DmaBuf, MappedDmaBuf};
 use dma_heap::{Heap, HeapKind};
-use frame_check::{DecodeCheckArgs, DecodeCheckArgsDump, decode_and_check_frame};
-use linux_mc::{MediaController, MediaControllerEntity, MediaControllerPad, media_entity_function};
+use frame_check::{
+    Control, ControlMode, DecodeCheckArgs, DecodeCheckArgsDump, FrameError, Pattern, SourceMode,
+    decode_and_check_frame,
+};
+use linux_mc::{
+    MediaController, MediaControllerEntity, MediaControllerPad, MediaControllerPipeline,
+    MediaControllerPipelineItem, MediaControllerTraversalDirection, MediaControllerTraversalOrder,
+    RevocableValue, media_entity_function,
+};
 use redid::EdidTypeConversionError;
 use rustix::io::Errno;
-use serde::Deserialize;
+use serde::{Deserialize, de::Error as _};
 use serde_with::{DurationSeconds, serde_as};
 use thiserror::Error;
 use threads_pool::ThreadPool;
@@ -28,11 +35,15 @@ use tracing::{Level, debug, debug_span, error, info, trace, warn};
 use tracing_subscriber::fmt::format::FmtSpan;
 use v4l2_raw::{
     format::v4l2_pix_fmt,
-    raw::{v4l2_buf_type, v4l2_buffer, v4l2_field, v4l2_ioctl_querybuf, v4l2_memory},
+    raw::{
+        v4l2_buf_type, v4l2_buffer, v4l2_field, v4l2_ioctl_querybuf, v4l2_memory,
+        v4l2_subdev_format_whence,
+    },
     wrapper::{
         v4l2_event_subscription, v4l2_event_subscription_type, v4l2_event_type, v4l2_format,
-        v4l2_ioctl_dqevent, v4l2_ioctl_subdev_s_fmt, v4l2_ioctl_subscribe_event,
-        v4l2_subdev_format,
+        v4l2_ioctl_dqevent, v4l2_ioctl_subdev_g_fmt, v4l2_ioctl_subdev_g_routing,
+        v4l2_ioctl_subdev_s_fmt, v4l2_ioctl_subscribe_event, v4l2_mbus_framefmt,
+        v4l2_subdev_enable_streams, v4l2_subdev_format, v4l2_subdev_route,
     },
 };
 use v4lise::{Device, Queue};
@@ -45,11 +56,32 @@ mod built_info {
     include!(concat!(env!("OUT_DIR"), "/built.rs"));
 }
 
+mod audio;
+use crate::audio::{AudioCapture, TestAudio, audio_device};
+
+mod cec;
+use crate::cec::{TestCec, cec_device};
+
 mod helpers;
 use crate::helpers::{
     bridge_set_edid, dequeue_buffer, queue_buffer, start_streaming, wait_and_set_dv_timings,
 };
 
+mod hotplug;
+use crate::hotplug::{TestHotplugItem, test_hotplug};
+
+mod infoframes;
+use crate::infoframes::{TestInfoFrames, bridge_read_infoframes};
+
+mod negotiation;
+use crate::negotiation::{NegotiationError, negotiate_mbus_code};
+
+mod pacing;
+use crate::pacing::{FramePacing, buffer_timestamp, edid_refresh_mhz};
+
+mod pipeline;
+use crate::pipeline::{TestPipeline, apply_formats};
+
 const BUFFER_TYPE: v4l2_buf_type = v4l2_buf_type::V4L2_BUF_TYPE_VIDEO_CAPTURE;
 const MEMORY_TYPE: v4l2_memory = v4l2_memory::V4L2_MEMORY_DMABUF;
 const NUM_BUFFERS: u32 = 5;
@@ -60,6 +92,11 @@ const fn default_timeout() -> Duration {
     Duration::from_secs(10)
 }
 
+// Tight enough to tell 59.94Hz and 60Hz apart.
+const fn default_refresh_tolerance_mhz() -> u64 {
+    30
+}
+
 #[derive(Error, Debug)]
 enum SetupError {
     #[error("I/O Error {0}")]
@@ -70,6 +107,9 @@ enum SetupError {
 
     #[error("Value Error: {0}")]
     Value(String),
+
+    #[error("Format Negotiation Failed: {0}")]
+    Negotiation(#[from] NegotiationError),
 }
 
 impl<T> From<EdidTypeConversionError<T>> for SetupError
@@ -86,11 +126,26 @@ enum TestError {
     #[error("Test Needs to be Started Again")]
     Retry,
 
+    #[error("Media Pipeline Went Away")]
+    PipelineGone,
+
     #[error("No Frame Received")]
     NoFrameReceived,
 
     #[error("Test Setup Failed: {0}")]
     SetupFailed(#[from] SetupError),
+
+    #[error("Frame Pacing Check Failed: {0}")]
+    FramePacing(String),
+
+    #[error("InfoFrames Check Failed: {0}")]
+    InfoFrames(String),
+
+    #[error("Audio Check Failed: {0}")]
+    Audio(String),
+
+    #[error("CEC Check Failed: {0}")]
+    Cec(String),
 }
 
 fn find_endpoint_predicate(
@@ -122,114 +177,81 @@ fn find_endpoint_predicate(
     }
 }
 
-fn find_internal_routed_pad(
-    entity: &MediaControllerEntity,
-    pad: &MediaControllerPad,
-) -> Result<Option<MediaControllerPad>, io::Error> {
-    let other = pad.kind().valid().other();
+fn find_dev_and_subdev(
+    mc: &MediaController,
+    bridge_name: Option<&str>,
+) -> Result<Vec<MediaControllerPipeline>, io::Error> {
+    debug!("Starting to discover our pipeline.");
 
-    let mut other_pads = entity
-        .pads()
-        .valid()?
+    let bridges = mc
+        .find_entities_by_function(media_entity_function::MEDIA_ENT_F_VID_IF_BRIDGE)?
         .into_iter()
-        .filter(|p| p.kind().valid() == other)
-        .collect::<Vec<_>>();
+        .filter_map(find_endpoint_predicate)
+        .collect::<Result<Vec<_>, io::Error>>()?;
 
-    Ok(match other_pads.len() {
-        0 => None,
-        1 => Some(other_pads.remove(0)),
-        2.. => {
-            // If it's a subdev, we should be calling g_routing. It's not enabled by default though,
-            // so we'll just take the first pad in the list, and hope for the best.
-            Some(other_pads.remove(0))
+    let mut pipelines = Vec::new();
+    for bridge in bridges {
+        if let Some(name) = bridge_name {
+            if bridge.name().valid() != name {
+                debug!("Ignoring HDMI bridge {}", bridge.name());
+                continue;
+            }
         }
-    })
-}
 
-#[derive(Debug)]
-struct MediaPipelineItem {
-    sink_pad: Option<MediaControllerPad>,
-    entity: MediaControllerEntity,
-    source_pad: Option<MediaControllerPad>,
-}
+        debug!("Found an HDMI bridge: {}", bridge.name());
 
-fn find_dev_and_subdev(mc: &MediaController) -> Result<Vec<MediaPipelineItem>, io::Error> {
-    let mut outputs = Vec::new();
-    let entities = mc.entities()?;
+        let entities = bridge
+            .traverse(
+                MediaControllerTraversalOrder::BreadthFirst,
+                MediaControllerTraversalDirection::Downstream,
+                false,
+            )
+            .valid()?;
 
-    debug!("Starting to discover our pipeline.");
+        for entity in entities {
+            if !entity.is_v4l2_device().valid()? {
+                continue;
+            }
 
-    let mut endpoints = entities.into_iter().filter_map(find_endpoint_predicate);
-    let hdmi_bridge = endpoints
-        .next()
-        .transpose()?
-        .ok_or(io::Error::from(io::ErrorKind::NotFound))?;
+            for pipeline in bridge.pipelines_to(&entity, false).valid()? {
+                debug!("Found a pipeline: {pipeline}");
 
-    if endpoints.next().is_some() {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidInput,
-            "Found more than one HDMI bridge",
-        ));
+                pipelines.push(pipeline);
+            }
+        }
     }
 
-    debug!("Found an HDMI bridge: {}", hdmi_bridge.name());
-
-    let hdmi_bridge_pad = hdmi_bridge
-        .pad(0)
-        .valid()?
-        .ok_or(io::Error::from(io::ErrorKind::NotFound))?;
-
-    debug!(
-        "Found our pipeline source: {}, pad {}",
-        hdmi_bridge.name(),
-        hdmi_bridge_pad.index()
-    );
-
-    outputs.push(MediaPipelineItem {
-        sink_pad: None,
-        entity: hdmi_bridge,
-        source_pad: Some(hdmi_bridge_pad.clone()),
-    });
-
-    let mut prev_source_pad = hdmi_bridge_pad;
-    loop {
-        let sink_pad = prev_source_pad
-            .remote_pad()
-            .valid()?
-            .ok_or(io::Error::from(io::ErrorKind::NotFound))?;
-
-        let entity = sink_pad.entity().valid();
+    Ok(pipelines)
+}
 
-        let source_pad = find_internal_routed_pad(&entity, &sink_pad)?;
+// Returns the format the sub-device right before the video device outputs
+fn capture_mbus_format(pipeline: &[PipelineItem]) -> io::Result<Option<v4l2_mbus_framefmt>> {
+    let Some(PipelineItem {
+        source_pad: Some(source_pad),
+        source_stream,
+        entity: wrapper,
+        ..
+    }) = pipeline.get(1)
+    else {
+        return Ok(None);
+    };
 
-        outputs.push(MediaPipelineItem {
-            sink_pad: Some(sink_pad.clone()),
-            entity: entity.clone(),
-            source_pad: source_pad.clone(),
-        });
+    let Some(subdev) = wrapper.device.as_ref() else {
+        return Ok(None);
+    };
 
-        // Should we test whether it's a v4l2 device and / or a default controller?
-        if let Some(source_pad) = source_pad {
-            debug!(
-                "Found an intermediate entity {}, input pad {}, output pad {}",
-                entity.name(),
-                sink_pad.index(),
-                source_pad.index()
-            );
-            prev_source_pad = source_pad;
-        } else {
-            debug!(
-                "Found the root entity {}, input pad {}",
-                entity.name(),
-                sink_pad.index()
-            );
+    let subdev_fmt = v4l2_subdev_format::new_active().set_pad(source_pad.index().valid());
 
-            break;
-        }
-    }
+    // Streams are only there if the sub-device supports routing.
+    let subdev_fmt = if wrapper.routes.is_some() {
+        subdev_fmt.set_stream(*source_stream)
+    } else {
+        subdev_fmt
+    };
 
-    outputs.reverse();
-    Ok(outputs)
+    Ok(Some(
+        v4l2_ioctl_subdev_g_fmt(subdev.as_fd(), subdev_fmt)?.format(),
+    ))
 }
 
 #[expect(
@@ -244,10 +266,40 @@ fn test_prepare_queue(
 ) -> Result<(), SetupError> {
     wait_and_set_dv_timings(suite, test.expected_width, test.expected_height)?;
 
-    let _: v4l2_pix_fmt = queue
+    // The pixel formats the video device can capture to, along with the media bus code it needs
+    // to receive for each, in order of preference. RGB24 is what we've always used, so it comes
+    // first.
+    let mut formats = queue
         .get_pixel_formats()
-        .find(|fmt| *fmt == v4l2_pix_fmt::V4L2_PIX_FMT_RGB24)
-        .expect("Couldn't find our format");
+        .filter_map(|fmt| fmt.to_mipi_csi2_mbus_pixelcode().map(|code| (fmt, code)))
+        .collect::<Vec<_>>();
+    formats.sort_by_key(|(fmt, _)| *fmt != v4l2_pix_fmt::V4L2_PIX_FMT_RGB24);
+
+    let candidates = formats.iter().map(|(_, code)| *code).collect::<Vec<_>>();
+    if candidates.is_empty() {
+        return Err(SetupError::from(io::Error::new(
+            Errno::INVAL.kind(),
+            "Couldn't find a pixel format with a mediabus equivalent",
+        )));
+    }
+
+    let code = negotiate_mbus_code(
+        &suite.pipeline,
+        &candidates,
+        test.expected_width,
+        test.expected_height,
+    )?;
+
+    let pixel_format = formats
+        .iter()
+        .find(|(_, candidate)| *candidate == code)
+        .map(|(fmt, _)| *fmt)
+        .ok_or(SetupError::from(io::Error::new(
+            Errno::INVAL.kind(),
+            "Couldn't find the pixel format for the negotiated mediabus format",
+        )))?;
+
+    debug!("Using mediabus format {code}");
 
     let pix_fmt = if let v4l2_format::VideoCapture(pix_fmt) = queue
         .get_current_format()
@@ -258,16 +310,25 @@ fn test_prepare_queue(
             .set_height(test.expected_height)
             // Reset the bytes per line field to avoid inheriting the one from the previous format.
             .set_bytes_per_line(0)
-            .set_pixel_format(v4l2_pix_fmt::V4L2_PIX_FMT_RGB24)
+            .set_pixel_format(pixel_format)
             .set_field(v4l2_field::V4L2_FIELD_NONE)
     } else {
         unreachable!()
     };
 
+    let mbus_fmt = pix_fmt.to_v4l2_mbus_framefmt(code).map_err(|_e| {
+        SetupError::from(io::Error::new(
+            Errno::INVAL.kind(),
+            "Couldn't convert v4l2_pix_format to v4l2_mbus_framefmt",
+        ))
+    })?;
+
     for PipelineItem {
         source_pad,
+        source_stream,
         entity: wrapper,
         sink_pad,
+        sink_stream,
     } in &suite.pipeline
     {
         trace!("Trying to set format on entity {}", wrapper.entity.name());
@@ -280,26 +341,18 @@ fn test_prepare_queue(
             continue;
         };
 
-        let mbus_fmt = pix_fmt
-            .to_v4l2_mbus_framefmt(pix_fmt.pixel_format().to_mipi_csi2_mbus_pixelcode().ok_or(
-                SetupError::from(io::Error::new(
-                    Errno::INVAL.kind(),
-                    "Couldn't find a mediabus format for the pixel format",
-                )),
-            )?)
-            .map_err(|_e| {
-                SetupError::from(io::Error::new(
-                    Errno::INVAL.kind(),
-                    "Couldn't convert v4l2_pix_format to v4l2_mbus_framefmt",
-                ))
-            })?;
-
         if let Some(source_pad) = source_pad {
             let subdev_fmt = v4l2_subdev_format::new_active()
                 .set_pad(source_pad.index().valid())
-                .set_stream(0)
                 .set_format(mbus_fmt);
 
+            // Streams are only there if the sub-device supports routing.
+            let subdev_fmt = if wrapper.routes.is_some() {
+                subdev_fmt.set_stream(*source_stream)
+            } else {
+                subdev_fmt
+            };
+
             debug!(
                 "Entity {}, Pad {} (Source): Setting {}",
                 wrapper.entity.name(),
@@ -322,11 +375,17 @@ fn test_prepare_queue(
         if let Some(sink_pad) = sink_pad {
             let subdev_fmt = v4l2_subdev_format::new_active()
                 .set_pad(sink_pad.index().valid())
-                .set_stream(0)
                 .set_format(mbus_fmt);
 
+            // Streams are only there if the sub-device supports routing.
+            let subdev_fmt = if wrapper.routes.is_some() {
+                subdev_fmt.set_stream(*sink_stream)
+            } else {
+                subdev_fmt
+            };
+
             debug!(
-                "Entity {}, Pad {} (Source): Setting {}",
+                "Entity {}, Pad {} (Sink): Setting {}",
                 wrapper.entity.name(),
                 sink_pad.index(),
                 subdev_fmt
@@ -372,6 +431,37 @@ fn test_prepare_queue(
         }
     }
 
+    apply_formats(&suite.mc, &suite.cfg.pipeline.formats)?;
+
+    // The formats of the configuration might have changed what the video device receives, so
+    // we capture that instead of what we negotiated.
+    let captured = if suite.cfg.pipeline.formats.is_empty() {
+        None
+    } else {
+        capture_mbus_format(&suite.pipeline)?
+    };
+
+    let pix_fmt = if let Some(mbus_fmt) = captured {
+        let pixel_format = formats
+            .iter()
+            .find(|(_, candidate)| *candidate == mbus_fmt.code())
+            .map(|(fmt, _)| *fmt)
+            .ok_or(SetupError::from(io::Error::new(
+                Errno::INVAL.kind(),
+                "Couldn't find the pixel format for the configured mediabus format",
+            )))?;
+
+        debug!("Configured mediabus format {}", mbus_fmt);
+
+        pix_fmt
+            .set_width(mbus_fmt.width())
+            .set_height(mbus_fmt.height())
+            .set_bytes_per_line(0)
+            .set_pixel_format(pixel_format)
+    } else {
+        pix_fmt
+    };
+
     let ret_fmt = queue
         .set_format(v4l2_format::VideoCapture(pix_fmt))
         .expect("Couldn't change our queue format");
@@ -433,6 +523,19 @@ fn test_run(
             "Missing V4L2 HDMI Bridge Device",
         )))?;
 
+    let audio_path = test
+        .audio
+        .map(|_| audio_device(cli, &bridge.entity))
+        .transpose()
+        .map_err(|e| TestError::Audio(e.to_string()))?;
+
+    let cec_path = test
+        .cec
+        .as_ref()
+        .map(|_| cec_device(cli, &bridge.entity))
+        .transpose()
+        .map_err(|e| TestError::Cec(e.to_string()))?;
+
     test_prepare_queue(suite, queue, test)?;
 
     queue
@@ -473,9 +576,15 @@ fn test_run(
     let _stream = start_streaming(root_device, BUFFER_TYPE).expect("Couldn't start streaming");
 
     let start = Instant::now();
+    let mut pacing = FramePacing::default();
     let mut first_frame_valid = None;
     let mut last_frame_valid = None;
     let mut last_frame_index = None;
+    // The first valid frame we see might have been left over by a previous instance of the source,
+    // which won't emit any more frames, so we switch to any other session showing up.
+    let mut last_session = None;
+    let mut last_output = None;
+    let mut audio = None;
     loop {
         if last_frame_valid.is_none() && start.elapsed() > suite.cfg.valid_frame_timeout {
             error!(
@@ -518,18 +627,17 @@ fn test_run(
 
             sleep(Duration::from_millis(5));
         }
-        .expect("Couldn't dequeue our buffer");
+        .map_err(|e| capture_error(suite, e))?;
 
         let idx = vbuf.index;
         let buf = &buffers[idx as usize];
         debug_span!("Frame Processing").in_scope(|| {
-            if let Ok(metadata) = buf.read(
+            let res = buf.read(
                 |b, a| {
-                    decode_and_check_frame(
+                    Ok(decode_and_check_frame(
                         &b[..(vbuf.bytesused as usize)],
                         a.expect("Missing arguments"),
-                    )
-                    .map_err(Into::into)
+                    ))
                 },
                 Some(DecodeCheckArgs {
                     sequence: vbuf.sequence,
@@ -545,27 +653,95 @@ fn test_run(
                         CliDump::Corrupted => DecodeCheckArgsDump::Corrupted(pool.clone()),
                         CliDump::Never => DecodeCheckArgsDump::Never,
                     },
+                    expected_test_id: test.control.map(|control| control.test_id),
+                    expected_mode: Some(test.expected_mode()),
+                    expected_session: last_session,
                 }),
-            ) {
-                debug!("Frame {} Valid", metadata.index);
-                if first_frame_valid.is_none() {
-                    first_frame_valid = Some(Instant::now());
-                    info!("Source started to transmit a valid frame");
-                }
+            );
 
-                last_frame_index = Some(metadata.index);
-                last_frame_valid = Some(Instant::now());
-            } else {
-                debug!("Frame Invalid.");
-                last_frame_index = None;
+            match res {
+                Ok(Ok(metadata)) => {
+                    debug!("Frame {} Valid", metadata.index);
+                    if first_frame_valid.is_none() {
+                        first_frame_valid = Some(Instant::now());
+                        info!("Source started to transmit a valid frame");
+                    }
+
+                    pacing.record_source(metadata.index, metadata.timing, buffer_timestamp(&vbuf));
+
+                    last_frame_index = Some(metadata.index);
+                    last_frame_valid = Some(Instant::now());
+                    last_session = metadata.session;
+                    last_output = Some(metadata.output);
+                }
+                Ok(Err(FrameError::TestMismatch)) => {
+                    debug!("Frame emitted for another test, ignoring.");
+                }
+                Ok(Err(FrameError::SessionMismatch(session))) => {
+                    info!("Source session changed, restarting the measurements.");
+
+                    pacing = FramePacing::default();
+                    // The audio capture starts again along with the next valid frame.
+                    audio = None;
+                    first_frame_valid = None;
+                    last_frame_index = None;
+                    last_session = session;
+                }
+                Ok(Err(_)) | Err(_) => {
+                    debug!("Frame Invalid.");
+                    last_frame_index = None;
+                }
             }
         });
 
-        queue_buffer(root_device, idx, buf.as_raw_fd()).expect("Couldn't queue our buffer");
+        // The buffers captured before the source started to emit valid frames have nothing to do
+        // with its pacing.
+        if first_frame_valid.is_some() {
+            pacing.record_capture(buffer_timestamp(&vbuf), vbuf.sequence);
+
+            if audio.is_none() {
+                if let (Some(expected), Some(path)) = (test.audio, &audio_path) {
+                    audio = Some(AudioCapture::start(path.clone(), expected));
+                }
+            }
+        }
+
+        queue_buffer(root_device, idx, buf.as_raw_fd()).map_err(|e| capture_error(suite, e))?;
 
         if let Some(duration) = test.duration {
             if let Some(first) = first_frame_valid {
                 if first.elapsed() > duration {
+                    info!("Frame Pacing: {}", pacing);
+
+                    pacing
+                        .check_refresh(test.expected_refresh_mhz(), test.refresh_tolerance_mhz)
+                        .map_err(TestError::FramePacing)?;
+
+                    if let Some(expected) = &test.infoframes {
+                        let infoframes = bridge_read_infoframes(bridge)
+                            .map_err(|e| TestError::InfoFrames(e.to_string()))?;
+                        info!("InfoFrames: {}", infoframes);
+
+                        expected
+                            .check(&infoframes, last_output.as_ref())
+                            .map_err(TestError::InfoFrames)?;
+                    }
+
+                    if let (Some(expected), Some(capture)) = (&test.audio, audio.take()) {
+                        let report = capture
+                            .finish()
+                            .map_err(|e| TestError::Audio(e.to_string()))?;
+                        info!("Audio: {}", report);
+
+                        expected.check(&report).map_err(TestError::Audio)?;
+                    }
+
+                    if let (Some(expected), Some(path)) = (&test.cec, &cec_path) {
+                        expected
+                            .check(path)
+                            .map_err(|e| TestError::Cec(e.to_string()))?;
+                    }
+
                     info!("Test Passed");
                     break;
                 }
@@ -576,7 +752,9 @@ fn test_run(
     Ok(())
 }
 
-fn test_display_one_mode(args: &Cli, suite: &Dradis<'_>, test: &TestItem) -> Result<(), TestError> {
+// Captures and checks the frames until the test is over, starting again whenever the source
+// changes.
+fn test_capture(args: &Cli, suite: &Dradis<'_>, test: &TestItem) -> Result<(), TestError> {
     let PipelineItem { entity: root, .. } =
         suite
             .pipeline
@@ -618,8 +796,6 @@ fn test_display_one_mode(args: &Cli, suite: &Dradis<'_>, test: &TestItem) -> Res
     )
     .map_err(SetupError::from)?;
 
-    bridge_set_edid(args, bridge, &test.edid)?;
-
     loop {
         match test_run(args, suite, &queue, test) {
             Ok(()) => break,
@@ -627,7 +803,13 @@ fn test_display_one_mode(args: &Cli, suite: &Dradis<'_>, test: &TestItem) -> Res
                 TestError::Retry => {
                     warn!("Test needs to be restarted.");
                 }
-                TestError::NoFrameReceived | TestError::SetupFailed(_) => {
+                TestError::NoFrameReceived
+                | TestError::PipelineGone
+                | TestError::SetupFailed(_)
+                | TestError::FramePacing(_)
+                | TestError::InfoFrames(_)
+                | TestError::Audio(_)
+                | TestError::Cec(_) => {
                     return Err(e);
                 }
             },
@@ -637,7 +819,22 @@ fn test_display_one_mode(args: &Cli, suite: &Dradis<'_>, test: &TestItem) -> Res
     Ok(())
 }
 
-#[derive(Debug, Deserialize)]
+fn test_display_one_mode(args: &Cli, suite: &Dradis<'_>, test: &TestItem) -> Result<(), TestError> {
+    let PipelineItem { entity: bridge, .. } =
+        suite
+            .pipeline
+            .last()
+            .ok_or(SetupError::from(io::Error::new(
+                Errno::NODEV.kind(),
+                "Missing HDMI Bridge Entity",
+            )))?;
+
+    bridge_set_edid(args, bridge, &test.edid, test.control.as_ref())?;
+
+    test_capture(args, suite, test)
+}
+
+#[derive(Clone, Debug, Deserialize)]
 struct TestEdidDetailedTiming {
     clock_khz: u32,
     hfp: u16,
@@ -650,15 +847,55 @@ struct TestEdidDetailedTiming {
     vsync: u8,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 #[serde(tag = "type", content = "timings")]
 enum TestEdid {
     #[serde(rename = "detailed")]
     DetailedTiming(TestEdidDetailedTiming),
 }
 
+#[derive(Clone, Copy, Debug, Deserialize)]
+struct TestControlMode {
+    width: u16,
+    height: u16,
+
+    #[serde(default)]
+    refresh: Option<u8>,
+}
+
+/// Instructions passed to Boomer through the EDID.
+#[derive(Clone, Copy, Debug, Deserialize)]
+struct TestControl {
+    #[serde(rename = "test-id")]
+    test_id: u32,
+
+    #[serde(rename = "ignore-hotplug", default)]
+    ignore_hotplug: bool,
+
+    #[serde(default)]
+    pattern: Option<Pattern>,
+
+    #[serde(default)]
+    mode: Option<TestControlMode>,
+}
+
+impl From<TestControl> for Control {
+    fn from(value: TestControl) -> Self {
+        Self {
+            test_id: value.test_id,
+            ignore_hotplug: value.ignore_hotplug,
+            pattern: value.pattern,
+            mode: value.mode.map(|mode| ControlMode {
+                width: mode.width,
+                height: mode.height,
+                refresh: mode.refresh,
+            }),
+        }
+    }
+}
+
 #[serde_as]
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 struct TestItem {
     #[serde_as(as = "Option<DurationSeconds<u64>>")]
     #[serde(default)]
@@ -670,7 +907,84 @@ struct TestItem {
     #[serde(rename = "expected-width")]
     expected_width: u32,
 
+    /// Maximum difference, in mHz, between the measured refresh rate and the one the EDID
+    /// timings should result in.
+    #[serde(
+        rename = "refresh-tolerance-mhz",
+        default = "default_refresh_tolerance_mhz"
+    )]
+    refresh_tolerance_mhz: u64,
+
     edid: TestEdid,
+
+    /// EDID whose timings the source is expected to emit, if not the one of the display. Only
+    /// set by hotplug tests expecting the source to ignore the new display.
+    #[serde(skip)]
+    timings_edid: Option<TestEdid>,
+
+    #[serde(default, deserialize_with = "deserialize_control")]
+    control: Option<Control>,
+
+    /// InfoFrames fields to check once the test is over. The connector properties the source
+    /// reports are also checked if set.
+    #[serde(default)]
+    infoframes: Option<TestInfoFrames>,
+
+    /// Audio to capture from the HDMI receiver while the frames are checked.
+    #[serde(default)]
+    audio: Option<TestAudio>,
+
+    /// CEC checks to run once the test is over.
+    #[serde(default)]
+    cec: Option<TestCec>,
+}
+
+impl TestItem {
+    fn expected_refresh_mhz(&self) -> u64 {
+        edid_refresh_mhz(self.timings_edid.as_ref().unwrap_or(&self.edid))
+    }
+
+    fn expected_mode(&self) -> SourceMode {
+        SourceMode {
+            width: self.expected_width,
+            height: self.expected_height,
+            refresh: u32::try_from((self.expected_refresh_mhz() + 500) / 1000).unwrap_or(u32::MAX),
+        }
+    }
+}
+
+fn deserialize_control<'de, D>(deserializer: D) -> Result<Option<Control>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(Option::<TestControl>::deserialize(deserializer)?.map(Control::from))
+}
+
+#[derive(Debug)]
+enum TestStep {
+    Display(TestItem),
+    Hotplug(TestHotplugItem),
+}
+
+impl<'de> Deserialize<'de> for TestStep {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        // An untagged enum would only tell us that the step didn't match any variant, so we pick
+        // the variant ourselves to report why it's actually invalid.
+        let value = serde_yaml::Value::deserialize(deserializer)?;
+
+        if value.get("hotplug").is_some() {
+            TestHotplugItem::deserialize(value)
+                .map(Self::Hotplug)
+                .map_err(|e| D::Error::custom(format!("Invalid hotplug test: {e}")))
+        } else {
+            TestItem::deserialize(value)
+                .map(Self::Display)
+                .map_err(|e| D::Error::custom(format!("Invalid display test: {e}")))
+        }
+    }
 }
 
 #[serde_as]
@@ -684,20 +998,27 @@ struct Test {
     #[serde(default = "default_timeout")]
     link_timeout: Duration,
 
-    tests: Vec<TestItem>,
+    /// Links and formats to set up on top of the automatic pipeline discovery.
+    #[serde(default)]
+    pipeline: TestPipeline,
+
+    tests: Vec<TestStep>,
 }
 
 #[derive(Debug)]
 struct V4l2EntityWrapper {
     entity: MediaControllerEntity,
     device: Option<Device>,
+    routes: Option<Vec<v4l2_subdev_route>>,
 }
 
 #[derive(Debug)]
 struct PipelineItem {
     source_pad: Option<MediaControllerPad>,
+    source_stream: u32,
     entity: V4l2EntityWrapper,
     sink_pad: Option<MediaControllerPad>,
+    sink_stream: u32,
 }
 
 impl fmt::Display for PipelineItem {
@@ -726,7 +1047,7 @@ impl fmt::Display for PipelineItem {
 
 #[derive(Debug)]
 pub(crate) struct Dradis<'a> {
-    cfg: Test,
+    cfg: &'a Test,
     mc: MediaController,
     pipeline: Vec<PipelineItem>,
     heap: &'a Heap,
@@ -755,6 +1076,24 @@ struct Cli {
     )]
     device: PathBuf,
 
+    #[arg(
+        long = "audio-device",
+        help = "ALSA PCM Capture Device File, if it can't be found in the media graph."
+    )]
+    audio_device: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Name of the HDMI bridge entity to use, if there's more than one."
+    )]
+    bridge: Option<String>,
+
+    #[arg(
+        long = "cec-device",
+        help = "CEC Device File, if it can't be found from the HDMI bridge."
+    )]
+    cec_device: Option<PathBuf>,
+
     #[arg(long = "dump-edid", help = "Folder to dump test EDIDs in.")]
     dump_edid: Option<PathBuf>,
 
@@ -775,6 +1114,241 @@ struct Cli {
     test: PathBuf,
 }
 
+fn subdev_routes(
+    entity: &MediaControllerEntity,
+    device: &Device,
+) -> io::Result<Option<Vec<v4l2_subdev_route>>> {
+    if !entity.is_v4l2_sub_device().valid()? {
+        return Ok(None);
+    }
+
+    if !v4l2_subdev_enable_streams(device.as_fd())? {
+        return Ok(None);
+    }
+
+    match v4l2_ioctl_subdev_g_routing(
+        device.as_fd(),
+        v4l2_subdev_format_whence::V4L2_SUBDEV_FORMAT_ACTIVE,
+    ) {
+        Ok(routes) => {
+            for route in &routes {
+                debug!("Entity {}: Route {}", entity.name(), route);
+            }
+
+            Ok(Some(routes))
+        }
+        // The sub-device doesn't support routing, so all its pads are connected together.
+        Err(e) if Errno::from_io_error(&e) == Some(Errno::NOTTY) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+// Opens the devices of a pipeline, and follows the sub-devices routes to find out the stream the
+// data goes through on each pad. Returns None if the routes don't connect the pipeline.
+fn open_pipeline(pipeline: MediaControllerPipeline) -> io::Result<Option<Vec<PipelineItem>>> {
+    let mut items = Vec::with_capacity(pipeline.len());
+    let mut stream = 0;
+
+    for MediaControllerPipelineItem {
+        sink_pad,
+        entity,
+        source_pad,
+    } in pipeline
+    {
+        let device = if let Some(itf) = entity.interfaces().valid()?.first() {
+            if let Some(node) = itf.device_node().valid() {
+                Some(Device::new(node.path(), true)?)
+            } else {
+                None
+            }
+        } else {
+            None
+        };
+
+        let routes = if let Some(device) = &device {
+            subdev_routes(&entity, device)?
+        } else {
+            None
+        };
+
+        let sink_stream = stream;
+        if let (Some(sink), Some(source), Some(routes)) = (&sink_pad, &source_pad, &routes) {
+            let sink_index = sink.index().valid();
+            let source_index = source.index().valid();
+
+            let Some(route) = routes.iter().find(|r| {
+                r.is_active()
+                    && r.sink_pad() == sink_index
+                    && r.sink_stream() == stream
+                    && r.source_pad() == source_index
+            }) else {
+                debug!(
+                    "Entity {} doesn't route pad {}, stream {} to pad {}. Ignoring pipeline.",
+                    entity.name(),
+                    sink_index,
+                    stream,
+                    source_index
+                );
+
+                return Ok(None);
+            };
+
+            stream = route.source_stream();
+        }
+
+        items.push(PipelineItem {
+            source_pad,
+            source_stream: stream,
+            entity: V4l2EntityWrapper {
+                entity,
+                device,
+                routes,
+            },
+            sink_pad,
+            sink_stream,
+        });
+    }
+
+    // The rest of the code expects the pipeline to start from the video device.
+    items.reverse();
+
+    Ok(Some(items))
+}
+
+fn build_pipeline(cli: &Cli, cfg: &Test, mc: &MediaController) -> io::Result<Vec<PipelineItem>> {
+    if !cfg.pipeline.links.is_empty() {
+        debug!("Setting the links up from the test description");
+        mc.setup_links(&cfg.pipeline.links)?;
+    }
+
+    let mut pipelines = Vec::new();
+    let mut candidates = Vec::new();
+
+    for pipeline in find_dev_and_subdev(mc, cli.bridge.as_deref())? {
+        if let Some(items) = open_pipeline(pipeline.clone())? {
+            pipelines.push(pipeline);
+            candidates.push(items);
+        }
+    }
+
+    let index = select_pipeline(&pipelines)?;
+    if let Some(pipeline) = pipelines.get(index) {
+        debug!("Using pipeline {pipeline}");
+    }
+
+    Ok(candidates.swap_remove(index))
+}
+
+// Picks the pipeline to use. If there's several candidates, the ones that have been enabled,
+// either by the test description or before we ran, are the ones we want. Some receivers can
+// send the frames to several video devices at once though, so if there's still more than one
+// left, we take the one going through the lowest pad indices to always end up with the same.
+fn select_pipeline(pipelines: &[MediaControllerPipeline]) -> io::Result<usize> {
+    let mut candidates = Vec::new();
+    for (index, pipeline) in pipelines.iter().enumerate() {
+        if pipelines.len() == 1 || pipeline.is_enabled().valid()? {
+            candidates.push((index, pipeline));
+        }
+    }
+
+    if pipelines.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "Couldn't find any HDMI pipeline.",
+        ));
+    }
+
+    if candidates.is_empty() {
+        let list = pipelines
+            .iter()
+            .map(|pipeline| format!("\t{pipeline}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!(
+                "None of the {} HDMI pipelines is enabled, enable one or set pipeline.links in \
+                 the test description:\n{list}",
+                pipelines.len()
+            ),
+        ));
+    }
+
+    let mut bridges = candidates
+        .iter()
+        .filter_map(|(_, pipeline)| pipeline.items().first())
+        .map(|item| item.entity.id().valid())
+        .collect::<Vec<_>>();
+    bridges.sort_unstable();
+    bridges.dedup();
+
+    if bridges.len() > 1 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Found more than one HDMI pipeline, select the bridge to use.",
+        ));
+    }
+
+    candidates
+        .into_iter()
+        .min_by_key(|(_, pipeline)| {
+            pipeline
+                .items()
+                .iter()
+                .map(|item| {
+                    (
+                        item.sink_pad.as_ref().map(|pad| pad.index().valid()),
+                        item.entity.id().valid(),
+                        item.source_pad.as_ref().map(|pad| pad.index().valid()),
+                    )
+                })
+                .collect::<Vec<_>>()
+        })
+        .map(|(index, _)| index)
+        .ok_or(io::Error::from(io::ErrorKind::NotFound))
+}
+
+// Refreshes the topology, and returns whether the entities of our pipeline have been revoked. The
+// topology changes are logged by the Media Controller itself.
+fn pipeline_is_stale(dradis: &Dradis<'_>) -> bool {
+    if let Err(e) = dradis.mc.events() {
+        warn!("Couldn't refresh the Media Controller topology: {e}");
+        return true;
+    }
+
+    dradis
+        .pipeline
+        .iter()
+        .any(|item| matches!(item.entity.entity.id(), RevocableValue::Revoked))
+}
+
+// A device error in the middle of a capture might come from the bridge driver being unbound, in
+// which case the test has to run again on the new pipeline.
+fn capture_error(dradis: &Dradis<'_>, err: io::Error) -> TestError {
+    if pipeline_is_stale(dradis) {
+        warn!("Media pipeline went away during the capture: {err}");
+        return TestError::PipelineGone;
+    }
+
+    TestError::SetupFailed(err.into())
+}
+
+// The bridge driver might have been unbound and bound again since the last test, in which case
+// the entities we hold have been revoked and we need to look the pipeline up again.
+fn refresh_pipeline(cli: &Cli, dradis: &mut Dradis<'_>) -> io::Result<()> {
+    if !pipeline_is_stale(dradis) {
+        return Ok(());
+    }
+
+    info!("Media pipeline is gone, looking it up again.");
+
+    dradis.mc = MediaController::new(&cli.device)?;
+    dradis.pipeline = build_pipeline(cli, dradis.cfg, &dradis.mc)?;
+
+    Ok(())
+}
+
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
@@ -808,46 +1382,307 @@ fn main() -> anyhow::Result<()> {
 
     debug!("Running from media controller {}", cli.device.display());
     let mc = MediaController::new(&cli.device)?;
-    let pipeline = find_dev_and_subdev(&mc)?
-        .into_iter()
-        .map(
-            |MediaPipelineItem {
-                 sink_pad,
-                 entity,
-                 source_pad,
-             }| {
-                let node = if let Some(itf) = entity.interfaces().valid()?.first() {
-                    if let Some(node) = itf.device_node().valid() {
-                        Some(Device::new(node.path(), true)?)
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                };
-
-                Ok(PipelineItem {
-                    source_pad,
-                    entity: V4l2EntityWrapper {
-                        entity,
-                        device: node,
-                    },
-                    sink_pad,
-                })
-            },
-        )
-        .collect::<Result<Vec<_>, io::Error>>()?;
+    let pipeline = build_pipeline(&cli, &test_config, &mc)?;
 
-    let dradis = Dradis {
-        cfg: test_config,
+    let mut dradis = Dradis {
+        cfg: &test_config,
         mc,
         pipeline,
         heap: &heap,
     };
 
-    for test in &dradis.cfg.tests {
-        test_display_one_mode(&cli, &dradis, test)?;
+    let mut current: Option<TestItem> = None;
+    for step in &test_config.tests {
+        let item = loop {
+            refresh_pipeline(&cli, &mut dradis)?;
+
+            let res = match step {
+                TestStep::Display(test) => {
+                    test_display_one_mode(&cli, &dradis, test).map(|()| test.clone())
+                }
+                TestStep::Hotplug(hotplug) => {
+                    let previous = current
+                        .as_ref()
+                        .context("Hotplug tests need a test before")?;
+
+                    test_hotplug(&cli, &dradis, hotplug, previous)
+                }
+            };
+
+            match res {
+                Err(TestError::PipelineGone) => {
+                    warn!("Media pipeline went away, running the test again.");
+                }
+                res => break res?,
+            }
+        };
+
+        current = Some(item);
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests_test_step {
+    use crate::TestStep;
+
+    #[test]
+    fn test_display() {
+        let step: TestStep = serde_yaml::from_str(
+            "
+            expected-height: 720
+            expected-width: 1280
+            edid:
+                type: detailed
+                timings:
+                    clock_khz: 74250
+                    hfp: 220
+                    hdisplay: 1280
+                    hbp: 110
+                    hsync: 40
+                    vfp: 20
+                    vdisplay: 720
+                    vbp: 5
+                    vsync: 5
+            ",
+        )
+        .unwrap();
+
+        assert!(matches!(step, TestStep::Display(_)));
+    }
+
+    #[test]
+    fn test_hotplug() {
+        let step: TestStep = serde_yaml::from_str(
+            "
+            duration: 10
+            hotplug:
+                disconnect-duration: 2
+                expect: same-timings
+            ",
+        )
+        .unwrap();
+
+        assert!(matches!(step, TestStep::Hotplug(_)));
+    }
+
+    #[test]
+    fn test_invalid_hotplug() {
+        let err = serde_yaml::from_str::<TestStep>(
+            "
+            hotplug:
+                disconnect-duration: 2
+                expect: other-timings
+            ",
+        )
+        .unwrap_err()
+        .to_string();
+
+        assert!(err.contains("Invalid hotplug test"), "{err}");
+        assert!(err.contains("other-timings"), "{err}");
+    }
+
+    #[test]
+    fn test_invalid_display() {
+        let err = serde_yaml::from_str::<TestStep>(
+            "
+            expected-height: 720
+            ",
+        )
+        .unwrap_err()
+        .to_string();
+
+        assert!(err.contains("Invalid display test"), "{err}");
+        assert!(err.contains("expected-width"), "{err}");
+    }
+}
+
+#[cfg(test)]
+mod tests_find_dev_and_subdev {
+    use linux_mc::{MediaController, MediaControllerLinkDescription, MediaControllerTopology};
+
+    use crate::{find_dev_and_subdev, select_pipeline};
+
+    fn controller(json: &str) -> MediaController {
+        MediaController::from_topology(&MediaControllerTopology::from_json(json).unwrap()).unwrap()
+    }
+
+    fn pipelines(mc: &MediaController, bridge: Option<&str>) -> Vec<(bool, String)> {
+        find_dev_and_subdev(mc, bridge)
+            .unwrap()
+            .into_iter()
+            .map(|p| (p.is_enabled().valid().unwrap(), p.to_string()))
+            .collect()
+    }
+
+    fn enabled_pipelines(mc: &MediaController, bridge: Option<&str>) -> Vec<String> {
+        pipelines(mc, bridge)
+            .into_iter()
+            .filter_map(|(enabled, desc)| enabled.then_some(desc))
+            .collect()
+    }
+
+    #[test]
+    fn raspberrypi4() {
+        let mc = controller(include_str!(
+            "../../docs/device-info/raspberrypi4+toshiba-tc358743/media-ctl-topology.json"
+        ));
+
+        assert_eq!(
+            pipelines(&mc, None),
+            [(
+                true,
+                String::from("tc358743 10-000f -> [0] => [0] -> unicam-image")
+            )]
+        );
+    }
+
+    #[test]
+    fn raspberrypi5() {
+        let mc = controller(include_str!(
+            "../../docs/device-info/raspberrypi5+geekworm-c779/media-ctl-topology.json"
+        ));
+
+        // The CSI-2 receiver can send the frames to any of its video devices, either directly or
+        // through the front-end, and none of the links is enabled by default.
+        assert_eq!(pipelines(&mc, None).len(), 13);
+        assert!(enabled_pipelines(&mc, None).is_empty());
+
+        let csi2 = mc.find_entity_by_name("csi2").unwrap().unwrap();
+        let ch0 = mc.find_entity_by_name("rp1-cfe-csi2_ch0").unwrap().unwrap();
+        mc.find_data_link_by_pads(
+            &csi2.pad(4).valid().unwrap().unwrap(),
+            &ch0.pad(0).valid().unwrap().unwrap(),
+        )
+        .valid()
+        .unwrap()
+        .unwrap()
+        .enable()
+        .valid()
+        .unwrap();
+
+        assert_eq!(
+            enabled_pipelines(&mc, None),
+            ["tc358743 11-000f -> [0] => [0] -> csi2 -> [4] => [0] -> rp1-cfe-csi2_ch0"]
+        );
+    }
+
+    #[test]
+    fn raspberrypi5_setup_links() {
+        let mc = controller(include_str!(
+            "../../docs/device-info/raspberrypi5+geekworm-c779/media-ctl-topology.json"
+        ));
+
+        mc.setup_links(
+            &MediaControllerLinkDescription::parse_list("\"csi2\":4 -> \"rp1-cfe-csi2_ch0\":0 [1]")
+                .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            enabled_pipelines(&mc, None),
+            ["tc358743 11-000f -> [0] => [0] -> csi2 -> [4] => [0] -> rp1-cfe-csi2_ch0"]
+        );
+    }
+
+    fn selected_pipeline(mc: &MediaController) -> String {
+        let pipelines = find_dev_and_subdev(mc, None).unwrap();
+        let index = select_pipeline(&pipelines).unwrap();
+
+        pipelines[index].to_string()
+    }
+
+    #[test]
+    fn raspberrypi5_several_enabled() {
+        let mc = controller(include_str!(
+            "../../docs/device-info/raspberrypi5+geekworm-c779/media-ctl-topology.json"
+        ));
+
+        let csi2 = mc.find_entity_by_name("csi2").unwrap().unwrap();
+        for (pad, name) in [(6, "rp1-cfe-csi2_ch2"), (4, "rp1-cfe-csi2_ch0")] {
+            let node = mc.find_entity_by_name(name).unwrap().unwrap();
+
+            mc.find_data_link_by_pads(
+                &csi2.pad(pad).valid().unwrap().unwrap(),
+                &node.pad(0).valid().unwrap().unwrap(),
+            )
+            .valid()
+            .unwrap()
+            .unwrap()
+            .enable()
+            .valid()
+            .unwrap();
+        }
+
+        assert_eq!(enabled_pipelines(&mc, None).len(), 2);
+
+        // The pipeline going through the lowest pad indices wins, whatever the order the links
+        // have been enabled in.
+        assert_eq!(
+            selected_pipeline(&mc),
+            "tc358743 11-000f -> [0] => [0] -> csi2 -> [4] => [0] -> rp1-cfe-csi2_ch0"
+        );
+    }
+
+    #[test]
+    fn rock5b_selected() {
+        let mc = controller(include_str!(
+            "../../docs/device-info/rock5b+toshiba-tc358743/media-ctl-topology.json"
+        ));
+
+        assert_eq!(
+            selected_pipeline(&mc),
+            concat!(
+                "tc358743 4-000f -> [0] => [0] -> dw-mipi-csi2rx fdd30000.csi -> [1] => ",
+                "[0] -> rkcif-mipi0 -> [1] => [0] -> rkcif-mipi0-id0"
+            )
+        );
+    }
+
+    #[test]
+    fn raspberrypi5_none_enabled() {
+        let mc = controller(include_str!(
+            "../../docs/device-info/raspberrypi5+geekworm-c779/media-ctl-topology.json"
+        ));
+
+        let err = select_pipeline(&find_dev_and_subdev(&mc, None).unwrap())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("pipeline.links"), "{err}");
+        assert!(
+            err.contains(
+                "tc358743 11-000f -> [0] => [0] -> csi2 -> [4] => [0] -> rp1-cfe-csi2_ch0"
+            ),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn rock5b() {
+        let mc = controller(include_str!(
+            "../../docs/device-info/rock5b+toshiba-tc358743/media-ctl-topology.json"
+        ));
+
+        // The CSI-2 receiver is a bridge too, but it has a sink pad so it's not where the frames
+        // come from.
+        assert_eq!(pipelines(&mc, None).len(), 4);
+        assert_eq!(
+            enabled_pipelines(&mc, None),
+            [concat!(
+                "tc358743 4-000f -> [0] => [0] -> dw-mipi-csi2rx fdd30000.csi -> [1] => ",
+                "[0] -> rkcif-mipi0 -> [1] => [0] -> rkcif-mipi0-id0"
+            )]
+        );
+    }
+
+    #[test]
+    fn bridge_name() {
+        let mc = controller(include_str!(
+            "../../docs/device-info/rock5b+toshiba-tc358743/media-ctl-topology.json"
+        ));
+
+        assert_eq!(pipelines(&mc, Some("tc358743 4-000f")).len(), 4);
+        assert!(pipelines(&mc, Some("tc358743 10-000f")).is_empty());
+    }
+}
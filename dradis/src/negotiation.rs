@@ -0,0 +1,280 @@
+use core::fmt;
+use std::{io, os::fd::AsFd as _};
+
+use rustix::io::Errno;
+use thiserror::Error;
+use tracing::{debug, trace};
+use v4l2_raw::{
+    format::media_bus_fmt,
+    raw::v4l2_subdev_format_whence,
+    wrapper::{v4l2_subdev_enum_frame_sizes, v4l2_subdev_enum_mbus_codes},
+};
+use v4lise::Device;
+
+use crate::PipelineItem;
+
+#[derive(Debug, Error)]
+pub(crate) enum NegotiationError {
+    #[error("I/O Error {0}")]
+    Io(#[from] io::Error),
+
+    #[error(
+        "No media bus format out of [{candidates}] is supported at {width}x{height} by the whole pipeline: {pads}"
+    )]
+    NoCommonFormat {
+        width: u32,
+        height: u32,
+        candidates: String,
+        pads: String,
+    },
+}
+
+/// The media bus codes a sub-device pad supports at the size we're after.
+///
+/// Drivers don't have to implement the enumeration, in which case we can't tell what they support
+/// and `codes` is None.
+#[derive(Debug)]
+struct PadFormats {
+    entity: String,
+    pad: u32,
+    stream: u32,
+    codes: Option<Vec<media_bus_fmt>>,
+}
+
+impl PadFormats {
+    fn supports(&self, code: media_bus_fmt) -> bool {
+        self.codes
+            .as_ref()
+            .is_none_or(|codes| codes.contains(&code))
+    }
+}
+
+impl fmt::Display for PadFormats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!(
+            "{} pad {}/{} supports ",
+            self.entity, self.pad, self.stream
+        ))?;
+
+        match &self.codes {
+            Some(codes) if codes.is_empty() => f.write_str("nothing"),
+            Some(codes) => f.write_str(&join(codes)),
+            None => f.write_str("anything"),
+        }
+    }
+}
+
+fn join<T>(items: &[T]) -> String
+where
+    T: fmt::Display,
+{
+    items
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// Sub-devices that don't implement the enumeration ioctls, or that don't report anything, don't
+// constrain the format.
+fn optional_enumeration<T>(res: io::Result<Vec<T>>) -> io::Result<Option<Vec<T>>> {
+    match res {
+        Ok(items) if items.is_empty() => Ok(None),
+        Ok(items) => Ok(Some(items)),
+        Err(e) if Errno::from_io_error(&e) == Some(Errno::NOTTY) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn pad_formats(
+    dev: &Device,
+    entity: String,
+    pad: u32,
+    stream: u32,
+    candidates: &[media_bus_fmt],
+    width: u32,
+    height: u32,
+) -> io::Result<PadFormats> {
+    let which = v4l2_subdev_format_whence::V4L2_SUBDEV_FORMAT_ACTIVE;
+
+    let Some(codes) =
+        optional_enumeration(v4l2_subdev_enum_mbus_codes(dev.as_fd(), which, pad, stream))?
+    else {
+        return Ok(PadFormats {
+            entity,
+            pad,
+            stream,
+            codes: None,
+        });
+    };
+
+    let mut supported = Vec::with_capacity(codes.len());
+    for code in codes {
+        if !candidates.contains(&code) {
+            supported.push(code);
+            continue;
+        }
+
+        let sizes = optional_enumeration(v4l2_subdev_enum_frame_sizes(
+            dev.as_fd(),
+            which,
+            pad,
+            stream,
+            code,
+        ))?;
+
+        match sizes {
+            Some(sizes) if !sizes.iter().any(|size| size.contains(width, height)) => {
+                trace!(
+                    "{entity} pad {pad}/{stream}: {code} doesn't support {width}x{height} ({})",
+                    join(&sizes)
+                );
+            }
+            Some(_) | None => supported.push(code),
+        }
+    }
+
+    Ok(PadFormats {
+        entity,
+        pad,
+        stream,
+        codes: Some(supported),
+    })
+}
+
+fn select_code(candidates: &[media_bus_fmt], pads: &[PadFormats]) -> Option<media_bus_fmt> {
+    candidates
+        .iter()
+        .copied()
+        .find(|code| pads.iter().all(|pad| pad.supports(*code)))
+}
+
+/// Finds the first media bus code out of `candidates`, in order of preference, that every
+/// sub-device pad of the pipeline supports at the given size.
+pub(crate) fn negotiate_mbus_code(
+    pipeline: &[PipelineItem],
+    candidates: &[media_bus_fmt],
+    width: u32,
+    height: u32,
+) -> Result<media_bus_fmt, NegotiationError> {
+    let mut pads = Vec::new();
+
+    for item in pipeline {
+        let Some(dev) = item.entity.device.as_ref() else {
+            continue;
+        };
+
+        if !item.entity.entity.is_v4l2_sub_device().valid()? {
+            continue;
+        }
+
+        // Streams are only there if the sub-device supports routing.
+        let has_streams = item.entity.routes.is_some();
+
+        for (pad, stream) in [
+            (&item.sink_pad, item.sink_stream),
+            (&item.source_pad, item.source_stream),
+        ] {
+            let Some(pad) = pad else {
+                continue;
+            };
+
+            let formats = pad_formats(
+                dev,
+                item.entity.entity.name().to_string(),
+                pad.index().valid(),
+                if has_streams { stream } else { 0 },
+                candidates,
+                width,
+                height,
+            )?;
+
+            debug!("{formats}");
+            pads.push(formats);
+        }
+    }
+
+    select_code(candidates, &pads).ok_or_else(|| NegotiationError::NoCommonFormat {
+        width,
+        height,
+        candidates: join(candidates),
+        pads: pads
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("; "),
+    })
+}
+
+#[cfg(test)]
+mod tests_negotiation {
+    use v4l2_raw::format::media_bus_fmt;
+
+    use crate::negotiation::{PadFormats, select_code};
+
+    fn pad(codes: Option<Vec<media_bus_fmt>>) -> PadFormats {
+        PadFormats {
+            entity: String::from("test"),
+            pad: 0,
+            stream: 0,
+            codes,
+        }
+    }
+
+    #[test]
+    fn preferred_first() {
+        let pads = [
+            pad(Some(vec![
+                media_bus_fmt::MEDIA_BUS_FMT_UYVY8_1X16,
+                media_bus_fmt::MEDIA_BUS_FMT_RGB888_1X24,
+                media_bus_fmt::MEDIA_BUS_FMT_BGR888_1X24,
+            ])),
+            pad(None),
+            pad(Some(vec![
+                media_bus_fmt::MEDIA_BUS_FMT_BGR888_1X24,
+                media_bus_fmt::MEDIA_BUS_FMT_RGB888_1X24,
+            ])),
+        ];
+
+        assert_eq!(
+            select_code(
+                &[
+                    media_bus_fmt::MEDIA_BUS_FMT_RGB888_1X24,
+                    media_bus_fmt::MEDIA_BUS_FMT_BGR888_1X24
+                ],
+                &pads
+            ),
+            Some(media_bus_fmt::MEDIA_BUS_FMT_RGB888_1X24)
+        );
+    }
+
+    #[test]
+    fn no_common_format() {
+        let pads = [
+            pad(Some(vec![media_bus_fmt::MEDIA_BUS_FMT_UYVY8_1X16])),
+            pad(Some(vec![media_bus_fmt::MEDIA_BUS_FMT_RGB888_1X24])),
+        ];
+
+        assert_eq!(
+            select_code(
+                &[
+                    media_bus_fmt::MEDIA_BUS_FMT_RGB888_1X24,
+                    media_bus_fmt::MEDIA_BUS_FMT_UYVY8_1X16
+                ],
+                &pads
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn unconstrained() {
+        assert_eq!(
+            select_code(
+                &[media_bus_fmt::MEDIA_BUS_FMT_RGB888_1X24],
+                &[pad(None), pad(None)]
+            ),
+            Some(media_bus_fmt::MEDIA_BUS_FMT_RGB888_1X24)
+        );
+    }
+}
@@ -0,0 +1,328 @@
+use core::{fmt, time::Duration};
+
+use frame_check::FrameTiming;
+use v4l2_raw::raw::v4l2_buffer;
+
+use crate::TestEdid;
+
+/// Returns the capture timestamp of a dequeued buffer.
+pub(crate) fn buffer_timestamp(buf: &v4l2_buffer) -> Duration {
+    let secs = u64::try_from(buf.timestamp.tv_sec).unwrap_or_default();
+    let usecs = u64::try_from(buf.timestamp.tv_usec).unwrap_or_default();
+
+    Duration::from_secs(secs) + Duration::from_micros(usecs)
+}
+
+/// Returns the refresh rate, in mHz, the EDID timings should result in.
+pub(crate) fn edid_refresh_mhz(edid: &TestEdid) -> u64 {
+    let TestEdid::DetailedTiming(dtd) = edid;
+
+    let htotal =
+        u64::from(dtd.hfp) + u64::from(dtd.hdisplay) + u64::from(dtd.hbp) + u64::from(dtd.hsync);
+    let vtotal =
+        u64::from(dtd.vfp) + u64::from(dtd.vdisplay) + u64::from(dtd.vbp) + u64::from(dtd.vsync);
+
+    (u64::from(dtd.clock_khz) * 1_000_000)
+        .checked_div(htotal * vtotal)
+        .unwrap_or_default()
+}
+
+/// Frame Pacing statistics, computed from the capture timestamps and sequence numbers, and from
+/// the frame metadata.
+#[derive(Debug, Default)]
+pub(crate) struct FramePacing {
+    first: Option<(Duration, u32)>,
+    last: Option<(Duration, u32)>,
+
+    intervals: u64,
+    interval_min: Option<Duration>,
+    interval_max: Duration,
+    interval_sum_ns: u128,
+    interval_sum_sq_ns: u128,
+
+    capture_drops: u64,
+
+    last_source: Option<(usize, Option<u64>)>,
+    source_drops: u64,
+    source_repeats: u64,
+    source_missed_vblanks: u64,
+
+    // The source and capture clocks aren't the same, so only the variations of the difference
+    // between the expected presentation and capture times are meaningful.
+    presentation_offset_ns: Option<(i128, i128)>,
+}
+
+impl FramePacing {
+    /// Records a captured buffer, with its timestamp and sequence number.
+    pub(crate) fn record_capture(&mut self, timestamp: Duration, sequence: u32) {
+        if let Some((last_ts, last_seq)) = self.last {
+            let delta = sequence.wrapping_sub(last_seq);
+
+            if delta > 1 {
+                self.capture_drops += u64::from(delta - 1);
+            }
+
+            // Intervals spanning a dropped buffer would skew the distribution, so we only consider
+            // consecutive buffers.
+            if delta == 1 {
+                let interval = timestamp.saturating_sub(last_ts);
+                let interval_ns = interval.as_nanos();
+
+                self.intervals += 1;
+                self.interval_min =
+                    Some(self.interval_min.map_or(interval, |min| min.min(interval)));
+                self.interval_max = self.interval_max.max(interval);
+                self.interval_sum_ns += interval_ns;
+                self.interval_sum_sq_ns += interval_ns * interval_ns;
+            }
+        }
+
+        if self.first.is_none() {
+            self.first = Some((timestamp, sequence));
+        }
+
+        self.last = Some((timestamp, sequence));
+    }
+
+    /// Records a valid frame index, its capture timestamp, and when the source expected it to be
+    /// displayed, if it told us.
+    pub(crate) fn record_source(
+        &mut self,
+        index: usize,
+        timing: Option<FrameTiming>,
+        timestamp: Duration,
+    ) {
+        let vblank = timing.map(|timing| timing.vblank);
+
+        if let Some(timing) = timing {
+            let offset = i128::try_from(timestamp.as_nanos()).unwrap_or(i128::MAX)
+                - i128::from(timing.presentation_ns);
+
+            self.presentation_offset_ns = Some(
+                self.presentation_offset_ns
+                    .map_or((offset, offset), |(min, max)| {
+                        (min.min(offset), max.max(offset))
+                    }),
+            );
+        }
+
+        if let Some((last_index, last_vblank)) = self.last_source {
+            match index.checked_sub(last_index) {
+                Some(0) => self.source_repeats += 1,
+                Some(delta) => {
+                    self.source_drops += u64::try_from(delta - 1).unwrap_or(u64::MAX);
+
+                    // Without any drop, each frame should be displayed one vblank after the
+                    // previous one. Any additional vblank means that the previous frame stayed on
+                    // screen for too long.
+                    if let (Some(last_vblank), Some(vblank)) = (last_vblank, vblank) {
+                        let expected = u64::try_from(delta).unwrap_or(u64::MAX);
+
+                        self.source_missed_vblanks +=
+                            vblank.saturating_sub(last_vblank).saturating_sub(expected);
+                    }
+                }
+                None => {}
+            }
+        }
+
+        self.last_source = Some((index, vblank));
+    }
+
+    /// Measured refresh rate, in mHz.
+    pub(crate) fn refresh_mhz(&self) -> Option<u64> {
+        let (first_ts, first_seq) = self.first?;
+        let (last_ts, last_seq) = self.last?;
+
+        let frames = u128::from(last_seq.wrapping_sub(first_seq));
+        let elapsed_ns = last_ts.saturating_sub(first_ts).as_nanos();
+
+        u64::try_from((frames * 1_000_000_000_000).checked_div(elapsed_ns)?).ok()
+    }
+
+    /// How much the delay between the time the source expected the frames to be displayed and
+    /// the time we captured them varies.
+    fn presentation_jitter(&self) -> Option<Duration> {
+        let (min, max) = self.presentation_offset_ns?;
+
+        Some(Duration::from_nanos(
+            u64::try_from(max - min).unwrap_or(u64::MAX),
+        ))
+    }
+
+    fn interval_mean_ns(&self) -> Option<u128> {
+        self.interval_sum_ns.checked_div(u128::from(self.intervals))
+    }
+
+    fn interval_stddev_ns(&self) -> Option<u128> {
+        let mean = self.interval_mean_ns()?;
+        let mean_sq = self
+            .interval_sum_sq_ns
+            .checked_div(u128::from(self.intervals))?;
+
+        Some(mean_sq.saturating_sub(mean * mean).isqrt())
+    }
+
+    /// Checks that the measured refresh rate is within `tolerance_mhz` of `expected_mhz`.
+    pub(crate) fn check_refresh(
+        &self,
+        expected_mhz: u64,
+        tolerance_mhz: u64,
+    ) -> Result<(), String> {
+        let measured = self.refresh_mhz().ok_or(String::from(
+            "Not enough frames to measure the refresh rate",
+        ))?;
+
+        if measured.abs_diff(expected_mhz) > tolerance_mhz {
+            return Err(format!(
+                "Measured refresh rate {}.{:03}Hz, expected {}.{:03}Hz",
+                measured / 1000,
+                measured % 1000,
+                expected_mhz / 1000,
+                expected_mhz % 1000,
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for FramePacing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(refresh) = self.refresh_mhz() {
+            f.write_fmt(format_args!(
+                "Refresh Rate {}.{:03}Hz, ",
+                refresh / 1000,
+                refresh % 1000
+            ))?;
+        }
+
+        if let (Some(min), Some(mean), Some(stddev)) = (
+            self.interval_min,
+            self.interval_mean_ns(),
+            self.interval_stddev_ns(),
+        ) {
+            f.write_fmt(format_args!(
+                "Frame Interval min {}us, mean {}us, max {}us, stddev {}us, ",
+                min.as_micros(),
+                mean / 1000,
+                self.interval_max.as_micros(),
+                stddev / 1000
+            ))?;
+        }
+
+        if let Some(jitter) = self.presentation_jitter() {
+            f.write_fmt(format_args!(
+                "Presentation Jitter {}us, ",
+                jitter.as_micros()
+            ))?;
+        }
+
+        f.write_fmt(format_args!(
+            "{} capture drops, {} source drops, {} source repeats, {} source missed vblanks",
+            self.capture_drops, self.source_drops, self.source_repeats, self.source_missed_vblanks
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests_frame_pacing {
+    use core::time::Duration;
+
+    use frame_check::FrameTiming;
+
+    use super::{FramePacing, edid_refresh_mhz};
+    use crate::{TestEdid, TestEdidDetailedTiming};
+
+    // 1/59.94 seconds
+    const NTSC_INTERVAL: Duration = Duration::from_nanos(16_683_350);
+
+    #[test]
+    fn test_refresh_rate() {
+        let mut pacing = FramePacing::default();
+
+        for seq in 0..600 {
+            pacing.record_capture(NTSC_INTERVAL * seq, seq);
+        }
+
+        assert_eq!(pacing.refresh_mhz(), Some(59_940));
+        assert!(pacing.check_refresh(59_940, 10).is_ok());
+        assert!(pacing.check_refresh(60_000, 10).is_err());
+    }
+
+    #[test]
+    fn test_capture_drops() {
+        let mut pacing = FramePacing::default();
+
+        for seq in [0, 1, 2, 5, 6] {
+            pacing.record_capture(NTSC_INTERVAL * seq, seq);
+        }
+
+        assert_eq!(pacing.capture_drops, 2);
+        assert_eq!(pacing.intervals, 3);
+        assert_eq!(pacing.interval_min, Some(NTSC_INTERVAL));
+        assert_eq!(pacing.interval_max, NTSC_INTERVAL);
+        assert_eq!(pacing.interval_stddev_ns(), Some(0));
+    }
+
+    fn timing(vblank: u64) -> Option<FrameTiming> {
+        Some(FrameTiming {
+            presentation_ns: u64::try_from(
+                (NTSC_INTERVAL * u32::try_from(vblank).unwrap()).as_nanos(),
+            )
+            .unwrap(),
+            vblank,
+        })
+    }
+
+    #[test]
+    fn test_source_drops() {
+        let mut pacing = FramePacing::default();
+
+        for (index, vblank) in [(0, 10), (1, 11), (1, 11), (4, 14), (5, 16)] {
+            pacing.record_source(
+                index,
+                timing(vblank),
+                NTSC_INTERVAL * u32::try_from(vblank).unwrap(),
+            );
+        }
+
+        assert_eq!(pacing.source_drops, 2);
+        assert_eq!(pacing.source_repeats, 1);
+        assert_eq!(pacing.source_missed_vblanks, 1);
+    }
+
+    #[test]
+    fn test_presentation_jitter() {
+        let mut pacing = FramePacing::default();
+        assert_eq!(pacing.presentation_jitter(), None);
+
+        // The capture clock is a second ahead of the source one, which doesn't matter.
+        let offset = Duration::from_secs(1);
+        let late = Duration::from_micros(500);
+
+        pacing.record_source(0, timing(0), offset);
+        pacing.record_source(1, timing(1), offset + NTSC_INTERVAL + late);
+        pacing.record_source(2, timing(2), offset + NTSC_INTERVAL * 2);
+        pacing.record_source(3, None, offset);
+
+        assert_eq!(pacing.presentation_jitter(), Some(late));
+    }
+
+    #[test]
+    fn test_edid_refresh() {
+        let edid = TestEdid::DetailedTiming(TestEdidDetailedTiming {
+            clock_khz: 74_250,
+            hfp: 110,
+            hdisplay: 1280,
+            hbp: 220,
+            hsync: 40,
+            vfp: 5,
+            vdisplay: 720,
+            vbp: 20,
+            vsync: 5,
+        });
+
+        assert_eq!(edid_refresh_mhz(&edid), 60_000);
+    }
+}
@@ -0,0 +1,221 @@
+use std::{io, os::fd::AsFd as _};
+
+use linux_mc::{
+    MediaController, MediaControllerFormatDescription, MediaControllerLinkDescription,
+    MediaControllerRectangle,
+};
+use serde::Deserialize;
+use serde_with::{DisplayFromStr, serde_as};
+use tracing::debug;
+use v4l2_raw::{
+    format::media_bus_fmt,
+    raw::{v4l2_rect, v4l2_subdev_format_whence},
+    wrapper::{
+        v4l2_encoding, v4l2_ioctl_subdev_g_fmt, v4l2_ioctl_subdev_s_fmt,
+        v4l2_ioctl_subdev_s_selection, v4l2_sel_target, v4l2_subdev_enable_streams,
+        v4l2_subdev_enum_mbus_codes, v4l2_subdev_format, v4l2_subdev_selection,
+    },
+};
+use v4lise::Device;
+
+/// Board-specific pipeline setup, applied on top of the automatic discovery.
+#[serde_as]
+#[derive(Clone, Debug, Default, Deserialize)]
+pub(crate) struct TestPipeline {
+    /// Links to set up, in the `media-ctl -l` syntax. Every other dynamic link gets disabled.
+    #[serde_as(as = "Vec<DisplayFromStr>")]
+    #[serde(default)]
+    pub(crate) links: Vec<MediaControllerLinkDescription>,
+
+    /// Sub-device pad formats to set, in the `media-ctl -V` syntax, once the pipeline format has
+    /// been set. The video device then captures whatever format they result in.
+    #[serde_as(as = "Vec<DisplayFromStr>")]
+    #[serde(default)]
+    pub(crate) formats: Vec<MediaControllerFormatDescription>,
+}
+
+fn not_found(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, msg)
+}
+
+fn find_mbus_code(
+    subdev: &Device,
+    desc: &MediaControllerFormatDescription,
+    name: &str,
+) -> io::Result<media_bus_fmt> {
+    v4l2_subdev_enum_mbus_codes(
+        subdev.as_fd(),
+        v4l2_subdev_format_whence::V4L2_SUBDEV_FORMAT_ACTIVE,
+        desc.pad.index,
+        desc.stream,
+    )?
+    .into_iter()
+    .find(|code| code.name() == Some(name))
+    .ok_or_else(|| not_found(format!("Pad {} doesn't support {name}", desc.pad)))
+}
+
+fn set_format(subdev: &Device, desc: &MediaControllerFormatDescription) -> io::Result<()> {
+    let subdev_fmt = v4l2_subdev_format::new_active().set_pad(desc.pad.index);
+    let subdev_fmt = if desc.stream != 0 {
+        subdev_fmt.set_stream(desc.stream)
+    } else {
+        subdev_fmt
+    };
+
+    let current = v4l2_ioctl_subdev_g_fmt(subdev.as_fd(), subdev_fmt)?;
+    let mut format = current.format();
+
+    if let Some(frame) = &desc.format {
+        format = format
+            .set_code(find_mbus_code(subdev, desc, &frame.code)?)
+            .set_width(frame.width)
+            .set_height(frame.height);
+    }
+
+    if let Some(field) = desc.field {
+        format = format.set_field(field);
+    }
+
+    if let Some(colorspace) = desc.colorspace {
+        format = format.set_colorspace(colorspace);
+    }
+
+    if let Some(xfer_func) = desc.xfer_func {
+        format = format.set_xfer_func(xfer_func);
+    }
+
+    if let Some(encoding) = desc.ycbcr_encoding {
+        format = format.set_encoding(v4l2_encoding::YCbCr(encoding));
+    }
+
+    if let Some(quantization) = desc.quantization {
+        format = format.set_quantization(quantization);
+    }
+
+    let subdev_fmt = current.set_format(format);
+
+    debug!("Pad {}: Setting {}", desc.pad, subdev_fmt);
+    v4l2_ioctl_subdev_s_fmt(subdev.as_fd(), subdev_fmt)?;
+
+    Ok(())
+}
+
+fn set_selection(
+    subdev: &Device,
+    desc: &MediaControllerFormatDescription,
+    target: v4l2_sel_target,
+    rect: MediaControllerRectangle,
+) -> io::Result<()> {
+    let sel = v4l2_subdev_selection::new_active(target)
+        .set_pad(desc.pad.index)
+        .set_rect(v4l2_rect {
+            left: rect.left,
+            top: rect.top,
+            width: rect.width,
+            height: rect.height,
+        });
+
+    let sel = if desc.stream != 0 {
+        sel.set_stream(desc.stream)
+    } else {
+        sel
+    };
+
+    debug!("Pad {}: Setting {:?} to {}", desc.pad, target, rect);
+    v4l2_ioctl_subdev_s_selection(subdev.as_fd(), sel)?;
+
+    Ok(())
+}
+
+/// Applies the formats of the test description to the sub-devices.
+pub(crate) fn apply_formats(
+    mc: &MediaController,
+    formats: &[MediaControllerFormatDescription],
+) -> io::Result<()> {
+    for desc in formats {
+        let pad = mc
+            .find_pad(&desc.pad)?
+            .ok_or_else(|| not_found(format!("Couldn't find pad {}", desc.pad)))?;
+        let entity = pad.entity().valid();
+
+        let node = entity
+            .interfaces()
+            .valid()?
+            .first()
+            .and_then(|itf| itf.device_node().valid())
+            .ok_or_else(|| not_found(format!("Entity {} has no device node", entity.name())))?;
+
+        let subdev = Device::new(node.path(), true)?;
+
+        if desc.stream != 0 && !v4l2_subdev_enable_streams(subdev.as_fd())? {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("Entity {} doesn't support streams", entity.name()),
+            ));
+        }
+
+        if desc.format.is_some()
+            || desc.field.is_some()
+            || desc.colorspace.is_some()
+            || desc.xfer_func.is_some()
+            || desc.ycbcr_encoding.is_some()
+            || desc.quantization.is_some()
+        {
+            set_format(&subdev, desc)?;
+        }
+
+        if let Some(crop) = desc.crop {
+            set_selection(&subdev, desc, v4l2_sel_target::Crop, crop)?;
+        }
+
+        if let Some(compose) = desc.compose {
+            set_selection(&subdev, desc, v4l2_sel_target::Compose, compose)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests_pipeline {
+    use crate::pipeline::TestPipeline;
+
+    #[test]
+    fn parse() {
+        let pipeline: TestPipeline = serde_yaml::from_str(
+            r#"
+links:
+  - '"csi2":4 -> "rp1-cfe-csi2_ch0":0 [1]'
+formats:
+  - '"csi2":0 [fmt:RGB888_1X24/1280x720]'
+  - '"csi2":4 [fmt:RGB888_1X24/1280x720 field:none colorspace:srgb crop:(0,0)/1280x720]'
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            pipeline
+                .links
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            ["\"csi2\":4 -> \"rp1-cfe-csi2_ch0\":0 [1]"]
+        );
+        assert_eq!(
+            pipeline
+                .formats
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "\"csi2\":0 [fmt:RGB888_1X24/1280x720]",
+                "\"csi2\":4 [fmt:RGB888_1X24/1280x720 field:none colorspace:srgb crop:(0,0)/1280x720]",
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(serde_yaml::from_str::<TestPipeline>("links: ['\"csi2\":4 -> [1]']\n").is_err());
+    }
+}
@@ -0,0 +1 @@
+#include <sound/asound.h>
@@ -0,0 +1,450 @@
+#![allow(non_camel_case_types)]
+#![allow(unsafe_code)]
+#![doc = include_str!("../README.md")]
+
+use core::{ffi::c_void, fmt, time::Duration};
+use std::{
+    io,
+    os::fd::{AsFd as _, BorrowedFd, OwnedFd},
+    path::{Path, PathBuf},
+};
+
+use rustix::{
+    event::{PollFd, PollFlags, poll},
+    fs::{Mode, OFlags, open},
+    io::Errno,
+    time::Timespec,
+};
+use tracing::{debug, warn};
+
+/// Raw, unsafe, abstraction
+pub mod raw;
+use raw::{
+    SNDRV_PCM_ACCESS_RW_INTERLEAVED, SNDRV_PCM_FORMAT_S16_LE, SNDRV_PCM_HW_PARAM_ACCESS,
+    SNDRV_PCM_HW_PARAM_CHANNELS, SNDRV_PCM_HW_PARAM_FIRST_INTERVAL, SNDRV_PCM_HW_PARAM_FORMAT,
+    SNDRV_PCM_HW_PARAM_PERIOD_SIZE, SNDRV_PCM_HW_PARAM_PERIODS, SNDRV_PCM_HW_PARAM_RATE,
+    SNDRV_PCM_HW_PARAM_SUBFORMAT, SNDRV_PCM_SUBFORMAT_STD, snd_interval, snd_mask,
+    snd_pcm_hw_params, snd_pcm_ioctl_drop, snd_pcm_ioctl_hw_params, snd_pcm_ioctl_hw_refine,
+    snd_pcm_ioctl_prepare, snd_pcm_ioctl_readi_frames, snd_pcm_ioctl_writei_frames, snd_xferi,
+};
+
+/// Direction of a PCM Stream
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PcmDirection {
+    /// Capture Stream
+    Capture,
+
+    /// Playback Stream
+    Playback,
+}
+
+/// PCM Stream Configuration
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PcmConfig {
+    /// Number of Channels
+    pub channels: u32,
+
+    /// Sample Rate, in Hz
+    pub rate: u32,
+
+    /// Size of a period, in frames
+    pub period_frames: u32,
+
+    /// Number of periods in the buffer
+    pub periods: u32,
+}
+
+impl fmt::Display for PcmConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!(
+            "{} channels, {}Hz, {} periods of {} frames",
+            self.channels, self.rate, self.periods, self.period_frames
+        ))
+    }
+}
+
+fn mask_single(bit: u32) -> snd_mask {
+    let mut mask = snd_mask::default();
+
+    if let Some(word) = mask.bits.get_mut((bit / 32) as usize) {
+        *word = 1 << (bit % 32);
+    }
+
+    mask
+}
+
+fn interval_any() -> snd_interval {
+    snd_interval {
+        min: 0,
+        max: u32::MAX,
+        ..snd_interval::default()
+    }
+}
+
+fn interval_exact(val: u32) -> snd_interval {
+    let mut interval = snd_interval {
+        min: val,
+        max: val,
+        ..snd_interval::default()
+    };
+    interval.set_integer(1);
+
+    interval
+}
+
+// Restricts an interval parameter to a single value, and asks the driver to refine it.
+fn set_interval(params: &mut snd_pcm_hw_params, param: usize, val: u32) {
+    if let Some(interval) = params
+        .intervals
+        .get_mut(param - SNDRV_PCM_HW_PARAM_FIRST_INTERVAL)
+    {
+        *interval = interval_exact(val);
+    }
+
+    params.rmask = u32::MAX;
+}
+
+// Returns the value of an interval parameter that is the closest to the one we want.
+fn interval_nearest(params: &snd_pcm_hw_params, param: usize, val: u32) -> u32 {
+    let Some(interval) = params
+        .intervals
+        .get(param - SNDRV_PCM_HW_PARAM_FIRST_INTERVAL)
+    else {
+        return val;
+    };
+
+    let min = interval.min.saturating_add(interval.openmin());
+    let max = interval.max.saturating_sub(interval.openmax()).max(min);
+
+    val.clamp(min, max)
+}
+
+// The period size and count are left for the driver to refine, since most drivers only support a
+// few values of those.
+fn hw_params(config: &PcmConfig) -> snd_pcm_hw_params {
+    let mut params = snd_pcm_hw_params::default();
+
+    for mask in &mut params.masks {
+        mask.bits = [u32::MAX; 8];
+    }
+
+    for interval in &mut params.intervals {
+        *interval = interval_any();
+    }
+
+    let mut set_mask = |param: usize, bit: u32| {
+        if let Some(mask) = params.masks.get_mut(param) {
+            *mask = mask_single(bit);
+        }
+    };
+
+    set_mask(SNDRV_PCM_HW_PARAM_ACCESS, SNDRV_PCM_ACCESS_RW_INTERLEAVED);
+    set_mask(SNDRV_PCM_HW_PARAM_FORMAT, SNDRV_PCM_FORMAT_S16_LE);
+    set_mask(SNDRV_PCM_HW_PARAM_SUBFORMAT, SNDRV_PCM_SUBFORMAT_STD);
+
+    set_interval(&mut params, SNDRV_PCM_HW_PARAM_CHANNELS, config.channels);
+    set_interval(&mut params, SNDRV_PCM_HW_PARAM_RATE, config.rate);
+
+    params
+}
+
+// Picks the period size, and then the period count, the closest to the ones we want among the
+// ones the driver supports.
+fn hw_params_negotiate(fd: BorrowedFd<'_>, config: &PcmConfig) -> io::Result<snd_pcm_hw_params> {
+    let mut params = snd_pcm_ioctl_hw_refine(fd, hw_params(config))?;
+
+    let period_frames = interval_nearest(
+        &params,
+        SNDRV_PCM_HW_PARAM_PERIOD_SIZE,
+        config.period_frames,
+    );
+    set_interval(&mut params, SNDRV_PCM_HW_PARAM_PERIOD_SIZE, period_frames);
+    let mut params = snd_pcm_ioctl_hw_refine(fd, params)?;
+
+    let periods = interval_nearest(&params, SNDRV_PCM_HW_PARAM_PERIODS, config.periods);
+    set_interval(&mut params, SNDRV_PCM_HW_PARAM_PERIODS, periods);
+
+    snd_pcm_ioctl_hw_params(fd, params)
+}
+
+fn interval_value(params: &snd_pcm_hw_params, param: usize) -> u32 {
+    params
+        .intervals
+        .get(param - SNDRV_PCM_HW_PARAM_FIRST_INTERVAL)
+        .map_or(0, |interval| interval.min)
+}
+
+/// A PCM Stream, exchanging interleaved signed 16-bit samples
+#[derive(Debug)]
+pub struct Pcm {
+    path: PathBuf,
+    fd: OwnedFd,
+    direction: PcmDirection,
+    config: PcmConfig,
+    xruns: u64,
+}
+
+impl Pcm {
+    /// Opens a PCM device, and sets it up with the given configuration
+    ///
+    /// # Errors
+    ///
+    /// If the device can't be opened, or if it doesn't support the configuration.
+    pub fn open(path: &Path, direction: PcmDirection, config: &PcmConfig) -> io::Result<Self> {
+        Self::open_with_flags(path, direction, config, OFlags::empty())
+    }
+
+    /// Opens a PCM device in non-blocking mode, and sets it up with the given configuration
+    ///
+    /// [`Pcm::read`] and [`Pcm::write`] transfer 0 frames instead of blocking, and [`Pcm::wait`]
+    /// waits for the device to be ready.
+    ///
+    /// # Errors
+    ///
+    /// If the device can't be opened, or if it doesn't support the configuration.
+    pub fn open_nonblocking(
+        path: &Path,
+        direction: PcmDirection,
+        config: &PcmConfig,
+    ) -> io::Result<Self> {
+        Self::open_with_flags(path, direction, config, OFlags::NONBLOCK)
+    }
+
+    fn open_with_flags(
+        path: &Path,
+        direction: PcmDirection,
+        config: &PcmConfig,
+        flags: OFlags,
+    ) -> io::Result<Self> {
+        let access = match direction {
+            PcmDirection::Capture => OFlags::RDONLY,
+            PcmDirection::Playback => OFlags::WRONLY,
+        };
+
+        let fd = open(path, access | flags | OFlags::CLOEXEC, Mode::empty())?;
+
+        let params = hw_params_negotiate(fd.as_fd(), config)?;
+        let config = PcmConfig {
+            channels: interval_value(&params, SNDRV_PCM_HW_PARAM_CHANNELS),
+            rate: interval_value(&params, SNDRV_PCM_HW_PARAM_RATE),
+            period_frames: interval_value(&params, SNDRV_PCM_HW_PARAM_PERIOD_SIZE),
+            periods: interval_value(&params, SNDRV_PCM_HW_PARAM_PERIODS),
+        };
+
+        debug!("PCM {} configured with {}", path.display(), config);
+
+        snd_pcm_ioctl_prepare(fd.as_fd())?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            fd,
+            direction,
+            config,
+            xruns: 0,
+        })
+    }
+
+    /// Returns the configuration the device ended up with
+    #[must_use]
+    pub fn config(&self) -> PcmConfig {
+        self.config
+    }
+
+    /// Returns the number of overruns or underruns that happened so far
+    #[must_use]
+    pub fn xruns(&self) -> u64 {
+        self.xruns
+    }
+
+    /// Waits for the device to have frames to read, or room to write frames
+    ///
+    /// Returns whether the device got ready before the timeout expired.
+    ///
+    /// # Errors
+    ///
+    /// If there's an I/O error while accessing the device.
+    pub fn wait(&self, timeout: Duration) -> io::Result<bool> {
+        let timeout = Timespec {
+            tv_sec: timeout.as_secs().try_into().map_err(|_e| Errno::INVAL)?,
+            tv_nsec: timeout.subsec_nanos().into(),
+        };
+
+        let events = match self.direction {
+            PcmDirection::Capture => PollFlags::IN,
+            PcmDirection::Playback => PollFlags::OUT,
+        };
+
+        // The device also reports an error on overruns and underruns, that the next transfer
+        // recovers from.
+        let mut fds = [PollFd::new(&self.fd, events)];
+        Ok(poll(&mut fds, Some(&timeout))? > 0)
+    }
+
+    fn channels(&self) -> usize {
+        usize::try_from(self.config.channels).unwrap_or(usize::MAX)
+    }
+
+    fn recover(&mut self) -> io::Result<()> {
+        warn!(
+            "PCM {} {}",
+            self.path.display(),
+            match self.direction {
+                PcmDirection::Capture => "overrun",
+                PcmDirection::Playback => "underrun",
+            }
+        );
+
+        self.xruns += 1;
+        snd_pcm_ioctl_prepare(self.fd.as_fd())
+    }
+
+    /// Reads interleaved samples, and returns the number of frames read
+    ///
+    /// Overruns are recovered from, and counted in [`Pcm::xruns`]. In non-blocking mode, returns
+    /// 0 if there's no frame to read.
+    ///
+    /// # Errors
+    ///
+    /// If the PCM isn't a capture PCM, or if there's an I/O error while accessing the device.
+    pub fn read(&mut self, samples: &mut [i16]) -> io::Result<usize> {
+        if self.direction != PcmDirection::Capture {
+            return Err(Errno::BADF.into());
+        }
+
+        let frames = samples.len() / self.channels().max(1);
+
+        loop {
+            let xferi = snd_xferi {
+                result: 0,
+                buf: samples.as_mut_ptr().cast::<c_void>(),
+                frames: frames as _,
+            };
+
+            // SAFETY: The buffer is valid for frames * channels samples, and is borrowed mutably
+            // for the duration of the call.
+            match unsafe { snd_pcm_ioctl_readi_frames(self.fd.as_fd(), xferi) } {
+                Ok(xferi) => return Ok(usize::try_from(xferi.result).unwrap_or_default()),
+                Err(e) if Errno::from_io_error(&e) == Some(Errno::AGAIN) => return Ok(0),
+                Err(e) if Errno::from_io_error(&e) == Some(Errno::PIPE) => self.recover()?,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Writes interleaved samples, and returns the number of frames written
+    ///
+    /// Underruns are recovered from, and counted in [`Pcm::xruns`]. In non-blocking mode, returns
+    /// 0 if there's no room to write frames.
+    ///
+    /// # Errors
+    ///
+    /// If the PCM isn't a playback PCM, or if there's an I/O error while accessing the device.
+    pub fn write(&mut self, samples: &[i16]) -> io::Result<usize> {
+        if self.direction != PcmDirection::Playback {
+            return Err(Errno::BADF.into());
+        }
+
+        let frames = samples.len() / self.channels().max(1);
+
+        loop {
+            let xferi = snd_xferi {
+                result: 0,
+                buf: samples.as_ptr().cast_mut().cast::<c_void>(),
+                frames: frames as _,
+            };
+
+            // SAFETY: The buffer is valid for frames * channels samples, and the kernel only
+            // reads from it.
+            match unsafe { snd_pcm_ioctl_writei_frames(self.fd.as_fd(), xferi) } {
+                Ok(xferi) => return Ok(usize::try_from(xferi.result).unwrap_or_default()),
+                Err(e) if Errno::from_io_error(&e) == Some(Errno::AGAIN) => return Ok(0),
+                Err(e) if Errno::from_io_error(&e) == Some(Errno::PIPE) => self.recover()?,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl Drop for Pcm {
+    fn drop(&mut self) {
+        if let Err(e) = snd_pcm_ioctl_drop(self.fd.as_fd()) {
+            warn!("Couldn't stop PCM {}: {e}", self.path.display());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_hw_params {
+    use super::{
+        PcmConfig, SNDRV_PCM_FORMAT_S16_LE, SNDRV_PCM_HW_PARAM_FIRST_INTERVAL,
+        SNDRV_PCM_HW_PARAM_FORMAT, SNDRV_PCM_HW_PARAM_PERIOD_SIZE, SNDRV_PCM_HW_PARAM_PERIODS,
+        SNDRV_PCM_HW_PARAM_RATE, hw_params, interval_nearest, interval_value, mask_single,
+        snd_interval,
+    };
+
+    #[test]
+    fn test_mask() {
+        assert_eq!(mask_single(2).bits[0], 0b100);
+        assert_eq!(mask_single(33).bits[1], 0b10);
+        assert_eq!(mask_single(33).bits[0], 0);
+    }
+
+    #[test]
+    fn test_hw_params() {
+        let params = hw_params(&PcmConfig {
+            channels: 2,
+            rate: 48_000,
+            period_frames: 1024,
+            periods: 4,
+        });
+
+        assert_eq!(interval_value(&params, SNDRV_PCM_HW_PARAM_RATE), 48_000);
+        assert_eq!(
+            params.masks[SNDRV_PCM_HW_PARAM_FORMAT],
+            mask_single(SNDRV_PCM_FORMAT_S16_LE)
+        );
+
+        // The driver gets to pick the periods.
+        assert_eq!(
+            interval_nearest(&params, SNDRV_PCM_HW_PARAM_PERIOD_SIZE, 1024),
+            1024
+        );
+        assert_eq!(interval_nearest(&params, SNDRV_PCM_HW_PARAM_PERIODS, 4), 4);
+    }
+
+    #[test]
+    fn test_interval_nearest() {
+        let mut params = hw_params(&PcmConfig {
+            channels: 2,
+            rate: 48_000,
+            period_frames: 480,
+            periods: 4,
+        });
+
+        let mut interval = snd_interval {
+            min: 64,
+            max: 256,
+            ..snd_interval::default()
+        };
+        interval.set_openmax(1);
+        params.intervals[SNDRV_PCM_HW_PARAM_PERIOD_SIZE - SNDRV_PCM_HW_PARAM_FIRST_INTERVAL] =
+            interval;
+
+        params.intervals[SNDRV_PCM_HW_PARAM_PERIODS - SNDRV_PCM_HW_PARAM_FIRST_INTERVAL] =
+            snd_interval {
+                min: 8,
+                max: 16,
+                ..snd_interval::default()
+            };
+
+        assert_eq!(
+            interval_nearest(&params, SNDRV_PCM_HW_PARAM_PERIOD_SIZE, 480),
+            255
+        );
+        assert_eq!(
+            interval_nearest(&params, SNDRV_PCM_HW_PARAM_PERIOD_SIZE, 32),
+            64
+        );
+        assert_eq!(interval_nearest(&params, SNDRV_PCM_HW_PARAM_PERIODS, 4), 8);
+    }
+}
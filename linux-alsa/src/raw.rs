@@ -0,0 +1,222 @@
+use std::{io, os::fd::BorrowedFd};
+
+use rustix::{
+    io::Errno,
+    ioctl::{NoArg, Updater, ioctl, opcode},
+};
+use tracing::instrument;
+
+pub(crate) mod bindgen {
+    #![allow(clippy::decimal_literal_representation)]
+    #![allow(clippy::multiple_unsafe_ops_per_block)]
+    #![allow(clippy::pub_underscore_fields)]
+    #![allow(clippy::std_instead_of_alloc)]
+    #![allow(clippy::std_instead_of_core)]
+    #![allow(clippy::undocumented_unsafe_blocks)]
+    #![allow(clippy::unreadable_literal)]
+    #![allow(dead_code)]
+    #![allow(missing_debug_implementations)]
+    #![allow(missing_docs)]
+    #![allow(non_upper_case_globals)]
+    #![allow(non_camel_case_types)]
+    #![allow(non_snake_case)]
+    #![allow(unreachable_pub)]
+    #![allow(unsafe_code)]
+
+    include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+}
+
+pub use bindgen::{snd_interval, snd_mask, snd_pcm_hw_params, snd_xferi};
+
+// The uapi header defines these through casts bindgen can't evaluate.
+
+/// Interleaved Read/Write Access
+pub const SNDRV_PCM_ACCESS_RW_INTERLEAVED: u32 = 3;
+
+/// Signed 16-bit Little Endian Format
+pub const SNDRV_PCM_FORMAT_S16_LE: u32 = 2;
+
+/// Standard Sub-Format
+pub const SNDRV_PCM_SUBFORMAT_STD: u32 = 0;
+
+/// Access Mask Parameter
+pub const SNDRV_PCM_HW_PARAM_ACCESS: usize = 0;
+
+/// Format Mask Parameter
+pub const SNDRV_PCM_HW_PARAM_FORMAT: usize = 1;
+
+/// Sub-Format Mask Parameter
+pub const SNDRV_PCM_HW_PARAM_SUBFORMAT: usize = 2;
+
+/// First Interval Parameter
+pub const SNDRV_PCM_HW_PARAM_FIRST_INTERVAL: usize = 8;
+
+/// Number of Channels Interval Parameter
+pub const SNDRV_PCM_HW_PARAM_CHANNELS: usize = 10;
+
+/// Sample Rate Interval Parameter
+pub const SNDRV_PCM_HW_PARAM_RATE: usize = 11;
+
+/// Period Size, in frames, Interval Parameter
+pub const SNDRV_PCM_HW_PARAM_PERIOD_SIZE: usize = 13;
+
+/// Number of Periods Interval Parameter
+pub const SNDRV_PCM_HW_PARAM_PERIODS: usize = 15;
+
+const SNDRV_PCM_IOC_MAGIC: u8 = b'A';
+const SNDRV_PCM_IOC_HW_REFINE: u8 = 0x10;
+const SNDRV_PCM_IOC_HW_PARAMS: u8 = 0x11;
+const SNDRV_PCM_IOC_PREPARE: u8 = 0x40;
+const SNDRV_PCM_IOC_DROP: u8 = 0x43;
+const SNDRV_PCM_IOC_WRITEI_FRAMES: u8 = 0x50;
+const SNDRV_PCM_IOC_READI_FRAMES: u8 = 0x51;
+
+const SNDRV_PCM_IOC_HW_REFINE_OPCODE: u32 =
+    opcode::read_write::<snd_pcm_hw_params>(SNDRV_PCM_IOC_MAGIC, SNDRV_PCM_IOC_HW_REFINE);
+
+/// Refines the hardware parameters to the ones the driver supports, without installing them
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the file descriptor, or if the parameters can't be
+/// satisfied.
+#[instrument(level = "trace", skip(params))]
+pub fn snd_pcm_ioctl_hw_refine(
+    fd: BorrowedFd<'_>,
+    mut params: snd_pcm_hw_params,
+) -> io::Result<snd_pcm_hw_params> {
+    // SAFETY: We checked both the opcode and the type.
+    let ioctl_obj =
+        unsafe { Updater::<SNDRV_PCM_IOC_HW_REFINE_OPCODE, snd_pcm_hw_params>::new(&mut params) };
+
+    // SAFETY: This function is unsafe because the driver isn't guaranteed to implement the ioctl
+    // properly. We don't have much of a choice and still have to trust the
+    // kernel there.
+    unsafe { ioctl(fd, ioctl_obj) }
+        .map(|()| params)
+        .map_err(<Errno as Into<io::Error>>::into)
+}
+
+const SNDRV_PCM_IOC_HW_PARAMS_OPCODE: u32 =
+    opcode::read_write::<snd_pcm_hw_params>(SNDRV_PCM_IOC_MAGIC, SNDRV_PCM_IOC_HW_PARAMS);
+
+/// Refines and installs the hardware parameters
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the file descriptor, or if the parameters can't be
+/// satisfied.
+#[instrument(level = "trace", skip(params))]
+pub fn snd_pcm_ioctl_hw_params(
+    fd: BorrowedFd<'_>,
+    mut params: snd_pcm_hw_params,
+) -> io::Result<snd_pcm_hw_params> {
+    // SAFETY: We checked both the opcode and the type.
+    let ioctl_obj =
+        unsafe { Updater::<SNDRV_PCM_IOC_HW_PARAMS_OPCODE, snd_pcm_hw_params>::new(&mut params) };
+
+    // SAFETY: This function is unsafe because the driver isn't guaranteed to implement the ioctl
+    // properly. We don't have much of a choice and still have to trust the
+    // kernel there.
+    unsafe { ioctl(fd, ioctl_obj) }
+        .map(|()| params)
+        .map_err(<Errno as Into<io::Error>>::into)
+}
+
+const SNDRV_PCM_IOC_PREPARE_OPCODE: u32 = opcode::none(SNDRV_PCM_IOC_MAGIC, SNDRV_PCM_IOC_PREPARE);
+
+/// Prepares the PCM for a transfer
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the file descriptor.
+#[instrument(level = "trace")]
+pub fn snd_pcm_ioctl_prepare(fd: BorrowedFd<'_>) -> io::Result<()> {
+    // SAFETY: We checked the opcode, and this ioctl doesn't take any argument.
+    let ioctl_obj = unsafe { NoArg::<SNDRV_PCM_IOC_PREPARE_OPCODE>::new() };
+
+    // SAFETY: This function is unsafe because the driver isn't guaranteed to implement the ioctl
+    // properly. We don't have much of a choice and still have to trust the
+    // kernel there.
+    unsafe { ioctl(fd, ioctl_obj) }.map_err(<Errno as Into<io::Error>>::into)
+}
+
+const SNDRV_PCM_IOC_DROP_OPCODE: u32 = opcode::none(SNDRV_PCM_IOC_MAGIC, SNDRV_PCM_IOC_DROP);
+
+/// Stops the PCM, dropping the pending frames
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the file descriptor.
+#[instrument(level = "trace")]
+pub fn snd_pcm_ioctl_drop(fd: BorrowedFd<'_>) -> io::Result<()> {
+    // SAFETY: We checked the opcode, and this ioctl doesn't take any argument.
+    let ioctl_obj = unsafe { NoArg::<SNDRV_PCM_IOC_DROP_OPCODE>::new() };
+
+    // SAFETY: This function is unsafe because the driver isn't guaranteed to implement the ioctl
+    // properly. We don't have much of a choice and still have to trust the
+    // kernel there.
+    unsafe { ioctl(fd, ioctl_obj) }.map_err(<Errno as Into<io::Error>>::into)
+}
+
+const SNDRV_PCM_IOC_READI_FRAMES_OPCODE: u32 =
+    opcode::read::<snd_xferi>(SNDRV_PCM_IOC_MAGIC, SNDRV_PCM_IOC_READI_FRAMES);
+
+/// Reads interleaved frames from a capture PCM
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the file descriptor. An overrun is reported through
+/// [`Errno::PIPE`].
+///
+/// # Safety
+///
+/// The `xferi` buffer must be valid for writes of `xferi.frames` frames.
+#[instrument(level = "trace")]
+pub unsafe fn snd_pcm_ioctl_readi_frames(
+    fd: BorrowedFd<'_>,
+    mut xferi: snd_xferi,
+) -> io::Result<snd_xferi> {
+    // SAFETY: We checked both the opcode and the type. The kernel reads the buffer address and
+    // size, and writes the result back.
+    let ioctl_obj =
+        unsafe { Updater::<SNDRV_PCM_IOC_READI_FRAMES_OPCODE, snd_xferi>::new(&mut xferi) };
+
+    // SAFETY: Our caller guarantees that the buffer is valid. This function is unsafe because the
+    // driver isn't guaranteed to implement the ioctl properly. We don't have much of a choice and
+    // still have to trust the kernel there.
+    unsafe { ioctl(fd, ioctl_obj) }
+        .map(|()| xferi)
+        .map_err(<Errno as Into<io::Error>>::into)
+}
+
+const SNDRV_PCM_IOC_WRITEI_FRAMES_OPCODE: u32 =
+    opcode::write::<snd_xferi>(SNDRV_PCM_IOC_MAGIC, SNDRV_PCM_IOC_WRITEI_FRAMES);
+
+/// Writes interleaved frames to a playback PCM
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the file descriptor. An underrun is reported through
+/// [`Errno::PIPE`].
+///
+/// # Safety
+///
+/// The `xferi` buffer must be valid for reads of `xferi.frames` frames.
+#[instrument(level = "trace")]
+pub unsafe fn snd_pcm_ioctl_writei_frames(
+    fd: BorrowedFd<'_>,
+    mut xferi: snd_xferi,
+) -> io::Result<snd_xferi> {
+    // SAFETY: We checked both the opcode and the type. The kernel reads the buffer address and
+    // size, and writes the result back even though the ioctl is declared as write-only.
+    let ioctl_obj =
+        unsafe { Updater::<SNDRV_PCM_IOC_WRITEI_FRAMES_OPCODE, snd_xferi>::new(&mut xferi) };
+
+    // SAFETY: Our caller guarantees that the buffer is valid. This function is unsafe because the
+    // driver isn't guaranteed to implement the ioctl properly. We don't have much of a choice and
+    // still have to trust the kernel there.
+    unsafe { ioctl(fd, ioctl_obj) }
+        .map(|()| xferi)
+        .map_err(<Errno as Into<io::Error>>::into)
+}
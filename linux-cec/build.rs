@@ -0,0 +1,36 @@
+#![allow(missing_docs)]
+
+use std::{env, path::PathBuf};
+
+fn main() {
+    // Tell cargo to invalidate the built crate whenever the wrapper changes
+    println!("cargo:rerun-if-changed=src/bindgen-wrapper.h");
+
+    let bindings = bindgen::Builder::default()
+        // The input header we would like to generate
+        // bindings for.
+        .header("src/bindgen-wrapper.h")
+        // Tell cargo to invalidate the built crate whenever any of the
+        // included header files changed.
+        .parse_callbacks(Box::new(bindgen::CargoCallbacks::new()))
+        .allowlist_type("cec_.*")
+        .allowlist_var("CEC_.*")
+        .layout_tests(true)
+        .derive_copy(true)
+        .derive_default(true)
+        .derive_debug(true)
+        .derive_partialeq(true)
+        .derive_partialord(true)
+        // Finish the builder and generate the bindings.
+        .generate()
+        // Unwrap the Result and panic on failure.
+        .expect("Unable to generate bindings");
+
+    // Write the bindings to the $OUT_DIR/bindings.rs file.
+    let out_path = PathBuf::from(
+        env::var("OUT_DIR").expect("Couldn't find the OUT_DIR environment variable."),
+    );
+    bindings
+        .write_to_file(out_path.join("bindings.rs"))
+        .expect("Couldn't write bindings!");
+}
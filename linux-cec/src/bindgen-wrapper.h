@@ -0,0 +1 @@
+#include <linux/cec.h>
@@ -0,0 +1,698 @@
+#![allow(unsafe_code)]
+#![doc = include_str!("../README.md")]
+
+use core::{ffi::c_char, fmt, str::FromStr, time::Duration};
+use std::{
+    io,
+    os::fd::{AsFd as _, OwnedFd},
+    path::{Path, PathBuf},
+};
+
+use bitflags::bitflags;
+use rustix::fs::{Mode, OFlags, open};
+use tracing::debug;
+
+/// Raw, unsafe, abstraction
+pub mod raw;
+use raw::{
+    cec_caps, cec_event, cec_ioctl_adap_g_caps, cec_ioctl_adap_g_log_addrs,
+    cec_ioctl_adap_g_phys_addr, cec_ioctl_adap_s_log_addrs, cec_ioctl_adap_s_phys_addr,
+    cec_ioctl_dqevent, cec_ioctl_receive, cec_ioctl_s_mode, cec_ioctl_transmit, cec_log_addrs,
+    cec_msg,
+};
+
+/// Logical Address of a TV
+pub const CEC_LOG_ADDR_TV: u8 = 0;
+
+/// Logical Address of the first Playback Device
+pub const CEC_LOG_ADDR_PLAYBACK_1: u8 = 4;
+
+/// Logical Address for broadcast messages, or of unregistered devices
+pub const CEC_LOG_ADDR_BROADCAST: u8 = 15;
+
+const CEC_MODE_INITIATOR: u32 = 1 << 0;
+const CEC_MODE_FOLLOWER: u32 = 1 << 4;
+
+const CEC_TX_STATUS_OK: u8 = 1 << 0;
+const CEC_RX_STATUS_OK: u8 = 1 << 0;
+const CEC_RX_STATUS_FEATURE_ABORT: u8 = 1 << 2;
+
+const CEC_EVENT_STATE_CHANGE: u32 = 1;
+const CEC_EVENT_LOST_MSGS: u32 = 2;
+const CEC_EVENT_HPD_LOW: u32 = 5;
+const CEC_EVENT_HPD_HIGH: u32 = 6;
+
+const CEC_OP_CEC_VERSION_2_0: u8 = 6;
+const CEC_VENDOR_ID_NONE: u32 = 0xffff_ffff;
+
+const CEC_MAX_MSG_SIZE: usize = 16;
+
+fn c_chars_to_string(chars: &[c_char]) -> String {
+    let bytes = chars
+        .iter()
+        .map(|c| c.to_ne_bytes()[0])
+        .take_while(|b| *b != 0)
+        .collect::<Vec<_>>();
+
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// An HDMI Physical Address, as found in the EDID
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PhysicalAddress(u16);
+
+impl PhysicalAddress {
+    /// The address of a device that doesn't have a physical address
+    pub const INVALID: Self = Self(0xffff);
+
+    /// The address of the root device, usually the TV
+    pub const ROOT: Self = Self(0);
+
+    /// Builds an address from its four components, the first being the most significant one
+    ///
+    /// The components are truncated to 4 bits.
+    #[must_use]
+    pub fn from_components(components: [u8; 4]) -> Self {
+        Self(components.into_iter().fold(0, |addr, component| {
+            (addr << 4) | u16::from(component & 0xf)
+        }))
+    }
+
+    /// Returns whether the address is valid
+    #[must_use]
+    pub fn is_valid(self) -> bool {
+        self != Self::INVALID
+    }
+}
+
+impl From<u16> for PhysicalAddress {
+    fn from(value: u16) -> Self {
+        Self(value)
+    }
+}
+
+impl From<PhysicalAddress> for u16 {
+    fn from(value: PhysicalAddress) -> Self {
+        value.0
+    }
+}
+
+impl fmt::Display for PhysicalAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.is_valid() {
+            return f.write_str("f.f.f.f");
+        }
+
+        f.write_fmt(format_args!(
+            "{:x}.{:x}.{:x}.{:x}",
+            self.0 >> 12,
+            (self.0 >> 8) & 0xf,
+            (self.0 >> 4) & 0xf,
+            self.0 & 0xf
+        ))
+    }
+}
+
+impl FromStr for PhysicalAddress {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let nibbles = s
+            .split('.')
+            .map(|nibble| {
+                u8::from_str_radix(nibble, 16)
+                    .ok()
+                    .filter(|val| *val < 0x10)
+                    .ok_or(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "Invalid Physical Address Component",
+                    ))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let [a, b, c, d] = nibbles[..] else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Physical Addresses have four components",
+            ));
+        };
+
+        Ok(Self::from_components([a, b, c, d]))
+    }
+}
+
+/// A CEC Message Opcode
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum CecOpcode {
+    /// Feature Abort
+    FeatureAbort = 0x00,
+
+    /// Image View On
+    ImageViewOn = 0x04,
+
+    /// Standby
+    Standby = 0x36,
+
+    /// Give OSD Name
+    GiveOsdName = 0x46,
+
+    /// Set OSD Name
+    SetOsdName = 0x47,
+
+    /// Active Source
+    ActiveSource = 0x82,
+
+    /// Give Physical Address
+    GivePhysicalAddr = 0x83,
+
+    /// Report Physical Address
+    ReportPhysicalAddr = 0x84,
+
+    /// Give Device Power Status
+    GiveDevicePowerStatus = 0x8f,
+
+    /// Report Power Status
+    ReportPowerStatus = 0x90,
+
+    /// CEC Version
+    CecVersion = 0x9e,
+
+    /// Get CEC Version
+    GetCecVersion = 0x9f,
+}
+
+impl TryFrom<u8> for CecOpcode {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0x00 => Self::FeatureAbort,
+            0x04 => Self::ImageViewOn,
+            0x36 => Self::Standby,
+            0x46 => Self::GiveOsdName,
+            0x47 => Self::SetOsdName,
+            0x82 => Self::ActiveSource,
+            0x83 => Self::GivePhysicalAddr,
+            0x84 => Self::ReportPhysicalAddr,
+            0x8f => Self::GiveDevicePowerStatus,
+            0x90 => Self::ReportPowerStatus,
+            0x9e => Self::CecVersion,
+            0x9f => Self::GetCecVersion,
+            _ => return Err(value),
+        })
+    }
+}
+
+impl fmt::Display for CecOpcode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::FeatureAbort => "Feature Abort",
+            Self::ImageViewOn => "Image View On",
+            Self::Standby => "Standby",
+            Self::GiveOsdName => "Give OSD Name",
+            Self::SetOsdName => "Set OSD Name",
+            Self::ActiveSource => "Active Source",
+            Self::GivePhysicalAddr => "Give Physical Address",
+            Self::ReportPhysicalAddr => "Report Physical Address",
+            Self::GiveDevicePowerStatus => "Give Device Power Status",
+            Self::ReportPowerStatus => "Report Power Status",
+            Self::CecVersion => "CEC Version",
+            Self::GetCecVersion => "Get CEC Version",
+        })
+    }
+}
+
+/// A CEC Message
+#[derive(Clone, Copy)]
+pub struct CecMessage(cec_msg);
+
+impl CecMessage {
+    /// Creates a message without any opcode, to poll a device
+    #[must_use]
+    pub fn poll(initiator: u8, destination: u8) -> Self {
+        let mut msg = cec_msg {
+            len: 1,
+            ..cec_msg::default()
+        };
+        msg.msg[0] = ((initiator & 0xf) << 4) | (destination & 0xf);
+
+        Self(msg)
+    }
+
+    /// Creates a message
+    #[must_use]
+    pub fn new(initiator: u8, destination: u8, opcode: CecOpcode, operands: &[u8]) -> Self {
+        let mut msg = Self::poll(initiator, destination).0;
+        msg.msg[1] = opcode as u8;
+
+        let len = operands.len().min(CEC_MAX_MSG_SIZE - 2);
+        if let (Some(dst), Some(src)) = (msg.msg.get_mut(2..2 + len), operands.get(..len)) {
+            dst.copy_from_slice(src);
+        }
+        msg.len = u32::try_from(len + 2).unwrap_or_default();
+
+        Self(msg)
+    }
+
+    /// Requests the kernel to wait for a reply to this message
+    #[must_use]
+    pub fn with_reply(mut self, reply: CecOpcode, timeout: Duration) -> Self {
+        self.0.reply = reply as u8;
+        self.0.timeout = u32::try_from(timeout.as_millis()).unwrap_or(u32::MAX);
+        self
+    }
+
+    /// Returns the initiator logical address
+    #[must_use]
+    pub fn initiator(&self) -> u8 {
+        self.0.msg[0] >> 4
+    }
+
+    /// Returns the destination logical address
+    #[must_use]
+    pub fn destination(&self) -> u8 {
+        self.0.msg[0] & 0xf
+    }
+
+    /// Returns the message opcode, if any
+    #[must_use]
+    pub fn opcode(&self) -> Option<Result<CecOpcode, u8>> {
+        if self.0.len < 2 {
+            return None;
+        }
+
+        Some(CecOpcode::try_from(self.0.msg[1]))
+    }
+
+    /// Returns the message operands
+    #[must_use]
+    pub fn operands(&self) -> &[u8] {
+        let len = usize::try_from(self.0.len)
+            .unwrap_or_default()
+            .min(CEC_MAX_MSG_SIZE);
+
+        self.0.msg.get(2..len).unwrap_or_default()
+    }
+
+    /// Returns whether the message was acknowledged
+    #[must_use]
+    pub fn is_acked(&self) -> bool {
+        self.0.tx_status & CEC_TX_STATUS_OK != 0
+    }
+
+    /// Returns whether the reply to the message was received
+    #[must_use]
+    pub fn is_replied(&self) -> bool {
+        self.0.rx_status & CEC_RX_STATUS_OK != 0
+            && self.0.rx_status & CEC_RX_STATUS_FEATURE_ABORT == 0
+    }
+
+    /// Returns the physical address in a Report Physical Address message
+    #[must_use]
+    pub fn reported_physical_address(&self) -> Option<PhysicalAddress> {
+        if self.opcode() != Some(Ok(CecOpcode::ReportPhysicalAddr)) {
+            return None;
+        }
+
+        let [hi, lo, ..] = *self.operands() else {
+            return None;
+        };
+
+        Some(PhysicalAddress(u16::from_be_bytes([hi, lo])))
+    }
+}
+
+impl fmt::Debug for CecMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CecMessage")
+            .field("initiator", &self.initiator())
+            .field("destination", &self.destination())
+            .field("opcode", &self.opcode())
+            .field("operands", &self.operands())
+            .field("tx_status", &self.0.tx_status)
+            .field("rx_status", &self.0.rx_status)
+            .finish()
+    }
+}
+
+impl fmt::Display for CecMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!(
+            "{:x} -> {:x}: ",
+            self.initiator(),
+            self.destination()
+        ))?;
+
+        match self.opcode() {
+            None => f.write_str("Poll"),
+            Some(Ok(opcode)) => fmt::Display::fmt(&opcode, f),
+            Some(Err(opcode)) => f.write_fmt(format_args!("Opcode {opcode:#04x}")),
+        }
+    }
+}
+
+bitflags! {
+    /// CEC Adapter Capabilities
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct CecCapabilities: u32 {
+        /// Userspace has to configure the physical address
+        const PHYS_ADDR = 1 << 0;
+
+        /// Userspace has to configure the logical addresses
+        const LOG_ADDRS = 1 << 1;
+
+        /// Userspace can transmit messages
+        const TRANSMIT = 1 << 2;
+
+        /// Passthrough mode is supported
+        const PASSTHROUGH = 1 << 3;
+
+        /// Remote Control messages are supported
+        const RC = 1 << 4;
+
+        /// All messages can be monitored
+        const MONITOR_ALL = 1 << 5;
+
+        /// The adapter needs the HPD to be high to transmit
+        const NEEDS_HPD = 1 << 6;
+
+        /// The CEC pin can be monitored
+        const MONITOR_PIN = 1 << 7;
+
+        /// The connector information can be queried
+        const CONNECTOR_INFO = 1 << 8;
+
+        /// The adapter can reply to Vendor ID messages
+        const REPLY_VENDOR_ID = 1 << 9;
+    }
+}
+
+/// CEC Adapter Capabilities
+#[derive(Debug)]
+pub struct CecCaps {
+    /// Driver Name
+    pub driver: String,
+
+    /// Adapter Name
+    pub name: String,
+
+    /// Number of logical addresses the adapter can claim
+    pub available_log_addrs: u32,
+
+    /// Adapter Capabilities
+    pub capabilities: CecCapabilities,
+}
+
+impl From<cec_caps> for CecCaps {
+    fn from(value: cec_caps) -> Self {
+        Self {
+            driver: c_chars_to_string(&value.driver),
+            name: c_chars_to_string(&value.name),
+            available_log_addrs: value.available_log_addrs,
+            capabilities: CecCapabilities::from_bits_retain(value.capabilities),
+        }
+    }
+}
+
+/// The kind of device to claim a logical address for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CecDeviceKind {
+    /// A TV
+    Tv,
+
+    /// A Playback Device
+    Playback,
+}
+
+impl CecDeviceKind {
+    fn log_addr_type(self) -> u8 {
+        match self {
+            Self::Tv => 0,
+            Self::Playback => 3,
+        }
+    }
+
+    fn primary_device_type(self) -> u8 {
+        match self {
+            Self::Tv => 0,
+            Self::Playback => 4,
+        }
+    }
+
+    fn all_device_types(self) -> u8 {
+        match self {
+            Self::Tv => 0x80,
+            Self::Playback => 0x10,
+        }
+    }
+}
+
+/// A CEC Event
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CecEvent {
+    /// The adapter configuration changed
+    StateChange {
+        /// The adapter physical address
+        physical_address: PhysicalAddress,
+
+        /// The logical addresses the adapter claimed
+        log_addr_mask: u16,
+    },
+
+    /// Messages were lost because the application didn't dequeue them fast enough
+    LostMessages(u32),
+
+    /// The HPD pin went low
+    HpdLow,
+
+    /// The HPD pin went high
+    HpdHigh,
+
+    /// Any other event
+    Other(u32),
+}
+
+impl From<cec_event> for CecEvent {
+    fn from(value: cec_event) -> Self {
+        match value.event {
+            CEC_EVENT_STATE_CHANGE => {
+                // SAFETY: The state_change field is the one set for this event.
+                let state = unsafe { value.__bindgen_anon_1.state_change };
+
+                Self::StateChange {
+                    physical_address: PhysicalAddress(state.phys_addr),
+                    log_addr_mask: state.log_addr_mask,
+                }
+            }
+            CEC_EVENT_LOST_MSGS => {
+                // SAFETY: The lost_msgs field is the one set for this event.
+                let lost = unsafe { value.__bindgen_anon_1.lost_msgs };
+
+                Self::LostMessages(lost.lost_msgs)
+            }
+            CEC_EVENT_HPD_LOW => Self::HpdLow,
+            CEC_EVENT_HPD_HIGH => Self::HpdHigh,
+            event => Self::Other(event),
+        }
+    }
+}
+
+/// A CEC Adapter
+#[derive(Debug)]
+pub struct CecDevice {
+    path: PathBuf,
+    fd: OwnedFd,
+}
+
+impl CecDevice {
+    /// Opens a CEC device file
+    ///
+    /// # Errors
+    ///
+    /// If the device can't be opened.
+    pub fn new(path: &Path) -> io::Result<Self> {
+        let fd = open(path, OFlags::RDWR | OFlags::CLOEXEC, Mode::empty())?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            fd,
+        })
+    }
+
+    /// Returns the adapter capabilities
+    ///
+    /// # Errors
+    ///
+    /// If there's an I/O error while accessing the device.
+    pub fn caps(&self) -> io::Result<CecCaps> {
+        cec_ioctl_adap_g_caps(self.fd.as_fd()).map(CecCaps::from)
+    }
+
+    /// Returns the adapter physical address
+    ///
+    /// # Errors
+    ///
+    /// If there's an I/O error while accessing the device.
+    pub fn physical_address(&self) -> io::Result<PhysicalAddress> {
+        cec_ioctl_adap_g_phys_addr(self.fd.as_fd()).map(PhysicalAddress)
+    }
+
+    /// Sets the adapter physical address
+    ///
+    /// # Errors
+    ///
+    /// If there's an I/O error while accessing the device, or if the physical address is set by
+    /// the driver.
+    pub fn set_physical_address(&self, addr: PhysicalAddress) -> io::Result<()> {
+        cec_ioctl_adap_s_phys_addr(self.fd.as_fd(), addr.0)
+    }
+
+    /// Returns the logical addresses the adapter claimed
+    ///
+    /// # Errors
+    ///
+    /// If there's an I/O error while accessing the device.
+    pub fn logical_addresses(&self) -> io::Result<Vec<u8>> {
+        let log_addrs = cec_ioctl_adap_g_log_addrs(self.fd.as_fd())?;
+
+        Ok(log_addrs
+            .log_addr
+            .into_iter()
+            .take(usize::from(log_addrs.num_log_addrs))
+            .collect())
+    }
+
+    /// Releases any logical address, and claims one for the given kind of device
+    ///
+    /// Returns the claimed logical address.
+    ///
+    /// # Errors
+    ///
+    /// If there's an I/O error while accessing the device, or if no logical address could be
+    /// claimed.
+    pub fn claim_logical_address(&self, kind: CecDeviceKind, osd_name: &str) -> io::Result<u8> {
+        cec_ioctl_adap_s_log_addrs(self.fd.as_fd(), cec_log_addrs::default())?;
+
+        let mut log_addrs = cec_log_addrs {
+            cec_version: CEC_OP_CEC_VERSION_2_0,
+            num_log_addrs: 1,
+            vendor_id: CEC_VENDOR_ID_NONE,
+            ..cec_log_addrs::default()
+        };
+
+        for (dst, src) in log_addrs
+            .osd_name
+            .iter_mut()
+            // Keep a NUL terminator
+            .take(14)
+            .zip(osd_name.bytes())
+        {
+            *dst = c_char::from_ne_bytes([src]);
+        }
+
+        log_addrs.primary_device_type[0] = kind.primary_device_type();
+        log_addrs.log_addr_type[0] = kind.log_addr_type();
+        log_addrs.all_device_types[0] = kind.all_device_types();
+
+        let log_addrs = cec_ioctl_adap_s_log_addrs(self.fd.as_fd(), log_addrs)?;
+        let addr = log_addrs.log_addr[0];
+        if addr == CEC_LOG_ADDR_BROADCAST {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrNotAvailable,
+                "Couldn't claim a logical address",
+            ));
+        }
+
+        debug!(
+            "CEC {}: claimed logical address {addr:x}",
+            self.path.display()
+        );
+
+        Ok(addr)
+    }
+
+    /// Sets whether this file handle should receive the messages sent to the adapter
+    ///
+    /// # Errors
+    ///
+    /// If there's an I/O error while accessing the device.
+    pub fn set_follower(&self, follower: bool) -> io::Result<()> {
+        cec_ioctl_s_mode(
+            self.fd.as_fd(),
+            CEC_MODE_INITIATOR | if follower { CEC_MODE_FOLLOWER } else { 0 },
+        )
+    }
+
+    /// Transmits a message, and waits for the reply if one was requested
+    ///
+    /// # Errors
+    ///
+    /// If there's an I/O error while accessing the device.
+    pub fn transmit(&self, msg: CecMessage) -> io::Result<CecMessage> {
+        debug!("CEC {}: transmitting {msg}", self.path.display());
+        cec_ioctl_transmit(self.fd.as_fd(), msg.0).map(CecMessage)
+    }
+
+    /// Waits for a message to be received
+    ///
+    /// # Errors
+    ///
+    /// If there's an I/O error while accessing the device, or if no message was received before
+    /// the timeout.
+    pub fn receive(&self, timeout: Duration) -> io::Result<CecMessage> {
+        let msg = cec_msg {
+            timeout: u32::try_from(timeout.as_millis()).unwrap_or(u32::MAX),
+            ..cec_msg::default()
+        };
+
+        cec_ioctl_receive(self.fd.as_fd(), msg).map(CecMessage)
+    }
+
+    /// Waits for the next event
+    ///
+    /// # Errors
+    ///
+    /// If there's an I/O error while accessing the device.
+    pub fn dequeue_event(&self) -> io::Result<CecEvent> {
+        cec_ioctl_dqevent(self.fd.as_fd()).map(CecEvent::from)
+    }
+}
+
+#[cfg(test)]
+mod tests_cec_message {
+    use super::{CecMessage, CecOpcode, PhysicalAddress};
+
+    #[test]
+    fn test_physical_address() {
+        let addr: PhysicalAddress = "1.0.0.0".parse().unwrap();
+
+        assert_eq!(u16::from(addr), 0x1000);
+        assert_eq!(addr.to_string(), "1.0.0.0");
+        assert_eq!(PhysicalAddress::from(0x3a20).to_string(), "3.a.2.0");
+        assert!("1.0.0".parse::<PhysicalAddress>().is_err());
+        assert!("1.0.0.10".parse::<PhysicalAddress>().is_err());
+        assert_eq!(
+            PhysicalAddress::from_components([3, 0xa, 2, 0]),
+            PhysicalAddress::from(0x3a20)
+        );
+    }
+
+    #[test]
+    fn test_message() {
+        let msg = CecMessage::new(0, 4, CecOpcode::ReportPhysicalAddr, &[0x10, 0x00, 0x04]);
+
+        assert_eq!(msg.initiator(), 0);
+        assert_eq!(msg.destination(), 4);
+        assert_eq!(msg.opcode(), Some(Ok(CecOpcode::ReportPhysicalAddr)));
+        assert_eq!(msg.operands(), &[0x10, 0x00, 0x04]);
+        assert_eq!(
+            msg.reported_physical_address(),
+            Some(PhysicalAddress::from(0x1000))
+        );
+
+        let poll = CecMessage::poll(0, 4);
+        assert_eq!(poll.opcode(), None);
+        assert!(poll.operands().is_empty());
+    }
+}
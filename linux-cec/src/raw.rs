@@ -0,0 +1,247 @@
+use std::{io, os::fd::BorrowedFd};
+
+use rustix::{
+    io::Errno,
+    ioctl::{Getter, Setter, Updater, ioctl, opcode},
+};
+use tracing::instrument;
+
+pub(crate) mod bindgen {
+    #![allow(clippy::decimal_literal_representation)]
+    #![allow(clippy::multiple_unsafe_ops_per_block)]
+    #![allow(clippy::pub_underscore_fields)]
+    #![allow(clippy::std_instead_of_alloc)]
+    #![allow(clippy::std_instead_of_core)]
+    #![allow(clippy::undocumented_unsafe_blocks)]
+    #![allow(clippy::unreadable_literal)]
+    #![allow(dead_code)]
+    #![allow(missing_debug_implementations)]
+    #![allow(missing_docs)]
+    #![allow(non_upper_case_globals)]
+    #![allow(non_camel_case_types)]
+    #![allow(non_snake_case)]
+    #![allow(unreachable_pub)]
+    #![allow(unsafe_code)]
+
+    include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+}
+
+pub use bindgen::{cec_caps, cec_event, cec_log_addrs, cec_msg};
+
+const CEC_IOC_MAGIC: u8 = b'a';
+const CEC_IOC_ADAP_G_CAPS: u8 = 0;
+const CEC_IOC_ADAP_G_PHYS_ADDR: u8 = 1;
+const CEC_IOC_ADAP_S_PHYS_ADDR: u8 = 2;
+const CEC_IOC_ADAP_G_LOG_ADDRS: u8 = 3;
+const CEC_IOC_ADAP_S_LOG_ADDRS: u8 = 4;
+const CEC_IOC_TRANSMIT: u8 = 5;
+const CEC_IOC_RECEIVE: u8 = 6;
+const CEC_IOC_DQEVENT: u8 = 7;
+const CEC_IOC_G_MODE: u8 = 8;
+const CEC_IOC_S_MODE: u8 = 9;
+
+const CEC_IOC_ADAP_G_CAPS_OPCODE: u32 =
+    opcode::read_write::<cec_caps>(CEC_IOC_MAGIC, CEC_IOC_ADAP_G_CAPS);
+
+/// Queries the adapter capabilities
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the file descriptor.
+#[instrument(level = "trace")]
+pub fn cec_ioctl_adap_g_caps(fd: BorrowedFd<'_>) -> io::Result<cec_caps> {
+    let mut caps = cec_caps::default();
+
+    // SAFETY: We checked both the opcode and the type.
+    let ioctl_obj = unsafe { Updater::<CEC_IOC_ADAP_G_CAPS_OPCODE, cec_caps>::new(&mut caps) };
+
+    // SAFETY: This function is unsafe because the driver isn't guaranteed to implement the ioctl
+    // properly. We don't have much of a choice and still have to trust the
+    // kernel there.
+    unsafe { ioctl(fd, ioctl_obj) }
+        .map(|()| caps)
+        .map_err(<Errno as Into<io::Error>>::into)
+}
+
+const CEC_IOC_ADAP_G_PHYS_ADDR_OPCODE: u32 =
+    opcode::read::<u16>(CEC_IOC_MAGIC, CEC_IOC_ADAP_G_PHYS_ADDR);
+
+/// Retrieves the adapter physical address
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the file descriptor.
+#[instrument(level = "trace")]
+pub fn cec_ioctl_adap_g_phys_addr(fd: BorrowedFd<'_>) -> io::Result<u16> {
+    // SAFETY: We checked both the opcode and the type.
+    let ioctl_obj = unsafe { Getter::<CEC_IOC_ADAP_G_PHYS_ADDR_OPCODE, u16>::new() };
+
+    // SAFETY: This function is unsafe because the driver isn't guaranteed to implement the ioctl
+    // properly. We don't have much of a choice and still have to trust the
+    // kernel there.
+    unsafe { ioctl(fd, ioctl_obj) }.map_err(<Errno as Into<io::Error>>::into)
+}
+
+const CEC_IOC_ADAP_S_PHYS_ADDR_OPCODE: u32 =
+    opcode::write::<u16>(CEC_IOC_MAGIC, CEC_IOC_ADAP_S_PHYS_ADDR);
+
+/// Sets the adapter physical address
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the file descriptor, or if the adapter doesn't let
+/// userspace set its physical address.
+#[instrument(level = "trace")]
+pub fn cec_ioctl_adap_s_phys_addr(fd: BorrowedFd<'_>, addr: u16) -> io::Result<()> {
+    // SAFETY: We checked both the opcode and the type.
+    let ioctl_obj = unsafe { Setter::<CEC_IOC_ADAP_S_PHYS_ADDR_OPCODE, u16>::new(addr) };
+
+    // SAFETY: This function is unsafe because the driver isn't guaranteed to implement the ioctl
+    // properly. We don't have much of a choice and still have to trust the
+    // kernel there.
+    unsafe { ioctl(fd, ioctl_obj) }.map_err(<Errno as Into<io::Error>>::into)
+}
+
+const CEC_IOC_ADAP_G_LOG_ADDRS_OPCODE: u32 =
+    opcode::read::<cec_log_addrs>(CEC_IOC_MAGIC, CEC_IOC_ADAP_G_LOG_ADDRS);
+
+/// Retrieves the adapter logical addresses
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the file descriptor.
+#[instrument(level = "trace")]
+pub fn cec_ioctl_adap_g_log_addrs(fd: BorrowedFd<'_>) -> io::Result<cec_log_addrs> {
+    // SAFETY: We checked both the opcode and the type.
+    let ioctl_obj = unsafe { Getter::<CEC_IOC_ADAP_G_LOG_ADDRS_OPCODE, cec_log_addrs>::new() };
+
+    // SAFETY: This function is unsafe because the driver isn't guaranteed to implement the ioctl
+    // properly. We don't have much of a choice and still have to trust the
+    // kernel there.
+    unsafe { ioctl(fd, ioctl_obj) }.map_err(<Errno as Into<io::Error>>::into)
+}
+
+const CEC_IOC_ADAP_S_LOG_ADDRS_OPCODE: u32 =
+    opcode::read_write::<cec_log_addrs>(CEC_IOC_MAGIC, CEC_IOC_ADAP_S_LOG_ADDRS);
+
+/// Claims the adapter logical addresses
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the file descriptor, or if the adapter is already
+/// configured.
+#[instrument(level = "trace")]
+pub fn cec_ioctl_adap_s_log_addrs(
+    fd: BorrowedFd<'_>,
+    mut log_addrs: cec_log_addrs,
+) -> io::Result<cec_log_addrs> {
+    // SAFETY: We checked both the opcode and the type.
+    let ioctl_obj =
+        unsafe { Updater::<CEC_IOC_ADAP_S_LOG_ADDRS_OPCODE, cec_log_addrs>::new(&mut log_addrs) };
+
+    // SAFETY: This function is unsafe because the driver isn't guaranteed to implement the ioctl
+    // properly. We don't have much of a choice and still have to trust the
+    // kernel there.
+    unsafe { ioctl(fd, ioctl_obj) }
+        .map(|()| log_addrs)
+        .map_err(<Errno as Into<io::Error>>::into)
+}
+
+const CEC_IOC_TRANSMIT_OPCODE: u32 = opcode::read_write::<cec_msg>(CEC_IOC_MAGIC, CEC_IOC_TRANSMIT);
+
+/// Transmits a message, and waits for its reply if one was requested
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the file descriptor.
+#[instrument(level = "trace")]
+pub fn cec_ioctl_transmit(fd: BorrowedFd<'_>, mut msg: cec_msg) -> io::Result<cec_msg> {
+    // SAFETY: We checked both the opcode and the type.
+    let ioctl_obj = unsafe { Updater::<CEC_IOC_TRANSMIT_OPCODE, cec_msg>::new(&mut msg) };
+
+    // SAFETY: This function is unsafe because the driver isn't guaranteed to implement the ioctl
+    // properly. We don't have much of a choice and still have to trust the
+    // kernel there.
+    unsafe { ioctl(fd, ioctl_obj) }
+        .map(|()| msg)
+        .map_err(<Errno as Into<io::Error>>::into)
+}
+
+const CEC_IOC_RECEIVE_OPCODE: u32 = opcode::read_write::<cec_msg>(CEC_IOC_MAGIC, CEC_IOC_RECEIVE);
+
+/// Receives a message
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the file descriptor, or if no message was received
+/// before the message timeout.
+#[instrument(level = "trace")]
+pub fn cec_ioctl_receive(fd: BorrowedFd<'_>, mut msg: cec_msg) -> io::Result<cec_msg> {
+    // SAFETY: We checked both the opcode and the type.
+    let ioctl_obj = unsafe { Updater::<CEC_IOC_RECEIVE_OPCODE, cec_msg>::new(&mut msg) };
+
+    // SAFETY: This function is unsafe because the driver isn't guaranteed to implement the ioctl
+    // properly. We don't have much of a choice and still have to trust the
+    // kernel there.
+    unsafe { ioctl(fd, ioctl_obj) }
+        .map(|()| msg)
+        .map_err(<Errno as Into<io::Error>>::into)
+}
+
+const CEC_IOC_DQEVENT_OPCODE: u32 = opcode::read_write::<cec_event>(CEC_IOC_MAGIC, CEC_IOC_DQEVENT);
+
+/// Dequeues an event
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the file descriptor.
+#[instrument(level = "trace")]
+pub fn cec_ioctl_dqevent(fd: BorrowedFd<'_>) -> io::Result<cec_event> {
+    let mut event = cec_event::default();
+
+    // SAFETY: We checked both the opcode and the type.
+    let ioctl_obj = unsafe { Updater::<CEC_IOC_DQEVENT_OPCODE, cec_event>::new(&mut event) };
+
+    // SAFETY: This function is unsafe because the driver isn't guaranteed to implement the ioctl
+    // properly. We don't have much of a choice and still have to trust the
+    // kernel there.
+    unsafe { ioctl(fd, ioctl_obj) }
+        .map(|()| event)
+        .map_err(<Errno as Into<io::Error>>::into)
+}
+
+const CEC_IOC_G_MODE_OPCODE: u32 = opcode::read::<u32>(CEC_IOC_MAGIC, CEC_IOC_G_MODE);
+
+/// Retrieves the file handle initiator and follower modes
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the file descriptor.
+#[instrument(level = "trace")]
+pub fn cec_ioctl_g_mode(fd: BorrowedFd<'_>) -> io::Result<u32> {
+    // SAFETY: We checked both the opcode and the type.
+    let ioctl_obj = unsafe { Getter::<CEC_IOC_G_MODE_OPCODE, u32>::new() };
+
+    // SAFETY: This function is unsafe because the driver isn't guaranteed to implement the ioctl
+    // properly. We don't have much of a choice and still have to trust the
+    // kernel there.
+    unsafe { ioctl(fd, ioctl_obj) }.map_err(<Errno as Into<io::Error>>::into)
+}
+
+const CEC_IOC_S_MODE_OPCODE: u32 = opcode::write::<u32>(CEC_IOC_MAGIC, CEC_IOC_S_MODE);
+
+/// Sets the file handle initiator and follower modes
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the file descriptor.
+#[instrument(level = "trace")]
+pub fn cec_ioctl_s_mode(fd: BorrowedFd<'_>, mode: u32) -> io::Result<()> {
+    // SAFETY: We checked both the opcode and the type.
+    let ioctl_obj = unsafe { Setter::<CEC_IOC_S_MODE_OPCODE, u32>::new(mode) };
+
+    // SAFETY: This function is unsafe because the driver isn't guaranteed to implement the ioctl
+    // properly. We don't have much of a choice and still have to trust the
+    // kernel there.
+    unsafe { ioctl(fd, ioctl_obj) }.map_err(<Errno as Into<io::Error>>::into)
+}
@@ -0,0 +1,759 @@
+use core::{fmt, str::FromStr};
+use std::io;
+
+use tracing::{debug, warn};
+use v4l2_raw::{
+    v4l2_colorspace, v4l2_field, v4l2_quantization, v4l2_xfer_func, v4l2_ycbcr_encoding,
+};
+
+use crate::{
+    MediaController, MediaControllerLink, MediaControllerLinkKind, MediaControllerPad,
+    RevocableResult, RevocableValue, raw,
+};
+
+// The names media-ctl uses for the frame format properties.
+pub(crate) const FIELD_NAMES: &[(&str, v4l2_field)] = &[
+    ("any", v4l2_field::V4L2_FIELD_ANY),
+    ("none", v4l2_field::V4L2_FIELD_NONE),
+    ("top", v4l2_field::V4L2_FIELD_TOP),
+    ("bottom", v4l2_field::V4L2_FIELD_BOTTOM),
+    ("interlaced", v4l2_field::V4L2_FIELD_INTERLACED),
+    ("seq-tb", v4l2_field::V4L2_FIELD_SEQ_TB),
+    ("seq-bt", v4l2_field::V4L2_FIELD_SEQ_BT),
+    ("alternate", v4l2_field::V4L2_FIELD_ALTERNATE),
+    ("interlaced-tb", v4l2_field::V4L2_FIELD_INTERLACED_TB),
+    ("interlaced-bt", v4l2_field::V4L2_FIELD_INTERLACED_BT),
+];
+
+pub(crate) const COLORSPACE_NAMES: &[(&str, v4l2_colorspace)] = &[
+    ("default", v4l2_colorspace::V4L2_COLORSPACE_DEFAULT),
+    ("smpte170m", v4l2_colorspace::V4L2_COLORSPACE_SMPTE170M),
+    ("smpte240m", v4l2_colorspace::V4L2_COLORSPACE_SMPTE240M),
+    ("rec709", v4l2_colorspace::V4L2_COLORSPACE_REC709),
+    ("470m", v4l2_colorspace::V4L2_COLORSPACE_470_SYSTEM_M),
+    ("470bg", v4l2_colorspace::V4L2_COLORSPACE_470_SYSTEM_BG),
+    ("jpeg", v4l2_colorspace::V4L2_COLORSPACE_JPEG),
+    ("srgb", v4l2_colorspace::V4L2_COLORSPACE_SRGB),
+    ("oprgb", v4l2_colorspace::V4L2_COLORSPACE_OPRGB),
+    ("bt2020", v4l2_colorspace::V4L2_COLORSPACE_BT2020),
+    ("raw", v4l2_colorspace::V4L2_COLORSPACE_RAW),
+    ("dcip3", v4l2_colorspace::V4L2_COLORSPACE_DCI_P3),
+];
+
+pub(crate) const XFER_FUNC_NAMES: &[(&str, v4l2_xfer_func)] = &[
+    ("default", v4l2_xfer_func::V4L2_XFER_FUNC_DEFAULT),
+    ("709", v4l2_xfer_func::V4L2_XFER_FUNC_709),
+    ("srgb", v4l2_xfer_func::V4L2_XFER_FUNC_SRGB),
+    ("oprgb", v4l2_xfer_func::V4L2_XFER_FUNC_OPRGB),
+    ("smpte240m", v4l2_xfer_func::V4L2_XFER_FUNC_SMPTE240M),
+    ("smpte2084", v4l2_xfer_func::V4L2_XFER_FUNC_SMPTE2084),
+    ("dcip3", v4l2_xfer_func::V4L2_XFER_FUNC_DCI_P3),
+    ("none", v4l2_xfer_func::V4L2_XFER_FUNC_NONE),
+];
+
+pub(crate) const YCBCR_ENCODING_NAMES: &[(&str, v4l2_ycbcr_encoding)] = &[
+    ("default", v4l2_ycbcr_encoding::V4L2_YCBCR_ENC_DEFAULT),
+    ("601", v4l2_ycbcr_encoding::V4L2_YCBCR_ENC_601),
+    ("709", v4l2_ycbcr_encoding::V4L2_YCBCR_ENC_709),
+    ("xv601", v4l2_ycbcr_encoding::V4L2_YCBCR_ENC_XV601),
+    ("xv709", v4l2_ycbcr_encoding::V4L2_YCBCR_ENC_XV709),
+    ("bt2020", v4l2_ycbcr_encoding::V4L2_YCBCR_ENC_BT2020),
+    (
+        "bt2020c",
+        v4l2_ycbcr_encoding::V4L2_YCBCR_ENC_BT2020_CONST_LUM,
+    ),
+    ("smpte240m", v4l2_ycbcr_encoding::V4L2_YCBCR_ENC_SMPTE240M),
+];
+
+pub(crate) const QUANTIZATION_NAMES: &[(&str, v4l2_quantization)] = &[
+    ("default", v4l2_quantization::V4L2_QUANTIZATION_DEFAULT),
+    (
+        "full-range",
+        v4l2_quantization::V4L2_QUANTIZATION_FULL_RANGE,
+    ),
+    ("lim-range", v4l2_quantization::V4L2_QUANTIZATION_LIM_RANGE),
+];
+
+// Returns the media-ctl name of a property value, or "unknown" like media-ctl does.
+pub(crate) fn value_name<T>(names: &[(&'static str, T)], value: T) -> &'static str
+where
+    T: Copy + PartialEq,
+{
+    names
+        .iter()
+        .find(|(_, v)| *v == value)
+        .map_or("unknown", |(name, _)| name)
+}
+
+/// An entity, as designated in a `media-ctl` description
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MediaControllerEntityReference {
+    /// Entity ID
+    Id(u32),
+
+    /// Entity Name, quoted in the description
+    Name(String),
+}
+
+impl fmt::Display for MediaControllerEntityReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MediaControllerEntityReference::Id(id) => f.write_fmt(format_args!("{id}")),
+            MediaControllerEntityReference::Name(name) => f.write_fmt(format_args!("\"{name}\"")),
+        }
+    }
+}
+
+/// A pad, as designated in a `media-ctl` description, ie. `"entity":index` or `id:index`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MediaControllerPadReference {
+    /// Entity the pad belongs to
+    pub entity: MediaControllerEntityReference,
+
+    /// Pad Index within its entity
+    pub index: u32,
+}
+
+impl fmt::Display for MediaControllerPadReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!("{}:{}", self.entity, self.index))
+    }
+}
+
+impl FromStr for MediaControllerPadReference {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Parser::new(s).single(Parser::pad)
+    }
+}
+
+/// A link setup, in the `media-ctl -l` syntax, ie. `"source":0 -> "sink":0 [1]`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MediaControllerLinkDescription {
+    /// Source Pad
+    pub source: MediaControllerPadReference,
+
+    /// Sink Pad
+    pub sink: MediaControllerPadReference,
+
+    /// Whether the link must be enabled or disabled
+    pub enabled: bool,
+}
+
+impl MediaControllerLinkDescription {
+    /// Parses a comma-separated list of link setups, like `media-ctl -l` does
+    ///
+    /// # Errors
+    ///
+    /// If the description is malformed
+    pub fn parse_list(input: &str) -> io::Result<Vec<Self>> {
+        Parser::new(input).list(Parser::link)
+    }
+}
+
+impl fmt::Display for MediaControllerLinkDescription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!(
+            "{} -> {} [{}]",
+            self.source,
+            self.sink,
+            u32::from(self.enabled)
+        ))
+    }
+}
+
+impl FromStr for MediaControllerLinkDescription {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Parser::new(s).single(Parser::link)
+    }
+}
+
+/// A rectangle, in the `media-ctl` syntax, ie. `(left,top)/widthxheight`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MediaControllerRectangle {
+    /// Horizontal Offset
+    pub left: i32,
+
+    /// Vertical Offset
+    pub top: i32,
+
+    /// Width
+    pub width: u32,
+
+    /// Height
+    pub height: u32,
+}
+
+impl fmt::Display for MediaControllerRectangle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!(
+            "({},{})/{}x{}",
+            self.left, self.top, self.width, self.height
+        ))
+    }
+}
+
+/// A frame format, in the `media-ctl` syntax, ie. `CODE/widthxheight`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MediaControllerFrameFormat {
+    /// Media Bus Code Name, without the `MEDIA_BUS_FMT_` prefix
+    pub code: String,
+
+    /// Frame Width
+    pub width: u32,
+
+    /// Frame Height
+    pub height: u32,
+}
+
+impl fmt::Display for MediaControllerFrameFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!("{}/{}x{}", self.code, self.width, self.height))
+    }
+}
+
+/// A sub-device pad format, in the `media-ctl -V` syntax, ie.
+/// `"entity":pad[/stream] [fmt:CODE/widthxheight field:... colorspace:... crop:... compose:...]`
+///
+/// The frame format, its field order and colorimetry, and the crop and compose rectangles are
+/// supported.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MediaControllerFormatDescription {
+    /// Sub-device Pad
+    pub pad: MediaControllerPadReference,
+
+    /// Sub-device Stream, if the sub-device supports routing
+    pub stream: u32,
+
+    /// Frame Format
+    pub format: Option<MediaControllerFrameFormat>,
+
+    /// Field Order
+    pub field: Option<v4l2_field>,
+
+    /// Colorspace
+    pub colorspace: Option<v4l2_colorspace>,
+
+    /// Transfer Function
+    pub xfer_func: Option<v4l2_xfer_func>,
+
+    /// YCbCr Encoding
+    pub ycbcr_encoding: Option<v4l2_ycbcr_encoding>,
+
+    /// Quantization Range
+    pub quantization: Option<v4l2_quantization>,
+
+    /// Crop Rectangle
+    pub crop: Option<MediaControllerRectangle>,
+
+    /// Compose Rectangle
+    pub compose: Option<MediaControllerRectangle>,
+}
+
+impl MediaControllerFormatDescription {
+    /// Parses a comma-separated list of pad formats, like `media-ctl -V` does
+    ///
+    /// # Errors
+    ///
+    /// If the description is malformed
+    pub fn parse_list(input: &str) -> io::Result<Vec<Self>> {
+        Parser::new(input).list(Parser::format)
+    }
+}
+
+impl fmt::Display for MediaControllerFormatDescription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!("{}", self.pad))?;
+
+        if self.stream != 0 {
+            f.write_fmt(format_args!("/{}", self.stream))?;
+        }
+
+        let mut properties = Vec::new();
+
+        if let Some(format) = &self.format {
+            properties.push(format!("fmt:{format}"));
+        }
+
+        if let Some(field) = self.field {
+            properties.push(format!("field:{}", value_name(FIELD_NAMES, field)));
+        }
+
+        if let Some(colorspace) = self.colorspace {
+            properties.push(format!(
+                "colorspace:{}",
+                value_name(COLORSPACE_NAMES, colorspace)
+            ));
+        }
+
+        if let Some(xfer_func) = self.xfer_func {
+            properties.push(format!("xfer:{}", value_name(XFER_FUNC_NAMES, xfer_func)));
+        }
+
+        if let Some(encoding) = self.ycbcr_encoding {
+            properties.push(format!(
+                "ycbcr:{}",
+                value_name(YCBCR_ENCODING_NAMES, encoding)
+            ));
+        }
+
+        if let Some(quantization) = self.quantization {
+            properties.push(format!(
+                "quantization:{}",
+                value_name(QUANTIZATION_NAMES, quantization)
+            ));
+        }
+
+        if let Some(crop) = &self.crop {
+            properties.push(format!("crop:{crop}"));
+        }
+
+        if let Some(compose) = &self.compose {
+            properties.push(format!("compose:{compose}"));
+        }
+
+        f.write_fmt(format_args!(" [{}]", properties.join(" ")))
+    }
+}
+
+impl FromStr for MediaControllerFormatDescription {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Parser::new(s).single(Parser::format)
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Self {
+        Self { input, pos: 0 }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.pos = self.input.len() - self.rest().trim_start().len();
+    }
+
+    fn error(&self, expected: &str) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "Expected {expected} at offset {} in {:?}",
+                self.pos, self.input
+            ),
+        )
+    }
+
+    fn is_done(&mut self) -> bool {
+        self.skip_whitespace();
+        self.rest().is_empty()
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+
+        if self.rest().starts_with(token) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> io::Result<()> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("'{token}'")))
+        }
+    }
+
+    // Returns the longest prefix of the remaining input made of characters matching the
+    // predicate, which gets their index as well.
+    fn take_while<F>(&mut self, pred: F) -> &'a str
+    where
+        F: Fn(usize, char) -> bool,
+    {
+        self.skip_whitespace();
+
+        let rest = self.rest();
+        let len = rest
+            .char_indices()
+            .find(|&(idx, c)| !pred(idx, c))
+            .map_or(rest.len(), |(idx, _)| idx);
+
+        self.pos += len;
+        &rest[..len]
+    }
+
+    fn number<T>(&mut self, what: &str) -> io::Result<T>
+    where
+        T: FromStr,
+    {
+        let start = self.pos;
+        let digits = self.take_while(|idx, c| c.is_ascii_digit() || (idx == 0 && c == '-'));
+
+        digits.parse().map_err(|_e| {
+            self.pos = start;
+            self.error(what)
+        })
+    }
+
+    fn word(&mut self, what: &str) -> io::Result<&'a str> {
+        let word = self.take_while(|_, c| c.is_ascii_alphanumeric() || c == '_');
+
+        if word.is_empty() {
+            return Err(self.error(what));
+        }
+
+        Ok(word)
+    }
+
+    fn value<T>(&mut self, names: &[(&'static str, T)], what: &str) -> io::Result<T>
+    where
+        T: Copy,
+    {
+        let start = self.pos;
+        let name = self.take_while(|_, c| c.is_ascii_alphanumeric() || c == '-');
+
+        names
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, v)| *v)
+            .ok_or_else(|| {
+                self.pos = start;
+                self.error(what)
+            })
+    }
+
+    fn entity(&mut self) -> io::Result<MediaControllerEntityReference> {
+        if !self.eat("\"") {
+            return Ok(MediaControllerEntityReference::Id(
+                self.number("an entity name or ID")?,
+            ));
+        }
+
+        let rest = self.rest();
+        let Some(len) = rest.find('"') else {
+            return Err(self.error("a closing quote"));
+        };
+
+        self.pos += len + 1;
+        Ok(MediaControllerEntityReference::Name(rest[..len].to_owned()))
+    }
+
+    fn pad(&mut self) -> io::Result<MediaControllerPadReference> {
+        let entity = self.entity()?;
+        self.expect(":")?;
+        let index = self.number("a pad index")?;
+
+        Ok(MediaControllerPadReference { entity, index })
+    }
+
+    fn size(&mut self) -> io::Result<(u32, u32)> {
+        let width = self.number("a width")?;
+        self.expect("x")?;
+        let height = self.number("a height")?;
+
+        Ok((width, height))
+    }
+
+    fn rectangle(&mut self) -> io::Result<MediaControllerRectangle> {
+        self.expect("(")?;
+        let left = self.number("a horizontal offset")?;
+        self.expect(",")?;
+        let top = self.number("a vertical offset")?;
+        self.expect(")")?;
+        self.expect("/")?;
+        let (width, height) = self.size()?;
+
+        Ok(MediaControllerRectangle {
+            left,
+            top,
+            width,
+            height,
+        })
+    }
+
+    fn link(&mut self) -> io::Result<MediaControllerLinkDescription> {
+        let source = self.pad()?;
+        self.expect("->")?;
+        let sink = self.pad()?;
+
+        self.expect("[")?;
+        let flags: u32 = self.number("the link flags")?;
+        if flags & !raw::bindgen::MEDIA_LNK_FL_ENABLED != 0 {
+            return Err(self.error("only the ENABLED link flag"));
+        }
+        self.expect("]")?;
+
+        Ok(MediaControllerLinkDescription {
+            source,
+            sink,
+            enabled: flags & raw::bindgen::MEDIA_LNK_FL_ENABLED != 0,
+        })
+    }
+
+    fn format(&mut self) -> io::Result<MediaControllerFormatDescription> {
+        let mut desc = MediaControllerFormatDescription {
+            pad: self.pad()?,
+            stream: 0,
+            format: None,
+            field: None,
+            colorspace: None,
+            xfer_func: None,
+            ycbcr_encoding: None,
+            quantization: None,
+            crop: None,
+            compose: None,
+        };
+
+        if self.eat("/") {
+            desc.stream = self.number("a stream")?;
+        }
+
+        self.expect("[")?;
+        while !self.eat("]") {
+            let start = self.pos;
+            let property = self.word("a format property or ']'")?;
+            self.expect(":")?;
+
+            match property {
+                "fmt" => {
+                    let code = self.word("a media bus code")?.to_owned();
+                    self.expect("/")?;
+                    let (width, height) = self.size()?;
+
+                    desc.format = Some(MediaControllerFrameFormat {
+                        code,
+                        width,
+                        height,
+                    });
+                }
+                "field" => desc.field = Some(self.value(FIELD_NAMES, "a field order")?),
+                "colorspace" => {
+                    desc.colorspace = Some(self.value(COLORSPACE_NAMES, "a colorspace")?);
+                }
+                "xfer" => {
+                    desc.xfer_func = Some(self.value(XFER_FUNC_NAMES, "a transfer function")?);
+                }
+                "ycbcr" => {
+                    desc.ycbcr_encoding =
+                        Some(self.value(YCBCR_ENCODING_NAMES, "a YCbCr encoding")?);
+                }
+                "quantization" => {
+                    desc.quantization =
+                        Some(self.value(QUANTIZATION_NAMES, "a quantization range")?);
+                }
+                "crop" => desc.crop = Some(self.rectangle()?),
+                "compose" => desc.compose = Some(self.rectangle()?),
+                _ => {
+                    self.pos = start;
+                    return Err(self.error(
+                        "fmt, field, colorspace, xfer, ycbcr, quantization, crop or compose",
+                    ));
+                }
+            }
+        }
+
+        Ok(desc)
+    }
+
+    fn single<T, F>(mut self, item: F) -> io::Result<T>
+    where
+        F: Fn(&mut Self) -> io::Result<T>,
+    {
+        let out = item(&mut self)?;
+
+        if !self.is_done() {
+            return Err(self.error("the end of the description"));
+        }
+
+        Ok(out)
+    }
+
+    fn list<T, F>(mut self, item: F) -> io::Result<Vec<T>>
+    where
+        F: Fn(&mut Self) -> io::Result<T>,
+    {
+        let mut out = Vec::new();
+
+        while !self.is_done() {
+            out.push(item(&mut self)?);
+
+            if !self.is_done() {
+                self.expect(",")?;
+            }
+        }
+
+        Ok(out)
+    }
+}
+
+fn valid<T>(res: RevocableResult<T, io::Error>) -> io::Result<T> {
+    match res {
+        RevocableResult::Ok(v) => Ok(v),
+        RevocableResult::Revoked => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "The Media Controller object has been revoked",
+        )),
+        RevocableResult::Err(e) => Err(e),
+    }
+}
+
+fn apply_links(
+    initial: &[(MediaControllerLink, bool)],
+    requested: &[(MediaControllerLink, bool)],
+) -> io::Result<()> {
+    // Every link we can change that isn't explicitly enabled is reset first, so that the result
+    // doesn't depend on what was set up before. The kernel might otherwise refuse to enable a
+    // link to a pad that is already connected.
+    for (link, _) in initial {
+        let id = valid(link.id().into())?;
+        let wanted = requested
+            .iter()
+            .any(|(l, enabled)| *enabled && l.id() == RevocableValue::Value(id));
+
+        if !wanted && valid(link.is_enabled().into())? {
+            debug!("Disabling link {link}");
+            valid(link.disable())?;
+        }
+    }
+
+    for (link, enabled) in requested {
+        if *enabled && !valid(link.is_enabled().into())? {
+            debug!("Enabling link {link}");
+            valid(link.enable())?;
+        }
+    }
+
+    Ok(())
+}
+
+fn restore_links(initial: &[(MediaControllerLink, bool)]) {
+    for (link, was_enabled) in initial.iter().filter(|(_, enabled)| !enabled) {
+        if link.is_enabled() == RevocableValue::Value(!was_enabled) {
+            if let RevocableResult::Err(e) = link.disable() {
+                warn!("Couldn't disable link {link} back: {e}");
+            }
+        }
+    }
+
+    for (link, was_enabled) in initial.iter().filter(|(_, enabled)| *enabled) {
+        if link.is_enabled() == RevocableValue::Value(!was_enabled) {
+            if let RevocableResult::Err(e) = link.enable() {
+                warn!("Couldn't enable link {link} back: {e}");
+            }
+        }
+    }
+}
+
+#[expect(
+    clippy::multiple_inherent_impl,
+    reason = "The media-ctl syntax handling lives with the configuration code."
+)]
+impl MediaController {
+    /// Returns the pad designated by a `media-ctl` pad reference, if there's any
+    ///
+    /// # Errors
+    ///
+    /// If the Media Controller device file access fails.
+    pub fn find_pad(
+        &self,
+        reference: &MediaControllerPadReference,
+    ) -> io::Result<Option<MediaControllerPad>> {
+        let entity = match &reference.entity {
+            MediaControllerEntityReference::Id(id) => self
+                .entities()?
+                .into_iter()
+                .find(|e| e.id() == RevocableValue::Value(*id)),
+            MediaControllerEntityReference::Name(name) => self.find_entity_by_name(name)?,
+        };
+
+        let Some(entity) = entity else {
+            return Ok(None);
+        };
+
+        match entity.pad(reference.index) {
+            RevocableResult::Ok(pad) => Ok(pad),
+            RevocableResult::Revoked => Ok(None),
+            RevocableResult::Err(e) => Err(e),
+        }
+    }
+
+    /// Sets the data links up, like `media-ctl -l` does after a reset
+    ///
+    /// All the links are looked up before anything is changed. Then every data link that isn't
+    /// immutable and isn't enabled by the description is disabled, and the ones it enables are.
+    /// If any of these steps fails, the links are restored to the state they were in.
+    ///
+    /// # Errors
+    ///
+    /// If a link can't be found, if the description tries to change an immutable link, or if the
+    /// Media Controller device file access fails.
+    pub fn setup_links(&self, links: &[MediaControllerLinkDescription]) -> io::Result<()> {
+        let mut requested = Vec::with_capacity(links.len());
+        for desc in links {
+            let not_found = |pad: &MediaControllerPadReference| {
+                io::Error::new(io::ErrorKind::NotFound, format!("Couldn't find pad {pad}"))
+            };
+
+            let source = self
+                .find_pad(&desc.source)?
+                .ok_or_else(|| not_found(&desc.source))?;
+            let sink = self
+                .find_pad(&desc.sink)?
+                .ok_or_else(|| not_found(&desc.sink))?;
+
+            let link = valid(self.find_data_link_by_pads(&source, &sink))?.ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("Couldn't find a link between {source} and {sink}"),
+                )
+            })?;
+
+            if valid(link.is_immutable().into())?
+                && valid(link.is_enabled().into())? != desc.enabled
+            {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Link {link} is immutable and can't be changed"),
+                ));
+            }
+
+            requested.push((link, desc.enabled));
+        }
+
+        let mut initial = Vec::new();
+        for link in self.links()? {
+            if valid(link.kind().into())? != MediaControllerLinkKind::Data
+                || valid(link.is_immutable().into())?
+            {
+                continue;
+            }
+
+            let enabled = valid(link.is_enabled().into())?;
+            initial.push((link, enabled));
+        }
+
+        if let Err(e) = apply_links(&initial, &requested) {
+            warn!("Couldn't set the links up, restoring them: {e}");
+            restore_links(&initial);
+
+            return Err(e);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,90 @@
+use core::fmt;
+
+use crate::{
+    MediaControllerEntity, MediaControllerInterface, MediaControllerLink, MediaControllerPad,
+};
+
+/// A change in the Media Controller topology
+///
+/// The objects that have been removed are revoked by the time the event is reported, so only
+/// their identifiers are kept around.
+#[derive(Clone, Debug)]
+pub enum MediaControllerEvent {
+    /// An entity has been added
+    EntityAdded(MediaControllerEntity),
+
+    /// An entity has been removed
+    EntityRemoved {
+        /// The removed entity ID
+        id: u32,
+
+        /// The removed entity name
+        name: String,
+    },
+
+    /// An interface has been added
+    InterfaceAdded(MediaControllerInterface),
+
+    /// An interface has been removed
+    InterfaceRemoved {
+        /// The removed interface ID
+        id: u32,
+    },
+
+    /// A pad has been added
+    PadAdded(MediaControllerPad),
+
+    /// A pad has been removed
+    PadRemoved {
+        /// The removed pad ID
+        id: u32,
+    },
+
+    /// A link has been added
+    LinkAdded(MediaControllerLink),
+
+    /// A link has been removed
+    LinkRemoved {
+        /// The removed link ID
+        id: u32,
+    },
+
+    /// The flags of a link have changed, ie. it has been enabled or disabled
+    LinkChanged(MediaControllerLink),
+}
+
+impl MediaControllerEvent {
+    /// Returns true if the event reports that an object has been removed
+    #[must_use]
+    pub fn is_removal(&self) -> bool {
+        match self {
+            Self::EntityRemoved { .. }
+            | Self::InterfaceRemoved { .. }
+            | Self::PadRemoved { .. }
+            | Self::LinkRemoved { .. } => true,
+            Self::EntityAdded(_)
+            | Self::InterfaceAdded(_)
+            | Self::PadAdded(_)
+            | Self::LinkAdded(_)
+            | Self::LinkChanged(_) => false,
+        }
+    }
+}
+
+impl fmt::Display for MediaControllerEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EntityAdded(entity) => f.write_fmt(format_args!("Entity {entity} added")),
+            Self::EntityRemoved { id, name } => {
+                f.write_fmt(format_args!("Entity {name} (ID {id}) removed"))
+            }
+            Self::InterfaceAdded(intf) => f.write_fmt(format_args!("Interface {intf} added")),
+            Self::InterfaceRemoved { id } => f.write_fmt(format_args!("Interface ID {id} removed")),
+            Self::PadAdded(pad) => f.write_fmt(format_args!("Pad {pad} added")),
+            Self::PadRemoved { id } => f.write_fmt(format_args!("Pad ID {id} removed")),
+            Self::LinkAdded(link) => f.write_fmt(format_args!("Link {link} added")),
+            Self::LinkRemoved { id } => f.write_fmt(format_args!("Link ID {id} removed")),
+            Self::LinkChanged(link) => f.write_fmt(format_args!("Link {link} changed")),
+        }
+    }
+}
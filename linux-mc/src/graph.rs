@@ -0,0 +1,419 @@
+use alloc::collections::VecDeque;
+use core::fmt;
+use std::{fs, io, path::Path};
+
+use crate::{
+    MediaController, MediaControllerEntity, MediaControllerLink, MediaControllerLinkKind,
+    MediaControllerPad, MediaControllerPadKind, RevocableResult, RevocableValue,
+    media_entity_function, try_result, try_value,
+};
+
+/// Order in which the entities are visited when walking the graph
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MediaControllerTraversalOrder {
+    /// Visits all the entities at a given distance before moving further
+    BreadthFirst,
+
+    /// Follows each branch as far as possible before backtracking
+    DepthFirst,
+}
+
+/// Direction in which the data links are followed when walking the graph
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MediaControllerTraversalDirection {
+    /// From source pads to sink pads, ie. in the direction the data flows
+    Downstream,
+
+    /// From sink pads to source pads
+    Upstream,
+}
+
+impl MediaControllerTraversalDirection {
+    fn pad_kind(self) -> MediaControllerPadKind {
+        match self {
+            Self::Downstream => MediaControllerPadKind::Source,
+            Self::Upstream => MediaControllerPadKind::Sink,
+        }
+    }
+}
+
+/// An entity in a pipeline, along with the pads the data goes through
+#[derive(Clone, Debug)]
+pub struct MediaControllerPipelineItem {
+    /// The pad the data comes in through, or None for the first entity of the pipeline
+    pub sink_pad: Option<MediaControllerPad>,
+
+    /// The entity
+    pub entity: MediaControllerEntity,
+
+    /// The pad the data goes out through, or None for the last entity of the pipeline
+    pub source_pad: Option<MediaControllerPad>,
+}
+
+impl fmt::Display for MediaControllerPipelineItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(sink) = &self.sink_pad {
+            f.write_fmt(format_args!("[{}] -> ", sink.index()))?;
+        }
+
+        f.write_fmt(format_args!("{}", self.entity))?;
+
+        if let Some(source) = &self.source_pad {
+            f.write_fmt(format_args!(" -> [{}]", source.index()))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A path through the graph, from the source entity to the sink entity
+#[derive(Clone, Debug)]
+pub struct MediaControllerPipeline(Vec<MediaControllerPipelineItem>);
+
+impl MediaControllerPipeline {
+    /// Returns the entities of the pipeline, from the source to the sink
+    #[must_use]
+    pub fn items(&self) -> &[MediaControllerPipelineItem] {
+        &self.0
+    }
+
+    /// Returns the number of entities in the pipeline
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns true if the pipeline has no entity
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns whether all the links of the pipeline are enabled, if they are still valid.
+    ///
+    /// # Errors
+    ///
+    /// If the Media Controller device file access fails.
+    pub fn is_enabled(&self) -> RevocableResult<bool, io::Error> {
+        for (item, next) in self.0.iter().zip(self.0.iter().skip(1)) {
+            let (Some(source), Some(sink)) = (&item.source_pad, &next.sink_pad) else {
+                continue;
+            };
+
+            let sink_id = try_value!(sink.id());
+            let mut enabled = false;
+            for link in try_result!(data_links(source)) {
+                if try_value!(link.sink_id()) == sink_id {
+                    enabled = try_value!(link.is_enabled());
+                    break;
+                }
+            }
+
+            if !enabled {
+                return RevocableResult::Ok(false);
+            }
+        }
+
+        RevocableResult::Ok(true)
+    }
+}
+
+impl fmt::Display for MediaControllerPipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, item) in self.0.iter().enumerate() {
+            if idx > 0 {
+                f.write_str(" => ")?;
+            }
+
+            f.write_fmt(format_args!("{item}"))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl IntoIterator for MediaControllerPipeline {
+    type Item = MediaControllerPipelineItem;
+    type IntoIter = alloc::vec::IntoIter<MediaControllerPipelineItem>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+fn data_links(pad: &MediaControllerPad) -> RevocableResult<Vec<MediaControllerLink>, io::Error> {
+    let mut links = Vec::new();
+
+    for link in try_result!(pad.links()) {
+        if try_value!(link.kind()) == MediaControllerLinkKind::Data {
+            links.push(link);
+        }
+    }
+
+    RevocableResult::Ok(links)
+}
+
+struct Hop {
+    local_pad: MediaControllerPad,
+    remote_pad: MediaControllerPad,
+    remote_entity: MediaControllerEntity,
+}
+
+fn hops(
+    entity: &MediaControllerEntity,
+    direction: MediaControllerTraversalDirection,
+    enabled_only: bool,
+) -> RevocableResult<Vec<Hop>, io::Error> {
+    let mut out = Vec::new();
+
+    for pad in try_result!(entity.pads()) {
+        if try_value!(pad.kind()) != direction.pad_kind() {
+            continue;
+        }
+
+        for link in try_result!(data_links(&pad)) {
+            if enabled_only && !try_value!(link.is_enabled()) {
+                continue;
+            }
+
+            let remote_pad = match direction {
+                MediaControllerTraversalDirection::Downstream => try_value!(link.sink_pad()),
+                MediaControllerTraversalDirection::Upstream => try_value!(link.source_pad()),
+            };
+
+            out.push(Hop {
+                local_pad: pad.clone(),
+                remote_entity: try_value!(remote_pad.entity()),
+                remote_pad,
+            });
+        }
+    }
+
+    RevocableResult::Ok(out)
+}
+
+fn walk_paths(
+    entity: &MediaControllerEntity,
+    sink_pad: Option<MediaControllerPad>,
+    target: u32,
+    enabled_only: bool,
+    path: &mut Vec<MediaControllerPipelineItem>,
+    visited: &mut Vec<u32>,
+    out: &mut Vec<MediaControllerPipeline>,
+) -> RevocableResult<(), io::Error> {
+    if try_value!(entity.id()) == target {
+        path.push(MediaControllerPipelineItem {
+            sink_pad,
+            entity: entity.clone(),
+            source_pad: None,
+        });
+        out.push(MediaControllerPipeline(path.clone()));
+        path.pop();
+
+        return RevocableResult::Ok(());
+    }
+
+    for hop in try_result!(hops(
+        entity,
+        MediaControllerTraversalDirection::Downstream,
+        enabled_only
+    )) {
+        let remote_id = try_value!(hop.remote_entity.id());
+        if visited.contains(&remote_id) {
+            continue;
+        }
+
+        path.push(MediaControllerPipelineItem {
+            sink_pad: sink_pad.clone(),
+            entity: entity.clone(),
+            source_pad: Some(hop.local_pad),
+        });
+        visited.push(remote_id);
+
+        try_result!(walk_paths(
+            &hop.remote_entity,
+            Some(hop.remote_pad),
+            target,
+            enabled_only,
+            path,
+            visited,
+            out,
+        ));
+
+        visited.pop();
+        path.pop();
+    }
+
+    RevocableResult::Ok(())
+}
+
+#[expect(
+    clippy::multiple_inherent_impl,
+    reason = "The graph walks live with the rest of the pipeline code."
+)]
+impl MediaControllerEntity {
+    /// Walks the graph from this entity by following the data links, and returns the entities
+    /// reachable from it in the order they have been visited, if the entity is still valid.
+    ///
+    /// This entity is always the first one returned. If `enabled_only` is set, the disabled
+    /// links are ignored.
+    ///
+    /// # Errors
+    ///
+    /// If the Media Controller device file access fails.
+    pub fn traverse(
+        &self,
+        order: MediaControllerTraversalOrder,
+        direction: MediaControllerTraversalDirection,
+        enabled_only: bool,
+    ) -> RevocableResult<Vec<MediaControllerEntity>, io::Error> {
+        let mut visited = match order {
+            MediaControllerTraversalOrder::BreadthFirst => vec![try_value!(self.id())],
+            MediaControllerTraversalOrder::DepthFirst => Vec::new(),
+        };
+        let mut pending = VecDeque::from([self.clone()]);
+        let mut out = Vec::new();
+
+        loop {
+            let next = match order {
+                MediaControllerTraversalOrder::BreadthFirst => pending.pop_front(),
+                MediaControllerTraversalOrder::DepthFirst => pending.pop_back(),
+            };
+
+            let Some(entity) = next else {
+                break;
+            };
+
+            // For a depth-first walk, an entity can be queued several times before we get to
+            // visit it, so we only mark them as visited once they are.
+            if order == MediaControllerTraversalOrder::DepthFirst {
+                let id = try_value!(entity.id());
+
+                if visited.contains(&id) {
+                    continue;
+                }
+
+                visited.push(id);
+            }
+
+            let mut next_hops = try_result!(hops(&entity, direction, enabled_only));
+
+            // The entities are taken from the back of the queue for a depth-first walk, so we
+            // need to push them in reverse to visit the pads in order.
+            if order == MediaControllerTraversalOrder::DepthFirst {
+                next_hops.reverse();
+            }
+
+            for hop in next_hops {
+                let id = try_value!(hop.remote_entity.id());
+
+                if visited.contains(&id) {
+                    continue;
+                }
+
+                if order == MediaControllerTraversalOrder::BreadthFirst {
+                    visited.push(id);
+                }
+
+                pending.push_back(hop.remote_entity);
+            }
+
+            out.push(entity);
+        }
+
+        RevocableResult::Ok(out)
+    }
+
+    /// Returns all the pipelines going from this entity to the sink entity by following the
+    /// data links downstream, if both entities are still valid.
+    ///
+    /// Each entity appears at most once in a given pipeline. If `enabled_only` is set, the
+    /// disabled links are ignored.
+    ///
+    /// # Errors
+    ///
+    /// If the Media Controller device file access fails.
+    pub fn pipelines_to(
+        &self,
+        sink: &MediaControllerEntity,
+        enabled_only: bool,
+    ) -> RevocableResult<Vec<MediaControllerPipeline>, io::Error> {
+        let mut out = Vec::new();
+
+        try_result!(walk_paths(
+            self,
+            None,
+            try_value!(sink.id()),
+            enabled_only,
+            &mut Vec::new(),
+            &mut vec![try_value!(self.id())],
+            &mut out,
+        ));
+
+        RevocableResult::Ok(out)
+    }
+}
+
+#[expect(
+    clippy::multiple_inherent_impl,
+    reason = "The pipeline lookups live with the rest of the pipeline code."
+)]
+impl MediaController {
+    /// Returns the entities with the given function
+    ///
+    /// # Errors
+    ///
+    /// If the Media Controller device file access fails.
+    pub fn find_entities_by_function(
+        &self,
+        function: media_entity_function,
+    ) -> io::Result<Vec<MediaControllerEntity>> {
+        Ok(self
+            .entities()?
+            .into_iter()
+            .filter(|e| e.function() == RevocableValue::Value(function))
+            .collect())
+    }
+
+    /// Returns the entity with the given name, if there's any
+    ///
+    /// # Errors
+    ///
+    /// If the Media Controller device file access fails.
+    pub fn find_entity_by_name(&self, name: &str) -> io::Result<Option<MediaControllerEntity>> {
+        Ok(self
+            .entities()?
+            .into_iter()
+            .find(|e| matches!(e.name(), RevocableValue::Value(n) if n == name)))
+    }
+
+    /// Returns the entity exposed through the given device file, if there's any
+    ///
+    /// # Errors
+    ///
+    /// If the Media Controller device file access fails, or if the path can't be resolved.
+    pub fn find_entity_by_device_node(
+        &self,
+        path: &Path,
+    ) -> io::Result<Option<MediaControllerEntity>> {
+        let path = fs::canonicalize(path)?;
+
+        for entity in self.entities()? {
+            let interfaces = match entity.interfaces() {
+                RevocableResult::Ok(interfaces) => interfaces,
+                RevocableResult::Revoked => continue,
+                RevocableResult::Err(e) => return Err(e),
+            };
+
+            for intf in interfaces {
+                if let RevocableValue::Value(Some(node)) = intf.device_node() {
+                    if node.path() == path {
+                        return Ok(Some(entity));
+                    }
+                }
+            }
+        }
+
+        Ok(None)
+    }
+}
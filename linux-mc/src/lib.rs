@@ -21,6 +21,7 @@ use bytemuck::cast_slice;
 use facet::Facet;
 use facet_enum_repr::FacetEnumRepr;
 use linux_raw::KernelVersion;
+use rustix::io::Errno;
 use tracing::{debug, trace};
 
 /// Raw, unsafe, abstraction
@@ -31,10 +32,39 @@ use raw::{
     media_v2_topology,
 };
 
+/// media-ctl Style Link and Format Descriptions
+mod config;
+pub use config::{
+    MediaControllerEntityReference, MediaControllerFormatDescription, MediaControllerFrameFormat,
+    MediaControllerLinkDescription, MediaControllerPadReference, MediaControllerRectangle,
+};
+
+/// Topology Change Events
+mod event;
+pub use event::MediaControllerEvent;
+
+/// Graph Traversal and Pipeline Discovery
+mod graph;
+pub use graph::{
+    MediaControllerPipeline, MediaControllerPipelineItem, MediaControllerTraversalDirection,
+    MediaControllerTraversalOrder,
+};
+
+/// Media Requests
+mod request;
+pub use request::MediaControllerRequest;
+
 /// Revocable Objects
 mod revocable;
 pub use revocable::{Revocable, RevocableResult, RevocableValue};
 
+/// Topology Snapshots and Formatting
+mod topology;
+pub use topology::{
+    MediaControllerTopology, MediaControllerTopologyDeviceNode, MediaControllerTopologyEntity,
+    MediaControllerTopologyInterface, MediaControllerTopologyLink, MediaControllerTopologyPad,
+};
+
 fn chars_to_string(chars: &[c_char], ascii_only: bool) -> String {
     let str = CStr::from_bytes_until_nul(cast_slice(chars))
         .expect("The kernel guarantees the string is null-terminated.")
@@ -329,8 +359,41 @@ impl TryFrom<u32> for MediaControllerInterfaceKind {
     }
 }
 
+impl From<MediaControllerInterfaceKind> for u32 {
+    fn from(value: MediaControllerInterfaceKind) -> Self {
+        match value {
+            MediaControllerInterfaceKind::Alsa(kind) => match kind {
+                MediaControllerInterfaceAlsaKind::Control => {
+                    raw::bindgen::MEDIA_INTF_T_ALSA_CONTROL
+                }
+                MediaControllerInterfaceAlsaKind::PcmCapture => {
+                    raw::bindgen::MEDIA_INTF_T_ALSA_PCM_CAPTURE
+                }
+                MediaControllerInterfaceAlsaKind::PcmPlayback => {
+                    raw::bindgen::MEDIA_INTF_T_ALSA_PCM_PLAYBACK
+                }
+            },
+            MediaControllerInterfaceKind::DVB(kind) => match kind {
+                MediaControllerInterfaceDvbKind::Ca => raw::bindgen::MEDIA_INTF_T_DVB_CA,
+                MediaControllerInterfaceDvbKind::Demux => raw::bindgen::MEDIA_INTF_T_DVB_DEMUX,
+                MediaControllerInterfaceDvbKind::Dvr => raw::bindgen::MEDIA_INTF_T_DVB_DVR,
+                MediaControllerInterfaceDvbKind::Fe => raw::bindgen::MEDIA_INTF_T_DVB_FE,
+                MediaControllerInterfaceDvbKind::Net => raw::bindgen::MEDIA_INTF_T_DVB_NET,
+            },
+            MediaControllerInterfaceKind::V4L(kind) => match kind {
+                MediaControllerInterfaceV4lKind::Radio => raw::bindgen::MEDIA_INTF_T_V4L_RADIO,
+                MediaControllerInterfaceV4lKind::Subdev => raw::bindgen::MEDIA_INTF_T_V4L_SUBDEV,
+                MediaControllerInterfaceV4lKind::SwRadio => raw::bindgen::MEDIA_INTF_T_V4L_SWRADIO,
+                MediaControllerInterfaceV4lKind::Touch => raw::bindgen::MEDIA_INTF_T_V4L_TOUCH,
+                MediaControllerInterfaceV4lKind::Vbi => raw::bindgen::MEDIA_INTF_T_V4L_VBI,
+                MediaControllerInterfaceV4lKind::Video => raw::bindgen::MEDIA_INTF_T_V4L_VIDEO,
+            },
+        }
+    }
+}
+
 /// Media Device Information
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct MediaControllerInfo {
     driver: String,
     model: String,
@@ -781,6 +844,9 @@ struct MediaControllerPadInner {
     id: u32,
     index: u32,
     flags: MediaControllerPadFlags,
+
+    // The formats stored in a topology snapshot. They are queried from the sub-device otherwise.
+    formats: Vec<String>,
 }
 
 impl fmt::Debug for MediaControllerPadInner {
@@ -1028,6 +1094,16 @@ impl TryFrom<u32> for MediaControllerLinkKind {
     }
 }
 
+impl From<MediaControllerLinkKind> for u32 {
+    fn from(value: MediaControllerLinkKind) -> Self {
+        match value {
+            MediaControllerLinkKind::Data => raw::bindgen::MEDIA_LNK_FL_DATA_LINK,
+            MediaControllerLinkKind::Interface => raw::bindgen::MEDIA_LNK_FL_INTERFACE_LINK,
+            MediaControllerLinkKind::Ancillary => raw::bindgen::MEDIA_LNK_FL_ANCILLARY_LINK,
+        }
+    }
+}
+
 #[derive(Debug)]
 enum MediaControllerLinkEnd {
     Entity(Rc<RefCell<Revocable<MediaControllerEntityInner>>>),
@@ -1141,10 +1217,22 @@ impl MediaControllerLink {
                     ..Default::default()
                 };
 
-                try_result_to_revocable!(media_ioctl_setup_link(
-                    inner.controller.borrow().fd.as_fd(),
-                    desc
-                ))
+                let controller = inner.controller.borrow();
+                match &controller.backend {
+                    MediaControllerBackend::Device(fd) => {
+                        try_result_to_revocable!(media_ioctl_setup_link(fd.as_fd(), desc))
+                    }
+                    MediaControllerBackend::Snapshot(_) => {
+                        // The kernel rejects any change to an immutable link.
+                        if inner.flags.contains(MediaControllerLinkFlags::IMMUTABLE)
+                            && inner.flags != flags
+                        {
+                            return RevocableResult::Err(Errno::INVAL.into());
+                        }
+
+                        desc
+                    }
+                }
             } else {
                 unreachable!()
             }
@@ -1356,7 +1444,7 @@ fn media_ioctl_g_topology(
 
         if let Some(links) = &mut args.links {
             links.clear();
-            links.reserve(args.prev.num_pads as usize);
+            links.reserve(args.prev.num_links as usize);
 
             topo.num_links = args.prev.num_links;
             topo.ptr_links = links.as_mut_ptr() as u64;
@@ -1393,14 +1481,127 @@ fn media_ioctl_g_topology(
     Ok(topo)
 }
 
+#[derive(Debug)]
+enum MediaControllerBackend {
+    /// A Media Controller device file
+    Device(OwnedFd),
+
+    /// A topology snapshot, without any device behind it
+    Snapshot(MediaControllerInfo),
+}
+
+impl MediaControllerBackend {
+    fn fd(&self) -> io::Result<BorrowedFd<'_>> {
+        match self {
+            MediaControllerBackend::Device(fd) => Ok(fd.as_fd()),
+            MediaControllerBackend::Snapshot(_) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Topology snapshots don't have a device file",
+            )),
+        }
+    }
+}
+
 #[derive(Debug)]
 struct MediaControllerInner {
-    fd: OwnedFd,
+    backend: MediaControllerBackend,
     last_topology_version: Option<u64>,
     entities: Vec<Rc<RefCell<Revocable<MediaControllerEntityInner>>>>,
     interfaces: Vec<Rc<RefCell<Revocable<MediaControllerInterfaceInner>>>>,
     links: Vec<Rc<RefCell<Revocable<MediaControllerLinkInner>>>>,
     pads: Vec<Rc<RefCell<Revocable<MediaControllerPadInner>>>>,
+    events: Vec<MediaControllerEvent>,
+}
+
+struct MergedObjects<T> {
+    objects: Vec<Rc<RefCell<Revocable<T>>>>,
+    added: Vec<Rc<RefCell<Revocable<T>>>>,
+    changed: Vec<Rc<RefCell<Revocable<T>>>>,
+    removed: Vec<Rc<RefCell<Revocable<T>>>>,
+}
+
+// Builds the new list of objects of a given type from the one the kernel returned. The objects
+// that were already there are kept, so that the handles users hold remain valid, and are updated
+// through the update closure that returns whether they changed. The objects that are gone are
+// returned but not revoked yet, so that we can still report what they were.
+fn merge_objects<T, R, RI, OI, C, U>(
+    old: &[Rc<RefCell<Revocable<T>>>],
+    raw: Vec<R>,
+    raw_id: RI,
+    obj_id: OI,
+    mut create: C,
+    mut update: U,
+) -> io::Result<MergedObjects<T>>
+where
+    RI: Fn(&R) -> u32,
+    OI: Fn(&T) -> u32,
+    C: FnMut(R) -> io::Result<Rc<RefCell<Revocable<T>>>>,
+    U: FnMut(&mut T, &R) -> io::Result<bool>,
+{
+    let mut objects = Vec::with_capacity(raw.len());
+    let mut added = Vec::new();
+    let mut changed = Vec::new();
+
+    for r in raw {
+        let id = raw_id(&r);
+        let existing = old.iter().find(|o| {
+            o.borrow()
+                .try_access()
+                .is_some_and(|inner| obj_id(&*inner) == id)
+        });
+
+        if let Some(existing) = existing {
+            let has_changed = {
+                let mut obj_ref = existing.borrow_mut();
+
+                match obj_ref.try_access_mut() {
+                    Some(mut inner) => update(&mut *inner, &r)?,
+                    None => false,
+                }
+            };
+
+            if has_changed {
+                changed.push(existing.clone());
+            }
+
+            objects.push(existing.clone());
+        } else {
+            let obj = create(r)?;
+            added.push(obj.clone());
+            objects.push(obj);
+        }
+    }
+
+    let removed = old
+        .iter()
+        .filter(|o| !objects.iter().any(|n| Rc::ptr_eq(n, o)))
+        .cloned()
+        .collect();
+
+    Ok(MergedObjects {
+        objects,
+        added,
+        changed,
+        removed,
+    })
+}
+
+// Updates the flags of a link that was already there, and returns whether they changed.
+fn update_link_flags(link: &mut MediaControllerLinkInner, flags: u32) -> io::Result<bool> {
+    let flags: MediaControllerLinkFlags = (flags & !raw::bindgen::MEDIA_LNK_FL_LINK_TYPE)
+        .try_into()
+        .map_err(|_e| io::Error::new(io::ErrorKind::InvalidData, "Unexpected link flags"))?;
+
+    let changed = link.flags != flags;
+    link.flags = flags;
+
+    Ok(changed)
+}
+
+fn revoke_objects<T>(objects: &[Rc<RefCell<Revocable<T>>>]) {
+    for obj in objects {
+        obj.borrow().revoke();
+    }
 }
 
 #[expect(clippy::too_many_lines)]
@@ -1413,7 +1614,7 @@ fn update_topology(
     let count = if let Some(count) = count {
         count
     } else {
-        media_ioctl_g_topology(inner.fd.as_fd(), None)?
+        media_ioctl_g_topology(inner.backend.fd()?, None)?
     };
 
     let mut raw_entities = Vec::with_capacity(count.num_entities as usize);
@@ -1422,7 +1623,7 @@ fn update_topology(
     let mut raw_pads = Vec::with_capacity(count.num_pads as usize);
 
     let topo = media_ioctl_g_topology(
-        inner.fd.as_fd(),
+        inner.backend.fd()?,
         Some(GTopologyArgs {
             prev: count,
             entities: Some(&mut raw_entities),
@@ -1432,11 +1633,15 @@ fn update_topology(
         }),
     )?;
 
+    let initial = inner.last_topology_version.is_none();
     inner.last_topology_version = Some(topo.topology_version);
 
-    let entities = raw_entities
-        .into_iter()
-        .map(|e| {
+    let entities = merge_objects(
+        &inner.entities,
+        raw_entities,
+        |e| e.id,
+        |e| e.id,
+        |e| {
             let function = e.function;
 
             Ok(Rc::new(RefCell::new(Revocable::new(
@@ -1452,14 +1657,18 @@ fn update_topology(
                     })?,
                 },
             ))))
-        })
-        .collect::<io::Result<Vec<_>>>()?;
+        },
+        |_entity, _raw| Ok(false),
+    )?;
 
-    inner.entities = entities;
+    inner.entities.clone_from(&entities.objects);
 
-    let interfaces = raw_interfaces
-        .into_iter()
-        .map(|e| {
+    let interfaces = merge_objects(
+        &inner.interfaces,
+        raw_interfaces,
+        |i| i.id,
+        |i| i.id,
+        |e| {
             let intf_type = e.intf_type;
             Ok(Rc::new(RefCell::new(Revocable::new(
                 MediaControllerInterfaceInner {
@@ -1476,14 +1685,18 @@ fn update_topology(
                     },
                 },
             ))))
-        })
-        .collect::<io::Result<Vec<_>>>()?;
+        },
+        |_interface, _raw| Ok(false),
+    )?;
 
-    inner.interfaces = interfaces;
+    inner.interfaces.clone_from(&interfaces.objects);
 
-    let pads = raw_pads
-        .into_iter()
-        .map(|p| {
+    let pads = merge_objects(
+        &inner.pads,
+        raw_pads,
+        |p| p.id,
+        |p| p.id,
+        |p| {
             let entity = inner
                 .entities
                 .iter()
@@ -1511,16 +1724,21 @@ fn update_topology(
                     id: p.id,
                     index: p.index,
                     flags: p.flags.into(),
+                    formats: Vec::new(),
                 },
             ))))
-        })
-        .collect::<Result<Vec<_>, io::Error>>()?;
+        },
+        |_pad, _raw| Ok(false),
+    )?;
 
-    inner.pads = pads;
+    inner.pads.clone_from(&pads.objects);
 
-    let links = raw_links
-        .into_iter()
-        .map(|l| {
+    let links = merge_objects(
+        &inner.links,
+        raw_links,
+        |l| l.id,
+        |l| l.id,
+        |l| {
             let kind = (l.flags & raw::bindgen::MEDIA_LNK_FL_LINK_TYPE)
                 .try_into()
                 .map_err(|_e| io::Error::new(io::ErrorKind::InvalidData, "Unexpected link type"))?;
@@ -1677,14 +1895,97 @@ fn update_topology(
                         })?,
                 },
             ))))
-        })
-        .collect::<Result<Vec<_>, io::Error>>()?;
+        },
+        |link, l| update_link_flags(link, l.flags),
+    )?;
 
-    inner.links = links;
+    inner.links.clone_from(&links.objects);
+
+    complete_merge(&mut inner, !initial, &entities, &interfaces, &pads, &links);
 
     Ok(())
 }
 
+// Records the changes a merge brought to the topology, if asked to, and revokes the objects that
+// are gone.
+fn complete_merge(
+    inner: &mut MediaControllerInner,
+    record_events: bool,
+    entities: &MergedObjects<MediaControllerEntityInner>,
+    interfaces: &MergedObjects<MediaControllerInterfaceInner>,
+    pads: &MergedObjects<MediaControllerPadInner>,
+    links: &MergedObjects<MediaControllerLinkInner>,
+) {
+    if record_events {
+        let mut events = Vec::new();
+
+        events.extend(links.removed.iter().filter_map(|l| {
+            l.borrow()
+                .try_access()
+                .map(|l| MediaControllerEvent::LinkRemoved { id: l.id })
+        }));
+        events.extend(pads.removed.iter().filter_map(|p| {
+            p.borrow()
+                .try_access()
+                .map(|p| MediaControllerEvent::PadRemoved { id: p.id })
+        }));
+        events.extend(entities.removed.iter().filter_map(|e| {
+            e.borrow()
+                .try_access()
+                .map(|e| MediaControllerEvent::EntityRemoved {
+                    id: e.id,
+                    name: e.name.clone(),
+                })
+        }));
+        events.extend(interfaces.removed.iter().filter_map(|i| {
+            i.borrow()
+                .try_access()
+                .map(|i| MediaControllerEvent::InterfaceRemoved { id: i.id })
+        }));
+
+        events.extend(
+            entities
+                .added
+                .iter()
+                .map(|e| MediaControllerEvent::EntityAdded(e.into())),
+        );
+        events.extend(
+            interfaces
+                .added
+                .iter()
+                .map(|i| MediaControllerEvent::InterfaceAdded(i.into())),
+        );
+        events.extend(
+            pads.added
+                .iter()
+                .map(|p| MediaControllerEvent::PadAdded(p.into())),
+        );
+        events.extend(
+            links
+                .added
+                .iter()
+                .map(|l| MediaControllerEvent::LinkAdded(l.into())),
+        );
+        events.extend(
+            links
+                .changed
+                .iter()
+                .map(|l| MediaControllerEvent::LinkChanged(l.into())),
+        );
+
+        for event in &events {
+            debug!("Media Controller topology change: {event}");
+        }
+
+        inner.events.extend(events);
+    }
+
+    revoke_objects(&links.removed);
+    revoke_objects(&pads.removed);
+    revoke_objects(&entities.removed);
+    revoke_objects(&interfaces.removed);
+}
+
 /// A Representation of a Media Controller
 #[derive(Clone, Debug)]
 pub struct MediaController(Rc<RefCell<MediaControllerInner>>);
@@ -1699,12 +2000,13 @@ impl MediaController {
         let file = File::open(path)?;
 
         let mc = Rc::new(RefCell::new(MediaControllerInner {
-            fd: file.into(),
+            backend: MediaControllerBackend::Device(file.into()),
             last_topology_version: None,
             entities: Vec::new(),
             interfaces: Vec::new(),
             links: Vec::new(),
             pads: Vec::new(),
+            events: Vec::new(),
         }));
 
         update_topology(&mc, None)?;
@@ -1718,7 +2020,12 @@ impl MediaController {
     ///
     /// If the Media Controller device file access fails.
     pub fn info(&self) -> Result<MediaControllerInfo, io::Error> {
-        media_ioctl_device_info(self.0.borrow().fd.as_fd()).map(Into::into)
+        match &self.0.borrow().backend {
+            MediaControllerBackend::Device(fd) => {
+                media_ioctl_device_info(fd.as_fd()).map(Into::into)
+            }
+            MediaControllerBackend::Snapshot(info) => Ok(info.clone()),
+        }
     }
 
     #[expect(
@@ -1731,7 +2038,25 @@ impl MediaController {
             "After the initial construction in new(), the topology version will always be set",
         );
 
-        let topo = raw::media_ioctl_g_topology(inner.fd.as_fd(), media_v2_topology::default())?;
+        // Snapshots only change through update_from_topology().
+        let MediaControllerBackend::Device(fd) = &inner.backend else {
+            return Ok(());
+        };
+
+        let topo = match raw::media_ioctl_g_topology(fd.as_fd(), media_v2_topology::default()) {
+            Ok(topo) => topo,
+            Err(e) => {
+                drop(inner);
+
+                if matches!(Errno::from_io_error(&e), Some(Errno::NODEV | Errno::IO)) {
+                    self.revoke_all();
+                }
+
+                return Err(e);
+            }
+        };
+        drop(inner);
+
         if topo.topology_version > current_version {
             update_topology(&self.0, Some(topo))?;
         }
@@ -1739,6 +2064,85 @@ impl MediaController {
         Ok(())
     }
 
+    // The device is gone, typically because its driver has been unbound, so none of our objects
+    // are valid anymore.
+    fn revoke_all(&self) {
+        let mut inner = self.0.borrow_mut();
+        let mut events = Vec::new();
+
+        for link in inner.links.drain(..) {
+            if let Some(l) = link.borrow().try_access() {
+                events.push(MediaControllerEvent::LinkRemoved { id: l.id });
+            }
+
+            link.borrow().revoke();
+        }
+
+        for pad in inner.pads.drain(..) {
+            if let Some(p) = pad.borrow().try_access() {
+                events.push(MediaControllerEvent::PadRemoved { id: p.id });
+            }
+
+            pad.borrow().revoke();
+        }
+
+        for entity in inner.entities.drain(..) {
+            if let Some(e) = entity.borrow().try_access() {
+                events.push(MediaControllerEvent::EntityRemoved {
+                    id: e.id,
+                    name: e.name.clone(),
+                });
+            }
+
+            entity.borrow().revoke();
+        }
+
+        for intf in inner.interfaces.drain(..) {
+            if let Some(i) = intf.borrow().try_access() {
+                events.push(MediaControllerEvent::InterfaceRemoved { id: i.id });
+            }
+
+            intf.borrow().revoke();
+        }
+
+        for event in &events {
+            debug!("Media Controller topology change: {event}");
+        }
+
+        inner.events.extend(events);
+    }
+
+    /// Checks whether the topology has changed since it was last retrieved, and updates it if so
+    ///
+    /// The entities, interfaces, pads and links that are still there are kept valid, the ones that
+    /// have been removed are revoked, and the changes are recorded so that they can be retrieved
+    /// through [`MediaController::events`].
+    ///
+    /// If the device has gone away, every object is revoked.
+    ///
+    /// # Errors
+    ///
+    /// If the Media Controller device file access fails.
+    pub fn refresh(&self) -> io::Result<()> {
+        self.check_topology_version()
+    }
+
+    /// Returns the topology changes that happened since the last call
+    ///
+    /// This refreshes the topology first, so it can be polled periodically by long-running
+    /// programs to find out when their pipeline has been torn down, for example when a driver
+    /// has been unbound and bound again.
+    ///
+    /// # Errors
+    ///
+    /// If the Media Controller device file access fails. Any object removal has been recorded
+    /// and is returned by the next call.
+    pub fn events(&self) -> io::Result<Vec<MediaControllerEvent>> {
+        self.refresh()?;
+
+        Ok(core::mem::take(&mut self.0.borrow_mut().events))
+    }
+
     /// Returns the current topology version
     ///
     /// # Errors
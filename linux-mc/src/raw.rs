@@ -1,8 +1,12 @@
-use std::{io, os::fd::BorrowedFd};
+use core::ffi::c_int;
+use std::{
+    io,
+    os::fd::{BorrowedFd, FromRawFd as _, OwnedFd},
+};
 
 use rustix::{
     io::Errno,
-    ioctl::{Updater, ioctl, opcode},
+    ioctl::{Getter, NoArg, Updater, ioctl, opcode},
 };
 use tracing::instrument;
 
@@ -48,6 +52,10 @@ const MEDIA_IOC_MAGIC: u8 = b'|';
 const MEDIA_IOC_DEVICE_INFO: u8 = 0x00;
 const MEDIA_IOC_SETUP_LINK: u8 = 0x03;
 const MEDIA_IOC_G_TOPOLOGY: u8 = 0x04;
+const MEDIA_IOC_REQUEST_ALLOC: u8 = 0x05;
+
+const MEDIA_REQUEST_IOC_QUEUE: u8 = 0x80;
+const MEDIA_REQUEST_IOC_REINIT: u8 = 0x81;
 
 pub use bindgen::media_device_info;
 const MEDIA_IOC_DEVICE_INFO_OPCODE: u32 =
@@ -127,3 +135,68 @@ pub fn media_ioctl_g_topology(
         .map(|()| topo)
         .map_err(<Errno as Into<io::Error>>::into)
 }
+
+const MEDIA_IOC_REQUEST_ALLOC_OPCODE: u32 =
+    opcode::read::<c_int>(MEDIA_IOC_MAGIC, MEDIA_IOC_REQUEST_ALLOC);
+
+/// Allocates a new request
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the file descriptor, or if the driver doesn't support
+/// requests.
+#[instrument(level = "trace")]
+pub fn media_ioctl_request_alloc(fd: BorrowedFd<'_>) -> io::Result<OwnedFd> {
+    // SAFETY: We checked both the opcode and the type.
+    let ioctl_obj = unsafe { Getter::<MEDIA_IOC_REQUEST_ALLOC_OPCODE, c_int>::new() };
+
+    // SAFETY: This function is unsafe because the driver isn't guaranteed to implement the ioctl
+    // properly. We don't have much of a choice and still have to trust the
+    // kernel there.
+    let request = unsafe { ioctl(fd, ioctl_obj) }.map_err(<Errno as Into<io::Error>>::into)?;
+
+    // SAFETY: The kernel just created that file descriptor for us, and nothing else owns it.
+    Ok(unsafe { OwnedFd::from_raw_fd(request) })
+}
+
+const MEDIA_REQUEST_IOC_QUEUE_OPCODE: u32 = opcode::none(MEDIA_IOC_MAGIC, MEDIA_REQUEST_IOC_QUEUE);
+
+/// Queues a request
+///
+/// The file descriptor must be a request file descriptor.
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the file descriptor, or if the request is invalid.
+#[instrument(level = "trace")]
+pub fn media_request_ioctl_queue(fd: BorrowedFd<'_>) -> io::Result<()> {
+    // SAFETY: We checked the opcode, and this ioctl doesn't take any argument.
+    let ioctl_obj = unsafe { NoArg::<MEDIA_REQUEST_IOC_QUEUE_OPCODE>::new() };
+
+    // SAFETY: This function is unsafe because the driver isn't guaranteed to implement the ioctl
+    // properly. We don't have much of a choice and still have to trust the
+    // kernel there.
+    unsafe { ioctl(fd, ioctl_obj) }.map_err(<Errno as Into<io::Error>>::into)
+}
+
+const MEDIA_REQUEST_IOC_REINIT_OPCODE: u32 =
+    opcode::none(MEDIA_IOC_MAGIC, MEDIA_REQUEST_IOC_REINIT);
+
+/// Re-initializes a completed request so that it can be used again
+///
+/// The file descriptor must be a request file descriptor.
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the file descriptor, or if the request is still
+/// queued.
+#[instrument(level = "trace")]
+pub fn media_request_ioctl_reinit(fd: BorrowedFd<'_>) -> io::Result<()> {
+    // SAFETY: We checked the opcode, and this ioctl doesn't take any argument.
+    let ioctl_obj = unsafe { NoArg::<MEDIA_REQUEST_IOC_REINIT_OPCODE>::new() };
+
+    // SAFETY: This function is unsafe because the driver isn't guaranteed to implement the ioctl
+    // properly. We don't have much of a choice and still have to trust the
+    // kernel there.
+    unsafe { ioctl(fd, ioctl_obj) }.map_err(<Errno as Into<io::Error>>::into)
+}
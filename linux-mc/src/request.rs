@@ -0,0 +1,115 @@
+use core::time::Duration;
+use std::{
+    io,
+    os::fd::{AsFd, AsRawFd as _, BorrowedFd, OwnedFd},
+};
+
+use rustix::{
+    event::{PollFd, PollFlags, poll},
+    io::Errno,
+    time::Timespec,
+};
+use tracing::debug;
+
+use crate::{MediaController, raw};
+
+/// A Media Request
+///
+/// Requests bind buffers and controls of the devices of a Media Controller together, so that the
+/// driver applies them all for the same frame. Buffers and controls are bound to a request
+/// through its file descriptor, then the request gets queued and completes once the driver is
+/// done with it.
+#[derive(Debug)]
+pub struct MediaControllerRequest(OwnedFd);
+
+impl MediaControllerRequest {
+    /// Queues the request to the driver
+    ///
+    /// # Errors
+    ///
+    /// If the request is empty or has already been queued, or if the request file access fails.
+    pub fn queue(&self) -> io::Result<()> {
+        debug!("Queuing request {}", self.0.as_raw_fd());
+
+        raw::media_request_ioctl_queue(self.0.as_fd())
+    }
+
+    /// Re-initializes a completed request, so that it can be used again
+    ///
+    /// # Errors
+    ///
+    /// If the request is still queued, or if the request file access fails.
+    pub fn reinit(&self) -> io::Result<()> {
+        debug!("Re-initializing request {}", self.0.as_raw_fd());
+
+        raw::media_request_ioctl_reinit(self.0.as_fd())
+    }
+
+    /// Waits for the request to complete
+    ///
+    /// Returns whether the request completed before the timeout expired. If there's no timeout,
+    /// waits until the request is complete.
+    ///
+    /// # Errors
+    ///
+    /// If the request hasn't been queued, or if the request file access fails.
+    pub fn wait(&self, timeout: Option<Duration>) -> io::Result<bool> {
+        let timeout = timeout
+            .map(|t| -> io::Result<_> {
+                Ok(Timespec {
+                    tv_sec: t.as_secs().try_into().map_err(|_e| Errno::INVAL)?,
+                    tv_nsec: t.subsec_nanos().into(),
+                })
+            })
+            .transpose()?;
+
+        let mut fds = [PollFd::new(&self.0, PollFlags::PRI)];
+        if poll(&mut fds, timeout.as_ref())? == 0 {
+            return Ok(false);
+        }
+
+        let events = fds[0].revents();
+        if events.contains(PollFlags::ERR) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The request hasn't been queued",
+            ));
+        }
+
+        Ok(events.contains(PollFlags::PRI))
+    }
+
+    /// Returns whether the request has completed, without waiting for it
+    ///
+    /// # Errors
+    ///
+    /// If the request hasn't been queued, or if the request file access fails.
+    pub fn is_complete(&self) -> io::Result<bool> {
+        self.wait(Some(Duration::ZERO))
+    }
+}
+
+impl AsFd for MediaControllerRequest {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.0.as_fd()
+    }
+}
+
+#[expect(
+    clippy::multiple_inherent_impl,
+    reason = "The request allocation lives with the rest of the request code."
+)]
+impl MediaController {
+    /// Allocates a new request
+    ///
+    /// # Errors
+    ///
+    /// If the driver doesn't support requests, or if the Media Controller device file access
+    /// fails.
+    pub fn allocate_request(&self) -> io::Result<MediaControllerRequest> {
+        let inner = self.0.borrow();
+        let fd = inner.backend.fd()?;
+
+        raw::media_ioctl_request_alloc(fd).map(MediaControllerRequest)
+    }
+}
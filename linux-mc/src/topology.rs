@@ -0,0 +1,989 @@
+use alloc::rc::Rc;
+use core::{
+    cell::RefCell,
+    fmt::{self, Write as _},
+};
+use std::{
+    fs::{self, File},
+    io,
+    os::fd::{AsFd as _, BorrowedFd},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+use v4l2_raw::{
+    v4l2_colorspace, v4l2_field, v4l2_quantization, v4l2_xfer_func, v4l2_ycbcr_encoding,
+    wrapper::{v4l2_encoding, v4l2_ioctl_subdev_g_fmt, v4l2_subdev_format},
+};
+
+use crate::{
+    DeviceNode, MediaController, MediaControllerBackend, MediaControllerEntityInner,
+    MediaControllerInfo, MediaControllerInner, MediaControllerInterfaceInner,
+    MediaControllerLinkEnd, MediaControllerLinkInner, MediaControllerLinkKind,
+    MediaControllerPadInner, Revocable, RevocableValue, complete_merge,
+    config::{
+        COLORSPACE_NAMES, FIELD_NAMES, QUANTIZATION_NAMES, XFER_FUNC_NAMES, YCBCR_ENCODING_NAMES,
+        value_name,
+    },
+    media_entity_function, merge_objects, raw, update_link_flags,
+};
+
+/// A Device Node, as found in a [`MediaControllerTopology`]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct MediaControllerTopologyDeviceNode {
+    /// Device File Major Number
+    pub major: u32,
+
+    /// Device File Minor Number
+    pub minor: u32,
+
+    /// Path to the Device File
+    pub path: PathBuf,
+}
+
+/// An Entity, as found in a [`MediaControllerTopology`]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct MediaControllerTopologyEntity {
+    /// Entity ID
+    pub id: u32,
+
+    /// Entity Name
+    pub name: String,
+
+    /// Entity Function, one of the `MEDIA_ENT_F_*` values
+    pub function: u32,
+
+    /// Entity Flags, a combination of the `MEDIA_ENT_FL_*` values
+    pub flags: u32,
+}
+
+/// An Interface, as found in a [`MediaControllerTopology`]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct MediaControllerTopologyInterface {
+    /// Interface ID
+    pub id: u32,
+
+    /// Interface Type, one of the `MEDIA_INTF_T_*` values
+    pub intf_type: u32,
+
+    /// Interface Device Node, if it could be found
+    pub device_node: Option<MediaControllerTopologyDeviceNode>,
+}
+
+/// A Pad, as found in a [`MediaControllerTopology`]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct MediaControllerTopologyPad {
+    /// Pad ID
+    pub id: u32,
+
+    /// ID of the Entity the Pad belongs to
+    pub entity_id: u32,
+
+    /// Pad Index within its Entity
+    pub index: u32,
+
+    /// Pad Flags, a combination of the `MEDIA_PAD_FL_*` values
+    pub flags: u32,
+
+    /// Sub-device Pad Formats, in the `media-ctl -p` syntax, ie.
+    /// `fmt:CODE/widthxheight field:none colorspace:srgb`
+    ///
+    /// Only the frame format of the first stream is reported.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub formats: Vec<String>,
+}
+
+/// A Link, as found in a [`MediaControllerTopology`]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct MediaControllerTopologyLink {
+    /// Link ID
+    pub id: u32,
+
+    /// ID of the source pad, interface or entity, depending on the link type
+    pub source_id: u32,
+
+    /// ID of the sink pad or entity, depending on the link type
+    pub sink_id: u32,
+
+    /// Link Flags, a combination of the `MEDIA_LNK_FL_*` values, including the link type
+    pub flags: u32,
+}
+
+impl MediaControllerTopologyLink {
+    fn is_data_link(&self) -> bool {
+        (self.flags & raw::bindgen::MEDIA_LNK_FL_LINK_TYPE) == raw::bindgen::MEDIA_LNK_FL_DATA_LINK
+    }
+
+    fn is_interface_link(&self) -> bool {
+        (self.flags & raw::bindgen::MEDIA_LNK_FL_LINK_TYPE)
+            == raw::bindgen::MEDIA_LNK_FL_INTERFACE_LINK
+    }
+}
+
+/// A snapshot of the whole Media Controller topology, along with the device information
+///
+/// It only holds plain values, so it can be formatted the way `media-ctl` does, serialized, and
+/// attached to bug or test reports.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct MediaControllerTopology {
+    /// Name of the driver implementing the Media Controller API
+    pub driver: String,
+
+    /// Model Name
+    pub model: String,
+
+    /// Device Serial Number
+    pub serial: String,
+
+    /// Location of the Device in the system
+    pub bus_info: String,
+
+    /// Media Controller API Version
+    pub media_version: String,
+
+    /// Hardware Revision
+    pub hw_revision: u32,
+
+    /// Media Device Driver Version
+    pub driver_version: String,
+
+    /// Topology Version
+    pub topology_version: u64,
+
+    /// Entities
+    pub entities: Vec<MediaControllerTopologyEntity>,
+
+    /// Interfaces
+    pub interfaces: Vec<MediaControllerTopologyInterface>,
+
+    /// Pads
+    pub pads: Vec<MediaControllerTopologyPad>,
+
+    /// Links
+    pub links: Vec<MediaControllerTopologyLink>,
+}
+
+// media-ctl still reports the entity types the kernel used before entity functions got
+// introduced, so we derive them the same way the kernel does.
+fn legacy_entity_type(function: u32) -> (&'static str, &'static str) {
+    let Ok(function) = media_entity_function::try_from(function) else {
+        return ("V4L2 subdev", "Unknown");
+    };
+
+    match function {
+        media_entity_function::MEDIA_ENT_F_IO_V4L
+        | media_entity_function::MEDIA_ENT_F_IO_VBI
+        | media_entity_function::MEDIA_ENT_F_IO_SWRADIO => ("Node", "V4L"),
+        media_entity_function::MEDIA_ENT_F_IO_DTV => ("Node", "DVB"),
+        media_entity_function::MEDIA_ENT_F_CAM_SENSOR => ("V4L2 subdev", "Sensor"),
+        media_entity_function::MEDIA_ENT_F_FLASH => ("V4L2 subdev", "Flash"),
+        media_entity_function::MEDIA_ENT_F_LENS => ("V4L2 subdev", "Lens"),
+        media_entity_function::MEDIA_ENT_F_ATV_DECODER => ("V4L2 subdev", "Decoder"),
+        media_entity_function::MEDIA_ENT_F_TUNER => ("V4L2 subdev", "Tuner"),
+        media_entity_function::MEDIA_ENT_F_UNKNOWN
+        | media_entity_function::MEDIA_ENT_F_V4L2_SUBDEV_UNKNOWN
+        | media_entity_function::MEDIA_ENT_F_DTV_DEMOD
+        | media_entity_function::MEDIA_ENT_F_TS_DEMUX
+        | media_entity_function::MEDIA_ENT_F_DTV_CA
+        | media_entity_function::MEDIA_ENT_F_DTV_NET_DECAP
+        | media_entity_function::MEDIA_ENT_F_IF_VID_DECODER
+        | media_entity_function::MEDIA_ENT_F_IF_AUD_DECODER
+        | media_entity_function::MEDIA_ENT_F_AUDIO_CAPTURE
+        | media_entity_function::MEDIA_ENT_F_AUDIO_PLAYBACK
+        | media_entity_function::MEDIA_ENT_F_AUDIO_MIXER
+        | media_entity_function::MEDIA_ENT_F_PROC_VIDEO_COMPOSER
+        | media_entity_function::MEDIA_ENT_F_PROC_VIDEO_PIXEL_FORMATTER
+        | media_entity_function::MEDIA_ENT_F_PROC_VIDEO_PIXEL_ENC_CONV
+        | media_entity_function::MEDIA_ENT_F_PROC_VIDEO_LUT
+        | media_entity_function::MEDIA_ENT_F_PROC_VIDEO_SCALER
+        | media_entity_function::MEDIA_ENT_F_PROC_VIDEO_STATISTICS
+        | media_entity_function::MEDIA_ENT_F_PROC_VIDEO_ENCODER
+        | media_entity_function::MEDIA_ENT_F_PROC_VIDEO_DECODER
+        | media_entity_function::MEDIA_ENT_F_PROC_VIDEO_ISP
+        | media_entity_function::MEDIA_ENT_F_VID_MUX
+        | media_entity_function::MEDIA_ENT_F_VID_IF_BRIDGE
+        | media_entity_function::MEDIA_ENT_F_DV_DECODER
+        | media_entity_function::MEDIA_ENT_F_DV_ENCODER => ("V4L2 subdev", "Unknown"),
+    }
+}
+
+fn link_flag_names(flags: u32) -> String {
+    let mut names = Vec::new();
+
+    if flags & raw::bindgen::MEDIA_LNK_FL_ENABLED != 0 {
+        names.push("ENABLED");
+    }
+
+    if flags & raw::bindgen::MEDIA_LNK_FL_IMMUTABLE != 0 {
+        names.push("IMMUTABLE");
+    }
+
+    if flags & raw::bindgen::MEDIA_LNK_FL_DYNAMIC != 0 {
+        names.push("DYNAMIC");
+    }
+
+    names.join(",")
+}
+
+fn plural(count: usize) -> &'static str {
+    if count > 1 { "s" } else { "" }
+}
+
+fn escape(name: &str) -> String {
+    name.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// Formats the active format of a sub-device pad like media-ctl does: the properties are only
+// there if they're set, and the colorimetry details only if the colorspace is.
+fn subdev_pad_format(fd: BorrowedFd<'_>, pad: u32) -> io::Result<String> {
+    let format =
+        v4l2_ioctl_subdev_g_fmt(fd, v4l2_subdev_format::new_active().set_pad(pad))?.format();
+
+    let mut properties = vec![format!(
+        "fmt:{}/{}x{}",
+        format.code().name().unwrap_or("unknown"),
+        format.width(),
+        format.height()
+    )];
+
+    if format.field() != v4l2_field::V4L2_FIELD_ANY {
+        properties.push(format!("field:{}", value_name(FIELD_NAMES, format.field())));
+    }
+
+    if format.colorspace() != v4l2_colorspace::V4L2_COLORSPACE_DEFAULT {
+        properties.push(format!(
+            "colorspace:{}",
+            value_name(COLORSPACE_NAMES, format.colorspace())
+        ));
+
+        if format.xfer_func() != v4l2_xfer_func::V4L2_XFER_FUNC_DEFAULT {
+            properties.push(format!(
+                "xfer:{}",
+                value_name(XFER_FUNC_NAMES, format.xfer_func())
+            ));
+        }
+
+        if let v4l2_encoding::YCbCr(encoding) = format.encoding() {
+            if encoding != v4l2_ycbcr_encoding::V4L2_YCBCR_ENC_DEFAULT {
+                properties.push(format!(
+                    "ycbcr:{}",
+                    value_name(YCBCR_ENCODING_NAMES, encoding)
+                ));
+            }
+        }
+
+        if format.quantization() != v4l2_quantization::V4L2_QUANTIZATION_DEFAULT {
+            properties.push(format!(
+                "quantization:{}",
+                value_name(QUANTIZATION_NAMES, format.quantization())
+            ));
+        }
+    }
+
+    Ok(properties.join(" "))
+}
+
+impl MediaControllerTopology {
+    fn entity(&self, id: u32) -> Option<&MediaControllerTopologyEntity> {
+        self.entities.iter().find(|e| e.id == id)
+    }
+
+    fn pad(&self, id: u32) -> Option<&MediaControllerTopologyPad> {
+        self.pads.iter().find(|p| p.id == id)
+    }
+
+    fn entity_pads(&self, entity_id: u32) -> Vec<&MediaControllerTopologyPad> {
+        let mut pads = self
+            .pads
+            .iter()
+            .filter(|p| p.entity_id == entity_id)
+            .collect::<Vec<_>>();
+        pads.sort_by_key(|p| p.index);
+        pads
+    }
+
+    fn pad_data_links(&self, pad_id: u32) -> impl Iterator<Item = &MediaControllerTopologyLink> {
+        self.links
+            .iter()
+            .filter(move |l| l.is_data_link() && (l.source_id == pad_id || l.sink_id == pad_id))
+    }
+
+    fn entity_device_node(&self, entity_id: u32) -> Option<&MediaControllerTopologyDeviceNode> {
+        self.links
+            .iter()
+            .filter(|l| l.is_interface_link() && l.sink_id == entity_id)
+            .find_map(|l| {
+                self.interfaces
+                    .iter()
+                    .find(|i| i.id == l.source_id)
+                    .and_then(|i| i.device_node.as_ref())
+            })
+    }
+
+    fn entity_subdev_node(&self, entity_id: u32) -> Option<&MediaControllerTopologyDeviceNode> {
+        self.links
+            .iter()
+            .filter(|l| l.is_interface_link() && l.sink_id == entity_id)
+            .find_map(|l| {
+                self.interfaces
+                    .iter()
+                    .find(|i| {
+                        i.id == l.source_id && i.intf_type == raw::bindgen::MEDIA_INTF_T_V4L_SUBDEV
+                    })
+                    .and_then(|i| i.device_node.as_ref())
+            })
+    }
+
+    // Queries the active format of every sub-device pad, like media-ctl -p does. We don't enable
+    // the streams API, so we only get the format of the first stream. The sub-devices we can't
+    // open or query just don't report any format.
+    fn read_subdev_formats(&mut self) {
+        let nodes = self
+            .entities
+            .iter()
+            .filter_map(|e| {
+                self.entity_subdev_node(e.id)
+                    .map(|node| (e.id, node.path.clone()))
+            })
+            .collect::<Vec<_>>();
+
+        for (entity_id, path) in nodes {
+            let subdev = match File::open(&path) {
+                Ok(subdev) => subdev,
+                Err(e) => {
+                    debug!("Couldn't open {}: {e}", path.display());
+                    continue;
+                }
+            };
+
+            for pad in self.pads.iter_mut().filter(|p| p.entity_id == entity_id) {
+                match subdev_pad_format(subdev.as_fd(), pad.index) {
+                    Ok(format) => pad.formats = vec![format],
+                    Err(e) => debug!(
+                        "Couldn't get the format of {} pad {}: {e}",
+                        path.display(),
+                        pad.index
+                    ),
+                }
+            }
+        }
+    }
+
+    fn is_node(entity: &MediaControllerTopologyEntity) -> bool {
+        legacy_entity_type(entity.function).0 == "Node"
+    }
+
+    /// Formats the topology like `media-ctl -p` does
+    #[expect(
+        clippy::missing_panics_doc,
+        reason = "Writing to a String can't fail, so using expect is fine."
+    )]
+    #[must_use]
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        self.write_text(&mut out)
+            .expect("Writing to a String never fails");
+        out
+    }
+
+    fn write_text(&self, out: &mut String) -> fmt::Result {
+        writeln!(out, "Media controller API version {}", self.media_version)?;
+        writeln!(out)?;
+        writeln!(out, "Media device information")?;
+        writeln!(out, "------------------------")?;
+        writeln!(out, "driver          {}", self.driver)?;
+        writeln!(out, "model           {}", self.model)?;
+        writeln!(out, "serial          {}", self.serial)?;
+        writeln!(out, "bus info        {}", self.bus_info)?;
+        writeln!(out, "hw revision     {:#x}", self.hw_revision)?;
+        writeln!(out, "driver version  {}", self.driver_version)?;
+        writeln!(out)?;
+        writeln!(out, "Device topology")?;
+
+        let mut entities = self.entities.iter().collect::<Vec<_>>();
+        entities.sort_by_key(|e| e.id);
+
+        for entity in entities {
+            let pads = self.entity_pads(entity.id);
+            let num_links = pads
+                .iter()
+                .map(|p| self.pad_data_links(p.id).count())
+                .sum::<usize>();
+
+            // media-ctl aligns the entity details with its name
+            let header = format!("- entity {}: ", entity.id);
+            let padding = " ".repeat(header.len());
+
+            writeln!(
+                out,
+                "{header}{} ({} pad{}, {} link{})",
+                entity.name,
+                pads.len(),
+                plural(pads.len()),
+                num_links,
+                plural(num_links)
+            )?;
+
+            let (kind, subkind) = legacy_entity_type(entity.function);
+            writeln!(
+                out,
+                "{padding}type {kind} subtype {subkind} flags {:x}",
+                entity.flags
+            )?;
+
+            if let Some(node) = self.entity_device_node(entity.id) {
+                writeln!(out, "{padding}device node name {}", node.path.display())?;
+            }
+
+            for pad in pads {
+                write!(
+                    out,
+                    "\tpad{}: {}",
+                    pad.index,
+                    if pad.flags & raw::bindgen::MEDIA_PAD_FL_SINK != 0 {
+                        "Sink"
+                    } else {
+                        "Source"
+                    }
+                )?;
+
+                if pad.flags & raw::bindgen::MEDIA_PAD_FL_MUST_CONNECT != 0 {
+                    write!(out, ", Must Connect")?;
+                }
+                writeln!(out)?;
+
+                for format in &pad.formats {
+                    writeln!(out, "\t\t[{format}]")?;
+                }
+
+                for link in self.pad_data_links(pad.id) {
+                    let (arrow, remote_id) = if link.source_id == pad.id {
+                        ("->", link.sink_id)
+                    } else {
+                        ("<-", link.source_id)
+                    };
+
+                    let Some(remote_pad) = self.pad(remote_id) else {
+                        continue;
+                    };
+
+                    let Some(remote_entity) = self.entity(remote_pad.entity_id) else {
+                        continue;
+                    };
+
+                    writeln!(
+                        out,
+                        "\t\t{arrow} \"{}\":{} [{}]",
+                        remote_entity.name,
+                        remote_pad.index,
+                        link_flag_names(link.flags)
+                    )?;
+                }
+            }
+
+            writeln!(out)?;
+        }
+
+        Ok(())
+    }
+
+    /// Formats the topology as a Graphviz graph, like `media-ctl --print-dot` does
+    #[expect(
+        clippy::missing_panics_doc,
+        reason = "Writing to a String can't fail, so using expect is fine."
+    )]
+    #[must_use]
+    pub fn to_dot(&self) -> String {
+        let mut out = String::new();
+        self.write_dot(&mut out)
+            .expect("Writing to a String never fails");
+        out
+    }
+
+    fn write_dot(&self, out: &mut String) -> fmt::Result {
+        writeln!(out, "digraph board {{")?;
+        writeln!(out, "\trankdir=TB")?;
+
+        let mut entities = self.entities.iter().collect::<Vec<_>>();
+        entities.sort_by_key(|e| e.id);
+
+        for entity in &entities {
+            let pads = self.entity_pads(entity.id);
+            let devnode = self
+                .entity_device_node(entity.id)
+                .map(|n| format!("\\n{}", n.path.display()))
+                .unwrap_or_default();
+
+            if Self::is_node(entity) {
+                writeln!(
+                    out,
+                    "\tn{:08x} [label=\"{}{devnode}\", shape=box, style=filled, fillcolor=yellow]",
+                    entity.id,
+                    escape(&entity.name)
+                )?;
+            } else {
+                let ports = |flag: u32| {
+                    pads.iter()
+                        .filter(|p| p.flags & flag != 0)
+                        .map(|p| format!("<port{}> {}", p.index, p.index))
+                        .collect::<Vec<_>>()
+                        .join(" | ")
+                };
+
+                writeln!(
+                    out,
+                    "\tn{:08x} [label=\"{{{{{}}} | {}{devnode} | {{{}}}}}\", shape=Mrecord, style=filled, fillcolor=green]",
+                    entity.id,
+                    ports(raw::bindgen::MEDIA_PAD_FL_SINK),
+                    escape(&entity.name),
+                    ports(raw::bindgen::MEDIA_PAD_FL_SOURCE),
+                )?;
+            }
+
+            for pad in pads
+                .iter()
+                .filter(|p| p.flags & raw::bindgen::MEDIA_PAD_FL_SOURCE != 0)
+            {
+                for link in self
+                    .pad_data_links(pad.id)
+                    .filter(|l| l.source_id == pad.id)
+                {
+                    let Some(remote_pad) = self.pad(link.sink_id) else {
+                        continue;
+                    };
+
+                    let Some(remote_entity) = self.entity(remote_pad.entity_id) else {
+                        continue;
+                    };
+
+                    write!(out, "\tn{:08x}", entity.id)?;
+                    if !Self::is_node(entity) {
+                        write!(out, ":port{}", pad.index)?;
+                    }
+
+                    write!(out, " -> n{:08x}", remote_entity.id)?;
+                    if !Self::is_node(remote_entity) {
+                        write!(out, ":port{}", remote_pad.index)?;
+                    }
+
+                    if link.flags & raw::bindgen::MEDIA_LNK_FL_IMMUTABLE != 0 {
+                        write!(out, " [style=bold]")?;
+                    } else if link.flags & raw::bindgen::MEDIA_LNK_FL_ENABLED == 0 {
+                        write!(out, " [style=dashed]")?;
+                    }
+
+                    writeln!(out)?;
+                }
+            }
+        }
+
+        writeln!(out, "}}")
+    }
+
+    /// Serializes the topology to JSON
+    ///
+    /// # Panics
+    ///
+    /// If the serialization fails, which can't happen since the topology only holds plain values.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self)
+            .expect("The topology only holds values that can be serialized")
+    }
+
+    /// Deserializes a topology from JSON
+    ///
+    /// # Errors
+    ///
+    /// If the JSON document isn't a valid topology
+    pub fn from_json(json: &str) -> io::Result<Self> {
+        serde_json::from_str(json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Saves the topology to a JSON file
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be written
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_json())
+    }
+
+    /// Loads a topology from a JSON file, as written by [`MediaControllerTopology::save`]
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be read, or isn't a valid topology
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+}
+
+impl fmt::Display for MediaControllerTopology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_text())
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn find_object<T, F>(
+    objects: &[Rc<RefCell<Revocable<T>>>],
+    id: u32,
+    obj_id: F,
+) -> io::Result<Rc<RefCell<Revocable<T>>>>
+where
+    F: Fn(&T) -> u32,
+{
+    objects
+        .iter()
+        .find(|o| o.borrow().try_access().is_some_and(|o| obj_id(&*o) == id))
+        .cloned()
+        .ok_or(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Object {id} not found"),
+        ))
+}
+
+// Merges a topology snapshot into the current one, the same way we merge the one the kernel
+// reports.
+#[expect(clippy::too_many_lines)]
+fn merge_topology(
+    mc: &Rc<RefCell<MediaControllerInner>>,
+    topology: &MediaControllerTopology,
+    record_events: bool,
+) -> io::Result<()> {
+    let mut inner = mc.borrow_mut();
+    inner.last_topology_version = Some(topology.topology_version);
+
+    let entities = merge_objects(
+        &inner.entities,
+        topology.entities.iter().collect(),
+        |e| e.id,
+        |e| e.id,
+        |e| {
+            Ok(Rc::new(RefCell::new(Revocable::new(
+                MediaControllerEntityInner {
+                    controller: mc.clone(),
+                    id: e.id,
+                    name: e.name.clone(),
+                    function: e
+                        .function
+                        .try_into()
+                        .map_err(|_e| invalid_data("Unexpected entity function"))?,
+                    flags: e
+                        .flags
+                        .try_into()
+                        .map_err(|_e| invalid_data("Unexpected entity flag"))?,
+                },
+            ))))
+        },
+        |_entity, _e| Ok(false),
+    )?;
+
+    inner.entities.clone_from(&entities.objects);
+
+    let interfaces = merge_objects(
+        &inner.interfaces,
+        topology.interfaces.iter().collect(),
+        |i| i.id,
+        |i| i.id,
+        |i| {
+            Ok(Rc::new(RefCell::new(Revocable::new(
+                MediaControllerInterfaceInner {
+                    _controller: mc.clone(),
+                    id: i.id,
+                    kind: i
+                        .intf_type
+                        .try_into()
+                        .map_err(|_e| invalid_data("Unexpected interface type"))?,
+                    device_node: i.device_node.as_ref().map(|n| DeviceNode {
+                        major: n.major,
+                        minor: n.minor,
+                        path: n.path.clone(),
+                    }),
+                },
+            ))))
+        },
+        |_interface, _i| Ok(false),
+    )?;
+
+    inner.interfaces.clone_from(&interfaces.objects);
+
+    let pads = merge_objects(
+        &inner.pads,
+        topology.pads.iter().collect(),
+        |p| p.id,
+        |p| p.id,
+        |p| {
+            Ok(Rc::new(RefCell::new(Revocable::new(
+                MediaControllerPadInner {
+                    controller: mc.clone(),
+                    entity: find_object(&inner.entities, p.entity_id, |e| e.id)?,
+                    id: p.id,
+                    index: p.index,
+                    flags: p.flags.into(),
+                    formats: p.formats.clone(),
+                },
+            ))))
+        },
+        |pad, p| {
+            pad.formats.clone_from(&p.formats);
+            Ok(false)
+        },
+    )?;
+
+    inner.pads.clone_from(&pads.objects);
+
+    let find_entity = |id| find_object(&inner.entities, id, |e| e.id);
+    let find_interface = |id| find_object(&inner.interfaces, id, |i| i.id);
+    let find_pad = |id| find_object(&inner.pads, id, |p| p.id);
+
+    let links = merge_objects(
+        &inner.links,
+        topology.links.iter().collect(),
+        |l| l.id,
+        |l| l.id,
+        |l| {
+            let link_type = l.flags & raw::bindgen::MEDIA_LNK_FL_LINK_TYPE;
+            let kind: MediaControllerLinkKind = link_type
+                .try_into()
+                .map_err(|_e| invalid_data("Unexpected link type"))?;
+
+            let (source, sink) = match kind {
+                MediaControllerLinkKind::Data => (
+                    MediaControllerLinkEnd::Pad(find_pad(l.source_id)?),
+                    MediaControllerLinkEnd::Pad(find_pad(l.sink_id)?),
+                ),
+                MediaControllerLinkKind::Interface => (
+                    MediaControllerLinkEnd::Interface(find_interface(l.source_id)?),
+                    MediaControllerLinkEnd::Entity(find_entity(l.sink_id)?),
+                ),
+                MediaControllerLinkKind::Ancillary => (
+                    MediaControllerLinkEnd::Entity(find_entity(l.source_id)?),
+                    MediaControllerLinkEnd::Entity(find_entity(l.sink_id)?),
+                ),
+            };
+
+            Ok(Rc::new(RefCell::new(Revocable::new(
+                MediaControllerLinkInner {
+                    controller: mc.clone(),
+                    id: l.id,
+                    source,
+                    sink,
+                    kind,
+                    flags: (l.flags & !raw::bindgen::MEDIA_LNK_FL_LINK_TYPE)
+                        .try_into()
+                        .map_err(|_e| invalid_data("Unexpected link flags"))?,
+                },
+            ))))
+        },
+        |link, l| update_link_flags(link, l.flags),
+    )?;
+
+    inner.links.clone_from(&links.objects);
+
+    complete_merge(
+        &mut inner,
+        record_events,
+        &entities,
+        &interfaces,
+        &pads,
+        &links,
+    );
+
+    Ok(())
+}
+
+#[expect(
+    clippy::multiple_inherent_impl,
+    reason = "The snapshot conversions live with the topology code."
+)]
+impl MediaController {
+    /// Creates a `MediaController` out of a topology snapshot
+    ///
+    /// There's no device behind it, so the topology never changes, and enabling or disabling a
+    /// link only updates it in memory, following the same rules than the kernel. This allows to
+    /// test the code looking for a pipeline without the hardware.
+    ///
+    /// # Errors
+    ///
+    /// If the topology holds values the kernel wouldn't report, or objects that don't exist.
+    pub fn from_topology(topology: &MediaControllerTopology) -> io::Result<Self> {
+        let info = MediaControllerInfo {
+            driver: topology.driver.clone(),
+            model: topology.model.clone(),
+            serial: topology.serial.clone(),
+            bus_info: topology.bus_info.clone(),
+            media_version: topology
+                .media_version
+                .parse()
+                .map_err(|_e| invalid_data("Invalid media version"))?,
+            hw_revision: topology.hw_revision,
+            driver_version: topology
+                .driver_version
+                .parse()
+                .map_err(|_e| invalid_data("Invalid driver version"))?,
+        };
+
+        let mc = Rc::new(RefCell::new(MediaControllerInner {
+            backend: MediaControllerBackend::Snapshot(info),
+            last_topology_version: None,
+            entities: Vec::new(),
+            interfaces: Vec::new(),
+            links: Vec::new(),
+            pads: Vec::new(),
+            events: Vec::new(),
+        }));
+
+        merge_topology(&mc, topology, false)?;
+
+        Ok(Self(mc))
+    }
+
+    /// Replaces the topology of a `MediaController` created out of a snapshot
+    ///
+    /// The objects are merged just like when the kernel reports a new topology: the ones that are
+    /// still there remain valid, the ones that are gone are revoked, and the changes are reported
+    /// through [`MediaController::events`]. This allows to test how a topology change is handled
+    /// without the hardware.
+    ///
+    /// # Errors
+    ///
+    /// If there's a device behind the `MediaController`, or if the topology holds values the
+    /// kernel wouldn't report, or objects that don't exist.
+    pub fn update_from_topology(&self, topology: &MediaControllerTopology) -> io::Result<()> {
+        if matches!(self.0.borrow().backend, MediaControllerBackend::Device(_)) {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Only topology snapshots can be updated",
+            ));
+        }
+
+        merge_topology(&self.0, topology, true)
+    }
+
+    /// Creates a `MediaController` out of a topology snapshot file
+    ///
+    /// See [`MediaController::from_topology`].
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be read, or isn't a valid topology.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::from_topology(&MediaControllerTopology::load(path)?)
+    }
+
+    /// Returns a snapshot of the current topology
+    ///
+    /// # Errors
+    ///
+    /// If the Media Controller device file access fails.
+    pub fn topology(&self) -> io::Result<MediaControllerTopology> {
+        let info = self.info()?;
+        let topology_version = self.topology_version()?;
+
+        let inner = self.0.borrow();
+
+        let entities = inner
+            .entities
+            .iter()
+            .filter_map(|e| {
+                e.borrow()
+                    .try_access()
+                    .map(|e| MediaControllerTopologyEntity {
+                        id: e.id,
+                        name: e.name.clone(),
+                        function: e.function as u32,
+                        flags: e.flags.bits(),
+                    })
+            })
+            .collect();
+
+        let interfaces = inner
+            .interfaces
+            .iter()
+            .filter_map(|i| {
+                i.borrow()
+                    .try_access()
+                    .map(|i| MediaControllerTopologyInterface {
+                        id: i.id,
+                        intf_type: i.kind.into(),
+                        device_node: i.device_node.as_ref().map(|n| {
+                            MediaControllerTopologyDeviceNode {
+                                major: n.major,
+                                minor: n.minor,
+                                path: n.path.clone(),
+                            }
+                        }),
+                    })
+            })
+            .collect();
+
+        let pads = inner
+            .pads
+            .iter()
+            .filter_map(|p| {
+                let p = p.borrow();
+                let p = p.try_access()?;
+                let entity_id = p.entity.borrow().try_access()?.id;
+
+                Some(MediaControllerTopologyPad {
+                    id: p.id,
+                    entity_id,
+                    index: p.index,
+                    flags: p.flags.bits(),
+                    formats: p.formats.clone(),
+                })
+            })
+            .collect();
+
+        let links = inner
+            .links
+            .iter()
+            .filter_map(|l| {
+                let l = l.borrow();
+                let l = l.try_access()?;
+
+                let RevocableValue::Value(source_id) = l.source.id() else {
+                    return None;
+                };
+
+                let RevocableValue::Value(sink_id) = l.sink.id() else {
+                    return None;
+                };
+
+                Some(MediaControllerTopologyLink {
+                    id: l.id,
+                    source_id,
+                    sink_id,
+                    flags: u32::from(l.kind) | l.flags.bits(),
+                })
+            })
+            .collect();
+
+        let mut topology = MediaControllerTopology {
+            driver: info.driver().to_owned(),
+            model: info.model().to_owned(),
+            serial: info.serial().to_owned(),
+            bus_info: info.bus_info().to_owned(),
+            media_version: info.media_controller_version().to_string(),
+            hw_revision: info.hardware_revision(),
+            driver_version: info.driver_version().to_string(),
+            topology_version,
+            entities,
+            interfaces,
+            pads,
+            links,
+        };
+
+        if matches!(inner.backend, MediaControllerBackend::Device(_)) {
+            topology.read_subdev_formats();
+        }
+
+        Ok(topology)
+    }
+}
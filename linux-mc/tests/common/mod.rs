@@ -0,0 +1,48 @@
+use std::{fs, path::PathBuf};
+
+use tracing::{debug, info};
+
+fn media_sysfs_dir(model_name: &str) -> PathBuf {
+    for entry in fs::read_dir("/sys/bus/media/devices/").unwrap() {
+        let entry = entry.unwrap();
+        let entry_path = entry.path();
+
+        debug!("Found file {}", entry_path.display());
+
+        let model = String::from_utf8(fs::read(entry_path.join("model")).unwrap()).unwrap();
+        let model = model.trim();
+
+        debug!("Media Controller Model: {model:#?}");
+        if model != model_name {
+            debug!("Model isn't {model_name}, skipping.");
+            continue;
+        }
+
+        info!("Media Bus Device is {model_name}, returning");
+        return entry_path;
+    }
+
+    panic!("Missing {model_name} device");
+}
+
+/// Returns the path to the device node of the Media Controller with the given model name
+pub fn media_device_path(model_name: &str) -> PathBuf {
+    let sysfs_dir = media_sysfs_dir(model_name);
+
+    let uevent = String::from_utf8(fs::read(sysfs_dir.join("uevent")).unwrap()).unwrap();
+    for line in uevent.lines() {
+        let parts = line.split('=').collect::<Vec<_>>();
+
+        let attr = parts[0];
+        if attr != "DEVNAME" {
+            continue;
+        }
+
+        let dev = PathBuf::from("/dev/").join(parts[1]);
+        info!("Found device file {}", dev.display());
+
+        return dev;
+    }
+
+    panic!("Couldn't find the associated device node");
+}
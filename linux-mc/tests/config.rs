@@ -0,0 +1,177 @@
+#![allow(missing_docs)]
+#![allow(unused_crate_dependencies)]
+
+use std::io;
+
+use linux_mc::{
+    MediaControllerEntityReference, MediaControllerFormatDescription, MediaControllerFrameFormat,
+    MediaControllerLinkDescription, MediaControllerPadReference, MediaControllerRectangle,
+};
+use rstest::rstest;
+use v4l2_raw::{
+    v4l2_colorspace, v4l2_field, v4l2_quantization, v4l2_xfer_func, v4l2_ycbcr_encoding,
+};
+
+#[test]
+fn link() {
+    let link: MediaControllerLinkDescription = "\"imx219 10-0010\":0 -> 5:0 [1]".parse().unwrap();
+
+    assert_eq!(
+        link,
+        MediaControllerLinkDescription {
+            source: MediaControllerPadReference {
+                entity: MediaControllerEntityReference::Name(String::from("imx219 10-0010")),
+                index: 0,
+            },
+            sink: MediaControllerPadReference {
+                entity: MediaControllerEntityReference::Id(5),
+                index: 0,
+            },
+            enabled: true,
+        }
+    );
+    assert_eq!(link.to_string(), "\"imx219 10-0010\":0 -> 5:0 [1]");
+}
+
+#[test]
+fn link_list() {
+    let links = MediaControllerLinkDescription::parse_list(
+        "\"csi2\":4->\"rp1-cfe-csi2_ch0\":0[1],  \"csi2\":5 -> \"rp1-cfe-embedded\":0 [0] ",
+    )
+    .unwrap();
+
+    assert_eq!(
+        links.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        [
+            "\"csi2\":4 -> \"rp1-cfe-csi2_ch0\":0 [1]",
+            "\"csi2\":5 -> \"rp1-cfe-embedded\":0 [0]",
+        ]
+    );
+
+    assert!(
+        MediaControllerLinkDescription::parse_list("")
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn format() {
+    let format: MediaControllerFormatDescription =
+        "\"csi2\":4/1 [fmt:RGB888_1X24/1920x1080 crop:(0,-2)/1920x1076 compose:(0,0)/960x540]"
+            .parse()
+            .unwrap();
+
+    assert_eq!(
+        format,
+        MediaControllerFormatDescription {
+            pad: MediaControllerPadReference {
+                entity: MediaControllerEntityReference::Name(String::from("csi2")),
+                index: 4,
+            },
+            stream: 1,
+            format: Some(MediaControllerFrameFormat {
+                code: String::from("RGB888_1X24"),
+                width: 1920,
+                height: 1080,
+            }),
+            field: None,
+            colorspace: None,
+            xfer_func: None,
+            ycbcr_encoding: None,
+            quantization: None,
+            crop: Some(MediaControllerRectangle {
+                left: 0,
+                top: -2,
+                width: 1920,
+                height: 1076,
+            }),
+            compose: Some(MediaControllerRectangle {
+                left: 0,
+                top: 0,
+                width: 960,
+                height: 540,
+            }),
+        }
+    );
+    assert_eq!(
+        format.to_string(),
+        "\"csi2\":4/1 [fmt:RGB888_1X24/1920x1080 crop:(0,-2)/1920x1076 compose:(0,0)/960x540]"
+    );
+}
+
+#[test]
+fn format_colorimetry() {
+    let format: MediaControllerFormatDescription = "\"tc358743 4-000f\":0 [fmt:UYVY8_1X16/1280x720 field:none colorspace:rec709 xfer:709 ycbcr:709 quantization:lim-range]"
+        .parse()
+        .unwrap();
+
+    assert_eq!(format.field, Some(v4l2_field::V4L2_FIELD_NONE));
+    assert_eq!(
+        format.colorspace,
+        Some(v4l2_colorspace::V4L2_COLORSPACE_REC709)
+    );
+    assert_eq!(format.xfer_func, Some(v4l2_xfer_func::V4L2_XFER_FUNC_709));
+    assert_eq!(
+        format.ycbcr_encoding,
+        Some(v4l2_ycbcr_encoding::V4L2_YCBCR_ENC_709)
+    );
+    assert_eq!(
+        format.quantization,
+        Some(v4l2_quantization::V4L2_QUANTIZATION_LIM_RANGE)
+    );
+    assert_eq!(
+        format.to_string(),
+        "\"tc358743 4-000f\":0 [fmt:UYVY8_1X16/1280x720 field:none colorspace:rec709 xfer:709 ycbcr:709 quantization:lim-range]"
+    );
+}
+
+#[test]
+fn format_list() {
+    let formats = MediaControllerFormatDescription::parse_list(
+        "\"tc358743 4-000f\":0 [fmt:UYVY8_1X16/1280x720], 3:0[fmt:UYVY8_1X16/1280x720]",
+    )
+    .unwrap();
+
+    assert_eq!(
+        formats.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        [
+            "\"tc358743 4-000f\":0 [fmt:UYVY8_1X16/1280x720]",
+            "3:0 [fmt:UYVY8_1X16/1280x720]",
+        ]
+    );
+}
+
+#[rstest]
+#[case::missing_arrow("\"a\":0 \"b\":0 [1]")]
+#[case::missing_flags("\"a\":0 -> \"b\":0")]
+#[case::unknown_flags("\"a\":0 -> \"b\":0 [3]")]
+#[case::unterminated_name("\"a:0 -> \"b\":0 [1]")]
+#[case::negative_pad("\"a\":-1 -> \"b\":0 [1]")]
+#[case::trailing("\"a\":0 -> \"b\":0 [1] [1]")]
+fn link_invalid(#[case] input: &str) {
+    assert_eq!(
+        input
+            .parse::<MediaControllerLinkDescription>()
+            .unwrap_err()
+            .kind(),
+        io::ErrorKind::InvalidInput
+    );
+}
+
+#[rstest]
+#[case::unknown_property("\"a\":0 [interval:1/30]")]
+#[case::unknown_field("\"a\":0 [field:progressive]")]
+#[case::unknown_quantization("\"a\":0 [quantization:full]")]
+#[case::missing_size("\"a\":0 [fmt:UYVY8_1X16]")]
+#[case::unterminated("\"a\":0 [fmt:UYVY8_1X16/1280x720")]
+#[case::invalid_rectangle("\"a\":0 [crop:0,0/10x10]")]
+fn format_invalid(#[case] input: &str) {
+    assert_eq!(
+        input
+            .parse::<MediaControllerFormatDescription>()
+            .unwrap_err()
+            .kind(),
+        io::ErrorKind::InvalidInput
+    );
+}
@@ -0,0 +1,415 @@
+#![allow(missing_docs)]
+#![allow(unused_crate_dependencies)]
+
+use std::{env, fs, io, process};
+
+use linux_mc::{
+    MediaController, MediaControllerLinkDescription, MediaControllerTopology,
+    MediaControllerTopologyEntity, MediaControllerTopologyLink, MediaControllerTopologyPad,
+    RevocableValue,
+};
+use rstest::rstest;
+use rustix::io::Errno;
+
+const TOPOLOGY: &str = r#"{
+    "driver": "test",
+    "model": "Test Device",
+    "serial": "",
+    "bus_info": "platform:test",
+    "media_version": "6.12.0",
+    "hw_revision": 0,
+    "driver_version": "6.12.0",
+    "topology_version": 42,
+    "entities": [
+        { "id": 1, "name": "sensor", "function": 131073, "flags": 0 },
+        { "id": 3, "name": "scaler", "function": 16389, "flags": 0 },
+        { "id": 6, "name": "video", "function": 65537, "flags": 0 }
+    ],
+    "interfaces": [
+        {
+            "id": 10,
+            "intf_type": 512,
+            "device_node": { "major": 81, "minor": 0, "path": "/dev/video0" }
+        },
+        {
+            "id": 12,
+            "intf_type": 515,
+            "device_node": { "major": 81, "minor": 1, "path": "/dev/v4l-subdev0" }
+        }
+    ],
+    "pads": [
+        {
+            "id": 2, "entity_id": 1, "index": 0, "flags": 2,
+            "formats": ["fmt:SRGGB10_1X10/640x480 field:none colorspace:raw"]
+        },
+        {
+            "id": 4, "entity_id": 3, "index": 0, "flags": 1,
+            "formats": ["fmt:SRGGB10_1X10/640x480 field:none colorspace:raw"]
+        },
+        {
+            "id": 5, "entity_id": 3, "index": 1, "flags": 2,
+            "formats": ["fmt:RGB888_1X24/640x480 field:none colorspace:srgb"]
+        },
+        { "id": 7, "entity_id": 6, "index": 0, "flags": 1 }
+    ],
+    "links": [
+        { "id": 8, "source_id": 2, "sink_id": 4, "flags": 3 },
+        { "id": 9, "source_id": 5, "sink_id": 7, "flags": 0 },
+        { "id": 11, "source_id": 10, "sink_id": 6, "flags": 268435459 },
+        { "id": 13, "source_id": 12, "sink_id": 3, "flags": 268435459 }
+    ]
+}"#;
+
+fn topology() -> MediaControllerTopology {
+    MediaControllerTopology::from_json(TOPOLOGY).unwrap()
+}
+
+#[test]
+fn text() {
+    let expected = [
+        "Media controller API version 6.12.0",
+        "",
+        "Media device information",
+        "------------------------",
+        "driver          test",
+        "model           Test Device",
+        "serial          ",
+        "bus info        platform:test",
+        "hw revision     0x0",
+        "driver version  6.12.0",
+        "",
+        "Device topology",
+        "- entity 1: sensor (1 pad, 1 link)",
+        "            type V4L2 subdev subtype Sensor flags 0",
+        "\tpad0: Source",
+        "\t\t[fmt:SRGGB10_1X10/640x480 field:none colorspace:raw]",
+        "\t\t-> \"scaler\":0 [ENABLED,IMMUTABLE]",
+        "",
+        "- entity 3: scaler (2 pads, 2 links)",
+        "            type V4L2 subdev subtype Unknown flags 0",
+        "            device node name /dev/v4l-subdev0",
+        "\tpad0: Sink",
+        "\t\t[fmt:SRGGB10_1X10/640x480 field:none colorspace:raw]",
+        "\t\t<- \"sensor\":0 [ENABLED,IMMUTABLE]",
+        "\tpad1: Source",
+        "\t\t[fmt:RGB888_1X24/640x480 field:none colorspace:srgb]",
+        "\t\t-> \"video\":0 []",
+        "",
+        "- entity 6: video (1 pad, 1 link)",
+        "            type Node subtype V4L flags 0",
+        "            device node name /dev/video0",
+        "\tpad0: Sink",
+        "\t\t<- \"scaler\":1 []",
+        "",
+    ];
+
+    assert_eq!(topology().to_text().lines().collect::<Vec<_>>(), expected);
+}
+
+// Compares with an actual media-ctl dump, without the formats that the snapshots don't store
+#[rstest]
+#[case::raspberrypi5(
+    include_str!("../../docs/device-info/raspberrypi5+geekworm-c779/media-ctl-topology.json"),
+    include_str!("../../docs/device-info/raspberrypi5+geekworm-c779/media-ctl-topology.txt")
+)]
+fn text_media_ctl(#[case] json: &str, #[case] expected: &str) {
+    let topology = MediaControllerTopology::from_json(json).unwrap();
+
+    assert_eq!(
+        topology.to_text().lines().collect::<Vec<_>>(),
+        expected
+            .lines()
+            .filter(|l| !l.starts_with("\t\t["))
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn dot() {
+    let expected = [
+        "digraph board {",
+        "\trankdir=TB",
+        "\tn00000001 [label=\"{{} | sensor | {<port0> 0}}\", shape=Mrecord, style=filled, fillcolor=green]",
+        "\tn00000001:port0 -> n00000003:port0 [style=bold]",
+        "\tn00000003 [label=\"{{<port0> 0} | scaler\\n/dev/v4l-subdev0 | {<port1> 1}}\", shape=Mrecord, style=filled, fillcolor=green]",
+        "\tn00000003:port1 -> n00000006 [style=dashed]",
+        "\tn00000006 [label=\"video\\n/dev/video0\", shape=box, style=filled, fillcolor=yellow]",
+        "}",
+    ];
+
+    assert_eq!(topology().to_dot().lines().collect::<Vec<_>>(), expected);
+}
+
+#[test]
+fn json() {
+    let topology = topology();
+
+    assert_eq!(topology.entities.len(), 3);
+    assert_eq!(topology.links.len(), 4);
+    assert_eq!(
+        MediaControllerTopology::from_json(&topology.to_json()).unwrap(),
+        topology
+    );
+}
+
+fn controller() -> MediaController {
+    MediaController::from_topology(&topology()).unwrap()
+}
+
+#[test]
+fn snapshot_round_trip() {
+    let mc = controller();
+
+    assert_eq!(mc.topology().unwrap(), topology());
+}
+
+#[test]
+fn snapshot_info() {
+    let info = controller().info().unwrap();
+
+    assert_eq!(info.driver(), "test");
+    assert_eq!(info.model(), "Test Device");
+    assert_eq!(info.bus_info(), "platform:test");
+    assert_eq!(info.driver_version().to_string(), "6.12.0");
+}
+
+#[test]
+fn snapshot_graph() {
+    let mc = controller();
+
+    assert_eq!(mc.topology_version().unwrap(), 42);
+
+    let sensor = mc.find_entity_by_name("sensor").unwrap().unwrap();
+    let video = mc.find_entity_by_name("video").unwrap().unwrap();
+
+    assert!(!sensor.is_v4l2_device().valid().unwrap());
+    assert!(video.is_v4l2_device().valid().unwrap());
+
+    let node = video.interfaces().valid().unwrap()[0]
+        .device_node()
+        .valid()
+        .unwrap();
+    assert_eq!(node.path().to_str(), Some("/dev/video0"));
+
+    let pipelines = sensor.pipelines_to(&video, false).valid().unwrap();
+    assert_eq!(pipelines.len(), 1);
+    assert_eq!(
+        pipelines[0].to_string(),
+        "sensor -> [0] => [0] -> scaler -> [1] => [0] -> video"
+    );
+    assert!(!pipelines[0].is_enabled().valid().unwrap());
+    assert!(
+        sensor
+            .pipelines_to(&video, true)
+            .valid()
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn snapshot_setup_link() {
+    let mc = controller();
+
+    let links = mc.links().unwrap();
+    let immutable = links.iter().find(|l| l.id().valid() == 8).unwrap();
+    let mutable = links.iter().find(|l| l.id().valid() == 9).unwrap();
+
+    mutable.enable().valid().unwrap();
+    assert!(mutable.is_enabled().valid());
+
+    immutable.enable().valid().unwrap();
+    assert_eq!(
+        Errno::from_io_error(&immutable.disable().valid().unwrap_err()),
+        Some(Errno::INVAL)
+    );
+    assert!(immutable.is_enabled().valid());
+
+    mutable.disable().valid().unwrap();
+    assert!(!mutable.is_enabled().valid());
+}
+
+#[test]
+fn snapshot_request() {
+    assert_eq!(
+        controller().allocate_request().unwrap_err().kind(),
+        io::ErrorKind::Unsupported
+    );
+}
+
+#[test]
+fn snapshot_save_load() {
+    let path = env::temp_dir().join(format!("linux-mc-topology-{}.json", process::id()));
+
+    topology().save(&path).unwrap();
+    let mc = MediaController::load(&path);
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(mc.unwrap().topology().unwrap(), topology());
+}
+
+#[test]
+fn snapshot_invalid() {
+    let mut topology = topology();
+    topology.links[0].sink_id = 100;
+
+    assert!(MediaController::from_topology(&topology).is_err());
+}
+
+#[test]
+fn snapshot_setup_links() {
+    let mc = controller();
+
+    let links = mc.links().unwrap();
+    let mutable = links.iter().find(|l| l.id().valid() == 9).unwrap();
+
+    let enable = MediaControllerLinkDescription::parse_list(
+        r#""sensor":0 -> "scaler":0 [1], "scaler":1 -> "video":0 [1]"#,
+    )
+    .unwrap();
+    mc.setup_links(&enable).unwrap();
+    assert!(mutable.is_enabled().valid());
+
+    // Links that aren't part of the description get reset
+    mc.setup_links(&[]).unwrap();
+    assert!(!mutable.is_enabled().valid());
+
+    mc.setup_links(&enable).unwrap();
+
+    // Nothing gets changed if any of the links is invalid
+    let missing = MediaControllerLinkDescription::parse_list(
+        r#""scaler":1 -> "video":0 [0], "sensor":0 -> "video":0 [1]"#,
+    )
+    .unwrap();
+    assert_eq!(
+        mc.setup_links(&missing).unwrap_err().kind(),
+        io::ErrorKind::NotFound
+    );
+    assert!(mutable.is_enabled().valid());
+
+    let immutable = MediaControllerLinkDescription::parse_list(
+        r#""scaler":1 -> "video":0 [0], 1:0 -> 3:0 [0]"#,
+    )
+    .unwrap();
+    assert_eq!(
+        mc.setup_links(&immutable).unwrap_err().kind(),
+        io::ErrorKind::InvalidInput
+    );
+    assert!(mutable.is_enabled().valid());
+}
+
+#[test]
+fn snapshot_find_pad() {
+    let mc = controller();
+
+    let pad = mc
+        .find_pad(&"\"scaler\":1".parse().unwrap())
+        .unwrap()
+        .unwrap();
+    assert_eq!(pad.id().valid(), 5);
+
+    assert!(mc.find_pad(&"3:2".parse().unwrap()).unwrap().is_none());
+    assert!(
+        mc.find_pad(&"\"sink\":0".parse().unwrap())
+            .unwrap()
+            .is_none()
+    );
+}
+
+// Replaces the sensor by another one, and enables the scaler to video link
+fn updated_topology() -> MediaControllerTopology {
+    let mut topology = topology();
+
+    topology.topology_version = 43;
+    topology.entities.retain(|e| e.id != 1);
+    topology.entities.push(MediaControllerTopologyEntity {
+        id: 14,
+        name: String::from("sensor2"),
+        function: 131_073,
+        flags: 0,
+    });
+    topology.pads.retain(|p| p.id != 2);
+    topology.pads.push(MediaControllerTopologyPad {
+        id: 15,
+        entity_id: 14,
+        index: 0,
+        flags: 2,
+        formats: Vec::new(),
+    });
+    topology.links.retain(|l| l.id != 8);
+    topology.links.push(MediaControllerTopologyLink {
+        id: 16,
+        source_id: 15,
+        sink_id: 4,
+        flags: 3,
+    });
+
+    let link = topology.links.iter_mut().find(|l| l.id == 9).unwrap();
+    link.flags = 1;
+
+    topology
+}
+
+#[test]
+fn snapshot_update_events() {
+    let mc = controller();
+    assert!(mc.events().unwrap().is_empty());
+
+    mc.update_from_topology(&updated_topology()).unwrap();
+
+    let expected = [
+        "Link ID 8 removed",
+        "Pad ID 2 removed",
+        "Entity sensor (ID 1) removed",
+        "Entity sensor2 added",
+        "Pad sensor2:0 added",
+        "Link Pad sensor2:0 -> Pad scaler:0 added",
+        "Link Pad scaler:1 -> Pad video:0 changed",
+    ];
+
+    assert_eq!(
+        mc.events()
+            .unwrap()
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>(),
+        expected
+    );
+    assert_eq!(mc.topology_version().unwrap(), 43);
+    assert_eq!(mc.topology().unwrap(), updated_topology());
+
+    // Nothing changes if the same topology is reported again
+    mc.update_from_topology(&updated_topology()).unwrap();
+    assert!(mc.events().unwrap().is_empty());
+}
+
+#[test]
+fn snapshot_update_handles() {
+    let mc = controller();
+
+    let sensor = mc.find_entity_by_name("sensor").unwrap().unwrap();
+    let scaler = mc.find_entity_by_name("scaler").unwrap().unwrap();
+    let links = mc.links().unwrap();
+    let removed = links.iter().find(|l| l.id().valid() == 8).unwrap();
+    let changed = links.iter().find(|l| l.id().valid() == 9).unwrap();
+
+    mc.update_from_topology(&updated_topology()).unwrap();
+
+    assert_eq!(sensor.name(), RevocableValue::Revoked);
+    assert_eq!(removed.id(), RevocableValue::Revoked);
+
+    assert_eq!(scaler.name(), RevocableValue::Value(String::from("scaler")));
+    assert!(changed.is_enabled().valid());
+    assert_eq!(
+        mc.find_entity_by_name("scaler").unwrap().unwrap().id(),
+        scaler.id()
+    );
+}
+
+#[test]
+fn snapshot_update_invalid() {
+    let mut topology = updated_topology();
+    topology.links[0].sink_id = 100;
+
+    assert!(controller().update_from_topology(&topology).is_err());
+}
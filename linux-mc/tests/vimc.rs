@@ -1,12 +1,15 @@
 #![allow(missing_docs)]
 #![allow(unused_crate_dependencies)]
 
+mod common;
+
 use std::{fs, path::PathBuf};
 
 use linux_mc::{
     MediaController, MediaControllerEntity, MediaControllerInterface, MediaControllerInterfaceKind,
     MediaControllerInterfaceV4lKind, MediaControllerLink, MediaControllerLinkKind,
-    MediaControllerPad, MediaControllerPadKind, media_entity_function,
+    MediaControllerPad, MediaControllerPadKind, MediaControllerTopology,
+    MediaControllerTraversalDirection, MediaControllerTraversalOrder, media_entity_function,
 };
 use linux_raw::KernelVersion;
 use rstest::{fixture, rstest};
@@ -14,49 +17,9 @@ use tracing::{debug, info};
 
 const VIMC_MODEL_NAME: &'static str = "VIMC MDEV";
 
-fn get_vimc_sysfs_dir() -> PathBuf {
-    for entry in fs::read_dir("/sys/bus/media/devices/").unwrap() {
-        let entry = entry.unwrap();
-        let entry_path = entry.path();
-
-        debug!("Found file {}", entry_path.display());
-
-        let model = String::from_utf8(fs::read(entry_path.join("model")).unwrap()).unwrap();
-        let model = model.trim();
-
-        debug!("Media Controller Model: {model:#?}");
-        if model != VIMC_MODEL_NAME {
-            debug!("Model isn't vimc, skipping.");
-            continue;
-        }
-
-        info!("Media Bus Device is VIMC, returning");
-        return entry_path;
-    }
-
-    panic!("Missing VIMC device");
-}
-
 #[fixture]
 fn get_vimc_device_path() -> PathBuf {
-    let sysfs_dir = get_vimc_sysfs_dir();
-
-    let uevent = String::from_utf8(fs::read(sysfs_dir.join("uevent")).unwrap()).unwrap();
-    for line in uevent.lines() {
-        let parts = line.split('=').collect::<Vec<_>>();
-
-        let attr = parts[0];
-        if attr != "DEVNAME" {
-            continue;
-        }
-
-        let dev = PathBuf::from("/dev/").join(parts[1]);
-        info!("Found device file {}", dev.display());
-
-        return dev;
-    }
-
-    panic!("Couldn't find the associated device node");
+    common::media_device_path(VIMC_MODEL_NAME)
 }
 
 #[rstest]
@@ -656,3 +619,190 @@ fn find_link_from_pads(#[from(get_vimc_device_path)] vimc: PathBuf) {
         .unwrap();
     assert!(link.is_some());
 }
+
+#[rstest]
+#[test_log::test]
+#[cfg_attr(not(feature = "vimc"), ignore)]
+fn refresh_without_changes(#[from(get_vimc_device_path)] vimc: PathBuf) {
+    let mc = MediaController::new(&vimc).unwrap();
+
+    let entities = mc.entities().unwrap();
+    let version = mc.topology_version().unwrap();
+
+    mc.refresh().unwrap();
+    assert!(mc.events().unwrap().is_empty());
+    assert_eq!(mc.topology_version().unwrap(), version);
+
+    let refreshed = mc.entities().unwrap();
+    assert_eq!(entities.len(), refreshed.len());
+    for (old, new) in entities.iter().zip(refreshed.iter()) {
+        assert_eq!(old.id().unwrap(), new.id().unwrap());
+    }
+}
+
+#[rstest]
+#[test_log::test]
+#[cfg_attr(not(feature = "vimc"), ignore)]
+fn find_entities(#[from(get_vimc_device_path)] vimc: PathBuf) {
+    let mc = MediaController::new(&vimc).unwrap();
+
+    let sensors = mc
+        .find_entities_by_function(media_entity_function::MEDIA_ENT_F_CAM_SENSOR)
+        .unwrap();
+    assert_eq!(sensors.len(), 2);
+
+    let scaler = mc.find_entity_by_name("Scaler").unwrap().unwrap();
+    assert_eq!(
+        scaler.function().unwrap(),
+        media_entity_function::MEDIA_ENT_F_PROC_VIDEO_SCALER
+    );
+
+    assert!(mc.find_entity_by_name("Not An Entity").unwrap().is_none());
+
+    let capture = mc.find_entity_by_name("RGB/YUV Capture").unwrap().unwrap();
+    let capture_intf = capture.interfaces().unwrap();
+    let capture_node = capture_intf
+        .first()
+        .unwrap()
+        .device_node()
+        .unwrap()
+        .unwrap();
+
+    let found = mc
+        .find_entity_by_device_node(capture_node.path())
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.id().unwrap(), capture.id().unwrap());
+}
+
+#[rstest]
+#[test_log::test]
+#[cfg_attr(not(feature = "vimc"), ignore)]
+fn traverse(#[from(get_vimc_device_path)] vimc: PathBuf) {
+    let mc = MediaController::new(&vimc).unwrap();
+
+    let sensor_a = mc.find_entity_by_name("Sensor A").unwrap().unwrap();
+
+    for order in [
+        MediaControllerTraversalOrder::BreadthFirst,
+        MediaControllerTraversalOrder::DepthFirst,
+    ] {
+        let names = sensor_a
+            .traverse(order, MediaControllerTraversalDirection::Downstream, false)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name().unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(names.first().unwrap(), "Sensor A");
+        for name in ["Debayer A", "Scaler", "RGB/YUV Capture", "Raw Capture 0"] {
+            assert!(names.iter().any(|n| n == name));
+        }
+        assert!(!names.iter().any(|n| n == "Sensor B"));
+    }
+
+    let capture = mc.find_entity_by_name("RGB/YUV Capture").unwrap().unwrap();
+    let names = capture
+        .traverse(
+            MediaControllerTraversalOrder::BreadthFirst,
+            MediaControllerTraversalDirection::Upstream,
+            false,
+        )
+        .unwrap()
+        .into_iter()
+        .map(|e| e.name().unwrap())
+        .collect::<Vec<_>>();
+
+    assert_eq!(names.len(), 6);
+    assert_eq!(names[0], "RGB/YUV Capture");
+    assert_eq!(names[1], "Scaler");
+
+    // Breadth-first, so the debayers must be visited before the sensors.
+    let mut debayers = names[2..4].to_vec();
+    debayers.sort();
+    assert_eq!(debayers, ["Debayer A", "Debayer B"]);
+
+    let mut sensors = names[4..].to_vec();
+    sensors.sort();
+    assert_eq!(sensors, ["Sensor A", "Sensor B"]);
+}
+
+#[rstest]
+#[test_log::test]
+#[cfg_attr(not(feature = "vimc"), ignore)]
+fn pipelines(#[from(get_vimc_device_path)] vimc: PathBuf) {
+    let mc = MediaController::new(&vimc).unwrap();
+
+    let sensor_a = mc.find_entity_by_name("Sensor A").unwrap().unwrap();
+    let capture = mc.find_entity_by_name("RGB/YUV Capture").unwrap().unwrap();
+
+    let pipelines = sensor_a.pipelines_to(&capture, false).unwrap();
+    assert_eq!(pipelines.len(), 1);
+
+    let pipeline = pipelines.first().unwrap();
+    info!("Found pipeline {pipeline}");
+
+    assert!(pipeline.is_enabled().unwrap());
+    assert_eq!(
+        pipeline
+            .items()
+            .iter()
+            .map(|i| i.entity.name().unwrap())
+            .collect::<Vec<_>>(),
+        ["Sensor A", "Debayer A", "Scaler", "RGB/YUV Capture"]
+    );
+
+    let first = pipeline.items().first().unwrap();
+    assert!(first.sink_pad.is_none());
+    assert!(first.source_pad.as_ref().unwrap().is_source().unwrap());
+
+    let last = pipeline.items().last().unwrap();
+    assert!(last.sink_pad.as_ref().unwrap().is_sink().unwrap());
+    assert!(last.source_pad.is_none());
+
+    let raw_capture = mc.find_entity_by_name("Raw Capture 1").unwrap().unwrap();
+    assert!(
+        sensor_a
+            .pipelines_to(&raw_capture, false)
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[rstest]
+#[test_log::test]
+#[cfg_attr(not(feature = "vimc"), ignore)]
+fn topology_snapshot(#[from(get_vimc_device_path)] vimc: PathBuf) {
+    let mc = MediaController::new(&vimc).unwrap();
+    let topology = mc.topology().unwrap();
+
+    assert_eq!(topology.driver, "vimc");
+    assert_eq!(topology.model, VIMC_MODEL_NAME);
+    assert_eq!(topology.entities.len(), mc.entities().unwrap().len());
+    assert_eq!(topology.pads.len(), mc.pads().unwrap().len());
+    assert_eq!(topology.links.len(), mc.links().unwrap().len());
+
+    let text = topology.to_text();
+    info!("Topology:\n{text}");
+
+    assert!(text.contains("driver          vimc\n"));
+    assert!(text.contains(": Sensor A (1 pad, 2 links)\n"));
+    assert!(text.contains("            type V4L2 subdev subtype Sensor flags 0\n"));
+    assert!(
+        text.contains("\tpad0: Source\n\t\t[fmt:RGB888_1X24/640x480 field:none colorspace:srgb")
+    );
+    assert!(text.contains("]\n\t\t-> \"Debayer A\":0 [ENABLED,IMMUTABLE]\n"));
+    assert!(text.contains("\t\t<- \"Sensor A\":0 [ENABLED,IMMUTABLE]\n"));
+
+    let dot = topology.to_dot();
+    info!("DOT Graph:\n{dot}");
+
+    assert!(dot.starts_with("digraph board {\n\trankdir=TB\n"));
+    assert!(dot.ends_with("}\n"));
+    assert!(dot.contains("shape=box, style=filled, fillcolor=yellow]"));
+    assert!(dot.contains("shape=Mrecord, style=filled, fillcolor=green]"));
+    assert!(dot.contains(" [style=bold]\n"));
+
+    let json = topology.to_json();
+    assert_eq!(MediaControllerTopology::from_json(&json).unwrap(), topology);
+}
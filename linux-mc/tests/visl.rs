@@ -0,0 +1,196 @@
+#![allow(missing_docs)]
+#![allow(unused_crate_dependencies)]
+
+mod common;
+
+use core::{ptr, time::Duration};
+use std::{fs::OpenOptions, os::fd::AsFd as _, path::PathBuf};
+
+use linux_mc::{MediaController, MediaControllerInterfaceKind, MediaControllerInterfaceV4lKind};
+use rstest::{fixture, rstest};
+use rustix::io::Errno;
+use tracing::info;
+use v4l2_raw::{
+    raw::{
+        V4L2_CID_STATELESS_FWHT_PARAMS, V4L2_FWHT_FL_COMPONENTS_NUM_OFFSET,
+        V4L2_FWHT_FL_PIXENC_YUV, V4L2_FWHT_VERSION, VIDEO_MAX_PLANES, v4l2_buffer,
+        v4l2_ctrl_fwht_params, v4l2_ext_control, v4l2_ioctl_dqbuf, v4l2_ioctl_qbuf, v4l2_plane,
+    },
+    v4l2_buf_type, v4l2_memory,
+    wrapper::{
+        v4l2_ioctl_g_ext_ctrls, v4l2_ioctl_reqbufs, v4l2_ioctl_s_ext_ctrls, v4l2_ioctl_streamoff,
+        v4l2_ioctl_streamon, v4l2_requestbuffers,
+    },
+};
+
+const VISL_MODEL_NAME: &'static str = "visl";
+
+#[fixture]
+fn get_visl_device_path() -> PathBuf {
+    common::media_device_path(VISL_MODEL_NAME)
+}
+
+fn fwht_params_control(params: &mut v4l2_ctrl_fwht_params) -> v4l2_ext_control {
+    let mut control = v4l2_ext_control {
+        id: V4L2_CID_STATELESS_FWHT_PARAMS,
+        size: u32::try_from(size_of::<v4l2_ctrl_fwht_params>()).unwrap(),
+        ..v4l2_ext_control::default()
+    };
+    control.__bindgen_anon_1.ptr = ptr::from_mut(params).cast();
+    control
+}
+
+fn get_video_device_path(mc: &MediaController) -> PathBuf {
+    mc.interfaces()
+        .unwrap()
+        .into_iter()
+        .find(|itf| {
+            itf.kind().unwrap()
+                == MediaControllerInterfaceKind::V4L(MediaControllerInterfaceV4lKind::Video)
+        })
+        .and_then(|itf| itf.device_node().unwrap())
+        .expect("Couldn't find the video device node")
+        .path()
+        .to_path_buf()
+}
+
+#[rstest]
+#[test_log::test]
+#[cfg_attr(not(feature = "visl"), ignore)]
+fn request_lifecycle(#[from(get_visl_device_path)] visl: PathBuf) {
+    let mc = MediaController::new(&visl).unwrap();
+    let request = mc.allocate_request().unwrap();
+
+    // A request that hasn't been queued can't complete
+    assert!(request.is_complete().is_err());
+
+    // An empty request can't be queued
+    assert_eq!(
+        Errno::from_io_error(&request.queue().unwrap_err()),
+        Some(Errno::NOENT)
+    );
+
+    request.reinit().unwrap();
+    assert!(request.wait(Some(Duration::ZERO)).is_err());
+}
+
+#[rstest]
+#[test_log::test]
+#[cfg_attr(not(feature = "visl"), ignore)]
+fn request_controls(#[from(get_visl_device_path)] visl: PathBuf) {
+    let mc = MediaController::new(&visl).unwrap();
+    let request = mc.allocate_request().unwrap();
+
+    let video = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(get_video_device_path(&mc))
+        .unwrap();
+
+    // The request values can only be retrieved once the request has completed
+    assert_eq!(
+        Errno::from_io_error(
+            &v4l2_ioctl_g_ext_ctrls(video.as_fd(), Some(request.as_fd()), &mut []).unwrap_err()
+        ),
+        Some(Errno::ACCESS)
+    );
+
+    v4l2_ioctl_g_ext_ctrls(video.as_fd(), None, &mut []).unwrap();
+}
+
+#[rstest]
+#[test_log::test]
+#[cfg_attr(not(feature = "visl"), ignore)]
+fn request_decode(#[from(get_visl_device_path)] visl: PathBuf) {
+    const OUTPUT: v4l2_buf_type = v4l2_buf_type::V4L2_BUF_TYPE_VIDEO_OUTPUT_MPLANE;
+    const CAPTURE: v4l2_buf_type = v4l2_buf_type::V4L2_BUF_TYPE_VIDEO_CAPTURE_MPLANE;
+    const MEMORY: v4l2_memory = v4l2_memory::V4L2_MEMORY_MMAP;
+
+    let mc = MediaController::new(&visl).unwrap();
+    let request = mc.allocate_request().unwrap();
+
+    let video = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(get_video_device_path(&mc))
+        .unwrap();
+
+    // visl defaults to the stateless FWHT format, and doesn't look at the bitstream itself. A
+    // single buffer per queue and the frame parameters are enough to run a decoding job.
+    let output = v4l2_ioctl_reqbufs(
+        video.as_fd(),
+        v4l2_requestbuffers::new(OUTPUT, MEMORY).set_count(1),
+    )
+    .unwrap();
+    assert!(output.supports_requests());
+
+    let _: v4l2_requestbuffers = v4l2_ioctl_reqbufs(
+        video.as_fd(),
+        v4l2_requestbuffers::new(CAPTURE, MEMORY).set_count(1),
+    )
+    .unwrap();
+
+    let mut capture_planes = [v4l2_plane::default(); VIDEO_MAX_PLANES as usize];
+    let mut capture_buf = v4l2_buffer {
+        index: 0,
+        type_: CAPTURE.into(),
+        memory: MEMORY.into(),
+        length: VIDEO_MAX_PLANES,
+        ..v4l2_buffer::default()
+    };
+    capture_buf.m.planes = capture_planes.as_mut_ptr();
+    let _: v4l2_buffer = v4l2_ioctl_qbuf(video.as_fd(), capture_buf).unwrap();
+
+    let mut params = v4l2_ctrl_fwht_params {
+        version: V4L2_FWHT_VERSION,
+        width: 640,
+        height: 480,
+        flags: V4L2_FWHT_FL_PIXENC_YUV | (3 << V4L2_FWHT_FL_COMPONENTS_NUM_OFFSET),
+        ..v4l2_ctrl_fwht_params::default()
+    };
+    v4l2_ioctl_s_ext_ctrls(
+        video.as_fd(),
+        Some(request.as_fd()),
+        &mut [fwht_params_control(&mut params)],
+    )
+    .unwrap();
+
+    let mut output_planes = [v4l2_plane::default(); VIDEO_MAX_PLANES as usize];
+    output_planes[0].bytesused = 1;
+    let mut output_buf = v4l2_buffer {
+        index: 0,
+        type_: OUTPUT.into(),
+        memory: MEMORY.into(),
+        length: VIDEO_MAX_PLANES,
+        ..v4l2_buffer::default()
+    };
+    output_buf.m.planes = output_planes.as_mut_ptr();
+    let _: v4l2_buffer =
+        v4l2_ioctl_qbuf(video.as_fd(), output_buf.set_request_fd(request.as_fd())).unwrap();
+
+    v4l2_ioctl_streamon(video.as_fd(), OUTPUT).unwrap();
+    v4l2_ioctl_streamon(video.as_fd(), CAPTURE).unwrap();
+
+    request.queue().unwrap();
+    assert!(request.wait(Some(Duration::from_secs(1))).unwrap());
+    assert!(request.is_complete().unwrap());
+
+    // The buffer bound to the request got decoded into the capture buffer
+    capture_buf.m.planes = capture_planes.as_mut_ptr();
+    let decoded = v4l2_ioctl_dqbuf(video.as_fd(), capture_buf).unwrap();
+    info!("Decoded frame {}", decoded.sequence);
+    assert_eq!(decoded.index, 0);
+
+    // And the request kept the control values it was completed with
+    let mut applied = v4l2_ctrl_fwht_params::default();
+    v4l2_ioctl_g_ext_ctrls(
+        video.as_fd(),
+        Some(request.as_fd()),
+        &mut [fwht_params_control(&mut applied)],
+    )
+    .unwrap();
+    assert_eq!(applied, params);
+
+    v4l2_ioctl_streamoff(video.as_fd(), CAPTURE).unwrap();
+    v4l2_ioctl_streamoff(video.as_fd(), OUTPUT).unwrap();
+}
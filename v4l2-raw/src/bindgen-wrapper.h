@@ -65,6 +65,54 @@ struct v4l2_subdev_format_newer {
 	__u32 reserved[7];
 };
 
+/**
+ * The stream parameter got introduced with Linux 6.3.
+ *
+ * <div rustbindgen replaces="v4l2_subdev_mbus_code_enum"></div>
+ */
+struct v4l2_subdev_mbus_code_enum_newer {
+	__u32 pad;
+	__u32 index;
+	__u32 code;
+	__u32 which;
+	__u32 flags;
+	__u32 stream;
+	__u32 reserved[6];
+};
+
+/**
+ * The stream parameter got introduced with Linux 6.3.
+ *
+ * <div rustbindgen replaces="v4l2_subdev_frame_size_enum"></div>
+ */
+struct v4l2_subdev_frame_size_enum_newer {
+	__u32 index;
+	__u32 pad;
+	__u32 code;
+	__u32 min_width;
+	__u32 max_width;
+	__u32 min_height;
+	__u32 max_height;
+	__u32 which;
+	__u32 stream;
+	__u32 reserved[7];
+};
+
+/**
+ * The stream parameter got introduced with Linux 6.3.
+ *
+ * <div rustbindgen replaces="v4l2_subdev_selection"></div>
+ */
+struct v4l2_subdev_selection_newer {
+	__u32 which;
+	__u32 pad;
+	__u32 target;
+	__u32 flags;
+	struct v4l2_rect r;
+	__u32 stream;
+	__u32 reserved[7];
+};
+
 /** <div rustbindgen attribute="#[derive(facet::Facet, facet_enum_repr::FacetEnumRepr)]" */
 /** <div rustbindgen attribute="#[facet_enum_repr(panic_into(u16))]"></div> */
 enum v4l2_xfer_func;
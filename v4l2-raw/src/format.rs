@@ -822,6 +822,20 @@ pub enum media_bus_fmt {
     MEDIA_BUS_FMT_META_24 = bindgen::MEDIA_BUS_FMT_META_24,
 }
 
+impl media_bus_fmt {
+    /// Returns the name of the media bus code, without the `MEDIA_BUS_FMT_` prefix, like
+    /// `media-ctl` uses.
+    #[must_use]
+    pub fn name(&self) -> Option<&'static str> {
+        Peek::new(self)
+            .into_enum()
+            .ok()?
+            .variant_name_active()
+            .ok()?
+            .strip_prefix("MEDIA_BUS_FMT_")
+    }
+}
+
 impl fmt::Display for media_bus_fmt {
     #[expect(
         clippy::unwrap_in_result,
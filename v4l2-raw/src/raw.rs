@@ -3,7 +3,7 @@ use std::{io, os::fd::BorrowedFd};
 
 use rustix::{
     io::Errno,
-    ioctl::{Getter, Opcode, Setter, Updater, ioctl, opcode},
+    ioctl::{Getter, NoArg, Opcode, Setter, Updater, ioctl, opcode},
 };
 
 pub(crate) mod bindgen {
@@ -25,6 +25,7 @@ pub(crate) mod bindgen {
     #![allow(unsafe_code)]
 
     use core::fmt;
+    use std::os::fd::{AsRawFd as _, BorrowedFd, RawFd};
 
     include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
 
@@ -182,6 +183,51 @@ pub(crate) mod bindgen {
         }
     }
 
+    impl v4l2_buffer {
+        /// Returns the request this buffer is bound to, if any
+        #[must_use]
+        pub fn request_fd(&self) -> Option<RawFd> {
+            if self.flags & V4L2_BUF_FLAG_REQUEST_FD == 0 {
+                return None;
+            }
+
+            // SAFETY: The kernel only uses request_fd if V4L2_BUF_FLAG_REQUEST_FD is set.
+            Some(unsafe { self.__bindgen_anon_1.request_fd })
+        }
+
+        /// Binds the buffer to a media request
+        ///
+        /// The buffer will only be queued to the driver once the request is.
+        #[must_use]
+        pub fn set_request_fd(mut self, request: BorrowedFd<'_>) -> Self {
+            self.flags |= V4L2_BUF_FLAG_REQUEST_FD;
+            self.__bindgen_anon_1.request_fd = request.as_raw_fd();
+            self
+        }
+    }
+
+    #[cfg(test)]
+    mod tests_v4l2_buffer {
+        use std::{
+            fs::File,
+            os::fd::{AsFd as _, AsRawFd as _},
+        };
+
+        use crate::raw;
+
+        #[test]
+        fn request_fd() {
+            let request = File::open("/dev/null").unwrap();
+            let buf = raw::v4l2_buffer::default();
+
+            assert_eq!(buf.request_fd(), None);
+
+            let buf = buf.set_request_fd(request.as_fd());
+            assert_ne!(buf.flags & raw::V4L2_BUF_FLAG_REQUEST_FD, 0);
+            assert_eq!(buf.request_fd(), Some(request.as_raw_fd()));
+        }
+    }
+
     impl fmt::Debug for v4l2_dv_timings {
         fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
             let kind = self.type_;
@@ -258,6 +304,71 @@ pub type v4l2_pix_format_encoding = v4l2_pix_format__bindgen_ty_1;
 #[doc(hidden)]
 pub type v4l2_format_content = v4l2_format__bindgen_ty_1;
 
+/// The route is active
+pub const V4L2_SUBDEV_ROUTE_FL_ACTIVE: u32 = 1 << 0;
+
+/// The client supports the streams API
+pub const V4L2_SUBDEV_CLIENT_CAP_STREAMS: u64 = 1 << 0;
+
+// The routing API got introduced with Linux 6.3, and its structure was extended with Linux 6.8.
+// The headers we're built against might predate it, so we define the 6.8 structures ourselves.
+//
+// The 6.8 v4l2_subdev_routing is bigger than the 6.3 one, and since the ioctl opcodes encode the
+// structure size, the G_ROUTING and S_ROUTING opcodes changed too. Linux 6.8 is thus the minimum
+// version these ioctls work with: older kernels don't know about our opcodes and return ENOTTY,
+// just like sub-devices without routing support do.
+
+/// Sub-device Route
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct v4l2_subdev_route {
+    /// Sink Pad Index
+    pub sink_pad: u32,
+
+    /// Sink Pad Stream
+    pub sink_stream: u32,
+
+    /// Source Pad Index
+    pub source_pad: u32,
+
+    /// Source Pad Stream
+    pub source_stream: u32,
+
+    /// Route Flags
+    pub flags: u32,
+
+    /// Reserved
+    pub reserved: [u32; 5],
+}
+
+/// Sub-device Routing Table
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct v4l2_subdev_routing {
+    /// Try or Active Routing
+    pub which: u32,
+
+    /// Number of routes the routes array can hold
+    pub len_routes: u32,
+
+    /// Pointer to the routes array
+    pub routes: u64,
+
+    /// Number of routes in the routing table
+    pub num_routes: u32,
+
+    /// Reserved
+    pub reserved: [u32; 11],
+}
+
+/// Sub-device Client Capabilities
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct v4l2_subdev_client_capability {
+    /// Capabilities Bitmask
+    pub capabilities: u64,
+}
+
 const V4L2_IOC_MAGIC: u8 = b'V';
 const V4L2_IOC_QUERYCAP: u8 = 0;
 const V4L2_IOC_ENUM_FMT: u8 = 2;
@@ -271,6 +382,10 @@ const V4L2_IOC_STREAMON: u8 = 18;
 const V4L2_IOC_STREAMOFF: u8 = 19;
 const V4L2_IOC_S_EDID: u8 = 41;
 const V4L2_IOC_TRY_FMT: u8 = 64;
+const V4L2_IOC_LOG_STATUS: u8 = 70;
+const V4L2_IOC_G_EXT_CTRLS: u8 = 71;
+const V4L2_IOC_S_EXT_CTRLS: u8 = 72;
+const V4L2_IOC_TRY_EXT_CTRLS: u8 = 73;
 const V4L2_IOC_ENUM_FRAMESIZES: u8 = 74;
 const V4L2_IOC_S_DV_TIMINGS: u8 = 87;
 const V4L2_IOC_DQEVENT: u8 = 89;
@@ -278,7 +393,17 @@ const V4L2_IOC_SUBSCRIBE_EVENT: u8 = 90;
 const V4L2_IOC_UNSUBSCRIBE_EVENT: u8 = 91;
 const V4L2_IOC_QUERY_DV_TIMINGS: u8 = 99;
 
+const V4L2_IOC_SUBDEV_G_ROUTING: u8 = 38;
+const V4L2_IOC_SUBDEV_S_ROUTING: u8 = 39;
+const V4L2_IOC_SUBDEV_G_SELECTION: u8 = 61;
+const V4L2_IOC_SUBDEV_S_SELECTION: u8 = 62;
+const V4L2_IOC_SUBDEV_G_CLIENT_CAP: u8 = 101;
+const V4L2_IOC_SUBDEV_S_CLIENT_CAP: u8 = 102;
+
+const V4L2_IOC_SUBDEV_ENUM_MBUS_CODE: u8 = V4L2_IOC_ENUM_FMT;
+const V4L2_IOC_SUBDEV_G_FMT: u8 = V4L2_IOC_G_FMT;
 const V4L2_IOC_SUBDEV_S_FMT: u8 = V4L2_IOC_S_FMT;
+const V4L2_IOC_SUBDEV_ENUM_FRAME_SIZE: u8 = V4L2_IOC_ENUM_FRAMESIZES;
 const V4L2_IOC_SUBDEV_S_EDID: u8 = V4L2_IOC_S_EDID;
 const V4L2_IOC_SUBDEV_S_DV_TIMINGS: u8 = V4L2_IOC_S_DV_TIMINGS;
 const V4L2_IOC_SUBDEV_QUERY_DV_TIMINGS: u8 = V4L2_IOC_QUERY_DV_TIMINGS;
@@ -364,6 +489,88 @@ pub fn v4l2_ioctl_s_fmt(fd: BorrowedFd<'_>, mut fmt: v4l2_format) -> io::Result<
         .map_err(<Errno as Into<io::Error>>::into)
 }
 
+const V4L2_IOC_SUBDEV_ENUM_MBUS_CODE_OPCODE: u32 = opcode::read_write::<v4l2_subdev_mbus_code_enum>(
+    V4L2_IOC_MAGIC,
+    V4L2_IOC_SUBDEV_ENUM_MBUS_CODE,
+);
+
+/// Enumerates the media bus codes supported on a pad of a v4l2 sub-device.
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the given file descriptor, or `EINVAL` once the index
+/// is past the last supported code.
+pub fn v4l2_ioctl_subdev_enum_mbus_code(
+    fd: BorrowedFd<'_>,
+    mut code: v4l2_subdev_mbus_code_enum,
+) -> io::Result<v4l2_subdev_mbus_code_enum> {
+    // SAFETY: We checked both the opcode and the type.
+    let ioctl_obj = unsafe {
+        Updater::<V4L2_IOC_SUBDEV_ENUM_MBUS_CODE_OPCODE, v4l2_subdev_mbus_code_enum>::new(&mut code)
+    };
+
+    // SAFETY: This function is unsafe because the driver isn't guaranteed to implement the ioctl
+    // properly. We don't have much of a choice and still have to trust the
+    // kernel there.
+    unsafe { ioctl(fd, ioctl_obj) }
+        .map(|()| code)
+        .map_err(<Errno as Into<io::Error>>::into)
+}
+
+const V4L2_IOC_SUBDEV_ENUM_FRAME_SIZE_OPCODE: u32 = opcode::read_write::<v4l2_subdev_frame_size_enum>(
+    V4L2_IOC_MAGIC,
+    V4L2_IOC_SUBDEV_ENUM_FRAME_SIZE,
+);
+
+/// Enumerates the frame sizes supported on a pad of a v4l2 sub-device for a given media bus code.
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the given file descriptor, or `EINVAL` once the index
+/// is past the last supported frame size.
+pub fn v4l2_ioctl_subdev_enum_frame_size(
+    fd: BorrowedFd<'_>,
+    mut size: v4l2_subdev_frame_size_enum,
+) -> io::Result<v4l2_subdev_frame_size_enum> {
+    // SAFETY: We checked both the opcode and the type.
+    let ioctl_obj = unsafe {
+        Updater::<V4L2_IOC_SUBDEV_ENUM_FRAME_SIZE_OPCODE, v4l2_subdev_frame_size_enum>::new(
+            &mut size,
+        )
+    };
+
+    // SAFETY: This function is unsafe because the driver isn't guaranteed to implement the ioctl
+    // properly. We don't have much of a choice and still have to trust the
+    // kernel there.
+    unsafe { ioctl(fd, ioctl_obj) }
+        .map(|()| size)
+        .map_err(<Errno as Into<io::Error>>::into)
+}
+
+const V4L2_IOC_SUBDEV_G_FMT_OPCODE: u32 =
+    opcode::read_write::<v4l2_subdev_format>(V4L2_IOC_MAGIC, V4L2_IOC_SUBDEV_G_FMT);
+
+/// Retrieves the data format on a v4l2 sub-device pad.
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the given file descriptor
+pub fn v4l2_ioctl_subdev_g_fmt(
+    fd: BorrowedFd<'_>,
+    mut fmt: v4l2_subdev_format,
+) -> io::Result<v4l2_subdev_format> {
+    // SAFETY: We checked both the opcode and the type.
+    let ioctl_obj =
+        unsafe { Updater::<V4L2_IOC_SUBDEV_G_FMT_OPCODE, v4l2_subdev_format>::new(&mut fmt) };
+
+    // SAFETY: This function is unsafe because the driver isn't guaranteed to implement the ioctl
+    // properly. We don't have much of a choice and still have to trust the
+    // kernel there.
+    unsafe { ioctl(fd, ioctl_obj) }
+        .map(|()| fmt)
+        .map_err(<Errno as Into<io::Error>>::into)
+}
+
 const V4L2_IOC_SUBDEV_S_FMT_OPCODE: u32 =
     opcode::read_write::<v4l2_subdev_format>(V4L2_IOC_MAGIC, V4L2_IOC_SUBDEV_S_FMT);
 
@@ -391,6 +598,172 @@ pub fn v4l2_ioctl_subdev_s_fmt(
         .map_err(<Errno as Into<io::Error>>::into)
 }
 
+const V4L2_IOC_SUBDEV_G_SELECTION_OPCODE: u32 =
+    opcode::read_write::<v4l2_subdev_selection>(V4L2_IOC_MAGIC, V4L2_IOC_SUBDEV_G_SELECTION);
+
+/// Retrieves a selection rectangle on a v4l2 sub-device pad.
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the given file descriptor
+pub fn v4l2_ioctl_subdev_g_selection(
+    fd: BorrowedFd<'_>,
+    mut sel: v4l2_subdev_selection,
+) -> io::Result<v4l2_subdev_selection> {
+    // SAFETY: We checked both the opcode and the type.
+    let ioctl_obj = unsafe {
+        Updater::<V4L2_IOC_SUBDEV_G_SELECTION_OPCODE, v4l2_subdev_selection>::new(&mut sel)
+    };
+
+    // SAFETY: This function is unsafe because the driver isn't guaranteed to implement the ioctl
+    // properly. We don't have much of a choice and still have to trust the
+    // kernel there.
+    unsafe { ioctl(fd, ioctl_obj) }
+        .map(|()| sel)
+        .map_err(<Errno as Into<io::Error>>::into)
+}
+
+const V4L2_IOC_SUBDEV_S_SELECTION_OPCODE: u32 =
+    opcode::read_write::<v4l2_subdev_selection>(V4L2_IOC_MAGIC, V4L2_IOC_SUBDEV_S_SELECTION);
+
+/// Sets a selection rectangle on a v4l2 sub-device pad.
+///
+/// The driver can adjust the rectangle to accommodate hardware limitations. The actual rectangle
+/// used by the driver will be returned.
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the given file descriptor
+pub fn v4l2_ioctl_subdev_s_selection(
+    fd: BorrowedFd<'_>,
+    mut sel: v4l2_subdev_selection,
+) -> io::Result<v4l2_subdev_selection> {
+    // SAFETY: We checked both the opcode and the type.
+    let ioctl_obj = unsafe {
+        Updater::<V4L2_IOC_SUBDEV_S_SELECTION_OPCODE, v4l2_subdev_selection>::new(&mut sel)
+    };
+
+    // SAFETY: This function is unsafe because the driver isn't guaranteed to implement the ioctl
+    // properly. We don't have much of a choice and still have to trust the
+    // kernel there.
+    unsafe { ioctl(fd, ioctl_obj) }
+        .map(|()| sel)
+        .map_err(<Errno as Into<io::Error>>::into)
+}
+
+const V4L2_IOC_SUBDEV_G_ROUTING_OPCODE: u32 =
+    opcode::read_write::<v4l2_subdev_routing>(V4L2_IOC_MAGIC, V4L2_IOC_SUBDEV_G_ROUTING);
+
+/// Retrieves the routing table of a v4l2 sub-device.
+///
+/// Requires Linux 6.8 or later.
+///
+/// The routes field must point to an array of at least `len_routes` [`v4l2_subdev_route`].
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the given file descriptor, `ENOSPC` if the routes
+/// array is too small to hold the routing table, or `ENOTTY` if the sub-device or the kernel
+/// doesn't support routing.
+pub fn v4l2_ioctl_subdev_g_routing(
+    fd: BorrowedFd<'_>,
+    mut routing: v4l2_subdev_routing,
+) -> io::Result<v4l2_subdev_routing> {
+    // SAFETY: We checked both the opcode and the type.
+    let ioctl_obj = unsafe {
+        Updater::<V4L2_IOC_SUBDEV_G_ROUTING_OPCODE, v4l2_subdev_routing>::new(&mut routing)
+    };
+
+    // SAFETY: This function is unsafe because the driver isn't guaranteed to implement the ioctl
+    // properly. We don't have much of a choice and still have to trust the
+    // kernel there.
+    unsafe { ioctl(fd, ioctl_obj) }
+        .map(|()| routing)
+        .map_err(<Errno as Into<io::Error>>::into)
+}
+
+const V4L2_IOC_SUBDEV_S_ROUTING_OPCODE: u32 =
+    opcode::read_write::<v4l2_subdev_routing>(V4L2_IOC_MAGIC, V4L2_IOC_SUBDEV_S_ROUTING);
+
+/// Sets the routing table of a v4l2 sub-device.
+///
+/// Requires Linux 6.8 or later.
+///
+/// The routes field must point to an array of at least `len_routes` [`v4l2_subdev_route`], with
+/// the first `num_routes` ones initialized.
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the given file descriptor
+pub fn v4l2_ioctl_subdev_s_routing(
+    fd: BorrowedFd<'_>,
+    mut routing: v4l2_subdev_routing,
+) -> io::Result<v4l2_subdev_routing> {
+    // SAFETY: We checked both the opcode and the type.
+    let ioctl_obj = unsafe {
+        Updater::<V4L2_IOC_SUBDEV_S_ROUTING_OPCODE, v4l2_subdev_routing>::new(&mut routing)
+    };
+
+    // SAFETY: This function is unsafe because the driver isn't guaranteed to implement the ioctl
+    // properly. We don't have much of a choice and still have to trust the
+    // kernel there.
+    unsafe { ioctl(fd, ioctl_obj) }
+        .map(|()| routing)
+        .map_err(<Errno as Into<io::Error>>::into)
+}
+
+const V4L2_IOC_SUBDEV_G_CLIENT_CAP_OPCODE: u32 =
+    opcode::read::<v4l2_subdev_client_capability>(V4L2_IOC_MAGIC, V4L2_IOC_SUBDEV_G_CLIENT_CAP);
+
+/// Retrieves the capabilities of the client of a v4l2 sub-device.
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the given file descriptor
+pub fn v4l2_ioctl_subdev_g_client_cap(
+    fd: BorrowedFd<'_>,
+) -> io::Result<v4l2_subdev_client_capability> {
+    // SAFETY: We checked both the opcode and the type.
+    let ioctl_obj = unsafe {
+        Getter::<V4L2_IOC_SUBDEV_G_CLIENT_CAP_OPCODE, v4l2_subdev_client_capability>::new()
+    };
+
+    // SAFETY: This function is unsafe because the driver isn't guaranteed to implement the ioctl
+    // properly. We don't have much of a choice and still have to trust the
+    // kernel there.
+    unsafe { ioctl(fd, ioctl_obj) }.map_err(<Errno as Into<io::Error>>::into)
+}
+
+const V4L2_IOC_SUBDEV_S_CLIENT_CAP_OPCODE: u32 = opcode::read_write::<v4l2_subdev_client_capability>(
+    V4L2_IOC_MAGIC,
+    V4L2_IOC_SUBDEV_S_CLIENT_CAP,
+);
+
+/// Sets the capabilities of the client of a v4l2 sub-device.
+///
+/// The kernel will clear the capabilities it doesn't support, and return the capabilities
+/// actually enabled.
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the given file descriptor
+pub fn v4l2_ioctl_subdev_s_client_cap(
+    fd: BorrowedFd<'_>,
+    mut cap: v4l2_subdev_client_capability,
+) -> io::Result<v4l2_subdev_client_capability> {
+    // SAFETY: We checked both the opcode and the type.
+    let ioctl_obj = unsafe {
+        Updater::<V4L2_IOC_SUBDEV_S_CLIENT_CAP_OPCODE, v4l2_subdev_client_capability>::new(&mut cap)
+    };
+
+    // SAFETY: This function is unsafe because the driver isn't guaranteed to implement the ioctl
+    // properly. We don't have much of a choice and still have to trust the
+    // kernel there.
+    unsafe { ioctl(fd, ioctl_obj) }
+        .map(|()| cap)
+        .map_err(<Errno as Into<io::Error>>::into)
+}
+
 const V4L2_IOC_REQBUFS_OPCODE: u32 =
     opcode::read_write::<v4l2_requestbuffers>(V4L2_IOC_MAGIC, V4L2_IOC_REQBUFS);
 
@@ -520,6 +893,25 @@ pub fn v4l2_ioctl_streamoff(fd: BorrowedFd<'_>, buf_kind: u32) -> io::Result<()>
     unsafe { ioctl(fd, ioctl_obj) }.map_err(<Errno as Into<io::Error>>::into)
 }
 
+const V4L2_IOC_LOG_STATUS_OPCODE: u32 = opcode::none(V4L2_IOC_MAGIC, V4L2_IOC_LOG_STATUS);
+
+/// Asks the driver to log its status to the kernel log
+///
+/// Works on both v4l2 devices and sub-devices.
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the file descriptor.
+pub fn v4l2_ioctl_log_status(fd: BorrowedFd<'_>) -> io::Result<()> {
+    // SAFETY: We checked the opcode, and this ioctl doesn't take any argument.
+    let ioctl_obj = unsafe { NoArg::<V4L2_IOC_LOG_STATUS_OPCODE>::new() };
+
+    // SAFETY: This function is unsafe because the driver isn't guaranteed to implement the ioctl
+    // properly. We don't have much of a choice and still have to trust the
+    // kernel there.
+    unsafe { ioctl(fd, ioctl_obj) }.map_err(<Errno as Into<io::Error>>::into)
+}
+
 const V4L2_IOC_S_EDID_OPCODE: u32 =
     opcode::read_write::<v4l2_edid>(V4L2_IOC_MAGIC, V4L2_IOC_S_EDID);
 const V4L2_IOC_SUBDEV_S_EDID_OPCODE: u32 =
@@ -740,3 +1132,67 @@ pub fn v4l2_ioctl_subdev_query_dv_timings(
 ) -> io::Result<v4l2_dv_timings> {
     v4l2_ioctl_update_dv_timings::<V4L2_IOC_SUBDEV_QUERY_DV_TIMINGS_OPCODE>(fd, timings)
 }
+
+const V4L2_IOC_G_EXT_CTRLS_OPCODE: u32 =
+    opcode::read_write::<v4l2_ext_controls>(V4L2_IOC_MAGIC, V4L2_IOC_G_EXT_CTRLS);
+const V4L2_IOC_S_EXT_CTRLS_OPCODE: u32 =
+    opcode::read_write::<v4l2_ext_controls>(V4L2_IOC_MAGIC, V4L2_IOC_S_EXT_CTRLS);
+const V4L2_IOC_TRY_EXT_CTRLS_OPCODE: u32 =
+    opcode::read_write::<v4l2_ext_controls>(V4L2_IOC_MAGIC, V4L2_IOC_TRY_EXT_CTRLS);
+
+fn v4l2_ioctl_ext_ctrls_inner<const OPCODE: Opcode>(
+    fd: BorrowedFd<'_>,
+    mut ctrls: v4l2_ext_controls,
+) -> io::Result<v4l2_ext_controls> {
+    // SAFETY: We checked both the opcode and the type.
+    let ioctl_obj = unsafe { Updater::<OPCODE, v4l2_ext_controls>::new(&mut ctrls) };
+
+    // SAFETY: This function is unsafe because the driver isn't guaranteed to implement the ioctl
+    // properly. We don't have much of a choice and still have to trust the
+    // kernel there.
+    unsafe { ioctl(fd, ioctl_obj) }
+        .map(|()| ctrls)
+        .map_err(<Errno as Into<io::Error>>::into)
+}
+
+/// Retrieves the value of several controls, either current or part of a request
+///
+/// The controls field must point to an array of at least `count` [`v4l2_ext_control`].
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the given file descriptor
+pub fn v4l2_ioctl_g_ext_ctrls(
+    fd: BorrowedFd<'_>,
+    ctrls: v4l2_ext_controls,
+) -> io::Result<v4l2_ext_controls> {
+    v4l2_ioctl_ext_ctrls_inner::<V4L2_IOC_G_EXT_CTRLS_OPCODE>(fd, ctrls)
+}
+
+/// Sets the value of several controls atomically, either right away or as part of a request
+///
+/// The controls field must point to an array of at least `count` [`v4l2_ext_control`].
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the given file descriptor
+pub fn v4l2_ioctl_s_ext_ctrls(
+    fd: BorrowedFd<'_>,
+    ctrls: v4l2_ext_controls,
+) -> io::Result<v4l2_ext_controls> {
+    v4l2_ioctl_ext_ctrls_inner::<V4L2_IOC_S_EXT_CTRLS_OPCODE>(fd, ctrls)
+}
+
+/// Checks whether the value of several controls would be accepted, without changing them
+///
+/// The controls field must point to an array of at least `count` [`v4l2_ext_control`].
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the given file descriptor
+pub fn v4l2_ioctl_try_ext_ctrls(
+    fd: BorrowedFd<'_>,
+    ctrls: v4l2_ext_controls,
+) -> io::Result<v4l2_ext_controls> {
+    v4l2_ioctl_ext_ctrls_inner::<V4L2_IOC_TRY_EXT_CTRLS_OPCODE>(fd, ctrls)
+}
@@ -1,9 +1,12 @@
 use core::fmt;
-use std::{io, os::fd::BorrowedFd};
+use std::{
+    io,
+    os::fd::{AsRawFd as _, BorrowedFd},
+};
 
 use linux_raw::KernelVersion;
-use rustix::time::Timespec;
-use tracing::instrument;
+use rustix::{io::Errno, time::Timespec};
+use tracing::{debug, instrument};
 
 use crate::{
     ConversionError,
@@ -13,7 +16,10 @@ use crate::{
         V4L2_EVENT_CTRL_CH_FLAGS, V4L2_EVENT_CTRL_CH_RANGE, V4L2_EVENT_CTRL_CH_VALUE,
         V4L2_EVENT_EOS, V4L2_EVENT_FRAME_SYNC, V4L2_EVENT_MD_FL_HAVE_FRAME_SEQ,
         V4L2_EVENT_MOTION_DET, V4L2_EVENT_SOURCE_CHANGE, V4L2_EVENT_SRC_CH_RESOLUTION,
-        V4L2_EVENT_VSYNC, v4l2_frmsize_discrete, v4l2_frmsize_stepwise, v4l2_frmsizetypes,
+        V4L2_EVENT_VSYNC, V4L2_SEL_TGT_COMPOSE, V4L2_SEL_TGT_COMPOSE_BOUNDS,
+        V4L2_SEL_TGT_COMPOSE_DEFAULT, V4L2_SEL_TGT_COMPOSE_PADDED, V4L2_SEL_TGT_CROP,
+        V4L2_SEL_TGT_CROP_BOUNDS, V4L2_SEL_TGT_CROP_DEFAULT, V4L2_SEL_TGT_NATIVE_SIZE,
+        v4l2_frmsize_discrete, v4l2_frmsize_stepwise, v4l2_frmsizetypes,
     },
     v4l2_buf_type, v4l2_colorspace, v4l2_field, v4l2_hsv_encoding, v4l2_memory, v4l2_quantization,
     v4l2_xfer_func, v4l2_ycbcr_encoding,
@@ -871,6 +877,126 @@ pub struct v4l2_mbus_framefmt {
     _reserved: [u16; 10],
 }
 
+impl v4l2_mbus_framefmt {
+    /// Returns the frame width
+    #[must_use]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns the frame height
+    #[must_use]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the media bus code
+    #[must_use]
+    pub fn code(&self) -> media_bus_fmt {
+        self.code
+    }
+
+    /// Sets the media bus code
+    #[must_use]
+    pub fn set_code(mut self, code: media_bus_fmt) -> Self {
+        self.code = code;
+        self
+    }
+
+    /// Returns the field order
+    #[must_use]
+    pub fn field(&self) -> v4l2_field {
+        self.field
+    }
+
+    /// Returns the colorspace
+    #[must_use]
+    pub fn colorspace(&self) -> v4l2_colorspace {
+        self.colorspace
+    }
+
+    /// Returns the colorspace encoding
+    #[must_use]
+    #[expect(
+        clippy::missing_panics_doc,
+        reason = "We can't construct the structure with an invalid encoding. It can't panic."
+    )]
+    pub fn encoding(&self) -> v4l2_encoding {
+        v4l2_encoding::try_from(u32::from(self.encoding)).expect("encoding cannot be invalid.")
+    }
+
+    /// Returns the quantization range
+    #[must_use]
+    #[expect(
+        clippy::missing_panics_doc,
+        reason = "We can't construct the structure with an invalid quantization. It can't panic."
+    )]
+    pub fn quantization(&self) -> v4l2_quantization {
+        v4l2_quantization::try_from(u32::from(self.quantization))
+            .expect("quantization cannot be invalid.")
+    }
+
+    /// Returns the transfer function
+    #[must_use]
+    #[expect(
+        clippy::missing_panics_doc,
+        reason = "We can't construct the structure with an invalid transfer function. It can't panic."
+    )]
+    pub fn xfer_func(&self) -> v4l2_xfer_func {
+        v4l2_xfer_func::try_from(u32::from(self.xfer_func))
+            .expect("transfer function cannot be invalid.")
+    }
+
+    /// Sets the frame width
+    #[must_use]
+    pub fn set_width(mut self, width: u32) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Sets the frame height
+    #[must_use]
+    pub fn set_height(mut self, height: u32) -> Self {
+        self.height = height;
+        self
+    }
+
+    /// Sets the field order
+    #[must_use]
+    pub fn set_field(mut self, field: v4l2_field) -> Self {
+        self.field = field;
+        self
+    }
+
+    /// Sets the colorspace
+    #[must_use]
+    pub fn set_colorspace(mut self, colorspace: v4l2_colorspace) -> Self {
+        self.colorspace = colorspace;
+        self
+    }
+
+    /// Sets the colorspace encoding
+    #[must_use]
+    pub fn set_encoding(mut self, enc: v4l2_encoding) -> Self {
+        self.encoding = enc.into();
+        self
+    }
+
+    /// Sets the quantization range
+    #[must_use]
+    pub fn set_quantization(mut self, quant: v4l2_quantization) -> Self {
+        self.quantization = quant.into();
+        self
+    }
+
+    /// Sets the transfer function
+    #[must_use]
+    pub fn set_xfer_func(mut self, func: v4l2_xfer_func) -> Self {
+        self.xfer_func = func.into();
+        self
+    }
+}
+
 impl TryFrom<raw::v4l2_mbus_framefmt> for v4l2_mbus_framefmt {
     type Error = ConversionError;
 
@@ -1051,6 +1177,33 @@ mod tests_v4l2_mbus_framefmt {
             std::mem::offset_of!(raw::v4l2_mbus_framefmt, reserved)
         );
     }
+
+    #[test]
+    fn colorimetry() {
+        let fmt = wrapper::v4l2_mbus_framefmt::default()
+            .set_field(raw::v4l2_field::V4L2_FIELD_NONE)
+            .set_colorspace(raw::v4l2_colorspace::V4L2_COLORSPACE_REC709)
+            .set_encoding(wrapper::v4l2_encoding::YCbCr(
+                raw::v4l2_ycbcr_encoding::V4L2_YCBCR_ENC_709,
+            ))
+            .set_quantization(raw::v4l2_quantization::V4L2_QUANTIZATION_LIM_RANGE)
+            .set_xfer_func(raw::v4l2_xfer_func::V4L2_XFER_FUNC_709);
+
+        assert_eq!(fmt.field(), raw::v4l2_field::V4L2_FIELD_NONE);
+        assert_eq!(
+            fmt.colorspace(),
+            raw::v4l2_colorspace::V4L2_COLORSPACE_REC709
+        );
+        assert_eq!(
+            fmt.encoding(),
+            wrapper::v4l2_encoding::YCbCr(raw::v4l2_ycbcr_encoding::V4L2_YCBCR_ENC_709)
+        );
+        assert_eq!(
+            fmt.quantization(),
+            raw::v4l2_quantization::V4L2_QUANTIZATION_LIM_RANGE
+        );
+        assert_eq!(fmt.xfer_func(), raw::v4l2_xfer_func::V4L2_XFER_FUNC_709);
+    }
 }
 
 /// Sub-device Frame Format
@@ -1089,6 +1242,24 @@ impl v4l2_subdev_format {
         }
     }
 
+    /// Returns the mediabus frame format
+    #[must_use]
+    pub fn format(&self) -> v4l2_mbus_framefmt {
+        self.format
+    }
+
+    /// Returns the sub-device pad this format applies to.
+    #[must_use]
+    pub fn pad(&self) -> u32 {
+        self.pad
+    }
+
+    /// Returns the sub-device stream this format applies to.
+    #[must_use]
+    pub fn stream(&self) -> u32 {
+        self.stream
+    }
+
     /// Sets the mediabus frame format
     #[must_use]
     pub fn set_format(mut self, fmt: v4l2_mbus_framefmt) -> Self {
@@ -1207,6 +1378,27 @@ mod tests_v4l2_subdev_format {
     }
 }
 
+/// Retrieves the format of the pad and stream set in `fmt` on the given sub-device file
+/// descriptor.
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the file descriptor.
+///
+/// # Panics
+///
+/// If the kernel returns an unexpected value
+#[instrument(level = "trace")]
+pub fn v4l2_ioctl_subdev_g_fmt(
+    fd: BorrowedFd<'_>,
+    fmt: v4l2_subdev_format,
+) -> io::Result<v4l2_subdev_format> {
+    raw::v4l2_ioctl_subdev_g_fmt(fd, fmt.into()).map(|f| {
+        f.try_into()
+            .expect("The kernel returned an unexpected type")
+    })
+}
+
 /// Sets the given format on the given sub-device file descriptor.
 ///
 /// # Errors
@@ -1227,137 +1419,891 @@ pub fn v4l2_ioctl_subdev_s_fmt(
     })
 }
 
-/// Reqbufs main structure
-#[repr(C)]
-#[derive(Clone, Copy)]
-pub struct v4l2_requestbuffers {
-    count: u32,
-    kind: v4l2_buf_type,
-    memory: v4l2_memory,
-    caps: u32,
-    flags: u8,
-    _reserved: [u8; 3],
+/// Enumerates the media bus codes supported by a sub-device pad.
+///
+/// `stream` must be 0 unless the streams API has been enabled through
+/// [`v4l2_subdev_enable_streams`]. The codes we don't know about are ignored.
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the file descriptor.
+#[instrument(level = "trace")]
+pub fn v4l2_subdev_enum_mbus_codes(
+    fd: BorrowedFd<'_>,
+    which: raw::v4l2_subdev_format_whence,
+    pad: u32,
+    stream: u32,
+) -> io::Result<Vec<media_bus_fmt>> {
+    let mut codes = Vec::new();
+
+    for index in 0.. {
+        let arg = raw::v4l2_subdev_mbus_code_enum {
+            pad,
+            index,
+            which: which as u32,
+            stream,
+            ..Default::default()
+        };
+
+        let code = match raw::v4l2_ioctl_subdev_enum_mbus_code(fd, arg) {
+            Ok(code) => code.code,
+            Err(e) if Errno::from_io_error(&e) == Some(Errno::INVAL) => break,
+            Err(e) => return Err(e),
+        };
+
+        match media_bus_fmt::try_from(code) {
+            Ok(fmt) => codes.push(fmt),
+            Err(_e) => debug!("Ignoring unknown media bus code {code:#x}"),
+        }
+    }
+
+    Ok(codes)
 }
 
-impl v4l2_requestbuffers {
-    /// Creates a new structure
+/// Range of Frame Sizes supported by a sub-device pad for a given media bus code
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct v4l2_subdev_frame_size {
+    min_width: u32,
+    max_width: u32,
+    min_height: u32,
+    max_height: u32,
+}
+
+impl v4l2_subdev_frame_size {
+    /// Returns the minimum frame width
     #[must_use]
-    pub fn new(kind: v4l2_buf_type, memory: v4l2_memory) -> Self {
-        Self {
-            count: 0,
-            kind,
-            memory,
-            caps: 0,
-            flags: 0,
-            _reserved: [0; 3],
-        }
+    pub fn min_width(&self) -> u32 {
+        self.min_width
     }
 
-    /// Sets the buffer count
+    /// Returns the maximum frame width
     #[must_use]
-    pub fn set_count(mut self, count: u32) -> Self {
-        self.count = count;
-        self
+    pub fn max_width(&self) -> u32 {
+        self.max_width
     }
-}
 
-impl fmt::Debug for v4l2_requestbuffers {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("v4l2_requestbuffers")
-            .field("count", &self.count)
-            .field("type", &self.kind)
-            .field("memory", &self.memory)
-            .field("capabilities", &self.caps)
-            .field("flags", &self.flags)
-            .finish_non_exhaustive()
+    /// Returns the minimum frame height
+    #[must_use]
+    pub fn min_height(&self) -> u32 {
+        self.min_height
     }
-}
 
-impl TryFrom<raw::v4l2_requestbuffers> for v4l2_requestbuffers {
-    type Error = ConversionError;
+    /// Returns the maximum frame height
+    #[must_use]
+    pub fn max_height(&self) -> u32 {
+        self.max_height
+    }
 
-    fn try_from(value: raw::v4l2_requestbuffers) -> Result<Self, Self::Error> {
-        Ok(Self {
-            count: value.count,
-            kind: v4l2_buf_type::try_from(value.type_)?,
-            memory: v4l2_memory::try_from(value.memory)?,
-            caps: value.capabilities,
-            flags: value.flags,
-            _reserved: [0; 3],
-        })
+    /// Returns whether a frame of the given size fits in that range
+    #[must_use]
+    pub fn contains(&self, width: u32, height: u32) -> bool {
+        (self.min_width..=self.max_width).contains(&width)
+            && (self.min_height..=self.max_height).contains(&height)
     }
 }
 
-impl From<v4l2_requestbuffers> for raw::v4l2_requestbuffers {
-    fn from(value: v4l2_requestbuffers) -> Self {
-        // SAFETY: We know from Rust layout rules and our tests that the layouts between the two
-        // structures are identical. We also know that all the fields in the Rust union are in a
-        // valid state. We can safely transmute.
-        unsafe { core::mem::transmute::<v4l2_requestbuffers, Self>(value) }
+impl From<raw::v4l2_subdev_frame_size_enum> for v4l2_subdev_frame_size {
+    fn from(value: raw::v4l2_subdev_frame_size_enum) -> Self {
+        Self {
+            min_width: value.min_width,
+            max_width: value.max_width,
+            min_height: value.min_height,
+            max_height: value.max_height,
+        }
+    }
+}
+
+impl fmt::Display for v4l2_subdev_frame_size {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.min_width == self.max_width && self.min_height == self.max_height {
+            f.write_fmt(format_args!("{}x{}", self.min_width, self.min_height))
+        } else {
+            f.write_fmt(format_args!(
+                "{}x{} - {}x{}",
+                self.min_width, self.min_height, self.max_width, self.max_height
+            ))
+        }
     }
 }
 
 #[cfg(test)]
-mod tests_v4l2_requestbuffers {
+mod tests_v4l2_subdev_frame_size {
     use crate::{raw, wrapper};
 
     #[test]
-    fn layout() {
-        assert_eq!(
-            size_of::<wrapper::v4l2_requestbuffers>(),
-            size_of::<raw::v4l2_requestbuffers>()
-        );
-
-        assert_eq!(
-            align_of::<wrapper::v4l2_requestbuffers>(),
-            align_of::<raw::v4l2_requestbuffers>()
-        );
-
-        assert_eq!(
-            std::mem::offset_of!(wrapper::v4l2_requestbuffers, count),
-            std::mem::offset_of!(raw::v4l2_requestbuffers, count)
-        );
-
-        assert_eq!(
-            std::mem::offset_of!(wrapper::v4l2_requestbuffers, kind),
-            std::mem::offset_of!(raw::v4l2_requestbuffers, type_)
-        );
-
-        assert_eq!(
-            std::mem::offset_of!(wrapper::v4l2_requestbuffers, memory),
-            std::mem::offset_of!(raw::v4l2_requestbuffers, memory)
-        );
+    fn contains() {
+        let size = wrapper::v4l2_subdev_frame_size::from(raw::v4l2_subdev_frame_size_enum {
+            min_width: 16,
+            max_width: 4096,
+            min_height: 16,
+            max_height: 2160,
+            ..Default::default()
+        });
+
+        assert!(size.contains(1920, 1080));
+        assert!(size.contains(4096, 16));
+        assert!(!size.contains(8, 1080));
+        assert!(!size.contains(1920, 4096));
+        assert_eq!(size.to_string(), "16x16 - 4096x2160");
+    }
 
-        assert_eq!(
-            std::mem::offset_of!(wrapper::v4l2_requestbuffers, caps),
-            std::mem::offset_of!(raw::v4l2_requestbuffers, capabilities)
-        );
+    #[test]
+    fn layout() {
+        assert_eq!(size_of::<raw::v4l2_subdev_mbus_code_enum>(), 48);
+        assert_eq!(size_of::<raw::v4l2_subdev_frame_size_enum>(), 64);
 
         assert_eq!(
-            std::mem::offset_of!(wrapper::v4l2_requestbuffers, flags),
-            std::mem::offset_of!(raw::v4l2_requestbuffers, flags)
+            std::mem::offset_of!(raw::v4l2_subdev_mbus_code_enum, stream),
+            20
         );
 
         assert_eq!(
-            std::mem::offset_of!(wrapper::v4l2_requestbuffers, _reserved),
-            std::mem::offset_of!(raw::v4l2_requestbuffers, reserved)
+            std::mem::offset_of!(raw::v4l2_subdev_frame_size_enum, stream),
+            32
         );
     }
 }
 
-/// Allocates Device Buffers.
-///
-/// # Errors
+/// Enumerates the frame sizes supported by a sub-device pad for a given media bus code.
 ///
-/// If there's an I/O Error while accessing the given file descriptor
+/// `stream` must be 0 unless the streams API has been enabled through
+/// [`v4l2_subdev_enable_streams`].
 ///
-/// # Panics
+/// # Errors
 ///
-/// If the kernel returns an unexpected value
+/// If there's an I/O Error while accessing the file descriptor.
 #[instrument(level = "trace")]
-pub fn v4l2_ioctl_reqbufs(
+pub fn v4l2_subdev_enum_frame_sizes(
     fd: BorrowedFd<'_>,
-    reqbufs: v4l2_requestbuffers,
+    which: raw::v4l2_subdev_format_whence,
+    pad: u32,
+    stream: u32,
+    code: media_bus_fmt,
+) -> io::Result<Vec<v4l2_subdev_frame_size>> {
+    let mut sizes = Vec::new();
+
+    for index in 0.. {
+        let arg = raw::v4l2_subdev_frame_size_enum {
+            index,
+            pad,
+            code: code as u32,
+            which: which as u32,
+            stream,
+            ..Default::default()
+        };
+
+        match raw::v4l2_ioctl_subdev_enum_frame_size(fd, arg) {
+            Ok(size) => sizes.push(size.into()),
+            Err(e) if Errno::from_io_error(&e) == Some(Errno::INVAL) => break,
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(sizes)
+}
+
+/// Selection Target
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum v4l2_sel_target {
+    /// Current Cropping Area
+    Crop = V4L2_SEL_TGT_CROP,
+
+    /// Suggested Cropping Area
+    CropDefault = V4L2_SEL_TGT_CROP_DEFAULT,
+
+    /// Bounds of the Cropping Area
+    CropBounds = V4L2_SEL_TGT_CROP_BOUNDS,
+
+    /// Native Size of the Device
+    NativeSize = V4L2_SEL_TGT_NATIVE_SIZE,
+
+    /// Current Composing Area
+    Compose = V4L2_SEL_TGT_COMPOSE,
+
+    /// Suggested Composing Area
+    ComposeDefault = V4L2_SEL_TGT_COMPOSE_DEFAULT,
+
+    /// Bounds of the Composing Area
+    ComposeBounds = V4L2_SEL_TGT_COMPOSE_BOUNDS,
+
+    /// Composing Area Modified by the Hardware
+    ComposePadded = V4L2_SEL_TGT_COMPOSE_PADDED,
+}
+
+impl TryFrom<u32> for v4l2_sel_target {
+    type Error = ConversionError;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        Ok(match value {
+            V4L2_SEL_TGT_CROP => Self::Crop,
+            V4L2_SEL_TGT_CROP_DEFAULT => Self::CropDefault,
+            V4L2_SEL_TGT_CROP_BOUNDS => Self::CropBounds,
+            V4L2_SEL_TGT_NATIVE_SIZE => Self::NativeSize,
+            V4L2_SEL_TGT_COMPOSE => Self::Compose,
+            V4L2_SEL_TGT_COMPOSE_DEFAULT => Self::ComposeDefault,
+            V4L2_SEL_TGT_COMPOSE_BOUNDS => Self::ComposeBounds,
+            V4L2_SEL_TGT_COMPOSE_PADDED => Self::ComposePadded,
+            _ => return Err(Self::Error::InvalidValue(format!("{value}"))),
+        })
+    }
+}
+
+impl fmt::Display for v4l2_sel_target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Crop => "crop",
+            Self::CropDefault => "crop default",
+            Self::CropBounds => "crop bounds",
+            Self::NativeSize => "native size",
+            Self::Compose => "compose",
+            Self::ComposeDefault => "compose default",
+            Self::ComposeBounds => "compose bounds",
+            Self::ComposePadded => "compose padded",
+        })
+    }
+}
+
+/// Sub-device Selection Rectangle
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct v4l2_subdev_selection {
+    which: raw::v4l2_subdev_format_whence,
+    pad: u32,
+    target: v4l2_sel_target,
+    flags: u32,
+    rect: raw::v4l2_rect,
+    stream: u32,
+    _reserved: [u32; 7],
+}
+
+impl v4l2_subdev_selection {
+    /// Creates a new [`v4l2_subdev_selection`] to apply to the hardware
+    #[must_use]
+    pub fn new_active(target: v4l2_sel_target) -> Self {
+        Self {
+            which: raw::v4l2_subdev_format_whence::V4L2_SUBDEV_FORMAT_ACTIVE,
+            pad: 0,
+            target,
+            flags: 0,
+            rect: raw::v4l2_rect::default(),
+            stream: 0,
+            _reserved: [0; 7],
+        }
+    }
+
+    /// Creates a new [`v4l2_subdev_selection`] structure to try on the sub-device
+    #[must_use]
+    pub fn new_try(target: v4l2_sel_target) -> Self {
+        Self {
+            which: raw::v4l2_subdev_format_whence::V4L2_SUBDEV_FORMAT_TRY,
+            ..Self::new_active(target)
+        }
+    }
+
+    /// Returns the selection target
+    #[must_use]
+    pub fn target(&self) -> v4l2_sel_target {
+        self.target
+    }
+
+    /// Returns the selection rectangle
+    #[must_use]
+    pub fn rect(&self) -> raw::v4l2_rect {
+        self.rect
+    }
+
+    /// Sets the selection rectangle
+    #[must_use]
+    pub fn set_rect(mut self, rect: raw::v4l2_rect) -> Self {
+        self.rect = rect;
+        self
+    }
+
+    /// Sets the selection flags, ie. a combination of `V4L2_SEL_FLAG_*`
+    #[must_use]
+    pub fn set_flags(mut self, flags: u32) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Sets the sub-device pad this selection applies to.
+    #[must_use]
+    pub fn set_pad(mut self, pad: u32) -> Self {
+        self.pad = pad;
+        self
+    }
+
+    /// Sets the sub-device stream this selection applies to.
+    ///
+    /// The streams API needs to be enabled first through [`v4l2_subdev_enable_streams`].
+    #[must_use]
+    pub fn set_stream(mut self, stream: u32) -> Self {
+        self.stream = stream;
+        self
+    }
+}
+
+impl TryFrom<raw::v4l2_subdev_selection> for v4l2_subdev_selection {
+    type Error = ConversionError;
+
+    fn try_from(value: raw::v4l2_subdev_selection) -> Result<Self, Self::Error> {
+        Ok(Self {
+            which: raw::v4l2_subdev_format_whence::try_from(value.which)?,
+            pad: value.pad,
+            target: v4l2_sel_target::try_from(value.target)?,
+            flags: value.flags,
+            rect: value.r,
+            stream: value.stream,
+            _reserved: [0; 7],
+        })
+    }
+}
+
+impl From<v4l2_subdev_selection> for raw::v4l2_subdev_selection {
+    fn from(value: v4l2_subdev_selection) -> Self {
+        // SAFETY: We know from Rust layout rules and our tests that the layouts between the two
+        // structures are identical. We can safely transmute.
+        unsafe { core::mem::transmute::<v4l2_subdev_selection, Self>(value) }
+    }
+}
+
+impl fmt::Display for v4l2_subdev_selection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!(
+            "pad: {}, stream: {}, which: {}, {}: ({},{})/{}x{}",
+            self.pad,
+            self.stream,
+            self.which,
+            self.target,
+            self.rect.left,
+            self.rect.top,
+            self.rect.width,
+            self.rect.height
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests_v4l2_subdev_selection {
+    use crate::{raw, wrapper};
+
+    #[test]
+    fn layout() {
+        assert_eq!(
+            size_of::<wrapper::v4l2_subdev_selection>(),
+            size_of::<raw::v4l2_subdev_selection>()
+        );
+
+        assert_eq!(
+            align_of::<wrapper::v4l2_subdev_selection>(),
+            align_of::<raw::v4l2_subdev_selection>()
+        );
+
+        assert_eq!(
+            std::mem::offset_of!(wrapper::v4l2_subdev_selection, which),
+            std::mem::offset_of!(raw::v4l2_subdev_selection, which)
+        );
+
+        assert_eq!(
+            std::mem::offset_of!(wrapper::v4l2_subdev_selection, pad),
+            std::mem::offset_of!(raw::v4l2_subdev_selection, pad)
+        );
+
+        assert_eq!(
+            std::mem::offset_of!(wrapper::v4l2_subdev_selection, target),
+            std::mem::offset_of!(raw::v4l2_subdev_selection, target)
+        );
+
+        assert_eq!(
+            std::mem::offset_of!(wrapper::v4l2_subdev_selection, flags),
+            std::mem::offset_of!(raw::v4l2_subdev_selection, flags)
+        );
+
+        assert_eq!(
+            std::mem::offset_of!(wrapper::v4l2_subdev_selection, rect),
+            std::mem::offset_of!(raw::v4l2_subdev_selection, r)
+        );
+
+        assert_eq!(
+            std::mem::offset_of!(wrapper::v4l2_subdev_selection, stream),
+            std::mem::offset_of!(raw::v4l2_subdev_selection, stream)
+        );
+
+        assert_eq!(
+            std::mem::offset_of!(wrapper::v4l2_subdev_selection, _reserved),
+            std::mem::offset_of!(raw::v4l2_subdev_selection, reserved)
+        );
+    }
+
+    #[test]
+    fn target() {
+        assert_eq!(
+            wrapper::v4l2_sel_target::try_from(raw::V4L2_SEL_TGT_COMPOSE).ok(),
+            Some(wrapper::v4l2_sel_target::Compose)
+        );
+        assert!(wrapper::v4l2_sel_target::try_from(0x4242).is_err());
+    }
+}
+
+/// Retrieves the selection rectangle of the pad, stream and target set in `sel` on the given
+/// sub-device file descriptor.
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the file descriptor, or if the sub-device doesn't
+/// support that target.
+///
+/// # Panics
+///
+/// If the kernel returns an unexpected value
+#[instrument(level = "trace")]
+pub fn v4l2_ioctl_subdev_g_selection(
+    fd: BorrowedFd<'_>,
+    sel: v4l2_subdev_selection,
+) -> io::Result<v4l2_subdev_selection> {
+    raw::v4l2_ioctl_subdev_g_selection(fd, sel.into()).map(|s| {
+        s.try_into()
+            .expect("The kernel returned an unexpected type")
+    })
+}
+
+/// Sets the given selection rectangle on the given sub-device file descriptor.
+///
+/// The driver can adjust the rectangle, and the one it ended up using is returned.
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the file descriptor, or if the sub-device doesn't
+/// support that target.
+///
+/// # Panics
+///
+/// If the kernel returns an unexpected value
+#[instrument(level = "trace")]
+pub fn v4l2_ioctl_subdev_s_selection(
+    fd: BorrowedFd<'_>,
+    sel: v4l2_subdev_selection,
+) -> io::Result<v4l2_subdev_selection> {
+    raw::v4l2_ioctl_subdev_s_selection(fd, sel.into()).map(|s| {
+        s.try_into()
+            .expect("The kernel returned an unexpected type")
+    })
+}
+
+/// Sub-device Route, between a stream on a sink pad and a stream on a source pad
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct v4l2_subdev_route {
+    sink_pad: u32,
+    sink_stream: u32,
+    source_pad: u32,
+    source_stream: u32,
+    flags: u32,
+    _reserved: [u32; 5],
+}
+
+impl v4l2_subdev_route {
+    /// Creates a new, active, [`v4l2_subdev_route`]
+    #[must_use]
+    pub fn new(sink_pad: u32, sink_stream: u32, source_pad: u32, source_stream: u32) -> Self {
+        Self {
+            sink_pad,
+            sink_stream,
+            source_pad,
+            source_stream,
+            flags: raw::V4L2_SUBDEV_ROUTE_FL_ACTIVE,
+            _reserved: [0; 5],
+        }
+    }
+
+    /// Returns the sink pad index
+    #[must_use]
+    pub fn sink_pad(&self) -> u32 {
+        self.sink_pad
+    }
+
+    /// Returns the stream on the sink pad
+    #[must_use]
+    pub fn sink_stream(&self) -> u32 {
+        self.sink_stream
+    }
+
+    /// Returns the source pad index
+    #[must_use]
+    pub fn source_pad(&self) -> u32 {
+        self.source_pad
+    }
+
+    /// Returns the stream on the source pad
+    #[must_use]
+    pub fn source_stream(&self) -> u32 {
+        self.source_stream
+    }
+
+    /// Returns whether the route is active
+    #[must_use]
+    pub fn is_active(&self) -> bool {
+        (self.flags & raw::V4L2_SUBDEV_ROUTE_FL_ACTIVE) != 0
+    }
+
+    /// Sets whether the route is active
+    #[must_use]
+    pub fn set_active(mut self, active: bool) -> Self {
+        if active {
+            self.flags |= raw::V4L2_SUBDEV_ROUTE_FL_ACTIVE;
+        } else {
+            self.flags &= !raw::V4L2_SUBDEV_ROUTE_FL_ACTIVE;
+        }
+
+        self
+    }
+}
+
+impl From<raw::v4l2_subdev_route> for v4l2_subdev_route {
+    fn from(value: raw::v4l2_subdev_route) -> Self {
+        Self {
+            sink_pad: value.sink_pad,
+            sink_stream: value.sink_stream,
+            source_pad: value.source_pad,
+            source_stream: value.source_stream,
+            flags: value.flags,
+            _reserved: [0; 5],
+        }
+    }
+}
+
+impl From<v4l2_subdev_route> for raw::v4l2_subdev_route {
+    fn from(value: v4l2_subdev_route) -> Self {
+        Self {
+            sink_pad: value.sink_pad,
+            sink_stream: value.sink_stream,
+            source_pad: value.source_pad,
+            source_stream: value.source_stream,
+            flags: value.flags,
+            reserved: [0; 5],
+        }
+    }
+}
+
+impl fmt::Display for v4l2_subdev_route {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!(
+            "{}/{} -> {}/{} [{}]",
+            self.sink_pad,
+            self.sink_stream,
+            self.source_pad,
+            self.source_stream,
+            if self.is_active() {
+                "ACTIVE"
+            } else {
+                "INACTIVE"
+            }
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests_v4l2_subdev_route {
+    use crate::{raw, wrapper};
+
+    #[test]
+    fn layout() {
+        assert_eq!(
+            size_of::<wrapper::v4l2_subdev_route>(),
+            size_of::<raw::v4l2_subdev_route>()
+        );
+
+        assert_eq!(size_of::<raw::v4l2_subdev_route>(), 40);
+        assert_eq!(size_of::<raw::v4l2_subdev_routing>(), 64);
+        assert_eq!(size_of::<raw::v4l2_subdev_client_capability>(), 8);
+
+        assert_eq!(
+            align_of::<wrapper::v4l2_subdev_route>(),
+            align_of::<raw::v4l2_subdev_route>()
+        );
+
+        assert_eq!(
+            std::mem::offset_of!(wrapper::v4l2_subdev_route, source_stream),
+            std::mem::offset_of!(raw::v4l2_subdev_route, source_stream)
+        );
+
+        assert_eq!(
+            std::mem::offset_of!(wrapper::v4l2_subdev_route, flags),
+            std::mem::offset_of!(raw::v4l2_subdev_route, flags)
+        );
+
+        assert_eq!(std::mem::offset_of!(raw::v4l2_subdev_routing, routes), 8);
+        assert_eq!(
+            std::mem::offset_of!(raw::v4l2_subdev_routing, num_routes),
+            16
+        );
+    }
+
+    #[test]
+    fn active() {
+        let route = wrapper::v4l2_subdev_route::new(0, 0, 1, 2);
+        assert!(route.is_active());
+        assert!(!route.set_active(false).is_active());
+        assert_eq!(route.to_string(), "0/0 -> 1/2 [ACTIVE]");
+    }
+}
+
+// The routing table can't be queried without a buffer, so we start with a reasonable guess and
+// grow it if the kernel tells us it's too small.
+const SUBDEV_ROUTES_INITIAL_LEN: usize = 8;
+const SUBDEV_ROUTES_MAX_LEN: usize = 1024;
+
+/// Retrieves the routing table of a sub-device.
+///
+/// The streams API needs to be enabled first through [`v4l2_ioctl_subdev_s_client_cap`]. This
+/// requires Linux 6.8 or later, older kernels don't support the routing table layout we use.
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the file descriptor, or if the sub-device or the
+/// kernel doesn't support routing.
+#[instrument(level = "trace")]
+pub fn v4l2_ioctl_subdev_g_routing(
+    fd: BorrowedFd<'_>,
+    which: raw::v4l2_subdev_format_whence,
+) -> io::Result<Vec<v4l2_subdev_route>> {
+    let mut len = SUBDEV_ROUTES_INITIAL_LEN;
+
+    loop {
+        let mut routes = vec![raw::v4l2_subdev_route::default(); len];
+
+        let arg = raw::v4l2_subdev_routing {
+            which: which as u32,
+            len_routes: u32::try_from(len).map_err(|_e| Errno::NOSPC)?,
+            routes: routes.as_mut_ptr() as u64,
+            ..Default::default()
+        };
+
+        match raw::v4l2_ioctl_subdev_g_routing(fd, arg) {
+            Ok(routing) => {
+                routes.truncate(routing.num_routes as usize);
+
+                return Ok(routes.into_iter().map(Into::into).collect());
+            }
+            Err(e) if Errno::from_io_error(&e) == Some(Errno::NOSPC) => {
+                if len >= SUBDEV_ROUTES_MAX_LEN {
+                    return Err(e);
+                }
+
+                len *= 2;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Sets the routing table of a sub-device, and returns the routing table it ended up with.
+///
+/// The streams API needs to be enabled first through [`v4l2_ioctl_subdev_s_client_cap`].
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the file descriptor, or if the sub-device doesn't
+/// support that routing table.
+#[instrument(level = "trace")]
+pub fn v4l2_ioctl_subdev_s_routing(
+    fd: BorrowedFd<'_>,
+    which: raw::v4l2_subdev_format_whence,
+    routes: &[v4l2_subdev_route],
+) -> io::Result<Vec<v4l2_subdev_route>> {
+    // Linux 6.8 and later return the routing table the driver has applied, so leave some room
+    // for it.
+    let len = routes.len().max(SUBDEV_ROUTES_INITIAL_LEN);
+    let mut raw_routes = routes
+        .iter()
+        .copied()
+        .map(raw::v4l2_subdev_route::from)
+        .collect::<Vec<_>>();
+    raw_routes.resize(len, raw::v4l2_subdev_route::default());
+
+    let arg = raw::v4l2_subdev_routing {
+        which: which as u32,
+        len_routes: u32::try_from(len).map_err(|_e| Errno::INVAL)?,
+        routes: raw_routes.as_mut_ptr() as u64,
+        num_routes: u32::try_from(routes.len()).map_err(|_e| Errno::INVAL)?,
+        ..Default::default()
+    };
+
+    let routing = raw::v4l2_ioctl_subdev_s_routing(fd, arg)?;
+
+    // Older kernels don't report the routing table back, and will have left our routes untouched.
+    raw_routes.truncate((routing.num_routes as usize).min(len));
+
+    Ok(raw_routes.into_iter().map(Into::into).collect())
+}
+
+/// Sets the client capabilities on a sub-device, and returns the ones the kernel enabled.
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the file descriptor, or if the kernel doesn't support
+/// client capabilities.
+#[instrument(level = "trace")]
+pub fn v4l2_ioctl_subdev_s_client_cap(fd: BorrowedFd<'_>, capabilities: u64) -> io::Result<u64> {
+    raw::v4l2_ioctl_subdev_s_client_cap(fd, raw::v4l2_subdev_client_capability { capabilities })
+        .map(|cap| cap.capabilities)
+}
+
+/// Enables the streams API on a sub-device file descriptor.
+///
+/// Returns whether the kernel and the sub-device support it.
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the file descriptor.
+#[instrument(level = "trace")]
+pub fn v4l2_subdev_enable_streams(fd: BorrowedFd<'_>) -> io::Result<bool> {
+    match v4l2_ioctl_subdev_s_client_cap(fd, raw::V4L2_SUBDEV_CLIENT_CAP_STREAMS) {
+        Ok(caps) => Ok((caps & raw::V4L2_SUBDEV_CLIENT_CAP_STREAMS) != 0),
+        // Kernels older than 6.3 don't know about client capabilities at all.
+        Err(e) if Errno::from_io_error(&e) == Some(Errno::NOTTY) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Reqbufs main structure
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct v4l2_requestbuffers {
+    count: u32,
+    kind: v4l2_buf_type,
+    memory: v4l2_memory,
+    caps: u32,
+    flags: u8,
+    _reserved: [u8; 3],
+}
+
+impl v4l2_requestbuffers {
+    /// Creates a new structure
+    #[must_use]
+    pub fn new(kind: v4l2_buf_type, memory: v4l2_memory) -> Self {
+        Self {
+            count: 0,
+            kind,
+            memory,
+            caps: 0,
+            flags: 0,
+            _reserved: [0; 3],
+        }
+    }
+
+    /// Sets the buffer count
+    #[must_use]
+    pub fn set_count(mut self, count: u32) -> Self {
+        self.count = count;
+        self
+    }
+
+    /// Returns whether the queue supports binding buffers to media requests
+    #[must_use]
+    pub fn supports_requests(&self) -> bool {
+        (self.caps & raw::V4L2_BUF_CAP_SUPPORTS_REQUESTS) != 0
+    }
+}
+
+impl fmt::Debug for v4l2_requestbuffers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("v4l2_requestbuffers")
+            .field("count", &self.count)
+            .field("type", &self.kind)
+            .field("memory", &self.memory)
+            .field("capabilities", &self.caps)
+            .field("flags", &self.flags)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TryFrom<raw::v4l2_requestbuffers> for v4l2_requestbuffers {
+    type Error = ConversionError;
+
+    fn try_from(value: raw::v4l2_requestbuffers) -> Result<Self, Self::Error> {
+        Ok(Self {
+            count: value.count,
+            kind: v4l2_buf_type::try_from(value.type_)?,
+            memory: v4l2_memory::try_from(value.memory)?,
+            caps: value.capabilities,
+            flags: value.flags,
+            _reserved: [0; 3],
+        })
+    }
+}
+
+impl From<v4l2_requestbuffers> for raw::v4l2_requestbuffers {
+    fn from(value: v4l2_requestbuffers) -> Self {
+        // SAFETY: We know from Rust layout rules and our tests that the layouts between the two
+        // structures are identical. We also know that all the fields in the Rust union are in a
+        // valid state. We can safely transmute.
+        unsafe { core::mem::transmute::<v4l2_requestbuffers, Self>(value) }
+    }
+}
+
+#[cfg(test)]
+mod tests_v4l2_requestbuffers {
+    use crate::{raw, wrapper};
+
+    #[test]
+    fn layout() {
+        assert_eq!(
+            size_of::<wrapper::v4l2_requestbuffers>(),
+            size_of::<raw::v4l2_requestbuffers>()
+        );
+
+        assert_eq!(
+            align_of::<wrapper::v4l2_requestbuffers>(),
+            align_of::<raw::v4l2_requestbuffers>()
+        );
+
+        assert_eq!(
+            std::mem::offset_of!(wrapper::v4l2_requestbuffers, count),
+            std::mem::offset_of!(raw::v4l2_requestbuffers, count)
+        );
+
+        assert_eq!(
+            std::mem::offset_of!(wrapper::v4l2_requestbuffers, kind),
+            std::mem::offset_of!(raw::v4l2_requestbuffers, type_)
+        );
+
+        assert_eq!(
+            std::mem::offset_of!(wrapper::v4l2_requestbuffers, memory),
+            std::mem::offset_of!(raw::v4l2_requestbuffers, memory)
+        );
+
+        assert_eq!(
+            std::mem::offset_of!(wrapper::v4l2_requestbuffers, caps),
+            std::mem::offset_of!(raw::v4l2_requestbuffers, capabilities)
+        );
+
+        assert_eq!(
+            std::mem::offset_of!(wrapper::v4l2_requestbuffers, flags),
+            std::mem::offset_of!(raw::v4l2_requestbuffers, flags)
+        );
+
+        assert_eq!(
+            std::mem::offset_of!(wrapper::v4l2_requestbuffers, _reserved),
+            std::mem::offset_of!(raw::v4l2_requestbuffers, reserved)
+        );
+    }
+}
+
+/// Allocates Device Buffers.
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the given file descriptor
+///
+/// # Panics
+///
+/// If the kernel returns an unexpected value
+#[instrument(level = "trace")]
+pub fn v4l2_ioctl_reqbufs(
+    fd: BorrowedFd<'_>,
+    reqbufs: v4l2_requestbuffers,
 ) -> io::Result<v4l2_requestbuffers> {
     raw::v4l2_ioctl_reqbufs(fd, reqbufs.into()).map(|f| {
         f.try_into()
@@ -1385,6 +2331,125 @@ pub fn v4l2_ioctl_streamoff(fd: BorrowedFd<'_>, buf_kind: v4l2_buf_type) -> io::
     raw::v4l2_ioctl_streamoff(fd, buf_kind.into())
 }
 
+/// Asks the driver to log its status to the kernel log
+///
+/// Works on both v4l2 devices and sub-devices.
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the file descriptor.
+#[instrument(level = "trace")]
+pub fn v4l2_ioctl_log_status(fd: BorrowedFd<'_>) -> io::Result<()> {
+    raw::v4l2_ioctl_log_status(fd)
+}
+
+fn v4l2_ext_controls(
+    request: Option<BorrowedFd<'_>>,
+    controls: &mut [raw::v4l2_ext_control],
+) -> io::Result<raw::v4l2_ext_controls> {
+    let mut arg = raw::v4l2_ext_controls {
+        count: u32::try_from(controls.len()).map_err(|_e| Errno::INVAL)?,
+        controls: controls.as_mut_ptr(),
+        ..Default::default()
+    };
+
+    if let Some(request) = request {
+        arg.__bindgen_anon_1.which = raw::V4L2_CTRL_WHICH_REQUEST_VAL;
+        arg.request_fd = request.as_raw_fd();
+    }
+
+    Ok(arg)
+}
+
+/// Retrieves the value of several controls
+///
+/// If a request is given, the values are the ones the request was completed with. Otherwise,
+/// they are the current ones.
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the file descriptor.
+#[instrument(level = "trace", skip(controls))]
+pub fn v4l2_ioctl_g_ext_ctrls(
+    fd: BorrowedFd<'_>,
+    request: Option<BorrowedFd<'_>>,
+    controls: &mut [raw::v4l2_ext_control],
+) -> io::Result<()> {
+    raw::v4l2_ioctl_g_ext_ctrls(fd, v4l2_ext_controls(request, controls)?).map(|_| ())
+}
+
+/// Sets the value of several controls atomically
+///
+/// If a request is given, the values will only be applied once the request is queued, together
+/// with the buffers bound to it. Otherwise, they are applied right away.
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the file descriptor.
+#[instrument(level = "trace", skip(controls))]
+pub fn v4l2_ioctl_s_ext_ctrls(
+    fd: BorrowedFd<'_>,
+    request: Option<BorrowedFd<'_>>,
+    controls: &mut [raw::v4l2_ext_control],
+) -> io::Result<()> {
+    raw::v4l2_ioctl_s_ext_ctrls(fd, v4l2_ext_controls(request, controls)?).map(|_| ())
+}
+
+/// Checks whether the driver would accept the value of several controls
+///
+/// The driver might adjust the values, but doesn't apply them.
+///
+/// # Errors
+///
+/// If there's an I/O Error while accessing the file descriptor.
+#[instrument(level = "trace", skip(controls))]
+pub fn v4l2_ioctl_try_ext_ctrls(
+    fd: BorrowedFd<'_>,
+    request: Option<BorrowedFd<'_>>,
+    controls: &mut [raw::v4l2_ext_control],
+) -> io::Result<()> {
+    raw::v4l2_ioctl_try_ext_ctrls(fd, v4l2_ext_controls(request, controls)?).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests_v4l2_ext_controls {
+    use std::{
+        fs::File,
+        os::fd::{AsFd as _, AsRawFd as _},
+    };
+
+    use crate::{raw, wrapper};
+
+    #[test]
+    fn current() {
+        let mut controls = [raw::v4l2_ext_control::default(); 2];
+        let arg = wrapper::v4l2_ext_controls(None, &mut controls).unwrap();
+
+        assert_eq!(arg.count, 2);
+        assert_eq!(arg.controls, controls.as_mut_ptr());
+        assert_eq!(arg.request_fd, 0);
+
+        // SAFETY: Both sides of the union are u32.
+        let which = unsafe { arg.__bindgen_anon_1.which };
+        assert_eq!(which, raw::V4L2_CTRL_WHICH_CUR_VAL);
+    }
+
+    #[test]
+    fn request() {
+        let request = File::open("/dev/null").unwrap();
+        let mut controls = [raw::v4l2_ext_control::default(); 1];
+        let arg = wrapper::v4l2_ext_controls(Some(request.as_fd()), &mut controls).unwrap();
+
+        assert_eq!(arg.count, 1);
+        assert_eq!(arg.controls, controls.as_mut_ptr());
+        assert_eq!(arg.request_fd, request.as_raw_fd());
+
+        // SAFETY: Both sides of the union are u32.
+        let which = unsafe { arg.__bindgen_anon_1.which };
+        assert_eq!(which, raw::V4L2_CTRL_WHICH_REQUEST_VAL);
+    }
+}
+
 /// Sets the EDID of a v4l2 device
 ///
 /// # Errors